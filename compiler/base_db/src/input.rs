@@ -1,5 +1,6 @@
 use relative_path::{RelativePath, RelativePathBuf};
 use rustc_hash::{FxHashMap, FxHashSet};
+use std::path::PathBuf;
 use syntax::TextSize;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -11,6 +12,8 @@ pub struct SourceRootId(pub u32);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SourceRoot {
     pub is_lib: bool,
+    /// The directory the relative paths of the files are relative to.
+    pub dir: Option<PathBuf>,
     files: FxHashMap<FileId, RelativePathBuf>,
 }
 
@@ -35,6 +38,7 @@ impl SourceRoot {
     pub fn new_local() -> Self {
         SourceRoot {
             is_lib: false,
+            dir: None,
             files: FxHashMap::default(),
         }
     }
@@ -42,6 +46,7 @@ impl SourceRoot {
     pub fn new_library() -> Self {
         SourceRoot {
             is_lib: true,
+            dir: None,
             files: FxHashMap::default(),
         }
     }
@@ -83,6 +83,10 @@ impl LibSet {
         Ok(())
     }
 
+    pub fn set_kind(&mut self, lib: LibId, kind: LibKind) {
+        self.libs.get_mut(&lib).unwrap().kind = kind;
+    }
+
     pub fn is_empty(&self) -> bool {
         self.libs.is_empty()
     }
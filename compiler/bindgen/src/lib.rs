@@ -0,0 +1,281 @@
+pub mod parse;
+
+use parse::{CType, Decl};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+
+const KEYWORDS: &[&str] = &[
+    "module", "import", "type", "foreign", "fun", "static", "const", "class", "instance", "infix", "infixl", "infixr",
+    "as", "do", "if", "unless", "then", "else", "while", "until", "loop", "break", "next", "yield", "return", "case",
+    "of", "let", "for", "where",
+];
+
+/// Generates a Shade module with `foreign` declarations for the contents of a C header.
+/// Declarations that cannot be expressed in Shade are listed as comments at the top of the module.
+pub fn generate(module: &str, header: &str) -> String {
+    let decls = parse::parse(header);
+    let mut gen = Generator {
+        structs: HashMap::new(),
+        defined: BTreeSet::new(),
+        names: BTreeSet::new(),
+        types: String::new(),
+        consts: String::new(),
+        funcs: String::new(),
+        skipped: String::new(),
+    };
+
+    for decl in &decls {
+        if let Decl::Typedef {
+            name,
+            ty: CType::Struct(tag),
+        } = decl
+        {
+            gen.structs.entry(tag.clone()).or_insert_with(|| type_name(name));
+        }
+    }
+
+    for decl in &decls {
+        match decl {
+            | Decl::Struct { tag, .. } => {
+                let name = type_name(tag);
+
+                gen.structs.entry(tag.clone()).or_insert(name);
+            },
+            | Decl::Typedef { name, .. } => {
+                gen.defined.insert(name.clone());
+            },
+            | _ => {},
+        }
+    }
+
+    for decl in &decls {
+        gen.decl(decl, &decls);
+    }
+
+    let mut out = String::new();
+
+    writeln!(out, "module {} =\n", module).unwrap();
+    writeln!(out, "import prim").unwrap();
+
+    if module != "libc" {
+        writeln!(out, "import libc").unwrap();
+    }
+
+    for section in [&gen.skipped, &gen.types, &gen.consts, &gen.funcs].iter() {
+        if !section.is_empty() {
+            writeln!(out).unwrap();
+            out.push_str(section);
+        }
+    }
+
+    out
+}
+
+struct Generator {
+    /// The Shade names of struct tags.
+    structs: HashMap<String, String>,
+    /// The typedef names declared in the header.
+    defined: BTreeSet<String>,
+    /// The names that were already declared. Headers may declare the same item more than once,
+    /// e.g. as C++ overloads or in both branches of an `#if`.
+    names: BTreeSet<String>,
+    types: String,
+    consts: String,
+    funcs: String,
+    skipped: String,
+}
+
+impl Generator {
+    fn decl(&mut self, decl: &Decl, decls: &[Decl]) {
+        match decl {
+            | Decl::Func { name, ret, params } => {
+                if let Err(reason) = self.func(name, ret, params) {
+                    writeln!(self.skipped, "-- skipped `{}`: {}", name, reason).unwrap();
+                }
+            },
+            | Decl::Typedef { name, ty } => {
+                let shade_name = type_name(name);
+
+                match ty {
+                    | CType::Struct(tag) if self.structs[tag] == shade_name => return,
+                    | _ if builtin_type(name).is_some() || !self.names.insert(shade_name.clone()) => return,
+                    | _ => {},
+                }
+
+                match self.ty(ty) {
+                    | Ok(ty) => writeln!(self.types, "type {} = {}", shade_name, ty).unwrap(),
+                    | Err(reason) => writeln!(self.skipped, "-- skipped `{}`: {}", name, reason).unwrap(),
+                }
+            },
+            | Decl::Struct { tag, fields: None } => {
+                let is_defined = decls
+                    .iter()
+                    .any(|d| matches!(d, Decl::Struct { tag: t, fields: Some(_) } if t == tag));
+
+                if !is_defined && self.names.insert(self.structs[tag].clone()) {
+                    writeln!(self.types, "foreign type {} :: Type", self.structs[tag]).unwrap();
+                }
+            },
+            | Decl::Struct {
+                tag,
+                fields: Some(fields),
+            } => {
+                if let Err(reason) = self.struct_(tag, fields) {
+                    writeln!(self.skipped, "-- skipped `struct {}`: {}", tag, reason).unwrap();
+                }
+            },
+            | Decl::Enum { variants, .. } => {
+                for (name, value) in variants {
+                    self.const_(name, *value);
+                }
+            },
+            | Decl::Const { name, value } => self.const_(name, *value),
+            | Decl::Skipped { line, reason } => {
+                writeln!(self.skipped, "-- skipped line {}: {}", line, reason).unwrap();
+            },
+        }
+    }
+
+    fn func(&mut self, name: &str, ret: &CType, params: &[CType]) -> Result<(), String> {
+        if !self.names.insert(name.to_string()) {
+            return Ok(());
+        }
+
+        if !is_value_name(name) {
+            return Err(String::from("the name is not a valid Shade identifier"));
+        }
+
+        let mut sig = Vec::with_capacity(params.len() + 1);
+
+        if params.is_empty() {
+            sig.push(String::from("()"));
+        }
+
+        for param in params {
+            sig.push(self.ty(param)?);
+        }
+
+        sig.push(self.ty(ret)?);
+        writeln!(self.funcs, "foreign fun {} :: {}", name, sig.join(" -> ")).unwrap();
+
+        Ok(())
+    }
+
+    /// Structs are declared as a type with a single constructor, because the fields of a record are sorted by name.
+    fn struct_(&mut self, tag: &str, fields: &[(String, CType)]) -> Result<(), String> {
+        if fields.is_empty() {
+            return Err(String::from("empty structs have no C representation"));
+        }
+
+        let name = self.structs[tag].clone();
+
+        if !self.names.insert(name.clone()) {
+            return Ok(());
+        }
+
+        let types = fields
+            .iter()
+            .map(|(_, ty)| self.ty(ty).map(|t| paren(&t)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let field_names = fields.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+
+        writeln!(self.types, "-- {}", field_names.join(", ")).unwrap();
+        writeln!(self.types, "type {} =\n    | {} {}", name, name, types.join(" ")).unwrap();
+
+        Ok(())
+    }
+
+    fn const_(&mut self, name: &str, value: i128) {
+        if KEYWORDS.contains(&name) {
+            writeln!(self.skipped, "-- skipped `{}`: the name is a keyword", name).unwrap();
+        } else if self.names.insert(name.to_string()) {
+            writeln!(self.consts, "const {} = {} :: C_Int", name, value).unwrap();
+        }
+    }
+
+    fn ty(&self, ty: &CType) -> Result<String, String> {
+        match ty {
+            | CType::Void => Ok(String::from("()")),
+            | CType::Bool => Ok(String::from("Bool")),
+            | CType::Float => Ok(String::from("C_Float")),
+            | CType::Double => Ok(String::from("C_Double")),
+            | CType::Int(int) => Ok(String::from(match int.as_str() {
+                | "char" => "C_Char",
+                | "signed char" => "C_Schar",
+                | "unsigned char" => "C_Uchar",
+                | "short" => "C_Short",
+                | "unsigned short" => "C_Ushort",
+                | "int" => "C_Int",
+                | "unsigned int" => "C_Uint",
+                | "long" => "C_Long",
+                | "unsigned long" => "C_Ulong",
+                | "long long" => "C_Longlong",
+                | "unsigned long long" => "C_Ulonglong",
+                | _ => return Err(format!("`{}` is not supported", int)),
+            })),
+            | CType::Named(name) => match builtin_type(name) {
+                | Some(ty) => Ok(String::from(ty)),
+                | None if self.defined.contains(name) => Ok(type_name(name)),
+                | None => Err(format!("unknown type `{}`", name)),
+            },
+            | CType::Struct(tag) => Ok(self.structs[tag].clone()),
+            | CType::Union(tag) => Err(format!("`union {}` is not supported", tag)),
+            | CType::Enum(_) => Ok(String::from("C_Int")),
+            | CType::Ptr(elem) => match &**elem {
+                | CType::Int(int) if int == "char" => Ok(String::from("C_Str")),
+                | CType::Void => Ok(String::from("BufPtr ()")),
+                | elem => Ok(format!("BufPtr {}", paren(&self.ty(elem)?))),
+            },
+            | CType::Array(elem, len) => Ok(format!("[{}]{}", len, paren(&self.ty(elem)?))),
+            | CType::FuncPtr => Ok(String::from("BufPtr ()")),
+        }
+    }
+}
+
+fn builtin_type(name: &str) -> Option<&'static str> {
+    Some(match name {
+        | "size_t" => "Size_T",
+        | "ssize_t" => "Ssize_T",
+        | "int8_t" => "Int8",
+        | "int16_t" => "Int16",
+        | "int32_t" => "Int32",
+        | "int64_t" => "Int64",
+        | "uint8_t" => "Uint8",
+        | "uint16_t" => "Uint16",
+        | "uint32_t" => "Uint32",
+        | "uint64_t" => "Uint64",
+        | "intptr_t" | "ptrdiff_t" => "Int",
+        | "uintptr_t" => "Uint",
+        | "bool" => "Bool",
+        | _ => return None,
+    })
+}
+
+/// Converts a C name to a Shade type name, e.g. `div_t` becomes `Div_T`.
+fn type_name(name: &str) -> String {
+    name.trim_start_matches('_')
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+
+            match chars.next() {
+                | Some(c) => c.to_uppercase().chain(chars).collect(),
+                | None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn is_value_name(name: &str) -> bool {
+    !KEYWORDS.contains(&name) && name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+}
+
+fn paren(ty: &str) -> String {
+    if ty.contains(' ') {
+        format!("({})", ty)
+    } else {
+        ty.to_string()
+    }
+}
@@ -0,0 +1,687 @@
+//! A parser for the subset of C that appears in library headers: function prototypes, typedefs,
+//! struct and enum definitions and integer `#define`s.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decl {
+    Func {
+        name: String,
+        ret: CType,
+        params: Vec<CType>,
+    },
+    Typedef {
+        name: String,
+        ty: CType,
+    },
+    /// A struct without fields is an opaque declaration.
+    Struct {
+        tag: String,
+        fields: Option<Vec<(String, CType)>>,
+    },
+    Enum {
+        tag: Option<String>,
+        variants: Vec<(String, i128)>,
+    },
+    Const {
+        name: String,
+        value: i128,
+    },
+    Skipped {
+        line: usize,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CType {
+    Void,
+    Bool,
+    /// The C integer type spelled with the given keywords, e.g. `unsigned long`.
+    Int(String),
+    Float,
+    Double,
+    Named(String),
+    Struct(String),
+    Union(String),
+    Enum(String),
+    Ptr(Box<CType>),
+    Array(Box<CType>, u64),
+    FuncPtr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i128),
+    Str,
+    Punct(char),
+    Ellipsis,
+}
+
+pub fn parse(src: &str) -> Vec<Decl> {
+    let mut parser = Parser {
+        tokens: Vec::new(),
+        pos: 0,
+        decls: Vec::new(),
+        anon: 0,
+    };
+
+    parser.lex(src);
+    parser.parse();
+    parser.decls
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    decls: Vec<Decl>,
+    anon: u32,
+}
+
+/// The type and name given by the specifiers and declarator of a declaration.
+type Declarator = (CType, Option<String>, Option<Vec<CType>>, bool);
+
+impl Parser {
+    fn lex(&mut self, src: &str) {
+        let src = strip_comments(src);
+        let mut lines = src.lines().enumerate();
+
+        while let Some((line, text)) = lines.next() {
+            let mut text = text.to_string();
+
+            while text.ends_with('\\') {
+                text.pop();
+                text.push_str(lines.next().map(|(_, l)| l).unwrap_or(""));
+            }
+
+            match text.trim().strip_prefix('#') {
+                | Some(directive) => self.directive(directive.trim()),
+                | None => self.lex_line(line + 1, &text),
+            }
+        }
+    }
+
+    fn directive(&mut self, directive: &str) {
+        if let Some(define) = directive.strip_prefix("define") {
+            let mut parts = define.trim().splitn(2, char::is_whitespace);
+            let name = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+
+            if name.contains('(') || value.is_empty() {
+                return;
+            }
+
+            // Other macros are usually aliases or expressions, which are left out.
+            if let Some(value) = parse_int(value.trim_start_matches('(').trim_end_matches(')')) {
+                self.decls.push(Decl::Const {
+                    name: name.to_string(),
+                    value,
+                });
+            }
+        }
+    }
+
+    fn lex_line(&mut self, line: usize, text: &str) {
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let token = match c {
+                | c if c.is_whitespace() => continue,
+                | c if c.is_alphabetic() || c == '_' => {
+                    let mut ident = c.to_string();
+
+                    while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                        ident.push(c);
+                        chars.next();
+                    }
+
+                    Token::Ident(ident)
+                },
+                | c if c.is_ascii_digit() => {
+                    let mut lit = c.to_string();
+
+                    while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric()) {
+                        lit.push(c);
+                        chars.next();
+                    }
+
+                    Token::Int(parse_int(&lit).unwrap_or(0))
+                },
+                | '"' => {
+                    while let Some(c) = chars.next() {
+                        match c {
+                            | '\\' => {
+                                chars.next();
+                            },
+                            | '"' => break,
+                            | _ => {},
+                        }
+                    }
+
+                    Token::Str
+                },
+                | '.' if chars.peek() == Some(&'.') => {
+                    chars.next();
+                    chars.next();
+                    Token::Ellipsis
+                },
+                | c => Token::Punct(c),
+            };
+
+            self.tokens.push((line, token));
+        }
+    }
+
+    fn parse(&mut self) {
+        while self.pos < self.tokens.len() {
+            if self.eat_ident("extern") && self.peek() == Some(&Token::Str) {
+                self.pos += 1;
+                self.eat_punct('{');
+                continue;
+            }
+
+            self.skip_attributes(false);
+
+            if self.pos >= self.tokens.len() || self.eat_punct('}') || self.eat_punct(';') {
+                continue;
+            }
+
+            let start = self.pos;
+            let line = self.tokens[start].0;
+            let len = self.decls.len();
+
+            if let Err(reason) = self.decl() {
+                self.decls.truncate(len);
+                self.pos = start;
+                self.skip_decl();
+                self.decls.push(Decl::Skipped { line, reason });
+            }
+        }
+    }
+
+    fn decl(&mut self) -> Result<(), String> {
+        while self.eat_ident("__extension__") {}
+
+        let is_typedef = self.eat_ident("typedef");
+        let base = self.specifiers()?;
+
+        if self.eat_punct(';') {
+            return Ok(());
+        }
+
+        loop {
+            let (ty, name, params, variadic) = self.declarator(base.clone())?;
+            let name = name.ok_or("declaration without a name")?;
+
+            // Macros that wrap a declaration, e.g. `__REDIRECT (name, (params), alias) __wur`.
+            if name.starts_with("__") && (params.is_none() || !name.contains(char::is_lowercase)) && !is_typedef {
+                return Err(format!("`{}` is not a declaration", name));
+            }
+
+            if is_typedef {
+                self.decls.push(Decl::Typedef { name, ty });
+            } else if let Some(params) = params {
+                if variadic {
+                    self.decls.push(Decl::Skipped {
+                        line: self.line(),
+                        reason: format!("`{}` is variadic", name),
+                    });
+                } else {
+                    self.decls.push(Decl::Func { name, ret: ty, params });
+                }
+            } else {
+                self.decls.push(Decl::Skipped {
+                    line: self.line(),
+                    reason: format!("variable `{}` is not supported", name),
+                });
+            }
+
+            self.skip_attributes(true);
+
+            if !self.eat_punct(',') {
+                break;
+            }
+        }
+
+        if self.eat_punct('{') {
+            return Err(String::from("function definitions are not supported"));
+        }
+
+        if self.eat_punct(';') {
+            Ok(())
+        } else {
+            Err(String::from("expected `;`"))
+        }
+    }
+
+    fn specifiers(&mut self) -> Result<CType, String> {
+        let mut words = Vec::new();
+        let mut ty = None;
+
+        loop {
+            self.skip_attributes(false);
+
+            let ident = match self.peek() {
+                | Some(Token::Ident(ident)) => ident.clone(),
+                | _ => break,
+            };
+
+            match ident.as_str() {
+                | "const" | "volatile" | "static" | "extern" | "inline" | "register" | "restrict" | "__restrict"
+                | "__inline" | "__extension__" => {
+                    self.pos += 1;
+                },
+                | "void" | "char" | "short" | "int" | "long" | "signed" | "unsigned" | "float" | "double" | "_Bool"
+                | "bool" => {
+                    self.pos += 1;
+                    words.push(ident);
+                },
+                | "struct" | "union" => {
+                    self.pos += 1;
+                    ty = Some(self.struct_specifier(ident == "union")?);
+                },
+                | "enum" => {
+                    self.pos += 1;
+                    ty = Some(self.enum_specifier()?);
+                },
+                | _ if ty.is_none() && words.is_empty() => {
+                    self.pos += 1;
+                    ty = Some(CType::Named(ident));
+                },
+                | _ => break,
+            }
+        }
+
+        if let Some(ty) = ty {
+            return Ok(ty);
+        }
+
+        let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+
+        match words.as_slice() {
+            | [] => Err(String::from("expected a type")),
+            | ["void"] => Ok(CType::Void),
+            | ["_Bool"] | ["bool"] => Ok(CType::Bool),
+            | ["float"] => Ok(CType::Float),
+            | ["double"] => Ok(CType::Double),
+            | _ if words.contains(&"double") || words.contains(&"float") => {
+                Err(format!("`{}` is not supported", words.join(" ")))
+            },
+            | _ => {
+                let unsigned = words.contains(&"unsigned");
+                let longs = words.iter().filter(|w| **w == "long").count();
+                let base = if words.contains(&"char") {
+                    if words.contains(&"signed") {
+                        "signed char"
+                    } else {
+                        "char"
+                    }
+                } else if words.contains(&"short") {
+                    "short"
+                } else if longs >= 2 {
+                    "long long"
+                } else if longs == 1 {
+                    "long"
+                } else {
+                    "int"
+                };
+
+                if unsigned {
+                    Ok(CType::Int(format!("unsigned {}", base.trim_start_matches("signed "))))
+                } else {
+                    Ok(CType::Int(base.to_string()))
+                }
+            },
+        }
+    }
+
+    fn struct_specifier(&mut self, is_union: bool) -> Result<CType, String> {
+        let tag = match self.ident() {
+            | Some(tag) => tag,
+            | None => {
+                self.anon += 1;
+                format!("anon{}", self.anon)
+            },
+        };
+
+        if is_union {
+            if self.eat_punct('{') {
+                self.skip_until('}');
+            }
+
+            return Ok(CType::Union(tag));
+        }
+
+        let fields = if self.eat_punct('{') {
+            let mut fields = Vec::new();
+
+            while !self.eat_punct('}') {
+                let base = self.specifiers()?;
+
+                loop {
+                    let (ty, name, params, _) = self.declarator(base.clone())?;
+                    let name = name.ok_or("field without a name")?;
+
+                    if params.is_some() {
+                        return Err(format!("field `{}` has a function type", name));
+                    }
+
+                    if self.eat_punct(':') {
+                        return Err(format!("bit field `{}` is not supported", name));
+                    }
+
+                    if let CType::Array(_, 0) = ty {
+                        return Err(format!("flexible array member `{}` is not supported", name));
+                    }
+
+                    fields.push((name, ty));
+
+                    if !self.eat_punct(',') {
+                        break;
+                    }
+                }
+
+                if !self.eat_punct(';') {
+                    return Err(String::from("expected `;`"));
+                }
+            }
+
+            Some(fields)
+        } else {
+            None
+        };
+
+        if fields.is_some()
+            || !self
+                .decls
+                .iter()
+                .any(|d| matches!(d, Decl::Struct { tag: t, .. } if *t == tag))
+        {
+            self.decls.push(Decl::Struct {
+                tag: tag.clone(),
+                fields,
+            });
+        }
+
+        Ok(CType::Struct(tag))
+    }
+
+    fn enum_specifier(&mut self) -> Result<CType, String> {
+        let tag = self.ident();
+
+        if self.eat_punct('{') {
+            let mut variants = Vec::new();
+            let mut next = 0;
+
+            while !self.eat_punct('}') {
+                let name = self.ident().ok_or("expected an enumerator")?;
+
+                if self.eat_punct('=') {
+                    next = self.const_expr()?;
+                }
+
+                variants.push((name, next));
+                next += 1;
+                self.eat_punct(',');
+            }
+
+            self.decls.push(Decl::Enum {
+                tag: tag.clone(),
+                variants,
+            });
+        }
+
+        Ok(CType::Enum(tag.unwrap_or_default()))
+    }
+
+    fn const_expr(&mut self) -> Result<i128, String> {
+        let negate = self.eat_punct('-');
+        let value = match self.next() {
+            | Some(Token::Int(i)) => i,
+            | Some(Token::Ident(name)) => self
+                .decls
+                .iter()
+                .find_map(|d| match d {
+                    | Decl::Const { name: n, value } if *n == name => Some(*value),
+                    | Decl::Enum { variants, .. } => variants.iter().find(|(n, _)| *n == name).map(|(_, v)| *v),
+                    | _ => None,
+                })
+                .ok_or_else(|| format!("unknown constant `{}`", name))?,
+            | _ => return Err(String::from("expected an integer constant")),
+        };
+
+        if negate {
+            Ok(-value)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn declarator(&mut self, mut ty: CType) -> Result<Declarator, String> {
+        while self.eat_punct('*') {
+            ty = CType::Ptr(Box::new(ty));
+
+            while self.eat_ident("const") || self.eat_ident("restrict") || self.eat_ident("__restrict") {}
+        }
+
+        if self.eat_punct('(') {
+            if !self.eat_punct('*') {
+                return Err(String::from("unsupported declarator"));
+            }
+
+            let name = self.ident();
+
+            if !self.eat_punct(')') || !self.eat_punct('(') {
+                return Err(String::from("unsupported declarator"));
+            }
+
+            self.skip_until(')');
+
+            return Ok((CType::FuncPtr, name, None, false));
+        }
+
+        let name = self.ident();
+
+        while self.eat_punct('[') {
+            // An array without a length is only allowed as a parameter or the last field.
+            let len = if self.eat_punct(']') {
+                0
+            } else {
+                let len = self.const_expr()? as u64;
+
+                if !self.eat_punct(']') {
+                    return Err(String::from("expected `]`"));
+                }
+
+                len
+            };
+
+            ty = CType::Array(Box::new(ty), len);
+        }
+
+        if self.eat_punct('(') {
+            let mut params = Vec::new();
+            let mut variadic = false;
+
+            if self.peek() == Some(&Token::Ident(String::from("void"))) && self.peek_nth(1) == Some(&Token::Punct(')'))
+            {
+                self.pos += 1;
+            }
+
+            while !self.eat_punct(')') {
+                if self.peek() == Some(&Token::Ellipsis) {
+                    self.pos += 1;
+                    variadic = true;
+                    continue;
+                }
+
+                let base = self.specifiers()?;
+                let (param, _, fn_params, _) = self.declarator(base)?;
+
+                params.push(match (param, fn_params) {
+                    | (_, Some(_)) => CType::FuncPtr,
+                    | (CType::Array(elem, _), None) => CType::Ptr(elem),
+                    | (param, None) => param,
+                });
+
+                self.eat_punct(',');
+            }
+
+            return Ok((ty, name, Some(params), variadic));
+        }
+
+        Ok((ty, name, None, false))
+    }
+
+    /// Skips GNU attributes and the annotation macros of system headers, e.g. `__THROW`.
+    /// Before a declarator only attributes and uppercase macros are skipped, as `__`-prefixed names
+    /// are also used for types and functions.
+    fn skip_attributes(&mut self, trailing: bool) {
+        while let Some(Token::Ident(ident)) = self.peek() {
+            if !ident.starts_with("__") || ident == "__restrict" || ident == "__inline" || ident == "__extension__" {
+                break;
+            }
+
+            let is_macro = !ident.contains(char::is_lowercase);
+            let is_attr = ident.starts_with("__attribute") || ident.starts_with("__asm");
+
+            if !trailing && !is_macro && !is_attr {
+                break;
+            }
+
+            if self.peek_nth(1) == Some(&Token::Punct('(')) {
+                self.pos += 2;
+                self.skip_until(')');
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skips tokens until the matching closing delimiter.
+    fn skip_until(&mut self, close: char) {
+        let open = if close == ')' { '(' } else { '{' };
+        let mut depth = 1;
+
+        while let Some(token) = self.next() {
+            match token {
+                | Token::Punct(c) if c == open => depth += 1,
+                | Token::Punct(c) if c == close => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return;
+                    }
+                },
+                | _ => {},
+            }
+        }
+    }
+
+    fn skip_decl(&mut self) {
+        let mut depth = 0;
+
+        while let Some(token) = self.next() {
+            match token {
+                | Token::Punct('{') | Token::Punct('(') => depth += 1,
+                | Token::Punct('}') if depth == 1 => return,
+                | Token::Punct('}') | Token::Punct(')') => depth -= 1,
+                | Token::Punct(';') if depth <= 0 => return,
+                | _ => {},
+            }
+        }
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos.saturating_sub(1)).map(|t| t.0).unwrap_or(0)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n).map(|t| &t.1)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|t| t.1.clone());
+
+        self.pos += 1;
+        token
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        match self.peek() {
+            | Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+
+                self.pos += 1;
+                Some(ident)
+            },
+            | _ => None,
+        }
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        match self.peek() {
+            | Some(Token::Ident(i)) if i == ident => {
+                self.pos += 1;
+                true
+            },
+            | _ => false,
+        }
+    }
+
+    fn eat_punct(&mut self, punct: char) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn strip_comments(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut rest = src;
+
+    while let Some(start) = rest.find("/*").into_iter().chain(rest.find("//")).min() {
+        out.push_str(&rest[..start]);
+
+        if rest[start..].starts_with("/*") {
+            let end = rest[start..].find("*/").map(|e| start + e + 2).unwrap_or(rest.len());
+
+            // Keep the line numbers intact.
+            out.extend(rest[start..end].chars().filter(|&c| c == '\n'));
+            rest = &rest[end..];
+        } else {
+            rest = &rest[rest[start..].find('\n').map(|e| start + e).unwrap_or(rest.len())..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn parse_int(lit: &str) -> Option<i128> {
+    let (negate, lit) = match lit.trim().strip_prefix('-') {
+        | Some(lit) => (true, lit.trim()),
+        | None => (false, lit.trim()),
+    };
+
+    let lit = lit.trim_end_matches(['u', 'U', 'l', 'L'].as_ref());
+    let value = if let Some(hex) = lit.strip_prefix("0x").or_else(|| lit.strip_prefix("0X")) {
+        i128::from_str_radix(hex, 16).ok()?
+    } else if lit.len() > 1 && lit.starts_with('0') {
+        i128::from_str_radix(&lit[1..], 8).ok()?
+    } else {
+        lit.parse().ok()?
+    };
+
+    if negate {
+        Some(-value)
+    } else {
+        Some(value)
+    }
+}
@@ -0,0 +1,63 @@
+const HEADER: &str = r#"
+#define BUF_SIZE 64
+
+typedef struct point {
+    int x;
+    int y;
+} point_t;
+
+struct opaque;
+
+enum color { RED, GREEN = 4 };
+
+int add(int a, int b);
+void log_message(const char *msg);
+struct opaque *open_thing(void);
+unsigned long count(point_t p);
+unsigned short scale(unsigned int a, long long b, unsigned long long c, signed char d, unsigned char e);
+long double precise(void);
+"#;
+
+#[test]
+fn generates_module() {
+    let out = bindgen::generate("things", HEADER);
+
+    assert!(out.starts_with("module things =\n\nimport prim\nimport libc\n"), "{}", out);
+    assert!(out.contains("const BUF_SIZE = 64 :: C_Int\n"), "{}", out);
+    assert!(out.contains("const RED = 0 :: C_Int\n"), "{}", out);
+    assert!(out.contains("const GREEN = 4 :: C_Int\n"), "{}", out);
+    assert!(out.contains("-- x, y\ntype Point_T =\n    | Point_T C_Int C_Int\n"), "{}", out);
+    assert!(out.contains("foreign type Opaque :: Type\n"), "{}", out);
+}
+
+#[test]
+fn generates_functions() {
+    let out = bindgen::generate("things", HEADER);
+
+    assert!(out.contains("foreign fun add :: C_Int -> C_Int -> C_Int\n"), "{}", out);
+    assert!(out.contains("foreign fun log_message :: C_Str -> ()\n"), "{}", out);
+    assert!(out.contains("foreign fun open_thing :: () -> BufPtr Opaque\n"), "{}", out);
+    assert!(out.contains("foreign fun count :: Point_T -> C_Ulong\n"), "{}", out);
+    assert!(
+        out.contains(
+            "foreign fun scale :: C_Uint -> C_Longlong -> C_Ulonglong -> C_Schar -> C_Uchar -> C_Ushort\n"
+        ),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn skips_unsupported() {
+    let out = bindgen::generate("things", HEADER);
+
+    assert!(out.contains("`long double` is not supported"), "{}", out);
+    assert!(!out.contains("foreign fun precise"), "{}", out);
+}
+
+#[test]
+fn libc_does_not_import_itself() {
+    let out = bindgen::generate("libc", "int abs(int x);\n");
+
+    assert_eq!(out, "module libc =\n\nimport prim\n\nforeign fun abs :: C_Int -> C_Int\n");
+}
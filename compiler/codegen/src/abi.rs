@@ -1,94 +1,313 @@
 use crate::*;
-use mir::layout::{Abi, Layout, Size};
+use mir::layout::{Abi, Fields, Layout, Primitive, Size, Variants};
+use target_lexicon::Architecture;
 
 #[derive(Clone, Copy)]
 pub enum PassMode {
     NoPass,
     ByVal(clif::Type),
     ByValPair(clif::Type, clif::Type),
-    ByRef { size: Option<Size> },
+    /// An aggregate that is passed in registers, stored in memory as the given consecutive chunks.
+    Cast(CastTarget),
+    /// An aggregate that is passed as a pointer. With the C abi the pointer is to a copy made by the caller.
+    /// With `on_stack` the caller copies the value into the argument area on the stack instead
+    /// (the SysV x86_64 memory class).
+    ByRef {
+        size: Option<Size>,
+        on_stack: bool,
+    },
 }
 
 #[derive(Clone, Copy)]
-pub enum EmptySinglePair<T> {
-    Empty,
-    Single(T),
-    Pair(T, T),
+pub struct CastTarget {
+    regs: [Option<clif::Type>; 4],
 }
 
-impl<T> Iterator for EmptySinglePair<T> {
-    type Item = T;
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RegClass {
+    Int,
+    Sse,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match std::mem::replace(self, EmptySinglePair::Empty) {
-            | EmptySinglePair::Empty => None,
-            | EmptySinglePair::Single(v) => Some(v),
-            | EmptySinglePair::Pair(a, b) => {
-                *self = EmptySinglePair::Single(b);
-                Some(a)
-            },
+impl CastTarget {
+    fn new(regs: impl IntoIterator<Item = clif::Type>) -> Self {
+        let mut target = CastTarget { regs: [None; 4] };
+
+        for (slot, reg) in target.regs.iter_mut().zip(regs) {
+            *slot = Some(reg);
         }
+
+        target
+    }
+
+    pub fn regs(&self) -> impl Iterator<Item = clif::Type> + '_ {
+        self.regs.iter().flatten().copied()
     }
 }
 
-impl ModuleCtx<'_> {
-    pub fn pass_mode(&self, layout: &Layout) -> PassMode {
+impl<M: clif::Module> ModuleCtx<'_, M> {
+    pub fn abi_of(&self, def: hir::id::DefWithBodyId) -> hir::Abi {
+        match def {
+            | hir::id::DefWithBodyId::FuncId(id) => hir::Func::from(id).abi(self.db.upcast()),
+            | _ => hir::Abi::Shade,
+        }
+    }
+
+    pub fn pass_mode(&self, abi: hir::Abi, layout: &Layout) -> PassMode {
         if layout.is_zst() {
-            PassMode::NoPass
-        } else {
-            match &layout.abi {
-                | Abi::Uninhabited => PassMode::NoPass,
-                | Abi::Scalar(s) => PassMode::ByVal(self.scalar_type(s)),
-                | Abi::ScalarPair(a, b) => {
-                    let a_ty = self.scalar_type(a);
-                    let b_ty = self.scalar_type(b);
-
-                    if a.value.size(&self.triple).bits() == 128 && b.value.size(&self.triple).bits() == 128 {
-                        PassMode::ByRef {
-                            size: Some(layout.size),
-                        }
-                    } else {
-                        PassMode::ByValPair(a_ty, b_ty)
+            return PassMode::NoPass;
+        }
+
+        match (&layout.abi, abi) {
+            | (Abi::Uninhabited, _) => PassMode::NoPass,
+            | (Abi::Scalar(s), _) => PassMode::ByVal(self.scalar_type(s)),
+            | (Abi::Aggregate { sized: false }, _) => PassMode::ByRef {
+                size: None,
+                on_stack: false,
+            },
+            | (_, hir::Abi::C) => self.pass_mode_c(layout),
+            | (Abi::ScalarPair(a, b), hir::Abi::Shade) => {
+                let a_ty = self.scalar_type(a);
+                let b_ty = self.scalar_type(b);
+
+                if a.value.size(&self.triple).bits() == 128 && b.value.size(&self.triple).bits() == 128 {
+                    PassMode::ByRef {
+                        size: Some(layout.size),
+                        on_stack: false,
+                    }
+                } else {
+                    PassMode::ByValPair(a_ty, b_ty)
+                }
+            },
+            | (Abi::Aggregate { sized: true }, hir::Abi::Shade) => PassMode::ByRef {
+                size: Some(layout.size),
+                on_stack: false,
+            },
+        }
+    }
+
+    /// Classifies an aggregate according to the C calling convention of the target.
+    /// Aggregates that are not passed in registers are copied onto the stack on x86_64
+    /// and passed as a pointer to a copy elsewhere.
+    fn pass_mode_c(&self, layout: &Layout) -> PassMode {
+        let by_ref = PassMode::ByRef {
+            size: Some(layout.size),
+            on_stack: false,
+        };
+
+        let size = layout.size.bytes();
+
+        match self.triple.architecture {
+            | Architecture::X86_64 if size <= 16 => {
+                let mut classes = [None; 2];
+
+                self.for_each_scalar(layout, 0, &mut |offset, prim| {
+                    let class = match prim {
+                        | Primitive::F32 | Primitive::F64 => RegClass::Sse,
+                        | _ => RegClass::Int,
+                    };
+
+                    let end = offset + prim.size(&self.triple).bytes() - 1;
+
+                    for eightbyte in offset / 8..=end / 8 {
+                        classes[eightbyte as usize] = match classes[eightbyte as usize] {
+                            | Some(RegClass::Int) => Some(RegClass::Int),
+                            | _ => Some(class),
+                        };
+                    }
+                });
+
+                let count = (size + 7) / 8;
+                let regs = (0..count).map(|i| {
+                    let rest = size - i * 8;
+
+                    match classes[i as usize] {
+                        | Some(RegClass::Sse) if rest <= 4 => clif::types::F32,
+                        | Some(RegClass::Sse) => clif::types::F64,
+                        | _ => int_reg(rest),
                     }
-                },
-                | Abi::Aggregate { sized: true } => PassMode::ByRef {
-                    size: Some(layout.size),
-                },
-                | Abi::Aggregate { sized: false } => PassMode::ByRef { size: None },
+                });
+
+                PassMode::Cast(CastTarget::new(regs))
+            },
+            | Architecture::X86_64 => PassMode::ByRef {
+                size: Some(layout.size),
+                on_stack: true,
+            },
+            | Architecture::Aarch64(_) => {
+                let mut floats = Vec::new();
+                let mut homogeneous = true;
+
+                self.for_each_scalar(layout, 0, &mut |offset, prim| match prim {
+                    | Primitive::F32 | Primitive::F64 => floats.push((offset, prim)),
+                    | _ => homogeneous = false,
+                });
+
+                let is_hfa = homogeneous
+                    && (1..=4).contains(&floats.len())
+                    && floats.iter().all(|&(_, prim)| prim == floats[0].1)
+                    && floats.len() as u64 * floats[0].1.size(&self.triple).bytes() == size;
+
+                if is_hfa {
+                    let ty = if floats[0].1 == Primitive::F32 {
+                        clif::types::F32
+                    } else {
+                        clif::types::F64
+                    };
+
+                    PassMode::Cast(CastTarget::new(floats.iter().map(|_| ty)))
+                } else if size <= 16 {
+                    PassMode::Cast(CastTarget::new((0..(size + 7) / 8).map(|_| clif::types::I64)))
+                } else {
+                    by_ref
+                }
+            },
+            | _ => by_ref,
+        }
+    }
+
+    fn for_each_scalar(&self, layout: &Layout, offset: u64, f: &mut dyn FnMut(u64, Primitive)) {
+        match &layout.abi {
+            | Abi::Scalar(s) => {
+                f(offset, s.value);
+                return;
+            },
+            | Abi::ScalarPair(a, b) => {
+                let b_offset: i64 = crate::place::scalar_pair_calculate_b_offset(&self.triple, a, b).into();
+
+                f(offset, a.value);
+                f(offset + b_offset as u64, b.value);
+                return;
+            },
+            | _ => {},
+        }
+
+        match &layout.fields {
+            | Fields::Primitive => {},
+            | Fields::Array { stride, count } => {
+                let elem = layout.elem(self.db.upcast()).unwrap();
+
+                for i in 0..*count as u64 {
+                    self.for_each_scalar(&elem, offset + stride.bytes() * i, f);
+                }
+            },
+            | Fields::Union { fields } => {
+                for field in fields {
+                    self.for_each_scalar(field, offset, f);
+                }
+            },
+            | Fields::Arbitrary { fields } => {
+                for (field_offset, field) in fields {
+                    self.for_each_scalar(field, offset + field_offset.bytes(), f);
+                }
+            },
+        }
+
+        if let Variants::Multiple { variants, .. } = &layout.variants {
+            for variant in variants {
+                self.for_each_scalar(variant, offset, f);
             }
         }
     }
 }
 
-impl FunctionCtx<'_, '_> {
-    pub fn value_for_arg(&mut self, arg: ValueRef) -> EmptySinglePair<clif::Value> {
-        match self.pass_mode(&arg.layout) {
-            | PassMode::NoPass => EmptySinglePair::Empty,
-            | PassMode::ByVal(_) => EmptySinglePair::Single(arg.load_scalar(self)),
+fn int_reg(bytes: u64) -> clif::Type {
+    match bytes {
+        | 1 => clif::types::I8,
+        | 2 => clif::types::I16,
+        | 3 | 4 => clif::types::I32,
+        | _ => clif::types::I64,
+    }
+}
+
+impl<M: clif::Module> FunctionCtx<'_, '_, M> {
+    pub fn value_for_arg(&mut self, abi: hir::Abi, arg: ValueRef) -> Vec<clif::Value> {
+        match self.pass_mode(abi, &arg.layout) {
+            | PassMode::NoPass => Vec::new(),
+            | PassMode::ByVal(_) => vec![arg.load_scalar(self)],
             | PassMode::ByValPair(_, _) => {
                 let (a, b) = arg.load_scalar_pair(self);
 
-                EmptySinglePair::Pair(a, b)
+                vec![a, b]
             },
-            | PassMode::ByRef { size: _ } => match arg.on_stack(self) {
-                | (ptr, None) => EmptySinglePair::Single(ptr.get_addr(self)),
-                | (ptr, Some(meta)) => EmptySinglePair::Pair(ptr.get_addr(self), meta),
+            | PassMode::Cast(target) => self.cast_to_regs(arg, target),
+            | PassMode::ByRef { size: Some(_), .. } if abi == hir::Abi::C => {
+                // the callee may modify the value, so it gets a copy. on x86_64 the callee copies whole
+                // eightbytes, so the slot is large enough for that as well
+                let ptr = self.cast_slot(&arg.layout);
+                let layout = arg.layout.clone();
+
+                PlaceRef::new_ref(ptr, layout).store(self, arg);
+                vec![ptr.get_addr(self)]
+            },
+            | PassMode::ByRef { .. } => match arg.on_stack(self) {
+                | (ptr, None) => vec![ptr.get_addr(self)],
+                | (ptr, Some(meta)) => vec![ptr.get_addr(self), meta],
             },
         }
     }
 
-    pub fn value_for_ret(&mut self, ret: ir::LocalId) -> EmptySinglePair<clif::Value> {
+    pub fn value_for_ret(&mut self, ret: ir::LocalId) -> Vec<clif::Value> {
         let place = self.locals[ret].clone();
 
-        match self.pass_mode(&place.layout) {
-            | PassMode::NoPass => EmptySinglePair::Empty,
-            | PassMode::ByVal(_) => EmptySinglePair::Single(place.to_value(self).load_scalar(self)),
+        match self.pass_mode(self.abi, &place.layout) {
+            | PassMode::NoPass => Vec::new(),
+            | PassMode::ByVal(_) => vec![place.to_value(self).load_scalar(self)],
             | PassMode::ByValPair(_, _) => {
                 let (a, b) = place.to_value(self).load_scalar_pair(self);
 
-                EmptySinglePair::Pair(a, b)
+                vec![a, b]
             },
-            | PassMode::ByRef { size: _ } => EmptySinglePair::Empty,
+            | PassMode::Cast(target) => {
+                let val = place.to_value(self);
+
+                self.cast_to_regs(val, target)
+            },
+            | PassMode::ByRef { .. } => Vec::new(),
+        }
+    }
+
+    /// Loads the registers of a value that is passed as a cast aggregate.
+    pub fn cast_to_regs(&mut self, val: ValueRef, target: CastTarget) -> Vec<clif::Value> {
+        let ptr = self.cast_slot(&val.layout);
+        let mut offset = 0;
+
+        PlaceRef::new_ref(ptr, val.layout.clone()).store(self, val);
+
+        target
+            .regs()
+            .map(|ty| {
+                let val = ptr.offset_i64(self, offset).load(self, ty, clif::MemFlags::trusted());
+
+                offset += ty.bytes() as i64;
+                val
+            })
+            .collect()
+    }
+
+    /// Stores the registers of an aggregate that was passed as a cast aggregate.
+    pub fn regs_to_value(&mut self, regs: &[clif::Value], layout: Arc<mir::layout::Layout>) -> ValueRef {
+        let ptr = self.cast_slot(&layout);
+        let mut offset = 0;
+
+        for &reg in regs {
+            let ty = self.bcx.func.dfg.value_type(reg);
+
+            ptr.offset_i64(self, offset).store(self, reg, clif::MemFlags::trusted());
+            offset += ty.bytes() as i64;
         }
+
+        ValueRef::new_ref(ptr, layout)
+    }
+
+    /// A stack slot that is large enough to load whole registers from.
+    fn cast_slot(&mut self, layout: &Layout) -> Pointer {
+        let slot = self.bcx.create_stack_slot(clif::ir::StackSlotData {
+            kind: clif::ir::StackSlotKind::ExplicitSlot,
+            size: ((layout.size.bytes() + 7) / 8 * 8) as u32,
+            offset: None,
+        });
+
+        Pointer::stack(slot)
     }
 }
@@ -1,4 +1,5 @@
 use crate::db::CodegenDatabase;
+use crate::linker::LinkError;
 use base_db::libs::LibKind;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -9,6 +10,9 @@ use tempfile::NamedTempFile;
 pub struct Assembly {
     lib: hir::Lib,
     file: NamedTempFile,
+    clif: String,
+    asm: String,
+    errors: Vec<String>,
 }
 
 impl PartialEq for Assembly {
@@ -25,44 +29,102 @@ impl Assembly {
         self.file.path()
     }
 
+    /// The Cranelift IR of every function, only set when `emit_clif` is enabled.
+    pub fn clif(&self) -> &str {
+        &self.clif
+    }
+
+    /// The disassembly of every function, only set when `emit_asm` is enabled.
+    pub fn asm(&self) -> &str {
+        &self.asm
+    }
+
+    /// The constructs that could not be compiled for the target, nothing is written when there are any.
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
     pub fn path(&self, db: &dyn CodegenDatabase, target_dir: &Path) -> PathBuf {
         target_dir
             .join(format!("{}{}", self.prefix(db), self.lib.name(db.upcast())))
             .with_extension(self.extension(db))
     }
 
-    pub fn link(&self, db: &dyn CodegenDatabase, deps: impl Iterator<Item = hir::Lib>, target_dir: &Path) {
-        let mut linker = crate::linker::create();
+    pub fn link(
+        &self,
+        db: &dyn CodegenDatabase,
+        deps: impl Iterator<Item = hir::Lib>,
+        target_dir: &Path,
+    ) -> Result<(), LinkError> {
+        // wasm modules already contain their dependencies
+        if let target_lexicon::OperatingSystem::Wasi = db.target_triple().operating_system {
+            let out = self.path(db, target_dir);
+
+            return match std::fs::copy(self.tmp_path(), &out) {
+                | Ok(_) => Ok(()),
+                | Err(e) => Err(LinkError::Copy(out, e)),
+            };
+        }
+
+        let opts = db.codegen_opts();
+        let kind = db.libs()[self.lib.into()].kind;
+        let static_exe = opts.static_link && kind == LibKind::Executable;
         let out = self.path(db, target_dir);
 
+        // the dependencies of static libraries are linked into the executable that uses them
+        if kind == LibKind::Static {
+            return crate::linker::archive(self.tmp_path(), &out);
+        }
+
+        let mut linker = crate::linker::create(opts.linker.as_deref());
+
         linker.add_object(self.tmp_path());
         linker.rpath(target_dir);
         linker.arg("-L");
         linker.arg(target_dir);
 
-        add_deps(&mut *linker, db.upcast(), deps.collect());
+        add_deps(&mut *linker, db.upcast(), deps.collect(), static_exe)?;
 
-        fn add_deps(linker: &mut dyn crate::linker::Linker, db: &dyn hir::db::HirDatabase, deps: Vec<hir::Lib>) {
+        fn add_deps(
+            linker: &mut dyn crate::linker::Linker,
+            db: &dyn hir::db::HirDatabase,
+            deps: Vec<hir::Lib>,
+            static_exe: bool,
+        ) -> Result<(), LinkError> {
             for dep in deps {
-                add_deps(linker, db, dep.dependencies(db).into_iter().map(|d| d.lib).collect());
-
                 let name = dep.name(db).to_string();
 
+                // a library comes before its dependencies, so that static libraries can use them
                 match db.libs()[dep.into()].kind {
+                    | LibKind::Dynamic if static_exe => return Err(LinkError::DynamicDependency(name)),
                     | LibKind::Dynamic => linker.add_shared_object(&name),
                     | LibKind::Static => linker.add_static_lib(&name),
                     | LibKind::Executable => panic!("cannot link with an executable"),
                 }
+
+                add_deps(
+                    linker,
+                    db,
+                    dep.dependencies(db).into_iter().map(|d| d.lib).collect(),
+                    static_exe,
+                )?;
             }
+
+            Ok(())
         }
 
-        match db.libs()[self.lib.into()].kind {
+        match kind {
             | LibKind::Dynamic => linker.build_shared_object(&out),
-            | LibKind::Static => linker.build_static_lib(&out),
+            | LibKind::Static => unreachable!("static libraries are archived"),
             | LibKind::Executable => linker.build_executable(&out),
         }
 
-        linker.run();
+        if static_exe {
+            linker.static_executable();
+        }
+
+        linker.args(&opts.link_args);
+        linker.run()
     }
 
     fn extension(&self, db: &dyn CodegenDatabase) -> &'static str {
@@ -98,9 +160,28 @@ impl Assembly {
 }
 
 pub(crate) fn build_assembly(db: &dyn CodegenDatabase, lib: hir::Lib) -> Arc<Assembly> {
-    let object = crate::ModuleCtx::with_mcx(db, |mcx| mcx.build(lib));
     let mut file = NamedTempFile::new().unwrap();
 
+    if let target_lexicon::OperatingSystem::Wasi = db.target_triple().operating_system {
+        let errors = match crate::wasm::build(db, lib) {
+            | Ok(module) => {
+                file.write(&module).unwrap();
+                Vec::new()
+            },
+            | Err(errors) => errors.iter().map(ToString::to_string).collect(),
+        };
+
+        return Arc::new(Assembly {
+            lib,
+            file,
+            clif: String::new(),
+            asm: String::new(),
+            errors,
+        });
+    }
+
+    let (object, clif, asm) = crate::ModuleCtx::with_mcx(db, lib, |mcx| mcx.build(lib));
+
     file.write(object.emit().unwrap().as_slice()).unwrap();
 
     // let mut linker = crate::linker::create();
@@ -130,5 +211,11 @@ pub(crate) fn build_assembly(db: &dyn CodegenDatabase, lib: hir::Lib) -> Arc<Ass
     //
     // linker.run();
 
-    Arc::new(Assembly { lib, file })
+    Arc::new(Assembly {
+        lib,
+        file,
+        clif,
+        asm,
+        errors: Vec::new(),
+    })
 }
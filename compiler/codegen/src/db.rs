@@ -5,6 +5,28 @@ use std::sync::Arc;
 
 #[salsa::query_group(CodegenDatabaseStorage)]
 pub trait CodegenDatabase: MirDatabase + Upcast<dyn MirDatabase> {
+    #[salsa::input]
+    fn codegen_opts(&self) -> Arc<CodegenOpts>;
+
     #[salsa::invoke(crate::assembly::build_assembly)]
     fn lib_assembly(&self, lib: hir::Lib) -> Arc<Assembly>;
 }
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct CodegenOpts {
+    pub debug_info: bool,
+    pub verifier: bool,
+    /// The CPU to generate code for, or `native` for the host CPU.
+    pub target_cpu: Option<String>,
+    pub target_features: Vec<String>,
+    /// Whether to keep the Cranelift IR of every function in the assembly.
+    pub emit_clif: bool,
+    /// Whether to keep the disassembly of every function in the assembly.
+    pub emit_asm: bool,
+    /// The linker command to use, see [`crate::linker::create`].
+    pub linker: Option<Vec<String>>,
+    /// Extra arguments passed to the linker.
+    pub link_args: Vec<String>,
+    /// Whether executables are linked statically, including the C library.
+    pub static_link: bool,
+}
@@ -0,0 +1,608 @@
+use crate::place::PlaceKind;
+use crate::ptr::{Pointer, PointerKind};
+use crate::*;
+use base_db::input::{FileId, LineCol};
+use clif::Module as _;
+use cranelift::codegen::entity::EntityRef as _;
+use gimli::write::{
+    Address, AttributeValue, DwarfUnit, EndianVec, Expression, LineProgram, LineString, Location, LocationList, Range,
+    RangeList, Sections, UnitEntryId, Writer,
+};
+use gimli::{Encoding, Format, LineEncoding, Register, RunTimeEndian, SectionId};
+use mir::layout::{Abi, Integer, Primitive};
+use std::convert::TryFrom;
+use syntax::TextSize;
+
+pub(crate) struct DebugContext {
+    endian: RunTimeEndian,
+    dwarf: DwarfUnit,
+    unit_ranges: RangeList,
+    files: FxHashMap<FileId, gimli::write::FileId>,
+    types: FxHashMap<Arc<mir::ty::Type>, UnitEntryId>,
+}
+
+/// The source locations and variables of the function that is being lowered.
+pub(crate) struct FunctionDebugContext {
+    source_map: Arc<hir::BodySourceMap>,
+    decl: Option<(FileId, LineCol)>,
+    /// The locations the `SourceLoc`s of the function index into.
+    locs: Vec<(FileId, LineCol)>,
+    loc_ids: FxHashMap<hir::ExprId, clif::ir::SourceLoc>,
+    vars: Vec<DebugVar>,
+}
+
+struct DebugVar {
+    name: hir::Name,
+    pat: hir::PatId,
+    ty: Arc<mir::ty::Type>,
+    decl: Option<(FileId, LineCol)>,
+    var: Option<clif::Variable>,
+    /// The stack slot and offset into it of a variable that lives on the stack.
+    stack: Option<(clif::ir::StackSlot, i64)>,
+    is_arg: bool,
+}
+
+impl DebugContext {
+    pub(crate) fn new(db: &dyn db::CodegenDatabase, isa: &dyn clif::isa::TargetIsa, lib: hir::Lib) -> Self {
+        let endian = match isa.triple().endianness().unwrap() {
+            | target_lexicon::Endianness::Little => RunTimeEndian::Little,
+            | target_lexicon::Endianness::Big => RunTimeEndian::Big,
+        };
+
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: isa.pointer_bytes(),
+        };
+
+        let root_file = lib.root_file(db.upcast());
+        let source_root = db.source_root(db.file_source_root(root_file));
+        let comp_dir = match &source_root.dir {
+            | Some(dir) => dir.clone(),
+            | None => std::env::current_dir().unwrap_or_default(),
+        };
+
+        let comp_dir = comp_dir.to_string_lossy().into_owned().into_bytes();
+        let name = source_root.relative_path(root_file).as_str().as_bytes().to_vec();
+        let mut dwarf = DwarfUnit::new(encoding);
+
+        dwarf.unit.line_program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(comp_dir.clone()),
+            LineString::String(name.clone()),
+            None,
+        );
+
+        let producer = dwarf.strings.add(format!("shadow {}", env!("CARGO_PKG_VERSION")));
+        let name = dwarf.strings.add(name);
+        let comp_dir = dwarf.strings.add(comp_dir);
+        let root = dwarf.unit.root();
+        let root = dwarf.unit.get_mut(root);
+
+        root.set(gimli::DW_AT_producer, AttributeValue::StringRef(producer));
+        root.set(gimli::DW_AT_name, AttributeValue::StringRef(name));
+        root.set(gimli::DW_AT_comp_dir, AttributeValue::StringRef(comp_dir));
+        root.set(gimli::DW_AT_low_pc, AttributeValue::Address(Address::Constant(0)));
+
+        DebugContext {
+            endian,
+            dwarf,
+            unit_ranges: RangeList(Vec::new()),
+            files: FxHashMap::default(),
+            types: FxHashMap::default(),
+        }
+    }
+
+    fn file_id(&mut self, db: &dyn db::CodegenDatabase, file: FileId) -> gimli::write::FileId {
+        if let Some(&id) = self.files.get(&file) {
+            return id;
+        }
+
+        let source_root = db.source_root(db.file_source_root(file));
+        let line_program = &mut self.dwarf.unit.line_program;
+        let dir = match &source_root.dir {
+            | Some(dir) => {
+                let dir = dir.to_string_lossy().into_owned().into_bytes();
+
+                line_program.add_directory(LineString::String(dir))
+            },
+            | None => line_program.default_directory(),
+        };
+
+        let name = source_root.relative_path(file).as_str().as_bytes().to_vec();
+        let id = line_program.add_file(LineString::String(name), dir, None);
+
+        self.files.insert(file, id);
+        id
+    }
+
+    fn type_id(&mut self, db: &dyn db::CodegenDatabase, ty: &Arc<mir::ty::Type>) -> UnitEntryId {
+        if let Some(&id) = self.types.get(ty) {
+            return id;
+        }
+
+        let layout = db.layout_of(ty.clone());
+        let (tag, encoding) = match &layout.abi {
+            | Abi::Scalar(scalar) => match scalar.value {
+                | Primitive::Int(Integer::I8, false) if scalar.valid_range == (0..=1) => {
+                    (gimli::DW_TAG_base_type, Some(gimli::DW_ATE_boolean))
+                },
+                | Primitive::Int(_, true) => (gimli::DW_TAG_base_type, Some(gimli::DW_ATE_signed)),
+                | Primitive::Int(_, false) => (gimli::DW_TAG_base_type, Some(gimli::DW_ATE_unsigned)),
+                | Primitive::F32 | Primitive::F64 => (gimli::DW_TAG_base_type, Some(gimli::DW_ATE_float)),
+                | Primitive::Pointer => (gimli::DW_TAG_base_type, Some(gimli::DW_ATE_address)),
+            },
+            | _ => (gimli::DW_TAG_structure_type, None),
+        };
+
+        let root = self.dwarf.unit.root();
+        let id = self.dwarf.unit.add(root, tag);
+        let name = self.dwarf.strings.add(ty.to_string());
+        let entry = self.dwarf.unit.get_mut(id);
+
+        entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name));
+        entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(layout.size.bytes()));
+
+        if let Some(encoding) = encoding {
+            entry.set(gimli::DW_AT_encoding, AttributeValue::Encoding(encoding));
+        }
+
+        self.types.insert(ty.clone(), id);
+        id
+    }
+
+    fn set_decl(&mut self, db: &dyn db::CodegenDatabase, id: UnitEntryId, decl: Option<(FileId, LineCol)>) {
+        if let Some((file, loc)) = decl {
+            let file = self.file_id(db, file);
+            let entry = self.dwarf.unit.get_mut(id);
+
+            entry.set(gimli::DW_AT_decl_file, AttributeValue::FileIndex(Some(file)));
+            entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(u64::from(loc.line) + 1));
+        }
+    }
+
+    pub(crate) fn emit(mut self, product: &mut cranelift_object::ObjectProduct) {
+        let ranges = self.dwarf.unit.ranges.add(self.unit_ranges);
+        let root = self.dwarf.unit.root();
+
+        self.dwarf
+            .unit
+            .get_mut(root)
+            .set(gimli::DW_AT_ranges, AttributeValue::RangeListRef(ranges));
+
+        let mut sections = Sections::new(WriterRelocate::new(self.endian));
+        let mut section_map = FxHashMap::default();
+
+        self.dwarf.write(&mut sections).unwrap();
+
+        let _: gimli::write::Result<()> = sections.for_each_mut(|id, section| {
+            if !section.writer.slice().is_empty() {
+                let section_id = add_debug_section(product, id, section.writer.take());
+
+                section_map.insert(id, section_id);
+            }
+
+            Ok(())
+        });
+
+        let _: gimli::write::Result<()> = sections.for_each(|id, section| {
+            if let Some(&section_id) = section_map.get(&id) {
+                for reloc in &section.relocs {
+                    add_debug_reloc(product, &section_map, section_id, reloc);
+                }
+            }
+
+            Ok(())
+        });
+    }
+}
+
+impl FunctionDebugContext {
+    pub(crate) fn new(db: &dyn db::CodegenDatabase, def: hir::id::DefWithBodyId) -> Self {
+        let (body, source_map) = db.body_source_map(def);
+        let decl = source_map
+            .expr_syntax(body.body_expr())
+            .ok()
+            .map(|src| line_col(db, src.file_id, src.value.syntax_node_ptr().range().start()));
+
+        FunctionDebugContext {
+            source_map,
+            decl,
+            locs: Vec::new(),
+            loc_ids: FxHashMap::default(),
+            vars: Vec::new(),
+        }
+    }
+
+    fn source_loc(&mut self, db: &dyn db::CodegenDatabase, expr: hir::ExprId) -> clif::ir::SourceLoc {
+        if let Some(&loc) = self.loc_ids.get(&expr) {
+            return loc;
+        }
+
+        let loc = match self.source_map.expr_syntax(expr) {
+            | Ok(src) => {
+                let start = src.value.syntax_node_ptr().range().start();
+
+                self.locs.push(line_col(db, src.file_id, start));
+                clif::ir::SourceLoc::new(self.locs.len() as u32 - 1)
+            },
+            | Err(_) => clif::ir::SourceLoc::default(),
+        };
+
+        self.loc_ids.insert(expr, loc);
+        loc
+    }
+}
+
+impl<M: clif::Module> FunctionCtx<'_, '_, M> {
+    pub(crate) fn set_source(&mut self, source: Option<hir::ExprId>) {
+        let db = self.mcx.db;
+
+        if let Some(debug) = &mut self.debug {
+            let loc = match source {
+                | Some(expr) => debug.source_loc(db, expr),
+                | None => clif::ir::SourceLoc::default(),
+            };
+
+            self.bcx.set_srcloc(loc);
+        }
+    }
+
+    /// Collects the user variables that are stored directly in a local.
+    pub(crate) fn collect_debug_vars(&mut self) {
+        let db = self.mcx.db;
+
+        if let Some(debug) = &mut self.debug {
+            for info in &self.body.var_debug_info {
+                if !info.place.elems.is_empty() || debug.vars.iter().any(|v| v.pat == info.pat) {
+                    continue;
+                }
+
+                let local = &self.body.locals[info.place.local];
+                let decl = debug
+                    .source_map
+                    .pat_syntax(info.pat)
+                    .ok()
+                    .map(|src| line_col(db, src.file_id, src.value.syntax_node_ptr().range().start()));
+
+                let (var, stack) = match self.locals[info.place.local].kind {
+                    | PlaceKind::Var(var) => (Some(var), None),
+                    | PlaceKind::Addr(
+                        Pointer {
+                            kind: PointerKind::Stack(slot),
+                            offset,
+                        },
+                        None,
+                    ) => (None, Some((slot, i64::from(offset)))),
+                    | _ => (None, None),
+                };
+
+                debug.vars.push(DebugVar {
+                    name: info.name.clone(),
+                    pat: info.pat,
+                    ty: local.ty.clone(),
+                    decl,
+                    var,
+                    stack,
+                    is_arg: local.kind == ir::LocalKind::Arg,
+                });
+            }
+        }
+    }
+}
+
+impl<M: clif::Module> ModuleCtx<'_, M> {
+    /// Adds the line table, subprogram and variables of a function that was just defined.
+    pub(crate) fn define_debug_function(
+        &mut self,
+        def: hir::id::DefWithBodyId,
+        id: clif::FuncId,
+        fdx: FunctionDebugContext,
+    ) {
+        let db = self.db;
+        let debug = match &mut self.debug_context {
+            | Some(debug) => debug,
+            | None => return,
+        };
+
+        let result = match &self.ctx.mach_compile_result {
+            | Some(result) => result,
+            | None => return,
+        };
+
+        let isa = self.module.isa();
+        let symbol = id.as_u32() as usize;
+        let func_end = result.buffer.data().len() as u64;
+        let start = Address::Symbol { symbol, addend: 0 };
+        let name = match def {
+            | hir::id::DefWithBodyId::FuncId(id) => hir::Func::from(id).name(db.upcast()),
+            | hir::id::DefWithBodyId::StaticId(id) => hir::Static::from(id).name(db.upcast()),
+            | hir::id::DefWithBodyId::ConstId(id) => hir::Const::from(id).name(db.upcast()),
+        };
+
+        let link_name = self.module.declarations().get_function_decl(id).name.clone();
+
+        debug.unit_ranges.0.push(Range::StartLength {
+            begin: start,
+            length: func_end,
+        });
+
+        debug.dwarf.unit.line_program.begin_sequence(Some(start));
+
+        for srcloc in result.buffer.get_srclocs_sorted() {
+            if srcloc.loc.is_default() {
+                continue;
+            }
+
+            let (file, loc) = fdx.locs[srcloc.loc.bits() as usize];
+            let file = debug.file_id(db, file);
+            let line_program = &mut debug.dwarf.unit.line_program;
+
+            line_program.row().address_offset = u64::from(srcloc.start);
+            line_program.row().file = file;
+            line_program.row().line = u64::from(loc.line) + 1;
+            line_program.row().column = u64::from(loc.col) + 1;
+            line_program.generate_row();
+        }
+
+        debug.dwarf.unit.line_program.end_sequence(func_end);
+
+        let root = debug.dwarf.unit.root();
+        let entry_id = debug.dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+        let name = debug.dwarf.strings.add(name.to_string());
+        let link_name = debug.dwarf.strings.add(link_name);
+        let entry = debug.dwarf.unit.get_mut(entry_id);
+
+        entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name));
+        entry.set(gimli::DW_AT_linkage_name, AttributeValue::StringRef(link_name));
+        entry.set(gimli::DW_AT_low_pc, AttributeValue::Address(start));
+        entry.set(gimli::DW_AT_high_pc, AttributeValue::Udata(func_end));
+
+        // stack slots are addressed relative to the stack pointer after the prologue
+        if let Some(sp) = stack_pointer(isa) {
+            let mut frame_base = Expression::new();
+
+            frame_base.op_breg(sp, 0);
+            entry.set(gimli::DW_AT_frame_base, AttributeValue::Exprloc(frame_base));
+        }
+
+        debug.set_decl(db, entry_id, fdx.decl);
+
+        let value_labels = self.ctx.build_value_labels_ranges(isa).unwrap_or_default();
+
+        for var in &fdx.vars {
+            let tag = if var.is_arg {
+                gimli::DW_TAG_formal_parameter
+            } else {
+                gimli::DW_TAG_variable
+            };
+
+            let ty = debug.type_id(db, &var.ty);
+            let var_id = debug.dwarf.unit.add(entry_id, tag);
+            let name = debug.dwarf.strings.add(var.name.to_string());
+            let entry = debug.dwarf.unit.get_mut(var_id);
+
+            entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name));
+            entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(ty));
+            debug.set_decl(db, var_id, var.decl);
+
+            if let Some((slot, offset)) = var.stack {
+                if stack_pointer(isa).is_some() {
+                    let mut expr = Expression::new();
+
+                    expr.op_fbreg(i64::from(result.stackslot_offsets[slot]) + offset);
+                    debug
+                        .dwarf
+                        .unit
+                        .get_mut(var_id)
+                        .set(gimli::DW_AT_location, AttributeValue::Exprloc(expr));
+                }
+
+                continue;
+            }
+
+            let ranges = var
+                .var
+                .and_then(|v| value_labels.get(&clif::ir::ValueLabel::new(v.index())));
+
+            let ranges = match ranges {
+                | Some(ranges) => ranges,
+                | None => continue,
+            };
+
+            let locations = ranges
+                .iter()
+                .filter_map(|range| {
+                    Some(Location::StartEnd {
+                        begin: Address::Symbol {
+                            symbol,
+                            addend: i64::from(range.start),
+                        },
+                        end: Address::Symbol {
+                            symbol,
+                            addend: i64::from(range.end),
+                        },
+                        data: translate_loc(isa, range.loc)?,
+                    })
+                })
+                .collect();
+
+            let locations = debug.dwarf.unit.locations.add(LocationList(locations));
+
+            debug
+                .dwarf
+                .unit
+                .get_mut(var_id)
+                .set(gimli::DW_AT_location, AttributeValue::LocationListRef(locations));
+        }
+    }
+}
+
+fn line_col(db: &dyn db::CodegenDatabase, file: FileId, offset: TextSize) -> (FileId, LineCol) {
+    (file, db.line_index(file).line_col(offset))
+}
+
+fn translate_loc(isa: &dyn clif::isa::TargetIsa, loc: clif::ir::LabelValueLoc) -> Option<Expression> {
+    let mut expr = Expression::new();
+
+    match loc {
+        | clif::ir::LabelValueLoc::ValueLoc(clif::ir::ValueLoc::Reg(reg)) => {
+            expr.op_reg(Register(isa.map_dwarf_register(reg).ok()?));
+        },
+        | clif::ir::LabelValueLoc::Reg(reg) => {
+            expr.op_reg(Register(isa.map_regalloc_reg_to_dwarf(reg).ok()?));
+        },
+        | clif::ir::LabelValueLoc::SPOffset(offset) => {
+            expr.op_breg(stack_pointer(isa)?, offset);
+        },
+        | _ => return None,
+    }
+
+    Some(expr)
+}
+
+fn stack_pointer(isa: &dyn clif::isa::TargetIsa) -> Option<Register> {
+    match isa.triple().architecture {
+        | target_lexicon::Architecture::X86_64 => Some(gimli::X86_64::RSP),
+        | target_lexicon::Architecture::Aarch64(_) => Some(gimli::AArch64::SP),
+        | _ => None,
+    }
+}
+
+fn add_debug_section(
+    product: &mut cranelift_object::ObjectProduct,
+    id: SectionId,
+    data: Vec<u8>,
+) -> (object::write::SectionId, object::write::SymbolId) {
+    let name = if product.object.format() == object::BinaryFormat::MachO {
+        id.name().replace('.', "__")
+    } else {
+        id.name().to_string()
+    };
+
+    let segment = product
+        .object
+        .segment_name(object::write::StandardSegment::Debug)
+        .to_vec();
+
+    let section_id = product
+        .object
+        .add_section(segment, name.into_bytes(), object::SectionKind::Debug);
+
+    product.object.section_mut(section_id).set_data(data, 1);
+
+    (section_id, product.object.section_symbol(section_id))
+}
+
+fn add_debug_reloc(
+    product: &mut cranelift_object::ObjectProduct,
+    section_map: &FxHashMap<SectionId, (object::write::SectionId, object::write::SymbolId)>,
+    (section, _): (object::write::SectionId, object::write::SymbolId),
+    reloc: &DebugReloc,
+) {
+    let symbol = match reloc.name {
+        | DebugRelocName::Section(id) => section_map[&id].1,
+        | DebugRelocName::Symbol(id) => product.function_symbol(clif::FuncId::from_u32(id as u32)),
+    };
+
+    product
+        .object
+        .add_relocation(section, object::write::Relocation {
+            offset: u64::from(reloc.offset),
+            size: reloc.size * 8,
+            kind: object::RelocationKind::Absolute,
+            encoding: object::RelocationEncoding::Generic,
+            symbol,
+            addend: reloc.addend,
+        })
+        .unwrap();
+}
+
+#[derive(Clone)]
+struct DebugReloc {
+    offset: u32,
+    size: u8,
+    name: DebugRelocName,
+    addend: i64,
+}
+
+#[derive(Clone, Copy)]
+enum DebugRelocName {
+    Section(SectionId),
+    Symbol(usize),
+}
+
+/// A gimli writer that records the relocations to other sections and function symbols.
+#[derive(Clone)]
+struct WriterRelocate {
+    relocs: Vec<DebugReloc>,
+    writer: EndianVec<RunTimeEndian>,
+}
+
+impl WriterRelocate {
+    fn new(endian: RunTimeEndian) -> Self {
+        WriterRelocate {
+            relocs: Vec::new(),
+            writer: EndianVec::new(endian),
+        }
+    }
+}
+
+impl Writer for WriterRelocate {
+    type Endian = RunTimeEndian;
+
+    fn endian(&self) -> Self::Endian {
+        self.writer.endian()
+    }
+
+    fn len(&self) -> usize {
+        self.writer.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.writer.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.writer.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> gimli::write::Result<()> {
+        match address {
+            | Address::Constant(val) => self.write_udata(val, size),
+            | Address::Symbol { symbol, addend } => {
+                self.relocs.push(DebugReloc {
+                    offset: u32::try_from(self.len()).unwrap(),
+                    size,
+                    name: DebugRelocName::Symbol(symbol),
+                    addend,
+                });
+
+                self.write_udata(0, size)
+            },
+        }
+    }
+
+    fn write_offset(&mut self, val: usize, section: SectionId, size: u8) -> gimli::write::Result<()> {
+        self.relocs.push(DebugReloc {
+            offset: u32::try_from(self.len()).unwrap(),
+            size,
+            name: DebugRelocName::Section(section),
+            addend: val as i64,
+        });
+
+        self.write_udata(0, size)
+    }
+
+    fn write_offset_at(&mut self, offset: usize, val: usize, section: SectionId, size: u8) -> gimli::write::Result<()> {
+        self.relocs.push(DebugReloc {
+            offset: u32::try_from(offset).unwrap(),
+            size,
+            name: DebugRelocName::Section(section),
+            addend: val as i64,
+        });
+
+        self.write_udata_at(offset, 0, size)
+    }
+}
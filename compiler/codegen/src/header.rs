@@ -0,0 +1,431 @@
+//! C header generation for the functions a library exports with `@export`.
+
+use crate::db::CodegenDatabase;
+use hir::display::HirDisplay;
+use hir::id::{CtorId, TypeCtorId};
+use hir::ty::{Ty, TyKind};
+use mir::layout::{Abi, Integer, Primitive};
+use rustc_hash::FxHashMap;
+use std::fmt::Write as _;
+
+pub struct Header {
+    pub text: String,
+    pub errors: Vec<HeaderError>,
+}
+
+/// An exported function whose signature has no C equivalent.
+pub struct HeaderError {
+    pub func: hir::Name,
+    pub ty: String,
+    pub reason: &'static str,
+}
+
+struct HeaderCtx<'a> {
+    db: &'a dyn CodegenDatabase,
+    lib: hir::Lib,
+    aliases: FxHashMap<Ty, hir::Name>,
+    types: FxHashMap<Ty, String>,
+    decls: String,
+    anon: u32,
+}
+
+/// Returns `None` when the library does not export any functions.
+pub fn generate(db: &dyn CodegenDatabase, lib: hir::Lib) -> Option<Header> {
+    let funcs = lib
+        .modules(db.upcast())
+        .into_iter()
+        .flat_map(|m| m.declarations(db.upcast()))
+        .filter_map(|def| match def {
+            | hir::ModuleDef::Func(f) if f.export_name(db.upcast()).is_some() && !f.is_foreign(db.upcast()) => Some(f),
+            | _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if funcs.is_empty() {
+        return None;
+    }
+
+    let mut hcx = HeaderCtx {
+        db,
+        lib,
+        aliases: FxHashMap::default(),
+        types: FxHashMap::default(),
+        decls: String::new(),
+        anon: 0,
+    };
+
+    for lib in hir::Lib::all(db.upcast()) {
+        for module in lib.modules(db.upcast()) {
+            for def in module.declarations(db.upcast()) {
+                if let hir::ModuleDef::TypeAlias(alias) = def {
+                    hcx.aliases
+                        .entry(alias.ty(db.upcast()))
+                        .or_insert_with(|| alias.name(db.upcast()));
+                }
+            }
+        }
+    }
+
+    let mut protos = String::new();
+    let mut errors = Vec::new();
+
+    for func in funcs {
+        let decls_len = hcx.decls.len();
+        let types = hcx.types.clone();
+
+        match hcx.func_decl(func) {
+            | Ok(proto) => writeln!(protos, "{};", proto).unwrap(),
+            | Err((ty, reason)) => {
+                // drop the declarations of a signature that could not be completed
+                hcx.decls.truncate(decls_len);
+                hcx.types = types;
+                errors.push(HeaderError {
+                    func: func.name(db.upcast()),
+                    ty: ty.display(db.upcast()).to_string(),
+                    reason,
+                });
+            },
+        }
+    }
+
+    let guard = format!(
+        "SHADE_{}_H",
+        sanitize(&lib.name(db.upcast()).to_string()).to_uppercase()
+    );
+    let mut text = String::new();
+
+    writeln!(text, "#ifndef {}", guard).unwrap();
+    writeln!(text, "#define {}\n", guard).unwrap();
+    writeln!(text, "#include <stdbool.h>").unwrap();
+    writeln!(text, "#include <stdint.h>\n").unwrap();
+    writeln!(text, "#ifdef __cplusplus\nextern \"C\" {{\n#endif\n").unwrap();
+    text.push_str(&hcx.decls);
+    text.push_str(&protos);
+    writeln!(text, "\n#ifdef __cplusplus\n}}\n#endif\n").unwrap();
+    writeln!(text, "#endif // {}", guard).unwrap();
+
+    Some(Header { text, errors })
+}
+
+type Result<T> = std::result::Result<T, (Ty, &'static str)>;
+
+impl HeaderCtx<'_> {
+    fn func_decl(&mut self, func: hir::Func) -> Result<String> {
+        let db = self.db;
+        let def: hir::id::FuncId = func.into();
+        let def: hir::id::DefWithBodyId = def.into();
+        let bodies = db.body_mir(def);
+        let arity = bodies[bodies.main_id(def).local_id].args().len();
+        let mut ty = func.ty(db.upcast());
+        let fn_type = self.lang_ctor("fn-type").ok_or((ty, "the `fn-type` lang item is missing"))?;
+        let mut params = Vec::with_capacity(arity);
+
+        if let TyKind::ForAll(_, _) | TyKind::Ctnt(_, _) = ty.lookup(db.upcast()) {
+            return Err((ty, "polymorphic functions cannot be exported"));
+        }
+
+        for _ in 0..arity {
+            match ty.match_ctor(db.upcast(), fn_type) {
+                | Some([param, ret]) => {
+                    if !self.is_unit(param) {
+                        params.push(self.c_type(param)?);
+                    }
+
+                    ty = ret;
+                },
+                | None => break,
+            }
+        }
+
+        let ret = if self.is_unit(ty) {
+            String::from("void")
+        } else {
+            self.c_type(ty)?
+        };
+
+        let params = if params.is_empty() {
+            String::from("void")
+        } else {
+            params.join(", ")
+        };
+
+        Ok(format!("{} {}({})", ret, func.link_name(db.upcast()), params))
+    }
+
+    fn c_type(&mut self, ty: Ty) -> Result<String> {
+        if let Some(name) = self.types.get(&ty) {
+            return Ok(name.clone());
+        }
+
+        let db = self.db;
+        let mut base = ty;
+        let mut args = Vec::new();
+
+        while let TyKind::App(a, b) = base.lookup(db.upcast()) {
+            args.push(b);
+            base = a;
+        }
+
+        args.reverse();
+
+        let name = match base.lookup(db.upcast()) {
+            | TyKind::Ctor(id) => self.ctor_type(ty, id, &args)?,
+            | TyKind::Tuple(tys) if !tys.is_empty() => {
+                let name = self.struct_name(ty, "Tuple");
+                let fields = tys.iter().enumerate().map(|(i, &t)| (format!("f{}", i), t)).collect();
+
+                self.declare_struct(ty, name, fields)?
+            },
+            | _ => return Err((ty, "type has no C representation")),
+        };
+
+        self.types.insert(ty, name.clone());
+
+        Ok(name)
+    }
+
+    fn ctor_type(&mut self, ty: Ty, id: TypeCtorId, args: &[Ty]) -> Result<String> {
+        let db = self.db;
+        let attrs = db.attrs(id.into());
+
+        if let Some(repr) = attrs.by_key("repr").attrs().next().and_then(|a| a.group()) {
+            if let Some(scalar) = repr.field("scalar").and_then(|s| s.string()) {
+                return match scalar_type(scalar) {
+                    | Some(scalar) => Ok(String::from(scalar)),
+                    | None => Err((ty, "unknown scalar representation")),
+                };
+            }
+
+            if let Some(elem) = repr.field("ptr").and_then(|p| p.group()).and_then(|p| p.field("elem")) {
+                return match elem.int().and_then(|idx| args.get(idx as usize)) {
+                    | Some(&elem) => self.pointer_to(elem),
+                    | None => Err((ty, "unknown pointer element")),
+                };
+            }
+
+            if Some(id) == self.lang_ctor("str-type") {
+                return self.declare_raw_struct(
+                    ty,
+                    String::from("ShadeStr"),
+                    vec![
+                        (String::from("ptr"), String::from("const uint8_t *")),
+                        (String::from("len"), String::from("uintptr_t")),
+                    ],
+                );
+            }
+
+            if Some(id) == self.lang_ctor("slice-type") {
+                let elem = self.c_type(args[0])?;
+                let name = format!("Slice_{}", sanitize(&elem));
+
+                return self.declare_raw_struct(
+                    ty,
+                    name,
+                    vec![
+                        (String::from("ptr"), format!("{} *", elem)),
+                        (String::from("len"), String::from("uintptr_t")),
+                    ],
+                );
+            }
+
+            if Some(id) == self.lang_ctor("record-type") {
+                if let TyKind::Row(fields, None) = args[0].lookup(db.upcast()) {
+                    let name = self.struct_name(ty, "Record");
+                    let fields = fields.iter().map(|f| (f.name.to_string(), f.ty)).collect();
+
+                    return self.declare_struct(ty, name, fields);
+                }
+            }
+
+            return Err((ty, "type has no C representation"));
+        }
+
+        if Some(id) == self.lang_ctor("bool-type") {
+            return Ok(String::from("bool"));
+        }
+
+        let data = db.type_ctor_data(id);
+        let mut name = sanitize(&data.name.to_string());
+
+        for &arg in args {
+            let arg = self.c_type(arg)?;
+
+            write!(name, "_{}", sanitize(&arg)).unwrap();
+        }
+
+        if data.ctors.len() == 1 {
+            let (local_id, ctor) = data.ctors.iter().next().unwrap();
+            let lower = db.ctor_ty(CtorId { local_id, parent: id });
+            let fields = ctor
+                .types
+                .iter()
+                .enumerate()
+                .map(|(i, &t)| {
+                    let ty = args.iter().fold(lower.types[t], |r, a| r.replace_var(db.upcast(), *a));
+
+                    (format!("f{}", i), ty)
+                })
+                .collect();
+
+            self.declare_struct(ty, name, fields)
+        } else if !data.ctors.is_empty() && data.ctors.iter().all(|(_, c)| c.types.is_empty()) {
+            let layout = db.layout_of(db.mir_type(ty));
+            let tag = match &layout.abi {
+                | Abi::Scalar(s) => primitive_type(s.value),
+                | _ => "uint8_t",
+            };
+
+            writeln!(self.decls, "typedef {} {};\n", tag, name).unwrap();
+            writeln!(self.decls, "enum {{").unwrap();
+
+            for (i, (_, ctor)) in data.ctors.iter().enumerate() {
+                writeln!(self.decls, "    {}_{} = {},", name, sanitize(&ctor.name.to_string()), i).unwrap();
+            }
+
+            writeln!(self.decls, "}};\n").unwrap();
+
+            Ok(name)
+        } else {
+            Err((ty, "only records, enums and pointers can be exported"))
+        }
+    }
+
+    fn pointer_to(&mut self, elem: Ty) -> Result<String> {
+        if self.is_unit(elem) {
+            Ok(String::from("void *"))
+        } else {
+            let elem = self.c_type(elem)?;
+
+            if elem.ends_with('*') {
+                Ok(format!("{}*", elem))
+            } else {
+                Ok(format!("{} *", elem))
+            }
+        }
+    }
+
+    fn declare_struct(&mut self, ty: Ty, name: String, fields: Vec<(String, Ty)>) -> Result<String> {
+        let array_type = self.lang_ctor("array-type").ok_or((ty, "the `array-type` lang item is missing"))?;
+
+        // Recursive types can refer to themselves through a pointer.
+        writeln!(self.decls, "typedef struct {0} {0};\n", name).unwrap();
+        self.types.insert(ty, name.clone());
+
+        let mut decls = Vec::with_capacity(fields.len());
+
+        for (field, t) in fields {
+            if self.is_unit(t) {
+                continue;
+            }
+
+            match t.match_ctor(self.db.upcast(), array_type) {
+                | Some([elem, len]) => match len.lookup(self.db.upcast()) {
+                    | TyKind::Figure(len) => decls.push(format!("{} {}[{}]", self.c_type(elem)?, field, len)),
+                    | _ => return Err((t, "array length is not known")),
+                },
+                | None => decls.push(format!("{} {}", self.c_type(t)?, field)),
+            }
+        }
+
+        if decls.is_empty() {
+            return Err((ty, "types without fields have no C representation"));
+        }
+
+        writeln!(self.decls, "struct {} {{", name).unwrap();
+
+        for decl in decls {
+            writeln!(self.decls, "    {};", decl).unwrap();
+        }
+
+        writeln!(self.decls, "}};\n").unwrap();
+
+        Ok(name)
+    }
+
+    fn declare_raw_struct(&mut self, ty: Ty, name: String, fields: Vec<(String, String)>) -> Result<String> {
+        if !self.types.values().any(|n| *n == name) {
+            writeln!(self.decls, "typedef struct {0} {{", name).unwrap();
+
+            for (field, t) in fields {
+                writeln!(
+                    self.decls,
+                    "    {}{}{};",
+                    t,
+                    if t.ends_with('*') { "" } else { " " },
+                    field
+                )
+                .unwrap();
+            }
+
+            writeln!(self.decls, "}} {};\n", name).unwrap();
+        }
+
+        self.types.insert(ty, name.clone());
+
+        Ok(name)
+    }
+
+    fn struct_name(&mut self, ty: Ty, prefix: &str) -> String {
+        if let Some(name) = self.aliases.get(&ty) {
+            sanitize(&name.to_string())
+        } else {
+            self.anon += 1;
+            format!("{}{}", prefix, self.anon)
+        }
+    }
+
+    fn is_unit(&self, ty: Ty) -> bool {
+        matches!(ty.lookup(self.db.upcast()), TyKind::Tuple(ts) if ts.is_empty())
+    }
+
+    fn lang_ctor(&self, name: &str) -> Option<TypeCtorId> {
+        self.db.lang_item(self.lib.into(), name.into())?.as_type_ctor()
+    }
+}
+
+fn scalar_type(repr: &str) -> Option<&'static str> {
+    Some(match repr {
+        | "u8" => "uint8_t",
+        | "u16" => "uint16_t",
+        | "u32" => "uint32_t",
+        | "u64" => "uint64_t",
+        | "u128" => "unsigned __int128",
+        | "i8" => "int8_t",
+        | "i16" => "int16_t",
+        | "i32" => "int32_t",
+        | "i64" => "int64_t",
+        | "i128" => "__int128",
+        | "f32" => "float",
+        | "f64" => "double",
+        | "ptr_sized_uint" => "uintptr_t",
+        | "ptr_sized_int" => "intptr_t",
+        | _ => return None,
+    })
+}
+
+fn primitive_type(prim: Primitive) -> &'static str {
+    match prim {
+        | Primitive::Int(Integer::I8, false) => "uint8_t",
+        | Primitive::Int(Integer::I16, false) => "uint16_t",
+        | Primitive::Int(Integer::I32, false) => "uint32_t",
+        | Primitive::Int(Integer::I64, false) => "uint64_t",
+        | Primitive::Int(Integer::I128, false) => "unsigned __int128",
+        | Primitive::Int(Integer::I8, true) => "int8_t",
+        | Primitive::Int(Integer::I16, true) => "int16_t",
+        | Primitive::Int(Integer::I32, true) => "int32_t",
+        | Primitive::Int(Integer::I64, true) => "int64_t",
+        | Primitive::Int(Integer::I128, true) => "__int128",
+        | Primitive::F32 => "float",
+        | Primitive::F64 => "double",
+        | Primitive::Pointer => "void *",
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.trim()
+        .replace('*', "ptr")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .replace("__", "_")
+}
@@ -1,7 +1,8 @@
 use crate::*;
 use clif::InstBuilder;
+use mir::intrinsic::{BinOp, CmpOp, Intrinsic, ScalarType, UnOp};
 
-impl FunctionCtx<'_, '_> {
+impl<M: clif::Module> FunctionCtx<'_, '_, M> {
     pub fn lower_intrinsic(&mut self, place: PlaceRef, name: &str, args: Vec<ValueRef>) -> Option<()> {
         let mut args = args.into_iter();
         let layout = place.layout.clone();
@@ -28,61 +29,305 @@ impl FunctionCtx<'_, '_> {
 
                 self.call_free(ptr);
             },
-            | "add_i32" => {
-                let lhs = args.next()?.load_scalar(self);
-                let rhs = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().iadd(lhs, rhs);
+            | "rc_new" => {
+                let val = args.next()?;
+                let box_layout = layout.elem(self.db.upcast()).unwrap();
+                let ptr_type = self.module.target_config().pointer_type();
+                let size = self.bcx.ins().iconst(ptr_type, box_layout.size.bytes() as i64);
+                let ptr = self.call_malloc(size);
+                let one = self.bcx.ins().iconst(ptr_type, 1);
+                let boxed = PlaceRef::new_ref(Pointer::addr(ptr), box_layout);
 
-                place.store(self, ValueRef::new_val(val, layout));
+                self.bcx.ins().store(clif::MemFlags::trusted(), one, ptr, 0);
+                boxed.field(self, 1).store(self, val);
+                place.store(self, ValueRef::new_val(ptr, layout));
             },
-            | "sub_i32" => {
-                let lhs = args.next()?.load_scalar(self);
-                let rhs = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().isub(lhs, rhs);
+            | "rc_retain" => {
+                let ptr = args.next()?.load_scalar(self);
+                let ptr_type = self.module.target_config().pointer_type();
+                let count = self.bcx.ins().load(ptr_type, clif::MemFlags::trusted(), ptr, 0);
+                let count = self.bcx.ins().iadd_imm(count, 1);
 
-                place.store(self, ValueRef::new_val(val, layout));
+                self.bcx.ins().store(clif::MemFlags::trusted(), count, ptr, 0);
             },
-            | "div_i32" => {
-                let lhs = args.next()?.load_scalar(self);
-                let rhs = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().sdiv(lhs, rhs);
+            | "rc_release" => {
+                let ptr = args.next()?.load_scalar(self);
+                let ptr_type = self.module.target_config().pointer_type();
+                let count = self.bcx.ins().load(ptr_type, clif::MemFlags::trusted(), ptr, 0);
+                let count = self.bcx.ins().iadd_imm(count, -1);
 
-                place.store(self, ValueRef::new_val(val, layout));
+                self.bcx.ins().store(clif::MemFlags::trusted(), count, ptr, 0);
+
+                // whether this was the last reference
+                let last = self.bcx.ins().icmp_imm(clif::IntCC::Equal, count, 0);
+                let last = self.bcx.ins().bint(clif::types::I8, last);
+
+                place.store(self, ValueRef::new_val(last, layout));
+            },
+            | _ => match Intrinsic::parse(name) {
+                | Some(intrinsic) => self.lower_scalar_intrinsic(place, intrinsic, args.collect())?,
+                | None => panic!("unknown intrinsic '{}'", name),
             },
-            | "rem_i32" => {
-                let lhs = args.next()?.load_scalar(self);
-                let rhs = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().srem(lhs, rhs);
+        }
+
+        Some(())
+    }
+
+    fn lower_scalar_intrinsic(&mut self, place: PlaceRef, intrinsic: Intrinsic, args: Vec<ValueRef>) -> Option<()> {
+        let args = args.into_iter().map(|a| a.load_scalar(self)).collect::<Vec<_>>();
+        let mut args = args.into_iter();
+        let layout = place.layout.clone();
+
+        match intrinsic {
+            | Intrinsic::Binary(op, ty) => {
+                let lhs = args.next()?;
+                let rhs = args.next()?;
+                let (val, _) = self.lower_binop(op, ty, lhs, rhs);
 
                 place.store(self, ValueRef::new_val(val, layout));
             },
-            | "eq_i32" => {
-                let lhs = args.next()?.load_scalar(self);
-                let rhs = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().icmp(clif::IntCC::Equal, lhs, rhs);
-                let val = self.bcx.ins().bint(clif::types::I8, val);
+            | Intrinsic::Checked(op, ty) => {
+                let lhs = args.next()?;
+                let rhs = args.next()?;
+                let (val, overflow) = self.lower_binop(op, ty, lhs, rhs);
+                let overflow = self.bcx.ins().bint(clif::types::I8, overflow?);
+                let val_place = place.clone().field(self, 0);
+                let overflow_place = place.field(self, 1);
+                let val_layout = val_place.layout.clone();
+                let overflow_layout = overflow_place.layout.clone();
+
+                val_place.store(self, ValueRef::new_val(val, val_layout));
+                overflow_place.store(self, ValueRef::new_val(overflow, overflow_layout));
+            },
+            | Intrinsic::Unary(op, ty) => {
+                let val = args.next()?;
+                let val = match op {
+                    | UnOp::Neg if ty.is_float() => self.bcx.ins().fneg(val),
+                    | UnOp::Neg => self.bcx.ins().ineg(val),
+                    | UnOp::Not => self.bcx.ins().bnot(val),
+                };
 
                 place.store(self, ValueRef::new_val(val, layout));
             },
-            | "ge_i32" => {
-                let lhs = args.next()?.load_scalar(self);
-                let rhs = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().icmp(clif::IntCC::SignedGreaterThanOrEqual, lhs, rhs);
+            | Intrinsic::Cmp(op, ty) => {
+                let lhs = args.next()?;
+                let rhs = args.next()?;
+                let val = if ty.is_float() {
+                    let cc = match op {
+                        | CmpOp::Eq => clif::FloatCC::Equal,
+                        | CmpOp::Ne => clif::FloatCC::NotEqual,
+                        | CmpOp::Lt => clif::FloatCC::LessThan,
+                        | CmpOp::Le => clif::FloatCC::LessThanOrEqual,
+                        | CmpOp::Gt => clif::FloatCC::GreaterThan,
+                        | CmpOp::Ge => clif::FloatCC::GreaterThanOrEqual,
+                    };
+
+                    self.bcx.ins().fcmp(cc, lhs, rhs)
+                } else {
+                    let signed = ty.is_signed();
+                    let cc = match op {
+                        | CmpOp::Eq => clif::IntCC::Equal,
+                        | CmpOp::Ne => clif::IntCC::NotEqual,
+                        | CmpOp::Lt if signed => clif::IntCC::SignedLessThan,
+                        | CmpOp::Le if signed => clif::IntCC::SignedLessThanOrEqual,
+                        | CmpOp::Gt if signed => clif::IntCC::SignedGreaterThan,
+                        | CmpOp::Ge if signed => clif::IntCC::SignedGreaterThanOrEqual,
+                        | CmpOp::Lt => clif::IntCC::UnsignedLessThan,
+                        | CmpOp::Le => clif::IntCC::UnsignedLessThanOrEqual,
+                        | CmpOp::Gt => clif::IntCC::UnsignedGreaterThan,
+                        | CmpOp::Ge => clif::IntCC::UnsignedGreaterThanOrEqual,
+                    };
+
+                    self.bcx.ins().icmp(cc, lhs, rhs)
+                };
+
                 let val = self.bcx.ins().bint(clif::types::I8, val);
 
                 place.store(self, ValueRef::new_val(val, layout));
             },
-            | "u16_to_u8" | "u32_to_u8" | "u64_to_u8" | "u128_to_u8" | "i16_to_u8" | "i32_to_u8" | "i64_to_u8"
-            | "i128_to_u8" | "u16_to_i8" | "u32_to_i8" | "u64_to_i8" | "u128_to_i8" | "i16_to_i8" | "i32_to_i8"
-            | "i64_to_i8" | "i128_to_i8" => {
-                let val = args.next()?.load_scalar(self);
-                let val = self.bcx.ins().ireduce(clif::types::I8, val);
+            | Intrinsic::Convert(from, to) => {
+                let val = args.next()?;
+                let to_ty = self.ir_type(&layout)?;
+                let val = self.lower_convert(from, to, val, to_ty);
 
                 place.store(self, ValueRef::new_val(val, layout));
             },
-            | _ => panic!("unknown intrinsic '{}'", name),
         }
 
         Some(())
     }
+
+    /// Returns the wrapped result and, for operations that can overflow, a boolean that is set when it did.
+    fn lower_binop(
+        &mut self,
+        op: BinOp,
+        ty: ScalarType,
+        lhs: clif::Value,
+        rhs: clif::Value,
+    ) -> (clif::Value, Option<clif::Value>) {
+        let signed = ty.is_signed();
+
+        if ty.is_float() {
+            let val = match op {
+                | BinOp::Add => self.bcx.ins().fadd(lhs, rhs),
+                | BinOp::Sub => self.bcx.ins().fsub(lhs, rhs),
+                | BinOp::Mul => self.bcx.ins().fmul(lhs, rhs),
+                | BinOp::Div => self.bcx.ins().fdiv(lhs, rhs),
+                | BinOp::Rem => self.call_fmod(lhs, rhs),
+                | _ => unreachable!(),
+            };
+
+            return (val, None);
+        }
+
+        match op {
+            | BinOp::Add => {
+                let val = self.bcx.ins().iadd(lhs, rhs);
+                let overflow = if signed {
+                    // overflow when both operands have a different sign than the result
+                    let a = self.bcx.ins().bxor(lhs, val);
+                    let b = self.bcx.ins().bxor(rhs, val);
+                    let c = self.bcx.ins().band(a, b);
+
+                    self.is_negative(c)
+                } else {
+                    self.bcx.ins().icmp(clif::IntCC::UnsignedLessThan, val, lhs)
+                };
+
+                (val, Some(overflow))
+            },
+            | BinOp::Sub => {
+                let val = self.bcx.ins().isub(lhs, rhs);
+                let overflow = if signed {
+                    // overflow when the operands have different signs and the result has the sign of rhs
+                    let a = self.bcx.ins().bxor(lhs, rhs);
+                    let b = self.bcx.ins().bxor(lhs, val);
+                    let c = self.bcx.ins().band(a, b);
+
+                    self.is_negative(c)
+                } else {
+                    self.bcx.ins().icmp(clif::IntCC::UnsignedLessThan, lhs, rhs)
+                };
+
+                (val, Some(overflow))
+            },
+            | BinOp::Mul => {
+                let ty = self.bcx.func.dfg.value_type(lhs);
+                let val = self.bcx.ins().imul(lhs, rhs);
+                let overflow = if ty.bits() <= 32 {
+                    // multiply at double the width and check that the result still fits
+                    let wide = ty.double_width().unwrap();
+                    let (l, r) = if signed {
+                        (self.bcx.ins().sextend(wide, lhs), self.bcx.ins().sextend(wide, rhs))
+                    } else {
+                        (self.bcx.ins().uextend(wide, lhs), self.bcx.ins().uextend(wide, rhs))
+                    };
+
+                    let full = self.bcx.ins().imul(l, r);
+                    let fits = if signed {
+                        self.bcx.ins().sextend(wide, val)
+                    } else {
+                        self.bcx.ins().uextend(wide, val)
+                    };
+
+                    self.bcx.ins().icmp(clif::IntCC::NotEqual, full, fits)
+                } else if signed {
+                    let high = self.bcx.ins().smulhi(lhs, rhs);
+                    let sign = self.bcx.ins().sshr_imm(val, ty.bits() as i64 - 1);
+
+                    self.bcx.ins().icmp(clif::IntCC::NotEqual, high, sign)
+                } else {
+                    let high = self.bcx.ins().umulhi(lhs, rhs);
+
+                    self.bcx.ins().icmp_imm(clif::IntCC::NotEqual, high, 0)
+                };
+
+                (val, Some(overflow))
+            },
+            | BinOp::Div if signed => (self.bcx.ins().sdiv(lhs, rhs), None),
+            | BinOp::Div => (self.bcx.ins().udiv(lhs, rhs), None),
+            | BinOp::Rem if signed => (self.bcx.ins().srem(lhs, rhs), None),
+            | BinOp::Rem => (self.bcx.ins().urem(lhs, rhs), None),
+            | BinOp::And => (self.bcx.ins().band(lhs, rhs), None),
+            | BinOp::Or => (self.bcx.ins().bor(lhs, rhs), None),
+            | BinOp::Xor => (self.bcx.ins().bxor(lhs, rhs), None),
+            | BinOp::Shl => (self.bcx.ins().ishl(lhs, rhs), None),
+            | BinOp::Shr if signed => (self.bcx.ins().sshr(lhs, rhs), None),
+            | BinOp::Shr => (self.bcx.ins().ushr(lhs, rhs), None),
+        }
+    }
+
+    fn lower_convert(&mut self, from: ScalarType, to: ScalarType, val: clif::Value, to_ty: clif::Type) -> clif::Value {
+        let from_ty = self.bcx.func.dfg.value_type(val);
+
+        match (from.is_float(), to.is_float()) {
+            | (false, false) if to_ty.bits() < from_ty.bits() => self.bcx.ins().ireduce(to_ty, val),
+            | (false, false) if to_ty.bits() == from_ty.bits() => val,
+            | (false, false) if from.is_signed() => self.bcx.ins().sextend(to_ty, val),
+            | (false, false) => self.bcx.ins().uextend(to_ty, val),
+            | (false, true) if from.is_signed() => self.bcx.ins().fcvt_from_sint(to_ty, val),
+            | (false, true) => self.bcx.ins().fcvt_from_uint(to_ty, val),
+            | (true, true) if to_ty.bits() > from_ty.bits() => self.bcx.ins().fpromote(to_ty, val),
+            | (true, true) if to_ty.bits() < from_ty.bits() => self.bcx.ins().fdemote(to_ty, val),
+            | (true, true) => val,
+            | (true, false) if to_ty.bits() >= 32 => {
+                if to.is_signed() {
+                    self.bcx.ins().fcvt_to_sint_sat(to_ty, val)
+                } else {
+                    self.bcx.ins().fcvt_to_uint_sat(to_ty, val)
+                }
+            },
+            | (true, false) => {
+                // saturate to 32 bits first and then clamp to the range of the smaller type
+                let bits = to_ty.bits() as u32;
+                let (val, min, max, lt, gt) = if to.is_signed() {
+                    let val = self.bcx.ins().fcvt_to_sint_sat(clif::types::I32, val);
+
+                    (
+                        val,
+                        -(1i64 << (bits - 1)),
+                        (1i64 << (bits - 1)) - 1,
+                        clif::IntCC::SignedLessThan,
+                        clif::IntCC::SignedGreaterThan,
+                    )
+                } else {
+                    let val = self.bcx.ins().fcvt_to_uint_sat(clif::types::I32, val);
+
+                    (
+                        val,
+                        0,
+                        (1i64 << bits) - 1,
+                        clif::IntCC::UnsignedLessThan,
+                        clif::IntCC::UnsignedGreaterThan,
+                    )
+                };
+
+                let min = self.bcx.ins().iconst(clif::types::I32, min);
+                let max = self.bcx.ins().iconst(clif::types::I32, max);
+                let is_lt = self.bcx.ins().icmp(lt, val, min);
+                let val = self.bcx.ins().select(is_lt, min, val);
+                let is_gt = self.bcx.ins().icmp(gt, val, max);
+                let val = self.bcx.ins().select(is_gt, max, val);
+
+                self.bcx.ins().ireduce(to_ty, val)
+            },
+        }
+    }
+
+    fn is_negative(&mut self, val: clif::Value) -> clif::Value {
+        let ty = self.bcx.func.dfg.value_type(val);
+        let zero = self.iconst(ty, 0);
+
+        self.bcx.ins().icmp(clif::IntCC::SignedLessThan, val, zero)
+    }
+
+    fn iconst(&mut self, ty: clif::Type, val: i64) -> clif::Value {
+        if ty == clif::types::I128 {
+            let val = self.bcx.ins().iconst(clif::types::I64, val);
+
+            self.bcx.ins().sextend(ty, val)
+        } else {
+            self.bcx.ins().iconst(ty, val)
+        }
+    }
 }
@@ -0,0 +1,340 @@
+//! Runs libraries in the current process, without writing object files or invoking a linker.
+
+use crate::db::CodegenDatabase;
+use crate::{clif, ModuleCtx};
+use cranelift_jit::{JITBuilder, JITModule};
+use hir::ty::TyKind;
+use mir::ir;
+use mir::layout::{Abi, Integer, Primitive};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::ffi::CString;
+use std::path::PathBuf;
+use std::mem::transmute;
+use std::os::raw::c_char;
+
+/// The result of a function evaluated by [`eval`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JitValue {
+    Unit,
+    Int(i128),
+    Uint(u128),
+    Float(f64),
+    Pointer(usize),
+}
+
+impl std::fmt::Display for JitValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            | JitValue::Unit => write!(f, "()"),
+            | JitValue::Int(i) => write!(f, "{}", i),
+            | JitValue::Uint(u) => write!(f, "{}", u),
+            | JitValue::Float(v) => write!(f, "{}", v),
+            | JitValue::Pointer(p) => write!(f, "{:#x}", p),
+        }
+    }
+}
+
+/// A function that [`eval`] cannot call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalError {
+    /// The function has parameters.
+    Params,
+    /// The function is polymorphic or has constraints, so there is no single body to call.
+    Polymorphic,
+    /// The result is not a scalar.
+    NotScalar,
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            | EvalError::Params => write!(f, "cannot evaluate a function with parameters"),
+            | EvalError::Polymorphic => write!(f, "cannot evaluate a polymorphic value"),
+            | EvalError::NotScalar => write!(f, "cannot evaluate a value that is not a scalar"),
+        }
+    }
+}
+
+/// The module the functions evaluated by [`eval`] are added to. The libraries they depend on are
+/// only lowered once. Pointers returned by the functions can point into the memory of the module,
+/// so it is only freed when this is dropped.
+#[derive(Default)]
+pub struct Jit {
+    state: Option<JitState>,
+}
+
+struct JitState {
+    module: JITModule,
+    func_ids: FxHashMap<ir::BodyId, (clif::FuncId, clif::Signature)>,
+    static_ids: FxHashMap<hir::Static, clif::DataId>,
+    libs: FxHashSet<hir::Lib>,
+    evaluated: u32,
+}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            unsafe { state.module.free_memory() };
+        }
+    }
+}
+
+/// Runs the main function of an executable and returns its exit code.
+/// The first argument is the name of the program.
+pub fn run(db: &dyn CodegenDatabase, lib: hir::Lib, args: &[String]) -> i32 {
+    let mut ctx = clif::Context::new();
+    let mut fcx = clif::FunctionBuilderContext::new();
+    let mut mcx = ModuleCtx::new(db, jit_module(db), &mut ctx, &mut fcx);
+
+    lower_lib(&mut mcx, lib, &mut FxHashSet::default());
+
+    let main = mcx.generate_main(lib);
+
+    mcx.module.finalize_definitions();
+
+    let main = mcx.module.get_finalized_function(main);
+    let main = unsafe { transmute::<_, extern "C" fn(isize, *const *const c_char) -> isize>(main) };
+    let args = args
+        .iter()
+        .map(|arg| CString::new(arg.as_str()).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut argv = args.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+
+    argv.push(std::ptr::null());
+
+    let code = main(args.len() as isize, argv.as_ptr()) as i32;
+
+    unsafe { mcx.module.free_memory() };
+    code
+}
+
+/// Calls a function without parameters and returns its result, the compiled code is kept in `jit`.
+/// The other declarations of its library are added the first time they are seen, the function
+/// itself is added again every time.
+pub fn eval(db: &dyn CodegenDatabase, jit: &mut Jit, func: hir::Func) -> Result<JitValue, EvalError> {
+    let lib = func.module(db.upcast()).lib();
+    let def: hir::id::FuncId = func.into();
+    let def = def.into();
+
+    if let TyKind::ForAll(_, _) | TyKind::Ctnt(_, _) = func.ty(db.upcast()).lookup(db.upcast()) {
+        return Err(EvalError::Polymorphic);
+    }
+
+    let bodies = db.body_mir(def);
+    let id = bodies.main_id(def);
+    let body = &bodies[id.local_id];
+
+    if !body.args().is_empty() {
+        return Err(EvalError::Params);
+    }
+
+    let layout = db.layout_of(body.locals[body.ret.unwrap()].ty.clone());
+    let scalar = match &layout.abi {
+        | _ if layout.is_zst() => None,
+        | Abi::Scalar(scalar) if !matches!(scalar.value, Primitive::Int(Integer::I128, _)) => Some(scalar.value),
+        | _ => return Err(EvalError::NotScalar),
+    };
+
+    let mut state = match jit.state.take() {
+        | Some(state) => state,
+        | None => JitState {
+            module: jit_module(db),
+            func_ids: FxHashMap::default(),
+            static_ids: FxHashMap::default(),
+            libs: FxHashSet::default(),
+            evaluated: 0,
+        },
+    };
+
+    let mut ctx = clif::Context::new();
+    let mut fcx = clif::FunctionBuilderContext::new();
+    let mut mcx = ModuleCtx::new(db, state.module, &mut ctx, &mut fcx);
+
+    mcx.func_ids = state.func_ids;
+    mcx.static_ids = state.static_ids;
+
+    for dep in lib.dependencies(db.upcast()) {
+        lower_lib(&mut mcx, dep.lib, &mut state.libs);
+    }
+
+    lower_new_items(&mut mcx, lib, func);
+
+    // every evaluated function gets a new name, because earlier ones are still defined
+    state.evaluated += 1;
+
+    let name = format!("{}#{}", func.link_name(db.upcast()), state.evaluated);
+
+    mcx.declare_func(func, &name, clif::Linkage::Local);
+    mcx.lower_func(func);
+    mcx.module.finalize_definitions();
+
+    let ptr = mcx.module.get_finalized_function(mcx.func_ids[&id].0);
+
+    // the id can be reused by a declaration that is added later
+    for id in bodies.ids(def) {
+        mcx.func_ids.remove(&id);
+    }
+
+    jit.state = Some(JitState {
+        module: mcx.module,
+        func_ids: mcx.func_ids,
+        static_ids: mcx.static_ids,
+        ..state
+    });
+
+    unsafe {
+        Ok(match scalar {
+            | None => {
+                transmute::<_, extern "C" fn()>(ptr)();
+                JitValue::Unit
+            },
+            | Some(Primitive::Int(int, signed)) => {
+                let (bits, value) = match int {
+                    | Integer::I8 => (8, transmute::<_, extern "C" fn() -> u8>(ptr)() as u128),
+                    | Integer::I16 => (16, transmute::<_, extern "C" fn() -> u16>(ptr)() as u128),
+                    | Integer::I32 => (32, transmute::<_, extern "C" fn() -> u32>(ptr)() as u128),
+                    | Integer::I64 => (64, transmute::<_, extern "C" fn() -> u64>(ptr)() as u128),
+                    | Integer::I128 => unreachable!(),
+                };
+
+                if signed {
+                    let shift = 128 - bits;
+
+                    JitValue::Int((value << shift) as i128 >> shift)
+                } else {
+                    JitValue::Uint(value)
+                }
+            },
+            | Some(Primitive::F32) => JitValue::Float(transmute::<_, extern "C" fn() -> f32>(ptr)() as f64),
+            | Some(Primitive::F64) => JitValue::Float(transmute::<_, extern "C" fn() -> f64>(ptr)()),
+            | Some(Primitive::Pointer) => JitValue::Pointer(transmute::<_, extern "C" fn() -> usize>(ptr)()),
+        })
+    }
+}
+
+fn jit_module(db: &dyn CodegenDatabase) -> JITModule {
+    let isa = crate::build_isa(db).expect("the target is checked by the driver");
+    let mut builder = JITBuilder::with_isa(isa, clif::default_libcall_names());
+    let libs = link_libraries(&db.codegen_opts().link_args);
+
+    builder.symbol_lookup_fn(Box::new(move |name| lookup_symbol(&libs, name)));
+
+    JITModule::new(builder)
+}
+
+fn lower_lib(mcx: &mut ModuleCtx<JITModule>, lib: hir::Lib, done: &mut FxHashSet<hir::Lib>) {
+    if !done.insert(lib) {
+        return;
+    }
+
+    for dep in lib.dependencies(mcx.db.upcast()) {
+        lower_lib(mcx, dep.lib, done);
+    }
+
+    mcx.lower_lib(lib);
+}
+
+/// Declares and defines the functions and statics of the library that are not in the module yet, except `skip`.
+fn lower_new_items(mcx: &mut ModuleCtx<JITModule>, lib: hir::Lib, skip: hir::Func) {
+    let db = mcx.db;
+    let mut funcs = Vec::new();
+    let mut statics = Vec::new();
+
+    for module in lib.modules(db.upcast()) {
+        if module.is_virtual(db.upcast()) {
+            continue;
+        }
+
+        for def in module.declarations(db.upcast()) {
+            match def {
+                | hir::ModuleDef::Func(f) if f != skip => funcs.push(f),
+                | hir::ModuleDef::Static(s) => statics.push(s),
+                | _ => {},
+            }
+        }
+
+        for inst in module.instances(db.upcast()) {
+            for def in inst.items(db.upcast()) {
+                match def {
+                    | hir::AssocItem::Func(f) => funcs.push(f),
+                    | hir::AssocItem::Static(s) => statics.push(s),
+                }
+            }
+        }
+    }
+
+    funcs.retain(|&f| {
+        let def: hir::id::FuncId = f.into();
+        let def = def.into();
+
+        !mcx.func_ids.contains_key(&db.body_mir(def).main_id(def))
+    });
+
+    statics.retain(|s| !mcx.static_ids.contains_key(s));
+
+    for &f in &funcs {
+        mcx.register_func(f);
+    }
+
+    for &s in &statics {
+        mcx.register_static(s);
+    }
+
+    for f in funcs {
+        mcx.lower_func(f);
+    }
+
+    for s in statics {
+        mcx.lower_static(s);
+    }
+}
+
+/// Opens the shared libraries linked with `-l` in the link arguments of the profile.
+/// The directories given with `-L` are searched before the default search path.
+fn link_libraries(args: &[String]) -> Vec<libloading::Library> {
+    let mut dirs = Vec::new();
+    let mut names = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "-L" {
+            dirs.extend(args.next().map(PathBuf::from));
+        } else if let Some(dir) = arg.strip_prefix("-L") {
+            dirs.push(PathBuf::from(dir));
+        } else if arg == "-l" {
+            names.extend(args.next().cloned());
+        } else if let Some(name) = arg.strip_prefix("-l") {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+        .iter()
+        .filter_map(|name| {
+            let file = libloading::library_filename(name);
+            let path = dirs
+                .iter()
+                .map(|dir| dir.join(&file))
+                .find(|path| path.exists())
+                .map_or(file, |path| path.into_os_string());
+
+            unsafe { libloading::Library::new(path).ok() }
+        })
+        .collect()
+}
+
+/// Resolves `foreign fun`s to the symbols of the linked libraries or of the libraries loaded into
+/// this process, like the C library.
+fn lookup_symbol(libs: &[libloading::Library], name: &str) -> Option<*const u8> {
+    #[cfg(unix)]
+    let this = libloading::os::unix::Library::this();
+    #[cfg(windows)]
+    let this = libloading::os::windows::Library::this().ok()?;
+
+    unsafe {
+        libs.iter()
+            .find_map(|lib| lib.get::<*const u8>(name.as_bytes()).ok().map(|sym| *sym))
+            .or_else(|| this.get::<*const u8>(name.as_bytes()).ok().map(|sym| *sym))
+    }
+}
@@ -1,13 +1,17 @@
 mod abi;
 pub mod assembly;
 pub mod db;
+mod debuginfo;
+pub mod header;
 mod intrinsic;
+pub mod jit;
 pub mod linker;
 mod lower;
 mod place;
 mod ptr;
 mod ssa;
 mod value;
+pub mod wasm;
 
 use clif::InstBuilder as _;
 use clif::Module as _;
@@ -18,6 +22,7 @@ use mir::ir;
 use place::PlaceRef;
 use ptr::Pointer;
 use rustc_hash::FxHashMap;
+use std::fmt::Write as _;
 use std::sync::Arc;
 use value::ValueRef;
 
@@ -32,49 +37,201 @@ mod clif {
     pub use cranelift_module::{default_libcall_names, DataContext, DataId, FuncId, Linkage, Module};
 }
 
-struct ModuleCtx<'a> {
+struct ModuleCtx<'a, M: clif::Module> {
     db: &'a dyn db::CodegenDatabase,
     triple: Arc<target_lexicon::Triple>,
-    module: cranelift_object::ObjectModule,
+    module: M,
     ctx: &'a mut clif::Context,
     fcx: &'a mut clif::FunctionBuilderContext,
     func_ids: FxHashMap<ir::BodyId, (clif::FuncId, clif::Signature)>,
     static_ids: FxHashMap<hir::Static, clif::DataId>,
+    debug_context: Option<debuginfo::DebugContext>,
+    clif: String,
+    asm: String,
 }
 
-struct FunctionCtx<'a, 'mcx> {
-    mcx: &'mcx mut ModuleCtx<'a>,
+struct FunctionCtx<'a, 'mcx, M: clif::Module> {
+    mcx: &'mcx mut ModuleCtx<'a, M>,
     bcx: clif::FunctionBuilder<'a>,
     body: &'mcx ir::Body,
     blocks: ArenaMap<ir::BlockId, clif::Block>,
     locals: ArenaMap<ir::LocalId, PlaceRef>,
     ssa_vars: u32,
+    abi: hir::Abi,
+    debug: Option<debuginfo::FunctionDebugContext>,
 }
 
-impl<'a> ModuleCtx<'a> {
-    pub fn with_mcx<T>(db: &dyn db::CodegenDatabase, f: impl FnOnce(ModuleCtx) -> T) -> T {
+/// An invalid target setting in the current profile.
+#[derive(Debug)]
+pub enum TargetError {
+    /// Cranelift cannot generate code for the target triple.
+    UnsupportedTarget(String),
+    /// The host machine is not supported by `target_cpu = "native"`.
+    UnsupportedHost(String),
+    /// `target_cpu = "native"` was used while compiling for another target.
+    NativeCrossCompile(String),
+    UnknownCpu(String),
+    UnknownFeature(String),
+}
+
+impl std::fmt::Display for TargetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            | TargetError::UnsupportedTarget(triple) => write!(f, "unsupported target `{}`", triple),
+            | TargetError::UnsupportedHost(reason) => write!(f, "cannot detect the native cpu: {}", reason),
+            | TargetError::NativeCrossCompile(triple) => {
+                write!(f, "cannot use the native cpu when compiling for `{}`", triple)
+            },
+            | TargetError::UnknownCpu(cpu) => write!(f, "unknown target cpu `{}`", cpu),
+            | TargetError::UnknownFeature(feature) => write!(f, "unknown target feature `{}`", feature),
+        }
+    }
+}
+
+/// Checks the target settings of the current profile.
+/// The driver calls this before code is generated, so that invalid settings are reported as errors.
+pub fn check_target(db: &dyn db::CodegenDatabase) -> Result<(), TargetError> {
+    // wasm modules are generated without Cranelift
+    if let target_lexicon::OperatingSystem::Wasi = db.target_triple().operating_system {
+        return Ok(());
+    }
+
+    build_isa(db).map(|_| ())
+}
+
+/// Creates the target isa with the settings of the current profile.
+fn build_isa(db: &dyn db::CodegenDatabase) -> Result<Box<dyn clif::isa::TargetIsa>, TargetError> {
+    let triple = db.target_triple();
+    let opts = db.codegen_opts();
+    let mut flags_builder = clif::settings::builder();
+
+    flags_builder.set("opt_level", db.opt_level().name()).unwrap();
+    flags_builder
+        .set("enable_verifier", if opts.verifier { "true" } else { "false" })
+        .unwrap();
+
+    let flags = clif::settings::Flags::new(flags_builder);
+    let mut isa_builder = match opts.target_cpu.as_deref() {
+        | Some("native") if *triple != target_lexicon::HOST => {
+            return Err(TargetError::NativeCrossCompile(triple.to_string()));
+        },
+        | Some("native") => {
+            cranelift_native::builder().map_err(|e| TargetError::UnsupportedHost(e.to_string()))?
+        },
+        | _ => clif::isa::lookup((*triple).clone()).map_err(|_| TargetError::UnsupportedTarget(triple.to_string()))?,
+    };
+
+    if let Some(cpu) = opts.target_cpu.as_deref().filter(|&cpu| cpu != "native") {
+        isa_builder
+            .enable(cpu)
+            .map_err(|_| TargetError::UnknownCpu(cpu.to_string()))?;
+    }
+
+    for feature in &opts.target_features {
+        isa_builder
+            .enable(feature)
+            .map_err(|_| TargetError::UnknownFeature(feature.clone()))?;
+    }
+
+    Ok(isa_builder.finish(flags))
+}
+
+/// The function that is called when an executable starts, either named `main` or marked with `@main`.
+fn main_func(db: &dyn db::CodegenDatabase, lib: hir::Lib) -> hir::Func {
+    for module in lib.modules(db.upcast()) {
+        for def in module.declarations(db.upcast()) {
+            if let hir::ModuleDef::Func(f) = def {
+                if f.name(db.upcast()).to_string() == "main" {
+                    return f;
+                } else {
+                    let attrs = db.attrs(hir::id::AttrDefId::FuncId(f.into()));
+
+                    if attrs.by_key("main").exists() {
+                        return f;
+                    }
+                }
+            }
+        }
+    }
+
+    panic!("executable contains no main function");
+}
+
+/// The `report` method of the `Termination` instance for the return type of `main`.
+fn report_func(db: &dyn db::CodegenDatabase, lib: hir::Lib, main: hir::Func) -> hir::id::FuncId {
+    let main_ret_ty = main.ty(db.upcast()).ret_type(db.upcast(), lib.into());
+    let termination = db.lang_item(lib.into(), "termination-class".into()).unwrap();
+    let termination = termination.as_class().unwrap();
+    let ctnt = hir::ty::Constraint::new(termination, [main_ret_ty]);
+    let solved = db.solve_constraint(ctnt).unwrap();
+    let inst = db.instance_data(solved.instance);
+    let method = inst.item(&"report".as_name()).unwrap();
+
+    method.as_func_id().unwrap()
+}
+
+impl<'a> ModuleCtx<'a, cranelift_object::ObjectModule> {
+    pub fn with_mcx<T>(
+        db: &dyn db::CodegenDatabase,
+        lib: hir::Lib,
+        f: impl FnOnce(ModuleCtx<cranelift_object::ObjectModule>) -> T,
+    ) -> T {
         let mut ctx = clif::Context::new();
         let mut fcx = clif::FunctionBuilderContext::new();
-        let triple = db.target_triple();
-        let flags_builder = clif::settings::builder();
-        let flags = clif::settings::Flags::new(flags_builder);
-        let isa = clif::isa::lookup((*triple).clone()).unwrap().finish(flags);
-        let builder = cranelift_object::ObjectBuilder::new(isa, "test", clif::default_libcall_names()).unwrap();
+        let isa = build_isa(db).expect("the target is checked by the driver");
+        let name = lib.name(db.upcast()).to_string();
+        let builder = cranelift_object::ObjectBuilder::new(isa, name, clif::default_libcall_names()).unwrap();
         let module = cranelift_object::ObjectModule::new(builder);
-        let mcx = ModuleCtx {
+
+        f(ModuleCtx::new(db, module, &mut ctx, &mut fcx))
+    }
+
+    /// Returns the object, the Cranelift IR of its functions if `emit_clif` is set
+    /// and their disassembly if `emit_asm` is set.
+    pub fn build(mut self, lib: hir::Lib) -> (cranelift_object::ObjectProduct, String, String) {
+        if self.db.codegen_opts().debug_info {
+            self.debug_context = Some(debuginfo::DebugContext::new(self.db, self.module.isa(), lib));
+        }
+
+        self.lower_lib(lib);
+
+        if let base_db::libs::LibKind::Executable = self.db.libs()[lib.into()].kind {
+            self.generate_main(lib);
+        }
+
+        let mut product = self.module.finish();
+
+        if let Some(debug_context) = self.debug_context {
+            debug_context.emit(&mut product);
+        }
+
+        (product, self.clif, self.asm)
+    }
+}
+
+impl<'a, M: clif::Module> ModuleCtx<'a, M> {
+    fn new(
+        db: &'a dyn db::CodegenDatabase,
+        module: M,
+        ctx: &'a mut clif::Context,
+        fcx: &'a mut clif::FunctionBuilderContext,
+    ) -> Self {
+        ModuleCtx {
             db,
             module,
             triple: db.target_triple(),
-            ctx: &mut ctx,
-            fcx: &mut fcx,
+            ctx,
+            fcx,
             func_ids: FxHashMap::default(),
             static_ids: FxHashMap::default(),
-        };
-
-        f(mcx)
+            debug_context: None,
+            clif: String::new(),
+            asm: String::new(),
+        }
     }
 
-    pub fn build(mut self, lib: hir::Lib) -> cranelift_object::ObjectProduct {
+    /// Declares and defines the functions and statics of every module in the library.
+    pub fn lower_lib(&mut self, lib: hir::Lib) {
         for module in lib.modules(self.db.upcast()) {
             if module.is_virtual(self.db.upcast()) {
                 continue;
@@ -114,12 +271,6 @@ impl<'a> ModuleCtx<'a> {
                 }
             }
         }
-
-        if let base_db::libs::LibKind::Executable = self.db.libs()[lib.into()].kind {
-            self.generate_main(lib);
-        }
-
-        self.module.finish()
     }
 
     pub fn register_func(&mut self, func: hir::Func) {
@@ -135,11 +286,15 @@ impl<'a> ModuleCtx<'a> {
             }
         }
 
-        let def: hir::id::FuncId = func.into();
-        let def: hir::id::DefWithBodyId = def.into();
-        let bodies = self.db.body_mir(def);
         let linkage = if func.is_foreign(self.db.upcast()) {
-            clif::Linkage::Import
+            let attrs = self.db.attrs(hir::id::AttrDefId::FuncId(func.into()));
+
+            // weak imports that are not linked in have the address 0
+            if attrs.by_key("weak").exists() {
+                clif::Linkage::Preemptible
+            } else {
+                clif::Linkage::Import
+            }
         } else if func.is_exported(self.db.upcast()) {
             clif::Linkage::Export
         } else {
@@ -147,12 +302,21 @@ impl<'a> ModuleCtx<'a> {
         };
 
         let name = func.link_name(self.db.upcast()).to_string();
+
+        self.declare_func(func, &name, linkage);
+    }
+
+    /// Declares the bodies of a function, the bodies after the first are named `{name}^{id}`.
+    pub fn declare_func(&mut self, func: hir::Func, name: &str, linkage: clif::Linkage) {
+        let def: hir::id::FuncId = func.into();
+        let def: hir::id::DefWithBodyId = def.into();
+        let bodies = self.db.body_mir(def);
         let mut first = true;
 
         for body in bodies.ids(def) {
             let name = if first {
                 first = false;
-                name.clone()
+                name.to_string()
             } else {
                 let local_id: u32 = body.local_id.into_raw().into();
 
@@ -217,14 +381,14 @@ impl<'a> ModuleCtx<'a> {
         let ret_lyt = fx.db.layout_of(fx.body.locals[ret].ty.clone());
         let ssa = ssa::analyze(&fx);
 
-        match fx.pass_mode(&ret_lyt) {
+        match fx.pass_mode(fx.abi, &ret_lyt) {
             | abi::PassMode::NoPass => {
                 fx.locals.insert(ret, PlaceRef::no_place(ret_lyt));
             },
-            | abi::PassMode::ByVal(_) | abi::PassMode::ByValPair(_, _) => {
+            | abi::PassMode::ByVal(_) | abi::PassMode::ByValPair(_, _) | abi::PassMode::Cast(_) => {
                 local_place(&mut fx, &ssa, ret, ret_lyt);
             },
-            | abi::PassMode::ByRef { size: _ } => {
+            | abi::PassMode::ByRef { .. } => {
                 let val = fx.bcx.append_block_param(start_block, ptr_type);
 
                 fx.locals.insert(ret, PlaceRef::new_ref(Pointer::addr(val), ret_lyt));
@@ -238,7 +402,7 @@ impl<'a> ModuleCtx<'a> {
             .map(|arg| {
                 let arg = &fx.body.locals[arg];
                 let layout = fx.db.layout_of(arg.ty.clone());
-                let value = match fx.mcx.pass_mode(&layout) {
+                let value = match fx.mcx.pass_mode(fx.abi, &layout) {
                     | abi::PassMode::NoPass => return None,
                     | abi::PassMode::ByVal(ty) => {
                         let param = fx.bcx.append_block_param(start_block, ty);
@@ -251,12 +415,20 @@ impl<'a> ModuleCtx<'a> {
 
                         ValueRef::new_val_pair(a, b, layout)
                     },
-                    | abi::PassMode::ByRef { size: Some(_) } => {
+                    | abi::PassMode::Cast(target) => {
+                        let regs = target
+                            .regs()
+                            .map(|ty| fx.bcx.append_block_param(start_block, ty))
+                            .collect::<Vec<_>>();
+
+                        fx.regs_to_value(&regs, layout)
+                    },
+                    | abi::PassMode::ByRef { size: Some(_), .. } => {
                         let param = fx.bcx.append_block_param(start_block, ptr_type);
 
                         ValueRef::new_ref(Pointer::addr(param), layout)
                     },
-                    | abi::PassMode::ByRef { size: None } => {
+                    | abi::PassMode::ByRef { size: None, .. } => {
                         let ptr = fx.bcx.append_block_param(start_block, ptr_type);
                         let meta = fx.bcx.append_block_param(start_block, ptr_type);
 
@@ -305,6 +477,7 @@ impl<'a> ModuleCtx<'a> {
             fx.blocks.insert(id, block);
         }
 
+        fx.collect_debug_vars();
         fx.bcx.ins().jump(fx.blocks[fx.body.entry.unwrap()], &[]);
         fx.bcx.seal_block(start_block);
         fx.lower();
@@ -313,10 +486,18 @@ impl<'a> ModuleCtx<'a> {
         fx.ctx.compute_cfg();
         fx.ctx.compute_domtree();
 
-        // let name = &fx.module.declarations().get_function_decl(id).name;
-        // eprintln!("{}: {}", name, fx.ctx.func);
+        let opts = fx.db.codegen_opts();
+        let name = fx.module.declarations().get_function_decl(id).name.clone();
+
+        if opts.emit_clif {
+            writeln!(fx.mcx.clif, "; {}\n{}", name, fx.ctx.func).unwrap();
+        }
 
         fx.mcx.ctx.eliminate_unreachable_code(fx.mcx.module.isa()).unwrap();
+        fx.mcx.ctx.set_disasm(opts.emit_asm);
+
+        let debug = fx.debug.take();
+
         fx.mcx
             .module
             .define_function(
@@ -327,10 +508,18 @@ impl<'a> ModuleCtx<'a> {
             )
             .unwrap();
 
+        if let Some(disasm) = fx.mcx.ctx.mach_compile_result.as_ref().and_then(|res| res.disasm.as_ref()) {
+            writeln!(fx.mcx.asm, "; {}\n{}", name, disasm).unwrap();
+        }
+
+        if let Some(debug) = debug {
+            fx.mcx.define_debug_function(def, id, debug);
+        }
+
         fx.ctx.clear();
 
         fn local_place(
-            fx: &mut FunctionCtx,
+            fx: &mut FunctionCtx<impl clif::Module>,
             ssa: &ArenaMap<ir::LocalId, ssa::SsaKind>,
             local: ir::LocalId,
             layout: Arc<mir::layout::Layout>,
@@ -360,10 +549,18 @@ impl<'a> ModuleCtx<'a> {
         }
     }
 
-    fn function<'mcx>(&'mcx mut self, def: hir::id::DefWithBodyId, body: &'mcx ir::Body) -> FunctionCtx<'a, 'mcx> {
+    fn function<'mcx>(&'mcx mut self, def: hir::id::DefWithBodyId, body: &'mcx ir::Body) -> FunctionCtx<'a, 'mcx, M> {
+        let debug = if self.debug_context.is_some() {
+            self.ctx.func.collect_debug_info();
+            Some(debuginfo::FunctionDebugContext::new(self.db, def))
+        } else {
+            None
+        };
+
         // unsafe is used here to fix lifetimes, this is indeed very unsafe :/
         let func = unsafe { &mut *(&mut self.ctx.func as *mut _) };
         let fcx = unsafe { &mut *(self.fcx as *mut _) };
+        let abi = self.abi_of(def);
 
         FunctionCtx {
             bcx: clif::FunctionBuilder::new(func, fcx),
@@ -371,33 +568,17 @@ impl<'a> ModuleCtx<'a> {
             blocks: ArenaMap::default(),
             locals: ArenaMap::default(),
             ssa_vars: 0,
+            abi,
+            debug,
             mcx: self,
         }
     }
 
-    fn generate_main(&mut self, lib: hir::Lib) {
-        let main = (|| {
-            for module in lib.modules(self.db.upcast()) {
-                for def in module.declarations(self.db.upcast()) {
-                    if let hir::ModuleDef::Func(f) = def {
-                        if f.name(self.db.upcast()).to_string() == "main" {
-                            return Some(f);
-                        } else {
-                            let attrs = self.db.attrs(hir::id::AttrDefId::FuncId(f.into()));
-
-                            if attrs.by_key("main").exists() {
-                                return Some(f);
-                            }
-                        }
-                    }
-                }
-            }
-
-            None
-        })();
-
-        let main = main.expect("executable contains no main function");
-        let main_ret_ty = main.ty(self.db.upcast()).ret_type(self.db.upcast(), lib.into());
+    /// Defines the `main` function of an executable, which calls the main function of the library
+    /// and reports its result.
+    fn generate_main(&mut self, lib: hir::Lib) -> clif::FuncId {
+        let main = main_func(self.db, lib);
+        let method = report_func(self.db, lib, main);
         let main: hir::id::FuncId = main.into();
         let main_def = main.into();
         let main = self.db.body_mir(main_def);
@@ -415,13 +596,6 @@ impl<'a> ModuleCtx<'a> {
             .declare_function("main", clif::Linkage::Export, &sig)
             .unwrap();
 
-        let termination = self.db.lang_item(lib.into(), "termination-class".into()).unwrap();
-        let termination = termination.as_class().unwrap();
-        let ctnt = hir::ty::Constraint::new(termination, [main_ret_ty]);
-        let solved = self.db.solve_constraint(ctnt).unwrap();
-        let inst = self.db.instance_data(solved.instance);
-        let method = inst.item(&"report".as_name()).unwrap();
-        let method = method.as_func_id().unwrap();
         let method_bodies = self.db.body_mir(method.into());
         let method_body_id = method_bodies.main_id(method.into());
         let method_sig = self.func_signature(method_body_id);
@@ -462,6 +636,9 @@ impl<'a> ModuleCtx<'a> {
                 &mut clif::NullStackMapSink {},
             )
             .unwrap();
+
+        self.ctx.clear();
+        id
     }
 
     pub fn ir_type(&self, layout: &mir::layout::Layout) -> Option<clif::Type> {
@@ -507,32 +684,52 @@ impl<'a> ModuleCtx<'a> {
 
         let ret = self.db.layout_of(body.locals[body.ret.unwrap()].ty.clone());
 
-        self.mk_signature(&ret, &args)
+        self.mk_signature(self.abi_of(id.def), &ret, &args)
     }
 
-    pub fn mk_signature(&self, ret: &mir::layout::Layout, args: &[Arc<mir::layout::Layout>]) -> clif::Signature {
+    pub fn mk_signature(
+        &self,
+        abi: hir::Abi,
+        ret: &mir::layout::Layout,
+        args: &[Arc<mir::layout::Layout>],
+    ) -> clif::Signature {
         let mut sig = self.module.make_signature();
         let ptr_type = self.module.target_config().pointer_type();
 
-        match self.pass_mode(ret) {
+        match self.pass_mode(abi, ret) {
             | abi::PassMode::NoPass => {},
             | abi::PassMode::ByVal(t) => sig.returns.push(clif::AbiParam::new(t)),
             | abi::PassMode::ByValPair(a, b) => {
                 sig.returns.push(clif::AbiParam::new(a));
                 sig.returns.push(clif::AbiParam::new(b));
             },
-            | abi::PassMode::ByRef { size: _ } => sig.params.push(clif::AbiParam::new(ptr_type)),
+            | abi::PassMode::Cast(target) => sig.returns.extend(target.regs().map(clif::AbiParam::new)),
+            | abi::PassMode::ByRef { .. } if abi == hir::Abi::C => {
+                sig.params.push(clif::AbiParam::special(
+                    ptr_type,
+                    clif::ir::ArgumentPurpose::StructReturn,
+                ));
+            },
+            | abi::PassMode::ByRef { .. } => sig.params.push(clif::AbiParam::new(ptr_type)),
         }
 
         for arg in args {
-            match self.pass_mode(&arg) {
+            match self.pass_mode(abi, &arg) {
                 | abi::PassMode::NoPass => {},
                 | abi::PassMode::ByVal(t) => sig.params.push(clif::AbiParam::new(t)),
                 | abi::PassMode::ByValPair(a, b) => {
                     sig.params.push(clif::AbiParam::new(a));
                     sig.params.push(clif::AbiParam::new(b));
                 },
-                | abi::PassMode::ByRef { size: _ } => sig.params.push(clif::AbiParam::new(ptr_type)),
+                | abi::PassMode::Cast(target) => sig.params.extend(target.regs().map(clif::AbiParam::new)),
+                | abi::PassMode::ByRef {
+                    size: Some(size),
+                    on_stack: true,
+                } => sig.params.push(clif::AbiParam::special(
+                    ptr_type,
+                    clif::ir::ArgumentPurpose::StructArgument(((size.bytes() + 7) / 8 * 8) as u32),
+                )),
+                | abi::PassMode::ByRef { .. } => sig.params.push(clif::AbiParam::new(ptr_type)),
             }
         }
 
@@ -572,9 +769,25 @@ impl<'a> ModuleCtx<'a> {
 
         bcx.ins().call(libc_free, &[ptr]);
     }
+
+    pub fn call_fmod(&mut self, bcx: &mut clif::FunctionBuilder, lhs: clif::Value, rhs: clif::Value) -> clif::Value {
+        let ty = bcx.func.dfg.value_type(lhs);
+        let name = if ty == clif::types::F32 { "fmodf" } else { "fmod" };
+        let mut sig = self.module.make_signature();
+
+        sig.params.push(clif::AbiParam::new(ty));
+        sig.params.push(clif::AbiParam::new(ty));
+        sig.returns.push(clif::AbiParam::new(ty));
+
+        let libc_fmod = self.module.declare_function(name, clif::Linkage::Import, &sig).unwrap();
+        let libc_fmod = self.module.declare_func_in_func(libc_fmod, &mut self.ctx.func);
+        let inst = bcx.ins().call(libc_fmod, &[lhs, rhs]);
+
+        bcx.inst_results(inst)[0]
+    }
 }
 
-impl<'a, 'mcx> FunctionCtx<'a, 'mcx> {
+impl<'a, 'mcx, M: clif::Module> FunctionCtx<'a, 'mcx, M> {
     pub fn next_ssa_var(&mut self) -> u32 {
         self.ssa_vars += 1;
         self.ssa_vars
@@ -587,17 +800,21 @@ impl<'a, 'mcx> FunctionCtx<'a, 'mcx> {
     pub fn call_free(&mut self, ptr: clif::Value) {
         self.mcx.call_free(&mut self.bcx, ptr)
     }
+
+    pub fn call_fmod(&mut self, lhs: clif::Value, rhs: clif::Value) -> clif::Value {
+        self.mcx.call_fmod(&mut self.bcx, lhs, rhs)
+    }
 }
 
-impl<'a, 'mcx> std::ops::Deref for FunctionCtx<'a, 'mcx> {
-    type Target = ModuleCtx<'a>;
+impl<'a, 'mcx, M: clif::Module> std::ops::Deref for FunctionCtx<'a, 'mcx, M> {
+    type Target = ModuleCtx<'a, M>;
 
     fn deref(&self) -> &Self::Target {
         self.mcx
     }
 }
 
-impl<'a, 'mcx> std::ops::DerefMut for FunctionCtx<'a, 'mcx> {
+impl<'a, 'mcx, M: clif::Module> std::ops::DerefMut for FunctionCtx<'a, 'mcx, M> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.mcx
     }
@@ -1,9 +1,52 @@
 use std::ffi::{OsStr, OsString};
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn create() -> Box<dyn Linker> {
-    Box::new(CcLinker::new())
+/// Creates the linker with the given command: `cc`, `clang`, `ld`, `lld` or a custom program followed by
+/// its arguments. `ld` and `lld` are invoked through `cc`, which adds the C runtime objects and the C library.
+/// Custom commands are expected to accept the same arguments as `cc`.
+pub fn create(cmd: Option<&[String]>) -> Box<dyn Linker> {
+    match cmd {
+        | None => Box::new(CcLinker::new(Command::new("cc"))),
+        | Some([name]) if name == "cc" || name == "clang" => Box::new(CcLinker::new(Command::new(name))),
+        | Some([name]) if name == "ld" => Box::new(CcLinker::with_ld(Command::new("cc"), "bfd")),
+        | Some([name]) if name == "lld" => Box::new(CcLinker::with_ld(Command::new("cc"), "lld")),
+        | Some([]) => Box::new(CcLinker::new(Command::new("cc"))),
+        | Some([program, args @ ..]) => {
+            let mut cmd = Command::new(program);
+
+            cmd.args(args);
+            Box::new(CcLinker::new(cmd))
+        },
+    }
+}
+
+/// Bundles an object file into a static library with `ar`.
+pub fn archive(object: &Path, out: &Path) -> Result<(), LinkError> {
+    let mut cmd = Command::new("ar");
+
+    // the archive is replaced, so that objects of earlier builds are not kept
+    let _ = std::fs::remove_file(out);
+
+    cmd.arg("crs");
+    cmd.arg(out);
+    cmd.arg(object);
+    run(&mut cmd)
+}
+
+#[derive(Debug)]
+pub enum LinkError {
+    /// The linker could not be started or exited with an error.
+    Failed {
+        cmd: String,
+        reason: String,
+        stderr: String,
+    },
+    /// A dynamic library cannot be linked into a static executable.
+    DynamicDependency(String),
+    /// A wasm module could not be copied to the target directory.
+    Copy(PathBuf, std::io::Error),
 }
 
 pub trait Linker {
@@ -16,78 +59,66 @@ pub trait Linker {
     fn add_static_lib(&mut self, name: &str);
 
     fn build_shared_object(&mut self, out: &Path);
-    fn build_static_lib(&mut self, out: &Path);
     fn build_executable(&mut self, out: &Path);
 
-    fn run(&mut self) {
-        self.cmd().status().unwrap();
+    /// Links the executable against static libraries only, including the C library.
+    fn static_executable(&mut self);
+
+    fn run(&mut self) -> Result<(), LinkError> {
+        run(self.cmd())
     }
 }
 
-impl dyn Linker + '_ {
-    pub fn arg(&mut self, arg: impl AsRef<OsStr>) {
-        self.cmd().arg(arg);
-    }
+fn run(cmd: &mut Command) -> Result<(), LinkError> {
+    let line = command_line(cmd);
+    let failed = |reason: String, stderr: &[u8]| LinkError::Failed {
+        cmd: line.clone(),
+        reason,
+        stderr: String::from_utf8_lossy(stderr).trim_end().to_string(),
+    };
 
-    pub fn args(&mut self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) {
-        self.cmd().args(args);
+    match cmd.output() {
+        | Ok(output) if output.status.success() => Ok(()),
+        | Ok(output) => Err(failed(output.status.to_string(), &output.stderr)),
+        | Err(e) => Err(failed(e.to_string(), &[])),
     }
 }
 
-pub struct LdLinker {
-    cmd: Command,
+fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(OsStr::to_string_lossy)
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
-impl LdLinker {
-    pub fn new() -> Self {
-        LdLinker {
-            cmd: Command::new("ld"),
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            | LinkError::Failed { cmd, reason, stderr } => {
+                write!(f, "linking failed: {}\n  = command: {}", reason, cmd)?;
+
+                for line in stderr.lines() {
+                    write!(f, "\n  = {}", line)?;
+                }
+
+                Ok(())
+            },
+            | LinkError::DynamicDependency(lib) => {
+                write!(f, "cannot link the dynamic library `{}` into a static executable", lib)
+            },
+            | LinkError::Copy(path, e) => write!(f, "cannot write '{}': {}", path.display(), e),
         }
     }
 }
 
-impl Linker for LdLinker {
-    fn cmd(&mut self) -> &mut Command {
-        &mut self.cmd
-    }
-
-    fn rpath(&mut self, rpath: &Path) {
-        self.cmd.arg("-rpath");
-        self.cmd.arg(rpath);
-    }
-
-    fn add_object(&mut self, path: &Path) {
-        self.cmd.arg(path);
-    }
-
-    fn add_shared_object(&mut self, name: &str) {
-        self.cmd.arg("-Bdynamic");
-        self.cmd.arg("-l");
-        self.cmd.arg(name);
-    }
-
-    fn add_static_lib(&mut self, name: &str) {
-        self.cmd.arg("-Bstatic");
-        self.cmd.arg("-l");
-        self.cmd.arg(name);
-    }
-
-    fn build_shared_object(&mut self, out: &Path) {
-        self.cmd.arg("-shared");
-        self.cmd.arg("-o");
-        self.cmd.arg(out);
-    }
-
-    fn build_static_lib(&mut self, out: &Path) {
-        self.cmd.arg("-static");
-        self.cmd.arg("-o");
-        self.cmd.arg(out);
+impl dyn Linker + '_ {
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) {
+        self.cmd().arg(arg);
     }
 
-    fn build_executable(&mut self, out: &Path) {
-        self.cmd.arg("-no-pie");
-        self.cmd.arg("-o");
-        self.cmd.arg(out);
+    pub fn args(&mut self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) {
+        self.cmd().args(args);
     }
 }
 
@@ -96,10 +127,14 @@ pub struct CcLinker {
 }
 
 impl CcLinker {
-    pub fn new() -> Self {
-        CcLinker {
-            cmd: Command::new("cc"),
-        }
+    pub fn new(cmd: Command) -> Self {
+        CcLinker { cmd }
+    }
+
+    /// Uses the given linker, like `lld`, instead of the default linker of `cc`.
+    pub fn with_ld(mut cmd: Command, ld: &str) -> Self {
+        cmd.arg(format!("-fuse-ld={}", ld));
+        CcLinker { cmd }
     }
 }
 
@@ -135,15 +170,15 @@ impl Linker for CcLinker {
         self.cmd.arg(out);
     }
 
-    fn build_static_lib(&mut self, out: &Path) {
-        self.cmd.arg("-static");
-        self.cmd.arg("-o");
-        self.cmd.arg(out);
-    }
-
     fn build_executable(&mut self, out: &Path) {
+        // export all symbols so that the panic handler can symbolize backtraces
+        self.cmd.arg("-rdynamic");
         self.cmd.arg("-no-pie");
         self.cmd.arg("-o");
         self.cmd.arg(out);
     }
+
+    fn static_executable(&mut self) {
+        self.cmd.arg("-static");
+    }
 }
@@ -5,14 +5,15 @@ use mir::layout::{Abi, Fields, Layout, Variants};
 use mir::ty::TypeKind;
 use std::sync::Arc;
 
-impl FunctionCtx<'_, '_> {
+impl<M: clif::Module> FunctionCtx<'_, '_, M> {
     pub fn lower(&mut self) {
         // eprintln!("{}", self.body.display(self.db.upcast()));
 
         for (id, block) in self.body.blocks.iter() {
             self.bcx.switch_to_block(self.blocks[id]);
 
-            for stmt in &block.stmts {
+            for (stmt, &source) in block.stmts.iter().zip(&block.sources) {
+                self.set_source(source);
                 self.lower_stmt(stmt);
             }
 
@@ -38,6 +39,7 @@ impl FunctionCtx<'_, '_> {
 
                 self.lower_call(ret, func, args);
             },
+            | ir::Stmt::Drop(_) => {},
         }
     }
 
@@ -164,16 +166,17 @@ impl FunctionCtx<'_, '_> {
     }
 
     pub fn lower_call(&mut self, ret: PlaceRef, func: &ir::Operand, args: Vec<ValueRef>) {
-        let ret_mode = self.pass_mode(&ret.layout);
+        let abi = self.call_abi(func);
+        let ret_mode = self.pass_mode(abi, &ret.layout);
         let ret_ptr = match ret_mode {
-            | abi::PassMode::ByRef { size: _ } => Some(ret.as_ptr().get_addr(self)),
+            | abi::PassMode::ByRef { .. } => Some(ret.as_ptr().get_addr(self)),
             | _ => None,
         };
 
         let arg_layouts = args.iter().map(|a| a.layout.clone()).collect::<Vec<_>>();
         let mut args = ret_ptr
             .into_iter()
-            .chain(args.into_iter().flat_map(|a| self.value_for_arg(a)))
+            .chain(args.into_iter().flat_map(|a| self.value_for_arg(abi, a)))
             .collect::<Vec<_>>();
 
         let inst = if let ir::Operand::Const(ir::Const::Addr(id), _) = func {
@@ -183,7 +186,7 @@ impl FunctionCtx<'_, '_> {
             self.bcx.ins().call(func, &args)
         } else {
             let func_ty = self.body.operand_type(func);
-            let mut sig = self.mk_signature(&ret.layout, &arg_layouts);
+            let mut sig = self.mk_signature(abi, &ret.layout, &arg_layouts);
             let func = self.lower_op(func, None);
 
             if let TypeKind::Clos(_, _) = func_ty.kind {
@@ -214,7 +217,7 @@ impl FunctionCtx<'_, '_> {
 
         match ret_mode {
             | abi::PassMode::NoPass => {},
-            | abi::PassMode::ByRef { size: _ } => {},
+            | abi::PassMode::ByRef { .. } => {},
             | abi::PassMode::ByVal(_) => {
                 let ret_val = res.next().unwrap();
                 let ret_val = ValueRef::new_val(ret_val, ret.layout.clone());
@@ -228,6 +231,20 @@ impl FunctionCtx<'_, '_> {
 
                 ret.store(self, ret_val);
             },
+            | abi::PassMode::Cast(_) => {
+                let regs = res.collect::<Vec<_>>();
+                let ret_val = self.regs_to_value(&regs, ret.layout.clone());
+
+                ret.store(self, ret_val);
+            },
+        }
+    }
+
+    /// The abi of the function that is called, indirect calls always use the Shade abi.
+    pub fn call_abi(&self, func: &ir::Operand) -> hir::Abi {
+        match func {
+            | ir::Operand::Const(ir::Const::Addr(id), _) => self.abi_of(id.def),
+            | _ => hir::Abi::Shade,
         }
     }
 
@@ -426,7 +443,7 @@ impl FunctionCtx<'_, '_> {
         return data_id;
 
         fn rec(
-            fx: &mut FunctionCtx,
+            fx: &mut FunctionCtx<impl clif::Module>,
             dcx: &mut clif::DataContext,
             c: &ir::Const,
             layout: Arc<Layout>,
@@ -1,6 +1,7 @@
 use crate::ptr::Pointer;
 use crate::value::{ValueKind, ValueRef};
-use crate::FunctionCtx;
+use crate::{clif, FunctionCtx};
+use cranelift::codegen::entity::EntityRef as _;
 use cranelift::codegen::ir as cir;
 use cranelift::frontend::Variable;
 use cranelift::prelude::InstBuilder;
@@ -22,7 +23,7 @@ pub(crate) enum PlaceKind {
 }
 
 impl PlaceRef {
-    pub(crate) fn new_var(fx: &mut FunctionCtx, layout: Arc<Layout>) -> Self {
+    pub(crate) fn new_var(fx: &mut FunctionCtx<impl clif::Module>, layout: Arc<Layout>) -> Self {
         let var = Variable::with_u32(fx.next_ssa_var());
 
         fx.bcx.declare_var(var, fx.ir_type(&layout).unwrap());
@@ -33,7 +34,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn new_var_pair(fx: &mut FunctionCtx, layout: Arc<Layout>) -> Self {
+    pub(crate) fn new_var_pair(fx: &mut FunctionCtx<impl clif::Module>, layout: Arc<Layout>) -> Self {
         let var1 = Variable::with_u32(fx.next_ssa_var());
         let var2 = Variable::with_u32(fx.next_ssa_var());
         let (ty1, ty2) = fx.ir_pair_type(&layout).unwrap();
@@ -54,7 +55,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn new_stack(fx: &mut FunctionCtx, layout: Arc<Layout>) -> Self {
+    pub(crate) fn new_stack(fx: &mut FunctionCtx<impl clif::Module>, layout: Arc<Layout>) -> Self {
         if layout.is_zst() {
             return PlaceRef::no_place(layout);
         }
@@ -101,7 +102,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn to_value(self, fx: &mut FunctionCtx) -> ValueRef {
+    pub(crate) fn to_value(self, fx: &mut FunctionCtx<impl clif::Module>) -> ValueRef {
         match self.kind {
             | PlaceKind::Var(var) => {
                 let val = fx.bcx.use_var(var);
@@ -124,13 +125,13 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn deref(self, fx: &mut FunctionCtx) -> Self {
+    pub(crate) fn deref(self, fx: &mut FunctionCtx<impl clif::Module>) -> Self {
         let pointee = self.layout.elem(fx.db.upcast()).unwrap();
 
         Self::new_ref(Pointer::addr(self.to_value(fx).load_scalar(fx)), pointee)
     }
 
-    pub(crate) fn index(self, fx: &mut FunctionCtx, idx: ValueRef) -> Self {
+    pub(crate) fn index(self, fx: &mut FunctionCtx<impl clif::Module>, idx: ValueRef) -> Self {
         let layout = self.layout.elem(fx.db.upcast()).unwrap();
         let idx = idx.load_scalar(fx);
         let new_idx = fx.bcx.ins().imul_imm(idx, layout.stride.bytes() as i64);
@@ -143,7 +144,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn offset(self, fx: &mut FunctionCtx, offset: ValueRef) -> Self {
+    pub(crate) fn offset(self, fx: &mut FunctionCtx<impl clif::Module>, offset: ValueRef) -> Self {
         let offset = offset.load_scalar(fx);
         let ptr = self.as_ptr();
         let ptr = ptr.offset_value(fx, offset);
@@ -154,7 +155,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn field(self, fx: &mut FunctionCtx, idx: usize) -> Self {
+    pub(crate) fn field(self, fx: &mut FunctionCtx<impl clif::Module>, idx: usize) -> Self {
         let layout = self.layout.field(fx.db.upcast(), idx).unwrap();
 
         match self.kind {
@@ -186,8 +187,13 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn store(self, fx: &mut FunctionCtx, from: ValueRef) {
-        fn transmute_value(fx: &mut FunctionCtx, var: Variable, data: cir::Value, dst_ty: cir::Type) {
+    pub(crate) fn store(self, fx: &mut FunctionCtx<impl clif::Module>, from: ValueRef) {
+        fn transmute_value(
+            fx: &mut FunctionCtx<impl clif::Module>,
+            var: Variable,
+            data: cir::Value,
+            dst_ty: cir::Type,
+        ) {
             let src_ty = fx.bcx.func.dfg.value_type(data);
             let data = match (src_ty, dst_ty) {
                 | (_, _) if src_ty == dst_ty => data,
@@ -200,6 +206,10 @@ impl PlaceRef {
             };
 
             fx.bcx.def_var(var, data);
+
+            if fx.debug.is_some() {
+                fx.bcx.set_val_label(data, cir::ValueLabel::new(var.index()));
+            }
         }
 
         let dst_layout = self.layout.clone();
@@ -290,7 +300,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn write_place_ref(self, fx: &mut FunctionCtx, dest: Self) {
+    pub(crate) fn write_place_ref(self, fx: &mut FunctionCtx<impl clif::Module>, dest: Self) {
         if let Abi::ScalarPair(_, _) = self.layout.abi {
             let (ptr, extra) = self.as_ptr_maybe_unsized();
             let ptr = if let Some(extra) = extra {
@@ -307,7 +317,7 @@ impl PlaceRef {
         }
     }
 
-    pub(crate) fn downcast_variant(self, fx: &mut FunctionCtx, variant: usize) -> Self {
+    pub(crate) fn downcast_variant(self, fx: &mut FunctionCtx<impl clif::Module>, variant: usize) -> Self {
         // if let ir::Type::Box(_) = self.layout.ty.kind {
         //     return self.deref(fx).downcast_variant(fx, variant);
         // }
@@ -33,7 +33,7 @@ impl Pointer {
         }
     }
 
-    pub(crate) fn const_addr(fx: &mut FunctionCtx, addr: i64) -> Self {
+    pub(crate) fn const_addr(fx: &mut FunctionCtx<impl clif::Module>, addr: i64) -> Self {
         let ptr_type = fx.module.target_config().pointer_type();
         let addr = fx.bcx.ins().iconst(ptr_type, addr);
 
@@ -50,7 +50,7 @@ impl Pointer {
         }
     }
 
-    pub(crate) fn get_addr(self, fx: &mut FunctionCtx) -> cir::Value {
+    pub(crate) fn get_addr(self, fx: &mut FunctionCtx<impl clif::Module>) -> cir::Value {
         let ptr_type = fx.module.target_config().pointer_type();
 
         match self.kind {
@@ -68,11 +68,11 @@ impl Pointer {
         }
     }
 
-    pub(crate) fn offset(self, fx: &mut FunctionCtx, offset: Offset32) -> Self {
+    pub(crate) fn offset(self, fx: &mut FunctionCtx<impl clif::Module>, offset: Offset32) -> Self {
         self.offset_i64(fx, offset.into())
     }
 
-    pub(crate) fn offset_i64(self, fx: &mut FunctionCtx, offset: i64) -> Self {
+    pub(crate) fn offset_i64(self, fx: &mut FunctionCtx<impl clif::Module>, offset: i64) -> Self {
         if let Some(new_offset) = self.offset.try_add_i64(offset) {
             Pointer {
                 kind: self.kind,
@@ -103,7 +103,7 @@ impl Pointer {
         }
     }
 
-    pub(crate) fn offset_value(self, fx: &mut FunctionCtx, offset: cir::Value) -> Self {
+    pub(crate) fn offset_value(self, fx: &mut FunctionCtx<impl clif::Module>, offset: cir::Value) -> Self {
         let ptr_type = fx.module.target_config().pointer_type();
 
         match self.kind {
@@ -130,7 +130,12 @@ impl Pointer {
         }
     }
 
-    pub(crate) fn load(self, fx: &mut FunctionCtx, ty: cir::Type, flags: cir::MemFlags) -> cir::Value {
+    pub(crate) fn load(
+        self,
+        fx: &mut FunctionCtx<impl clif::Module>,
+        ty: cir::Type,
+        flags: cir::MemFlags,
+    ) -> cir::Value {
         match self.kind {
             | PointerKind::Addr(addr) => fx.bcx.ins().load(ty, flags, addr, self.offset),
             | PointerKind::Stack(ss) => fx.bcx.ins().stack_load(ty, ss, self.offset),
@@ -138,7 +143,7 @@ impl Pointer {
         }
     }
 
-    pub(crate) fn store(self, fx: &mut FunctionCtx, value: cir::Value, flags: cir::MemFlags) {
+    pub(crate) fn store(self, fx: &mut FunctionCtx<impl clif::Module>, value: cir::Value, flags: cir::MemFlags) {
         match self.kind {
             | PointerKind::Addr(addr) => {
                 fx.bcx.ins().store(flags, value, addr, self.offset);
@@ -7,7 +7,7 @@ pub(super) enum SsaKind {
     Ssa,
 }
 
-pub(super) fn analyze(fx: &FunctionCtx) -> ArenaMap<ir::LocalId, SsaKind> {
+pub(super) fn analyze(fx: &FunctionCtx<impl clif::Module>) -> ArenaMap<ir::LocalId, SsaKind> {
     let mut map = ArenaMap::default();
 
     for (id, local) in fx.body.locals.iter() {
@@ -28,9 +28,9 @@ pub(super) fn analyze(fx: &FunctionCtx) -> ArenaMap<ir::LocalId, SsaKind> {
                         map.insert(place.local, SsaKind::NotSsa);
                     }
                 }
-            } else if let ir::Stmt::Call(ret, ..) = stmt {
+            } else if let ir::Stmt::Call(ret, func, _) = stmt {
                 let ret_lyt = fx.db.layout_of(fx.body.locals[ret.local].ty.clone());
-                let mode = fx.pass_mode(&ret_lyt);
+                let mode = fx.pass_mode(fx.call_abi(func), &ret_lyt);
 
                 if let abi::PassMode::ByRef { .. } = mode {
                     map.insert(ret.local, SsaKind::NotSsa);
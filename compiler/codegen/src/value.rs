@@ -1,6 +1,6 @@
 use crate::place::PlaceRef;
 use crate::ptr::Pointer;
-use crate::FunctionCtx;
+use crate::{clif, FunctionCtx};
 use cranelift::codegen::ir as cir;
 use cranelift::prelude::InstBuilder;
 use mir::layout::{Abi, Layout, Primitive};
@@ -49,7 +49,7 @@ impl ValueRef {
         }
     }
 
-    pub(crate) fn new_const(val: u128, fx: &mut FunctionCtx, layout: Arc<Layout>) -> Self {
+    pub(crate) fn new_const(val: u128, fx: &mut FunctionCtx<impl clif::Module>, layout: Arc<Layout>) -> Self {
         let ty = fx.ir_type(&layout).unwrap();
         let val = match &layout.abi {
             | Abi::Scalar(scalar) => match scalar.value {
@@ -77,7 +77,7 @@ impl ValueRef {
         ValueRef::new_ref(Pointer::dangling(layout.align), layout)
     }
 
-    pub(crate) fn on_stack(self, fx: &mut FunctionCtx) -> (Pointer, Option<cir::Value>) {
+    pub(crate) fn on_stack(self, fx: &mut FunctionCtx<impl clif::Module>) -> (Pointer, Option<cir::Value>) {
         match self.kind {
             | ValueKind::Ref(ptr, meta) => (ptr, meta),
             | ValueKind::Val(_) | ValueKind::ValPair(_, _) => {
@@ -91,7 +91,7 @@ impl ValueRef {
     }
 
     #[track_caller]
-    pub(crate) fn load_scalar(self, fx: &mut FunctionCtx) -> cir::Value {
+    pub(crate) fn load_scalar(self, fx: &mut FunctionCtx<impl clif::Module>) -> cir::Value {
         match self.kind {
             | ValueKind::Ref(ptr, None) => {
                 let ty = fx.ir_type(&self.layout).unwrap();
@@ -104,7 +104,7 @@ impl ValueRef {
         }
     }
 
-    pub(crate) fn load_scalar_pair(self, fx: &mut FunctionCtx) -> (cir::Value, cir::Value) {
+    pub(crate) fn load_scalar_pair(self, fx: &mut FunctionCtx<impl clif::Module>) -> (cir::Value, cir::Value) {
         match self.kind {
             | ValueKind::Ref(ptr, None) => {
                 let (a, b) = match &self.layout.abi {
@@ -126,14 +126,14 @@ impl ValueRef {
         }
     }
 
-    pub(crate) fn cast(self, _fx: &mut FunctionCtx, layout: Arc<Layout>) -> Self {
+    pub(crate) fn cast(self, _fx: &mut FunctionCtx<impl clif::Module>, layout: Arc<Layout>) -> Self {
         ValueRef {
             kind: self.kind,
             layout,
         }
     }
 
-    pub(crate) fn field(self, fx: &mut FunctionCtx, idx: usize) -> Self {
+    pub(crate) fn field(self, fx: &mut FunctionCtx<impl clif::Module>, idx: usize) -> Self {
         match self.kind {
             | ValueKind::Val(_) => {
                 if idx == 0 {
@@ -164,7 +164,7 @@ impl ValueRef {
         }
     }
 
-    pub(crate) fn deref(self, fx: &mut FunctionCtx) -> Self {
+    pub(crate) fn deref(self, fx: &mut FunctionCtx<impl clif::Module>) -> Self {
         let pointee = self.layout.elem(fx.db.upcast()).unwrap();
         let ptr = self.load_scalar(fx);
 
@@ -182,7 +182,7 @@ impl ValueRef {
 }
 
 fn gen_field(
-    fx: &mut FunctionCtx,
+    fx: &mut FunctionCtx<impl clif::Module>,
     base: Pointer,
     meta: Option<cir::Value>,
     layout: Arc<Layout>,
@@ -0,0 +1,476 @@
+//! A minimal encoder for the WebAssembly binary format.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValType {
+    fn code(self) -> u8 {
+        match self {
+            | ValType::I32 => 0x7F,
+            | ValType::I64 => 0x7E,
+            | ValType::F32 => 0x7D,
+            | ValType::F64 => 0x7C,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub ty: u32,
+}
+
+pub struct Global {
+    pub ty: ValType,
+    pub mutable: bool,
+    pub init: i64,
+}
+
+pub enum Export {
+    Func(String, u32),
+    Memory(String),
+}
+
+pub struct Function {
+    pub ty: u32,
+    pub locals: Vec<ValType>,
+    pub code: Code,
+}
+
+#[derive(Default)]
+pub struct Module {
+    pub types: Vec<FuncType>,
+    pub imports: Vec<Import>,
+    pub funcs: Vec<Function>,
+    pub table: Vec<u32>,
+    pub memory_pages: u32,
+    pub globals: Vec<Global>,
+    pub exports: Vec<Export>,
+    pub data: Vec<(u32, Vec<u8>)>,
+}
+
+impl Module {
+    pub fn func_type(&mut self, params: Vec<ValType>, results: Vec<ValType>) -> u32 {
+        let ty = FuncType { params, results };
+
+        match self.types.iter().position(|t| *t == ty) {
+            | Some(idx) => idx as u32,
+            | None => {
+                self.types.push(ty);
+                self.types.len() as u32 - 1
+            },
+        }
+    }
+
+    pub fn finish(&self) -> Vec<u8> {
+        let mut out = b"\0asm".to_vec();
+
+        out.extend_from_slice(&[1, 0, 0, 0]);
+
+        section(&mut out, 1, &self.types, |s, ty| {
+            s.push(0x60);
+            vec(s, &ty.params, |s, p| s.push(p.code()));
+            vec(s, &ty.results, |s, r| s.push(r.code()));
+        });
+
+        section(&mut out, 2, &self.imports, |s, import| {
+            name(s, &import.module);
+            name(s, &import.name);
+            s.push(0x00);
+            uleb(s, import.ty as u64);
+        });
+
+        section(&mut out, 3, &self.funcs, |s, func| uleb(s, func.ty as u64));
+
+        // slot 0 of the table stays empty so that a null function pointer traps
+        section(&mut out, 4, &[self.table.len() as u32 + 1], |s, &min| {
+            s.push(0x70);
+            s.push(0x00);
+            uleb(s, min as u64);
+        });
+
+        section(&mut out, 5, &[self.memory_pages], |s, &min| {
+            s.push(0x00);
+            uleb(s, min as u64);
+        });
+
+        section(&mut out, 6, &self.globals, |s, global| {
+            s.push(global.ty.code());
+            s.push(global.mutable as u8);
+
+            let mut init = Code::default();
+
+            init.const_(global.ty, global.init as u64);
+            init.end();
+            s.extend_from_slice(&init.bytes);
+        });
+
+        section(&mut out, 7, &self.exports, |s, export| match export {
+            | Export::Func(n, idx) => {
+                name(s, n);
+                s.push(0x00);
+                uleb(s, *idx as u64);
+            },
+            | Export::Memory(n) => {
+                name(s, n);
+                s.push(0x02);
+                uleb(s, 0);
+            },
+        });
+
+        if !self.table.is_empty() {
+            section(&mut out, 9, &[&self.table], |s, table| {
+                uleb(s, 0);
+                s.extend_from_slice(&[0x41, 0x01, 0x0B]);
+                vec(s, &table[..], |s, &idx| uleb(s, idx as u64));
+            });
+        }
+
+        section(&mut out, 10, &self.funcs, |s, func| {
+            let mut body = Vec::new();
+            let mut groups: Vec<(u32, ValType)> = Vec::new();
+
+            for &local in &func.locals {
+                match groups.last_mut() {
+                    | Some((count, ty)) if *ty == local => *count += 1,
+                    | _ => groups.push((1, local)),
+                }
+            }
+
+            vec(&mut body, &groups, |s, (count, ty)| {
+                uleb(s, *count as u64);
+                s.push(ty.code());
+            });
+
+            body.extend_from_slice(&func.code.bytes);
+            body.push(0x0B);
+            uleb(s, body.len() as u64);
+            s.extend_from_slice(&body);
+        });
+
+        section(&mut out, 11, &self.data, |s, (offset, bytes)| {
+            uleb(s, 0);
+            s.push(0x41);
+            sleb(s, *offset as i32 as i64);
+            s.push(0x0B);
+            uleb(s, bytes.len() as u64);
+            s.extend_from_slice(bytes);
+        });
+
+        out
+    }
+}
+
+/// The instructions of a function body.
+#[derive(Default, Clone)]
+pub struct Code {
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BlockType {
+    Empty,
+    Value(ValType),
+}
+
+impl Code {
+    pub fn op(&mut self, op: u8) {
+        self.bytes.push(op);
+    }
+
+    pub fn append(&mut self, code: Code) {
+        self.bytes.extend(code.bytes);
+    }
+
+    pub fn unreachable(&mut self) {
+        self.op(0x00);
+    }
+
+    pub fn block(&mut self, ty: BlockType) {
+        self.op(0x02);
+        self.block_type(ty);
+    }
+
+    pub fn loop_(&mut self, ty: BlockType) {
+        self.op(0x03);
+        self.block_type(ty);
+    }
+
+    pub fn if_(&mut self, ty: BlockType) {
+        self.op(0x04);
+        self.block_type(ty);
+    }
+
+    pub fn else_(&mut self) {
+        self.op(0x05);
+    }
+
+    pub fn end(&mut self) {
+        self.op(0x0B);
+    }
+
+    pub fn br(&mut self, depth: u32) {
+        self.op(0x0C);
+        uleb(&mut self.bytes, depth as u64);
+    }
+
+    pub fn br_if(&mut self, depth: u32) {
+        self.op(0x0D);
+        uleb(&mut self.bytes, depth as u64);
+    }
+
+    pub fn br_table(&mut self, targets: &[u32], default: u32) {
+        self.op(0x0E);
+        vec(&mut self.bytes, targets, |s, &t| uleb(s, t as u64));
+        uleb(&mut self.bytes, default as u64);
+    }
+
+    pub fn return_(&mut self) {
+        self.op(0x0F);
+    }
+
+    pub fn call(&mut self, func: u32) {
+        self.op(0x10);
+        uleb(&mut self.bytes, func as u64);
+    }
+
+    pub fn call_indirect(&mut self, ty: u32) {
+        self.op(0x11);
+        uleb(&mut self.bytes, ty as u64);
+        self.bytes.push(0x00);
+    }
+
+    pub fn drop(&mut self) {
+        self.op(0x1A);
+    }
+
+    pub fn select(&mut self) {
+        self.op(0x1B);
+    }
+
+    pub fn local_get(&mut self, idx: u32) {
+        self.op(0x20);
+        uleb(&mut self.bytes, idx as u64);
+    }
+
+    pub fn local_set(&mut self, idx: u32) {
+        self.op(0x21);
+        uleb(&mut self.bytes, idx as u64);
+    }
+
+    pub fn local_tee(&mut self, idx: u32) {
+        self.op(0x22);
+        uleb(&mut self.bytes, idx as u64);
+    }
+
+    pub fn global_get(&mut self, idx: u32) {
+        self.op(0x23);
+        uleb(&mut self.bytes, idx as u64);
+    }
+
+    pub fn global_set(&mut self, idx: u32) {
+        self.op(0x24);
+        uleb(&mut self.bytes, idx as u64);
+    }
+
+    /// A load or store instruction with the natural alignment of the accessed bytes.
+    pub fn mem(&mut self, op: u8, offset: u32) {
+        let align = match op {
+            | op::I32_LOAD8_S | op::I32_LOAD8_U | op::I64_LOAD8_S | op::I64_LOAD8_U => 0,
+            | op::I32_STORE8 | op::I64_STORE8 => 0,
+            | op::I32_LOAD16_S | op::I32_LOAD16_U | op::I64_LOAD16_S | op::I64_LOAD16_U => 1,
+            | op::I32_STORE16 | op::I64_STORE16 => 1,
+            | op::I64_LOAD | op::F64_LOAD | op::I64_STORE | op::F64_STORE => 3,
+            | _ => 2,
+        };
+
+        // the alignment is only a hint, memory accesses never trap when it's wrong
+        self.op(op);
+        uleb(&mut self.bytes, align);
+        uleb(&mut self.bytes, offset as u64);
+    }
+
+    pub fn memory_size(&mut self) {
+        self.bytes.extend_from_slice(&[0x3F, 0x00]);
+    }
+
+    pub fn memory_grow(&mut self) {
+        self.bytes.extend_from_slice(&[0x40, 0x00]);
+    }
+
+    pub fn i32_const(&mut self, val: i32) {
+        self.op(0x41);
+        sleb(&mut self.bytes, val as i64);
+    }
+
+    pub fn i64_const(&mut self, val: i64) {
+        self.op(0x42);
+        sleb(&mut self.bytes, val);
+    }
+
+    /// A constant of the given type from its raw bits.
+    pub fn const_(&mut self, ty: ValType, bits: u64) {
+        match ty {
+            | ValType::I32 => self.i32_const(bits as u32 as i32),
+            | ValType::I64 => self.i64_const(bits as i64),
+            | ValType::F32 => {
+                self.op(0x43);
+                self.bytes.extend_from_slice(&(bits as u32).to_le_bytes());
+            },
+            | ValType::F64 => {
+                self.op(0x44);
+                self.bytes.extend_from_slice(&bits.to_le_bytes());
+            },
+        }
+    }
+
+    fn block_type(&mut self, ty: BlockType) {
+        match ty {
+            | BlockType::Empty => self.bytes.push(0x40),
+            | BlockType::Value(ty) => self.bytes.push(ty.code()),
+        }
+    }
+}
+
+pub mod op {
+    pub const I32_LOAD: u8 = 0x28;
+    pub const I64_LOAD: u8 = 0x29;
+    pub const F32_LOAD: u8 = 0x2A;
+    pub const F64_LOAD: u8 = 0x2B;
+    pub const I32_LOAD8_S: u8 = 0x2C;
+    pub const I32_LOAD8_U: u8 = 0x2D;
+    pub const I32_LOAD16_S: u8 = 0x2E;
+    pub const I32_LOAD16_U: u8 = 0x2F;
+    pub const I64_LOAD8_S: u8 = 0x30;
+    pub const I64_LOAD8_U: u8 = 0x31;
+    pub const I64_LOAD16_S: u8 = 0x32;
+    pub const I64_LOAD16_U: u8 = 0x33;
+    pub const I64_LOAD32_U: u8 = 0x35;
+    pub const I32_STORE: u8 = 0x36;
+    pub const I64_STORE: u8 = 0x37;
+    pub const F32_STORE: u8 = 0x38;
+    pub const F64_STORE: u8 = 0x39;
+    pub const I32_STORE8: u8 = 0x3A;
+    pub const I32_STORE16: u8 = 0x3B;
+    pub const I64_STORE8: u8 = 0x3C;
+    pub const I64_STORE16: u8 = 0x3D;
+
+    pub const I32_EQZ: u8 = 0x45;
+    pub const I32_EQ: u8 = 0x46;
+    pub const I32_NE: u8 = 0x47;
+    pub const I32_LT_S: u8 = 0x48;
+    pub const I32_LT_U: u8 = 0x49;
+    pub const I32_GT_S: u8 = 0x4A;
+    pub const I32_GT_U: u8 = 0x4B;
+    pub const I32_LE_S: u8 = 0x4C;
+    pub const I32_LE_U: u8 = 0x4D;
+    pub const I32_GE_S: u8 = 0x4E;
+    pub const I32_GE_U: u8 = 0x4F;
+    pub const I64_EQZ: u8 = 0x50;
+    pub const I64_EQ: u8 = 0x51;
+    pub const I64_NE: u8 = 0x52;
+    pub const F32_EQ: u8 = 0x5B;
+    pub const F64_EQ: u8 = 0x61;
+
+    pub const I32_ADD: u8 = 0x6A;
+    pub const I32_SUB: u8 = 0x6B;
+    pub const I32_MUL: u8 = 0x6C;
+    pub const I32_DIV_S: u8 = 0x6D;
+    pub const I32_DIV_U: u8 = 0x6E;
+    pub const I32_REM_S: u8 = 0x6F;
+    pub const I32_REM_U: u8 = 0x70;
+    pub const I32_AND: u8 = 0x71;
+    pub const I32_OR: u8 = 0x72;
+    pub const I32_XOR: u8 = 0x73;
+    pub const I32_SHL: u8 = 0x74;
+    pub const I32_SHR_S: u8 = 0x75;
+    pub const I32_SHR_U: u8 = 0x76;
+    pub const I64_ADD: u8 = 0x7C;
+    pub const I64_MUL: u8 = 0x7E;
+    pub const I64_DIV_S: u8 = 0x7F;
+    pub const I64_SHR_S: u8 = 0x87;
+    pub const I64_SHR_U: u8 = 0x88;
+    pub const F32_NEG: u8 = 0x8C;
+    pub const F32_TRUNC: u8 = 0x8F;
+    pub const F32_ADD: u8 = 0x92;
+    pub const F64_NEG: u8 = 0x9A;
+    pub const F64_TRUNC: u8 = 0x9D;
+    pub const F64_ADD: u8 = 0xA0;
+
+    pub const I32_WRAP_I64: u8 = 0xA7;
+    pub const I32_TRUNC_F32_S: u8 = 0xA8;
+    pub const I64_EXTEND_I32_S: u8 = 0xAC;
+    pub const I64_EXTEND_I32_U: u8 = 0xAD;
+    pub const I64_TRUNC_F32_S: u8 = 0xAE;
+    pub const F32_CONVERT_I32_S: u8 = 0xB2;
+    pub const F32_DEMOTE_F64: u8 = 0xB6;
+    pub const F64_CONVERT_I32_S: u8 = 0xB7;
+    pub const F64_PROMOTE_F32: u8 = 0xBB;
+}
+
+fn section<T>(out: &mut Vec<u8>, id: u8, items: &[T], f: impl FnMut(&mut Vec<u8>, &T)) {
+    if items.is_empty() {
+        return;
+    }
+
+    let mut contents = Vec::new();
+
+    vec(&mut contents, items, f);
+    out.push(id);
+    uleb(out, contents.len() as u64);
+    out.extend_from_slice(&contents);
+}
+
+fn vec<T>(out: &mut Vec<u8>, items: &[T], mut f: impl FnMut(&mut Vec<u8>, &T)) {
+    uleb(out, items.len() as u64);
+
+    for item in items {
+        f(out, item);
+    }
+}
+
+fn name(out: &mut Vec<u8>, name: &str) {
+    uleb(out, name.len() as u64);
+    out.extend_from_slice(name.as_bytes());
+}
+
+fn uleb(out: &mut Vec<u8>, mut val: u64) {
+    loop {
+        let byte = (val & 0x7F) as u8;
+
+        val >>= 7;
+
+        if val == 0 {
+            out.push(byte);
+            break;
+        }
+
+        out.push(byte | 0x80);
+    }
+}
+
+fn sleb(out: &mut Vec<u8>, mut val: i64) {
+    loop {
+        let byte = (val & 0x7F) as u8;
+
+        val >>= 7;
+
+        if (val == 0 && byte & 0x40 == 0) || (val == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            break;
+        }
+
+        out.push(byte | 0x80);
+    }
+}
@@ -0,0 +1,159 @@
+//! Runs `wasm32-wasi` executables with an interpreter that provides the WASI functions used by the runtime.
+
+use std::io::Write;
+use std::path::Path;
+use wasmi::{
+    Externals, FuncInstance, FuncRef, ImportsBuilder, MemoryRef, ModuleImportResolver, ModuleInstance, RuntimeArgs,
+    RuntimeValue, Signature, Trap, TrapKind, ValueType,
+};
+
+const FD_WRITE: usize = 0;
+const PROC_EXIT: usize = 1;
+
+/// An error that stops a module before it exits.
+#[derive(Debug)]
+pub enum RunError {
+    Read(std::io::Error),
+    Instantiate(wasmi::Error),
+    /// The module executed an instruction that traps, like `unreachable` or a division by zero.
+    Trap(TrapKind),
+    Failed(wasmi::Error),
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            | RunError::Read(e) => write!(f, "cannot read the module: {}", e),
+            | RunError::Instantiate(e) => write!(f, "invalid module: {}", e),
+            | RunError::Trap(kind) => write!(f, "the module trapped: {:?}", kind),
+            | RunError::Failed(e) => write!(f, "failed to run the module: {}", e),
+        }
+    }
+}
+
+/// Runs the `_start` function of a module and returns its exit code.
+pub fn run(path: &Path) -> Result<i32, RunError> {
+    let bytes = std::fs::read(path).map_err(RunError::Read)?;
+    let module = wasmi::Module::from_buffer(&bytes).map_err(RunError::Instantiate)?;
+    let imports = ImportsBuilder::new().with_resolver("wasi_snapshot_preview1", &WasiResolver);
+    let instance = ModuleInstance::new(&module, &imports)
+        .map_err(RunError::Instantiate)?
+        .assert_no_start();
+
+    let mut wasi = Wasi {
+        memory: instance.export_by_name("memory").and_then(|e| e.as_memory().cloned()),
+    };
+
+    match instance.invoke_export("_start", &[], &mut wasi) {
+        | Ok(_) => Ok(0),
+        | Err(wasmi::Error::Trap(trap)) => {
+            if let TrapKind::Host(e) = trap.kind() {
+                if let Some(Exit(code)) = e.downcast_ref::<Exit>() {
+                    return Ok(*code);
+                }
+            }
+
+            Err(RunError::Trap(trap.into_kind()))
+        },
+        | Err(e) => Err(RunError::Failed(e)),
+    }
+}
+
+struct WasiResolver;
+
+impl ModuleImportResolver for WasiResolver {
+    fn resolve_func(&self, field_name: &str, signature: &Signature) -> Result<FuncRef, wasmi::Error> {
+        let (index, expected) = match field_name {
+            | "fd_write" => (FD_WRITE, Signature::new(vec![ValueType::I32; 4], Some(ValueType::I32))),
+            | "proc_exit" => (PROC_EXIT, Signature::new(vec![ValueType::I32], None)),
+            | _ => {
+                return Err(wasmi::Error::Instantiation(format!(
+                    "unknown import wasi_snapshot_preview1.{}",
+                    field_name
+                )))
+            },
+        };
+
+        if *signature != expected {
+            return Err(wasmi::Error::Instantiation(format!(
+                "wasi_snapshot_preview1.{} has the wrong signature",
+                field_name
+            )));
+        }
+
+        Ok(FuncInstance::alloc_host(expected, index))
+    }
+}
+
+struct Wasi {
+    memory: Option<MemoryRef>,
+}
+
+#[derive(Debug)]
+struct Exit(i32);
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "exit with code {}", self.0)
+    }
+}
+
+impl wasmi::HostError for Exit {
+}
+
+impl Externals for Wasi {
+    fn invoke_index(&mut self, index: usize, args: RuntimeArgs) -> Result<Option<RuntimeValue>, Trap> {
+        match index {
+            | FD_WRITE => {
+                let fd: u32 = args.nth_checked(0)?;
+                let iovs: u32 = args.nth_checked(1)?;
+                let iovs_len: u32 = args.nth_checked(2)?;
+                let nwritten: u32 = args.nth_checked(3)?;
+                let errno = self.fd_write(fd, iovs, iovs_len, nwritten)?;
+
+                Ok(Some(RuntimeValue::I32(errno)))
+            },
+            | PROC_EXIT => {
+                let code: i32 = args.nth_checked(0)?;
+
+                Err(Trap::new(TrapKind::Host(Box::new(Exit(code)))))
+            },
+            | _ => unreachable!(),
+        }
+    }
+}
+
+impl Wasi {
+    const ERRNO_BADF: i32 = 8;
+    const ERRNO_IO: i32 = 29;
+
+    fn fd_write(&mut self, fd: u32, iovs: u32, iovs_len: u32, nwritten: u32) -> Result<i32, Trap> {
+        let memory = self
+            .memory
+            .as_ref()
+            .ok_or_else(|| Trap::new(TrapKind::MemoryAccessOutOfBounds))?;
+        let out_of_bounds = |_| Trap::new(TrapKind::MemoryAccessOutOfBounds);
+        let mut bytes = Vec::new();
+
+        for i in 0..iovs_len {
+            let ptr: u32 = memory.get_value(iovs + i * 8).map_err(out_of_bounds)?;
+            let len: u32 = memory.get_value(iovs + i * 8 + 4).map_err(out_of_bounds)?;
+
+            bytes.extend(memory.get(ptr, len as usize).map_err(out_of_bounds)?);
+        }
+
+        let res = match fd {
+            | 1 => std::io::stdout().write_all(&bytes),
+            | 2 => std::io::stderr().write_all(&bytes),
+            | _ => return Ok(Self::ERRNO_BADF),
+        };
+
+        if res.is_err() {
+            return Ok(Self::ERRNO_IO);
+        }
+
+        memory.set_value(nwritten, bytes.len() as u32).map_err(out_of_bounds)?;
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,1839 @@
+//! Compiles libraries to a WebAssembly module for `wasm32-wasi`.
+//!
+//! Unlike the Cranelift backend every local lives in memory. Functions allocate a frame on a shadow stack
+//! that grows down from the `sp` global, take a pointer to their return place followed by a pointer to a
+//! copy of each argument, and function pointers are indices into the table.
+//! A library is compiled together with all of its dependencies into a single module.
+
+mod encode;
+mod interp;
+mod runtime;
+
+use crate::db::CodegenDatabase;
+use base_db::libs::LibKind;
+use encode::{op, BlockType, Code, ValType};
+use hir::arena::ArenaMap;
+use mir::intrinsic::{BinOp, CmpOp, Intrinsic, ScalarType, UnOp};
+use mir::ir;
+use mir::layout::{Abi, Fields, Integer, Layout, Primitive, TagEncoding, Variants};
+use mir::ty::TypeKind;
+use mir::visit::Visitor;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt;
+use std::sync::Arc;
+
+pub use interp::{run, RunError};
+
+/// The start of the static data, the first bytes are kept free so that null pointers don't point to data.
+const DATA_START: u32 = 1024;
+const STACK_SIZE: u32 = 1 << 20;
+const PAGE_SIZE: u32 = 1 << 16;
+
+const SP: u32 = 0;
+const HEAP_END: u32 = 1;
+const FREE_LIST: u32 = 2;
+
+/// A construct that cannot be compiled to WebAssembly. No module is produced when there are any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The link name of the function or static that uses it.
+    pub item: String,
+    pub what: &'static str,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} are not supported on wasm targets, used by `{}`", self.what, self.item)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Callee {
+    Shade(u32),
+    /// A foreign function, which is called with the C calling convention.
+    Foreign(u32),
+}
+
+#[derive(Clone, Copy)]
+enum LocalPlace {
+    Param(u32),
+    Frame(u32),
+}
+
+/// How a value is passed to a foreign function.
+#[derive(Clone, Copy)]
+enum CPass {
+    Ignore,
+    Direct(ValType),
+    Indirect,
+}
+
+struct PlaceInfo {
+    layout: Arc<Layout>,
+    /// The local holding the length of an unsized place.
+    meta: Option<(u32, ValType)>,
+}
+
+struct ModuleCtx<'a> {
+    db: &'a dyn CodegenDatabase,
+    module: encode::Module,
+    rt: runtime::Runtime,
+    pointer_bits: u32,
+    /// The bodies that are defined in this module, in the order of their function indices.
+    bodies: Vec<ir::BodyId>,
+    foreign: Vec<(String, ir::BodyId)>,
+    /// The foreign functions that trap when called, with their type.
+    stubs: Vec<(ir::BodyId, u32)>,
+    exports: Vec<(String, ir::BodyId)>,
+    func_ids: FxHashMap<ir::BodyId, Callee>,
+    table_slots: FxHashMap<u32, u32>,
+    strings: FxHashMap<String, u32>,
+    data: Vec<u8>,
+    /// The item that is being lowered, for reporting unsupported constructs.
+    item: String,
+    errors: Vec<Unsupported>,
+}
+
+struct FunctionCtx<'a, 'mcx> {
+    mcx: &'mcx mut ModuleCtx<'a>,
+    body: &'mcx ir::Body,
+    code: Code,
+    params: u32,
+    locals: Vec<ValType>,
+    places: ArenaMap<ir::LocalId, LocalPlace>,
+    blocks: ArenaMap<ir::BlockId, u32>,
+    frame_size: u32,
+    fp: u32,
+    old_sp: u32,
+    bb: u32,
+    /// The number of blocks between the current instruction and the loop that dispatches to the next block.
+    depth: u32,
+}
+
+pub(crate) fn build(db: &dyn CodegenDatabase, lib: hir::Lib) -> Result<Vec<u8>, Vec<Unsupported>> {
+    let mut mcx = ModuleCtx {
+        db,
+        module: encode::Module::default(),
+        rt: runtime::Runtime::default(),
+        pointer_bits: db.target_triple().pointer_width().unwrap().bits() as u32,
+        bodies: Vec::new(),
+        foreign: Vec::new(),
+        stubs: Vec::new(),
+        exports: Vec::new(),
+        func_ids: FxHashMap::default(),
+        table_slots: FxHashMap::default(),
+        strings: FxHashMap::default(),
+        data: Vec::new(),
+        item: String::new(),
+        errors: Vec::new(),
+    };
+
+    mcx.collect_lib(lib, &mut FxHashSet::default());
+
+    let mut roots = mcx.exports.iter().map(|&(_, id)| id).collect::<Vec<_>>();
+
+    if let LibKind::Executable = db.libs()[lib.into()].kind {
+        let main = crate::main_func(db, lib);
+        let report = crate::report_func(db, lib, main);
+        let main: hir::id::FuncId = main.into();
+
+        roots.push(db.body_mir(main.into()).main_id(main.into()));
+        roots.push(db.body_mir(report.into()).main_id(report.into()));
+    }
+
+    mcx.retain_reachable(roots);
+    mcx.declare_funcs(db.libs()[lib.into()].kind);
+    mcx.lower_bodies();
+
+    if let LibKind::Executable = db.libs()[lib.into()].kind {
+        mcx.generate_start(lib);
+    }
+
+    if mcx.errors.is_empty() {
+        Ok(mcx.finish())
+    } else {
+        Err(mcx.errors)
+    }
+}
+
+impl<'a> ModuleCtx<'a> {
+    fn collect_lib(&mut self, lib: hir::Lib, done: &mut FxHashSet<hir::Lib>) {
+        if !done.insert(lib) {
+            return;
+        }
+
+        for dep in lib.dependencies(self.db.upcast()) {
+            self.collect_lib(dep.lib, done);
+        }
+
+        for module in lib.modules(self.db.upcast()) {
+            if module.is_virtual(self.db.upcast()) {
+                continue;
+            }
+
+            for def in module.declarations(self.db.upcast()) {
+                if let hir::ModuleDef::Func(f) = def {
+                    self.collect_func(f);
+                }
+            }
+
+            for inst in module.instances(self.db.upcast()) {
+                for def in inst.items(self.db.upcast()) {
+                    if let hir::AssocItem::Func(f) = def {
+                        self.collect_func(f);
+                    }
+                }
+            }
+        }
+    }
+
+    fn collect_func(&mut self, func: hir::Func) {
+        if let Some(it) = func.as_assoc_item(self.db.upcast()) {
+            if let hir::AssocItemContainer::Class(_) = it.container(self.db.upcast()) {
+                return;
+            }
+        } else if func.is_foreign(self.db.upcast()) {
+            let attrs = self.db.attrs(hir::id::AttrDefId::FuncId(func.into()));
+
+            if attrs.by_key("intrinsic").exists() {
+                return;
+            }
+        }
+
+        let def: hir::id::FuncId = func.into();
+        let def: hir::id::DefWithBodyId = def.into();
+        let bodies = self.db.body_mir(def);
+        let name = func.link_name(self.db.upcast()).to_string();
+
+        if func.is_foreign(self.db.upcast()) {
+            self.foreign.push((name, bodies.main_id(def)));
+            return;
+        }
+
+        if func.is_exported(self.db.upcast()) {
+            self.exports.push((name, bodies.main_id(def)));
+        }
+
+        self.bodies.extend(bodies.ids(def));
+    }
+
+    /// Removes the bodies and foreign functions that cannot be reached from `roots`, so that functions
+    /// of dependencies that use unsupported constructs are only reported when they are used.
+    /// Statics are reported when their address is taken.
+    fn retain_reachable(&mut self, roots: Vec<ir::BodyId>) {
+        fn visit_const(c: &ir::Const, found: &mut Vec<ir::BodyId>) {
+            match c {
+                | ir::Const::Addr(id) => found.push(*id),
+                | ir::Const::Tuple(cs) | ir::Const::Ctor(_, cs) => cs.iter().for_each(|c| visit_const(c, found)),
+                | ir::Const::Ref(c) => visit_const(c, found),
+                | _ => {},
+            }
+        }
+
+        struct Refs(Vec<ir::BodyId>);
+
+        impl Visitor for Refs {
+            fn visit_operand(&mut self, op: &ir::Operand) {
+                if let ir::Operand::Const(c, _) = op {
+                    visit_const(c, &mut self.0);
+                }
+
+                self.super_operand(op);
+            }
+        }
+
+        let foreign = self.foreign.iter().map(|&(_, id)| id).collect::<FxHashSet<_>>();
+        let mut reachable = FxHashSet::default();
+        let mut queue = roots;
+
+        while let Some(id) = queue.pop() {
+            if !reachable.insert(id) || foreign.contains(&id) {
+                continue;
+            }
+
+            if let hir::id::DefWithBodyId::StaticId(_) = id.def {
+                continue;
+            }
+
+            let bodies = self.db.body_mir(id.def);
+            let mut refs = Refs(Vec::new());
+
+            refs.visit_body(&bodies[id.local_id]);
+            queue.extend(refs.0);
+        }
+
+        self.bodies.retain(|id| reachable.contains(id));
+        self.foreign.retain(|(_, id)| reachable.contains(id));
+    }
+
+    fn unsupported(&mut self, what: &'static str) {
+        self.errors.push(Unsupported {
+            item: self.item.clone(),
+            what,
+        });
+    }
+
+    fn item_name(&self, def: hir::id::DefWithBodyId) -> String {
+        match def {
+            | hir::id::DefWithBodyId::FuncId(id) => hir::Func::from(id).link_name(self.db.upcast()).to_string(),
+            | hir::id::DefWithBodyId::StaticId(id) => hir::Static::from(id).link_name(self.db.upcast()).to_string(),
+            | hir::id::DefWithBodyId::ConstId(id) => hir::Const::from(id).path(self.db.upcast()).to_string(),
+        }
+    }
+
+    /// Reports the 128-bit integers in the body, which cannot be represented by wasm values.
+    fn check_i128(&mut self, id: ir::BodyId) -> bool {
+        struct Check<'a> {
+            db: &'a dyn CodegenDatabase,
+            body: &'a ir::Body,
+            found: bool,
+        }
+
+        impl Visitor for Check<'_> {
+            fn visit_local(&mut self, local: &ir::Local) {
+                self.found |= has_i128(self.db, &self.db.layout_of(local.ty.clone()));
+            }
+
+            fn visit_operand(&mut self, op: &ir::Operand) {
+                if let ir::Operand::Const(..) = op {
+                    self.found |= has_i128(self.db, &self.db.layout_of(self.body.operand_type(op)));
+                }
+
+                self.super_operand(op);
+            }
+        }
+
+        let bodies = self.db.body_mir(id.def);
+        let body = &bodies[id.local_id];
+        let mut check = Check {
+            db: self.db,
+            body,
+            found: false,
+        };
+
+        check.visit_body(body);
+
+        if check.found {
+            self.unsupported("128-bit integers");
+        }
+
+        !check.found
+    }
+
+    /// Assigns function indices. Imports come first, foreign functions that the runtime implements
+    /// are not imported. Executables are run with only the WASI imports, so the other foreign functions
+    /// they use are replaced by stubs that trap. Libraries import them from `env` instead.
+    fn declare_funcs(&mut self, kind: LibKind) {
+        let wasi = "wasi_snapshot_preview1";
+        let fd_write = self.module.func_type(vec![ValType::I32; 4], vec![ValType::I32]);
+        let proc_exit = self.module.func_type(vec![ValType::I32], Vec::new());
+
+        self.import(wasi, "fd_write", fd_write);
+        self.import(wasi, "proc_exit", proc_exit);
+
+        let mut provided = Vec::new();
+
+        for (name, id) in std::mem::take(&mut self.foreign) {
+            self.item = name.clone();
+
+            if !self.check_i128(id) {
+                self.func_ids.insert(id, Callee::Foreign(0));
+                continue;
+            }
+
+            let (params, results) = self.c_signature(id);
+
+            if runtime::provides(&name, &params, &results) {
+                provided.push((name, id));
+            } else if let LibKind::Executable = kind {
+                let ty = self.module.func_type(params, results);
+
+                self.stubs.push((id, ty));
+            } else {
+                let ty = self.module.func_type(params, results);
+                let idx = self.import("env", &name, ty);
+
+                self.func_ids.insert(id, Callee::Foreign(idx));
+            }
+        }
+
+        let base = self.module.imports.len() as u32;
+
+        self.rt = runtime::Runtime::new(0, 1, base);
+
+        for (name, id) in provided {
+            self.func_ids.insert(id, Callee::Foreign(self.rt.get(&name)));
+        }
+
+        for (i, &id) in self.bodies.iter().enumerate() {
+            self.func_ids
+                .insert(id, Callee::Shade(base + runtime::COUNT + i as u32));
+        }
+
+        let base = base + runtime::COUNT + self.bodies.len() as u32;
+
+        for (i, &(id, _)) in self.stubs.iter().enumerate() {
+            self.func_ids.insert(id, Callee::Foreign(base + i as u32));
+        }
+    }
+
+    fn import(&mut self, module: &str, name: &str, ty: u32) -> u32 {
+        self.module.imports.push(encode::Import {
+            module: module.to_string(),
+            name: name.to_string(),
+            ty,
+        });
+
+        self.module.imports.len() as u32 - 1
+    }
+
+    fn lower_bodies(&mut self) {
+        let newline = self.alloc_data(b"\n", 1);
+
+        runtime::define(&mut self.module, &self.rt, newline);
+
+        for id in self.bodies.clone() {
+            self.lower_body(id);
+        }
+
+        for (_, ty) in std::mem::take(&mut self.stubs) {
+            let mut code = Code::default();
+
+            code.unreachable();
+            self.module.funcs.push(encode::Function {
+                ty,
+                locals: Vec::new(),
+                code,
+            });
+        }
+
+        for (name, id) in std::mem::take(&mut self.exports) {
+            if let Callee::Shade(idx) = self.func_ids[&id] {
+                self.module.exports.push(encode::Export::Func(name, idx));
+            }
+        }
+    }
+
+    fn lower_body(&mut self, id: ir::BodyId) {
+        self.item = self.item_name(id.def);
+
+        if !self.check_i128(id) {
+            return;
+        }
+
+        let bodies = self.db.body_mir(id.def);
+        let body = &bodies[id.local_id];
+        let args = body.args();
+        let params = args.len() as u32 + 1;
+        let mut fx = FunctionCtx {
+            mcx: self,
+            body,
+            code: Code::default(),
+            params,
+            locals: vec![ValType::I32; 3],
+            places: ArenaMap::default(),
+            blocks: ArenaMap::default(),
+            frame_size: 0,
+            fp: params,
+            old_sp: params + 1,
+            bb: params + 2,
+            depth: 0,
+        };
+
+        fx.places.insert(body.ret.unwrap(), LocalPlace::Param(0));
+
+        for (i, &arg) in args.iter().enumerate() {
+            fx.places.insert(arg, LocalPlace::Param(i as u32 + 1));
+        }
+
+        for (local, data) in body.locals.iter() {
+            if let ir::LocalKind::Var = data.kind {
+                let layout = fx.db.layout_of(data.ty.clone());
+                let offset = fx.alloc_frame(&layout);
+
+                fx.places.insert(local, LocalPlace::Frame(offset));
+            }
+        }
+
+        for (i, (block, _)) in body.blocks.iter().enumerate() {
+            fx.blocks.insert(block, i as u32);
+        }
+
+        fx.lower();
+
+        let func = fx.finish();
+
+        self.module.funcs.push(func);
+    }
+
+    /// Defines `_start`, which calls the main function of the library, reports its result and exits with the
+    /// returned code.
+    fn generate_start(&mut self, lib: hir::Lib) {
+        let main = crate::main_func(self.db, lib);
+        let report = crate::report_func(self.db, lib, main);
+        let main: hir::id::FuncId = main.into();
+        let main = self.db.body_mir(main.into()).main_id(main.into());
+        let report = self.db.body_mir(report.into()).main_id(report.into());
+        let main_ret = self.ret_layout(main);
+        let report_ret = self.ret_layout(report);
+        let ret_offset = align_to(main_ret.size.bytes() as u32, report_ret.align.bytes() as u32);
+        let frame_size = align_to(ret_offset + report_ret.size.bytes() as u32, 16);
+        let mut code = Code::default();
+
+        code.global_get(SP);
+        code.i32_const(frame_size as i32);
+        code.op(op::I32_SUB);
+        code.local_tee(0);
+        code.global_set(SP);
+
+        code.local_get(0);
+        code.call(self.callee(main));
+
+        code.local_get(0);
+        code.i32_const(ret_offset as i32);
+        code.op(op::I32_ADD);
+        code.local_get(0);
+        code.call(self.callee(report));
+
+        if report_ret.is_zst() {
+            code.i32_const(0);
+        } else {
+            code.local_get(0);
+
+            if load(&mut code, &report_ret, ret_offset) == ValType::I64 {
+                code.op(op::I32_WRAP_I64);
+            }
+        }
+
+        code.call(self.rt.proc_exit);
+
+        let idx = (self.module.imports.len() + self.module.funcs.len()) as u32;
+        let ty = self.module.func_type(Vec::new(), Vec::new());
+
+        self.module.funcs.push(encode::Function {
+            ty,
+            locals: vec![ValType::I32],
+            code,
+        });
+
+        self.module
+            .exports
+            .push(encode::Export::Func(String::from("_start"), idx));
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let data_end = DATA_START + self.data.len() as u32;
+        let stack_top = align_to(data_end, 16) + STACK_SIZE;
+        let global = |init: u32| encode::Global {
+            ty: ValType::I32,
+            mutable: true,
+            init: init as i64,
+        };
+
+        // the heap starts above the stack and grows upwards
+        self.module.globals = vec![global(stack_top), global(stack_top), global(0)];
+        self.module.memory_pages = (stack_top + PAGE_SIZE - 1) / PAGE_SIZE + 1;
+        self.module.exports.push(encode::Export::Memory(String::from("memory")));
+        self.module.data.push((DATA_START, std::mem::take(&mut self.data)));
+        self.module.finish()
+    }
+
+    fn callee(&self, id: ir::BodyId) -> u32 {
+        match self.func_ids.get(&id) {
+            | Some(Callee::Shade(idx)) | Some(Callee::Foreign(idx)) => *idx,
+            | None => panic!("function {:?} is not part of the module", id),
+        }
+    }
+
+    fn ret_layout(&self, id: ir::BodyId) -> Arc<Layout> {
+        let bodies = self.db.body_mir(id.def);
+        let body = &bodies[id.local_id];
+
+        self.db.layout_of(body.locals[body.ret.unwrap()].ty.clone())
+    }
+
+    /// The signature of a foreign function according to the C calling convention of `wasm32`.
+    fn c_signature(&self, id: ir::BodyId) -> (Vec<ValType>, Vec<ValType>) {
+        let bodies = self.db.body_mir(id.def);
+        let body = &bodies[id.local_id];
+        let mut params = Vec::new();
+        let mut results = Vec::new();
+
+        match c_pass(&self.ret_layout(id)) {
+            | CPass::Ignore => {},
+            | CPass::Direct(ty) => results.push(ty),
+            | CPass::Indirect => params.push(ValType::I32),
+        }
+
+        for arg in body.args() {
+            match c_pass(&self.db.layout_of(body.locals[arg].ty.clone())) {
+                | CPass::Ignore => {},
+                | CPass::Direct(ty) => params.push(ty),
+                | CPass::Indirect => params.push(ValType::I32),
+            }
+        }
+
+        (params, results)
+    }
+
+    /// The address of a function is its slot in the table.
+    fn body_addr(&mut self, id: &ir::BodyId) -> u32 {
+        let func = match (id.def, self.func_ids.get(id)) {
+            | (hir::id::DefWithBodyId::StaticId(_), _) => {
+                self.unsupported("addresses of statics");
+                return 0;
+            },
+            | (_, Some(Callee::Shade(idx))) => *idx,
+            | (_, Some(Callee::Foreign(_))) => {
+                self.unsupported("addresses of foreign functions");
+                return 0;
+            },
+            | (_, None) => panic!("function {:?} is not part of the module", id),
+        };
+
+        if let Some(&slot) = self.table_slots.get(&func) {
+            return slot;
+        }
+
+        self.module.table.push(func);
+
+        let slot = self.module.table.len() as u32;
+
+        self.table_slots.insert(func, slot);
+        slot
+    }
+
+    fn alloc_data(&mut self, bytes: &[u8], align: u32) -> u32 {
+        let start = align_to(DATA_START + self.data.len() as u32, align);
+
+        self.data.resize((start - DATA_START) as usize, 0);
+        self.data.extend_from_slice(bytes);
+        start
+    }
+
+    fn alloc_string(&mut self, s: &str) -> u32 {
+        if let Some(&addr) = self.strings.get(s) {
+            return addr;
+        }
+
+        let addr = self.alloc_data(s.as_bytes(), 1);
+
+        self.strings.insert(s.to_string(), addr);
+        addr
+    }
+
+    fn alloc_const(&mut self, c: &ir::Const, layout: &Layout) -> u32 {
+        let mut bytes = vec![0; layout.size.bytes() as usize];
+
+        self.write_const(&mut bytes, c, layout);
+        self.alloc_data(&bytes, layout.align.bytes() as u32)
+    }
+
+    fn write_const(&mut self, bytes: &mut [u8], c: &ir::Const, layout: &Layout) {
+        match c {
+            | ir::Const::Undefined => {},
+            | ir::Const::Scalar(s) => {
+                let len = bytes.len();
+
+                bytes.copy_from_slice(&s.to_le_bytes()[..len]);
+            },
+            | ir::Const::Tuple(cs) if cs.is_empty() => {},
+            | ir::Const::Tuple(cs) => match &layout.fields {
+                | Fields::Primitive => self.write_const(bytes, &cs[0], layout),
+                | fields => {
+                    for (i, c) in cs.iter().enumerate() {
+                        let field = layout.field(self.db.upcast(), i).unwrap();
+                        let offset = fields.offset(i).bytes() as usize;
+                        let size = field.size.bytes() as usize;
+
+                        self.write_const(&mut bytes[offset..offset + size], c, &field);
+                    }
+                },
+            },
+            | ir::Const::Addr(id) => {
+                let addr = self.body_addr(id);
+
+                bytes.copy_from_slice(&addr.to_le_bytes());
+            },
+            | ir::Const::Ref(to) => {
+                let elem = layout.elem(self.db.upcast()).unwrap();
+                let addr = self.alloc_const(to, &elem);
+
+                bytes.copy_from_slice(&addr.to_le_bytes());
+            },
+            | ir::Const::String(s) => {
+                let ptr = self.alloc_string(s);
+                let len_layout = layout.field(self.db.upcast(), 1).unwrap();
+                let ptr_offset = layout.fields.offset(0).bytes() as usize;
+                let len_offset = layout.fields.offset(1).bytes() as usize;
+                let len_size = len_layout.size.bytes() as usize;
+
+                bytes[ptr_offset..ptr_offset + 4].copy_from_slice(&ptr.to_le_bytes());
+                bytes[len_offset..len_offset + len_size].copy_from_slice(&(s.len() as u64).to_le_bytes()[..len_size]);
+            },
+            | ir::Const::Ctor(..) => self.unsupported("constructor constants"),
+        }
+    }
+}
+
+impl<'a> FunctionCtx<'a, '_> {
+    fn lower(&mut self) {
+        let body = self.body;
+        let count = body.blocks.len() as u32;
+
+        self.code.i32_const(self.blocks[body.entry.unwrap()] as i32);
+        self.code.local_set(self.bb);
+        self.code.loop_(BlockType::Empty);
+
+        for _ in 0..count {
+            self.code.block(BlockType::Empty);
+        }
+
+        self.code.local_get(self.bb);
+        self.code.br_table(&(0..count).collect::<Vec<_>>(), count - 1);
+
+        for (i, (_, block)) in body.blocks.iter().enumerate() {
+            self.code.end();
+            self.depth = count - 1 - i as u32;
+
+            for stmt in &block.stmts {
+                self.lower_stmt(stmt);
+            }
+
+            self.lower_term(&block.term);
+        }
+
+        self.code.end();
+        self.code.unreachable();
+    }
+
+    fn finish(self) -> encode::Function {
+        let frame_size = align_to(self.frame_size, 16);
+        let mut code = Code::default();
+
+        code.global_get(SP);
+        code.local_tee(self.old_sp);
+        code.i32_const(frame_size as i32);
+        code.op(op::I32_SUB);
+        code.local_tee(self.fp);
+        code.global_set(SP);
+        code.append(self.code);
+
+        encode::Function {
+            ty: self
+                .mcx
+                .module
+                .func_type(vec![ValType::I32; self.params as usize], Vec::new()),
+            locals: self.locals,
+            code,
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt: &ir::Stmt) {
+        match stmt {
+            | ir::Stmt::Assign(place, rvalue) => {
+                let place = self.lower_place(place);
+                let dest = self.new_local(ValType::I32);
+
+                self.code.local_set(dest);
+                self.lower_rvalue(dest, &place.layout, rvalue);
+            },
+            | ir::Stmt::SetDiscr(place, discr) => {
+                let place = self.lower_place(place);
+                let dest = self.new_local(ValType::I32);
+
+                self.code.local_set(dest);
+                self.lower_set_discr(dest, &place.layout, *discr);
+            },
+            | ir::Stmt::Call(ret, func, args) => self.lower_call(ret, func, args),
+            | ir::Stmt::Drop(_) => {},
+        }
+    }
+
+    fn lower_term(&mut self, term: &ir::Term) {
+        match term {
+            | ir::Term::Abort => self.code.unreachable(),
+            | ir::Term::Return => {
+                self.code.local_get(self.old_sp);
+                self.code.global_set(SP);
+                self.code.return_();
+            },
+            | ir::Term::Jump(to) => self.jump(*to),
+            | ir::Term::Switch(op, vals, targets) => {
+                let layout = self.operand_layout(op);
+                let ty = self.lower_scalar(op);
+                let val = self.new_local(ty);
+
+                self.code.local_set(val);
+
+                for (&v, &target) in vals.iter().zip(targets) {
+                    self.code.local_get(val);
+                    self.code.const_(ty, normalize_const(&layout, v));
+                    self.code.op(cmp_op(ty, CmpOp::Eq, true));
+                    self.code.if_(BlockType::Empty);
+                    self.depth += 1;
+                    self.jump(target);
+                    self.depth -= 1;
+                    self.code.end();
+                }
+
+                self.jump(*targets.last().unwrap());
+            },
+        }
+    }
+
+    fn jump(&mut self, to: ir::BlockId) {
+        self.code.i32_const(self.blocks[to] as i32);
+        self.code.local_set(self.bb);
+        self.code.br(self.depth);
+    }
+
+    fn lower_rvalue(&mut self, dest: u32, layout: &Arc<Layout>, rvalue: &ir::RValue) {
+        match rvalue {
+            | ir::RValue::Use(op) => self.write_operand(dest, 0, op),
+            | ir::RValue::AddrOf(place) => {
+                let place = self.lower_place(place);
+                let addr = self.new_local(ValType::I32);
+
+                self.code.local_set(addr);
+                self.code.local_get(dest);
+                self.code.local_get(addr);
+                self.code.mem(op::I32_STORE, 0);
+
+                if let Some((meta, ty)) = place.meta {
+                    self.code.local_get(dest);
+                    self.code.local_get(meta);
+                    store_prim(&mut self.code, prim_of(ty), layout.fields.offset(1).bytes() as u32);
+                }
+            },
+            | ir::RValue::GetDiscr(place) => {
+                let place = self.lower_place(place);
+                let src = self.new_local(ValType::I32);
+
+                self.code.local_set(src);
+                self.lower_get_discr(dest, layout, src, &place.layout);
+            },
+            | ir::RValue::Intrinsic(name, args) => self.lower_intrinsic(dest, layout, name, args),
+        }
+    }
+
+    fn lower_set_discr(&mut self, dest: u32, layout: &Layout, discr: u128) {
+        match &layout.variants {
+            | Variants::Single { index } => {
+                assert_eq!(*index, discr as usize);
+            },
+            | Variants::Multiple {
+                tag_field,
+                tag_encoding,
+                ..
+            } => {
+                let offset = layout.fields.offset(*tag_field).bytes() as u32;
+                let size = layout.field(self.db.upcast(), *tag_field).unwrap().size.bytes();
+
+                match tag_encoding {
+                    | TagEncoding::Direct => self.store_bytes(dest, offset, discr, size),
+                    | TagEncoding::Niche {
+                        dataful_variant,
+                        niche_variants,
+                        niche_start,
+                    } => {
+                        if discr != *dataful_variant as u128 {
+                            let niche_value = discr - *niche_variants.start() as u128;
+                            let niche_value = niche_value.wrapping_add(*niche_start);
+
+                            self.store_bytes(dest, offset, niche_value, size);
+                        }
+                    },
+                }
+            },
+        }
+    }
+
+    fn lower_get_discr(&mut self, dest: u32, layout: &Layout, src: u32, src_layout: &Layout) {
+        match &src_layout.variants {
+            | Variants::Single { index } => {
+                self.store_bytes(dest, 0, *index as u128, layout.size.bytes());
+            },
+            | Variants::Multiple {
+                tag_field,
+                tag_encoding: TagEncoding::Direct,
+                ..
+            } => {
+                let tag = src_layout.field(self.db.upcast(), *tag_field).unwrap();
+                let offset = src_layout.fields.offset(*tag_field).bytes() as u32;
+
+                self.code.local_get(dest);
+                self.code.local_get(src);
+
+                let ty = load(&mut self.code, &tag, offset);
+
+                cast_int(&mut self.code, ty, val_type(layout), false);
+                store(&mut self.code, layout, 0);
+            },
+            | Variants::Multiple {
+                tag_encoding: TagEncoding::Niche { .. },
+                ..
+            } => self.unsupported("niche encoded discriminants"),
+        }
+    }
+
+    fn lower_call(&mut self, ret: &ir::Place, func: &ir::Operand, args: &[ir::Operand]) {
+        if let ir::Operand::Const(ir::Const::Addr(id), _) = func {
+            match self.func_ids.get(id).copied() {
+                | Some(Callee::Foreign(idx)) => self.lower_foreign_call(ret, idx, args),
+                | Some(Callee::Shade(idx)) => {
+                    self.lower_place(ret);
+
+                    for arg in args {
+                        self.copy_to_temp(arg);
+                    }
+
+                    self.code.call(idx);
+                },
+                | None => panic!("function {:?} is not part of the module", id),
+            }
+
+            return;
+        }
+
+        let func_ty = self.body.operand_type(func);
+        let mut params = args.len() + 1;
+
+        if let TypeKind::Clos(_, _) = func_ty.kind {
+            let layout = self.operand_layout(func);
+            let env_layout = layout.field(self.db.upcast(), 0).unwrap();
+            let clos = self.new_local(ValType::I32);
+
+            self.operand_addr(func);
+            self.code.local_set(clos);
+            self.lower_place(ret);
+
+            // the environment is passed before the other arguments
+            let env = self.temp(&env_layout);
+
+            self.code.local_get(env);
+            self.code.local_get(clos);
+            self.code.mem(op::I32_LOAD, layout.fields.offset(0).bytes() as u32);
+            self.code.mem(op::I32_STORE, 0);
+            self.code.local_get(env);
+
+            for arg in args {
+                self.copy_to_temp(arg);
+            }
+
+            self.code.local_get(clos);
+            self.code.mem(op::I32_LOAD, layout.fields.offset(1).bytes() as u32);
+            params += 1;
+        } else {
+            self.lower_place(ret);
+
+            for arg in args {
+                self.copy_to_temp(arg);
+            }
+
+            self.lower_scalar(func);
+        }
+
+        let ty = self.mcx.module.func_type(vec![ValType::I32; params], Vec::new());
+
+        self.code.call_indirect(ty);
+    }
+
+    fn lower_foreign_call(&mut self, ret: &ir::Place, func: u32, args: &[ir::Operand]) {
+        let ret = self.lower_place(ret);
+        let ret_pass = c_pass(&ret.layout);
+
+        // a direct result is stored at the address that stays on the stack, an indirect one is written
+        // by the callee to the address passed as the first argument
+        if let CPass::Ignore = ret_pass {
+            self.code.drop();
+        }
+
+        for arg in args {
+            match c_pass(&self.operand_layout(arg)) {
+                | CPass::Ignore => {},
+                | CPass::Direct(_) => {
+                    self.lower_scalar(arg);
+                },
+                | CPass::Indirect => self.copy_to_temp(arg),
+            }
+        }
+
+        self.code.call(func);
+
+        if let CPass::Direct(_) = ret_pass {
+            store(&mut self.code, &ret.layout, 0);
+        }
+    }
+
+    fn lower_intrinsic(&mut self, dest: u32, layout: &Arc<Layout>, name: &str, args: &[ir::Operand]) {
+        match name {
+            | "transmute" => {
+                self.code.local_get(dest);
+                self.operand_addr(&args[0]);
+                self.code.i32_const(layout.size.bytes() as i32);
+                self.code.call(self.rt.memcpy);
+            },
+            | "memcpy" => {
+                for arg in args {
+                    self.lower_usize(arg);
+                }
+
+                self.code.call(self.rt.memcpy);
+            },
+            | "alloc" => {
+                self.code.local_get(dest);
+                self.lower_usize(&args[0]);
+                self.code.call(self.rt.malloc);
+                store(&mut self.code, layout, 0);
+            },
+            | "dealloc" => {
+                self.lower_usize(&args[0]);
+                self.code.call(self.rt.free);
+            },
+            | "rc_new" => {
+                let box_layout = layout.elem(self.db.upcast()).unwrap();
+                let ptr = self.new_local(ValType::I32);
+
+                self.code.i32_const(box_layout.size.bytes() as i32);
+                self.code.call(self.rt.malloc);
+                self.code.local_set(ptr);
+                self.code.local_get(ptr);
+                self.code.i32_const(1);
+                self.code.mem(op::I32_STORE, 0);
+                self.write_operand(ptr, box_layout.fields.offset(1).bytes() as u32, &args[0]);
+                self.code.local_get(dest);
+                self.code.local_get(ptr);
+                store(&mut self.code, layout, 0);
+            },
+            | "rc_retain" | "rc_release" => {
+                let ptr = self.new_local(ValType::I32);
+                let count = self.new_local(ValType::I32);
+
+                self.lower_usize(&args[0]);
+                self.code.local_set(ptr);
+                self.code.local_get(ptr);
+                self.code.local_get(ptr);
+                self.code.mem(op::I32_LOAD, 0);
+                self.code.i32_const(if name == "rc_retain" { 1 } else { -1 });
+                self.code.op(op::I32_ADD);
+                self.code.local_tee(count);
+                self.code.mem(op::I32_STORE, 0);
+
+                if name == "rc_release" {
+                    // whether this was the last reference
+                    self.code.local_get(dest);
+                    self.code.local_get(count);
+                    self.code.op(op::I32_EQZ);
+                    store(&mut self.code, layout, 0);
+                }
+            },
+            | _ => match Intrinsic::parse(name) {
+                | Some(intrinsic) => self.lower_scalar_intrinsic(dest, layout, intrinsic, args),
+                | None => panic!("unknown intrinsic '{}'", name),
+            },
+        }
+    }
+
+    fn lower_scalar_intrinsic(&mut self, dest: u32, layout: &Arc<Layout>, intrinsic: Intrinsic, args: &[ir::Operand]) {
+        let args = args
+            .iter()
+            .map(|arg| {
+                let ty = self.lower_scalar(arg);
+                let local = self.new_local(ty);
+
+                self.code.local_set(local);
+                local
+            })
+            .collect::<Vec<_>>();
+
+        self.code.local_get(dest);
+
+        match intrinsic {
+            | Intrinsic::Binary(op, ty) => {
+                self.lower_binop(op, ty, args[0], args[1]);
+                store(&mut self.code, layout, 0);
+            },
+            | Intrinsic::Checked(op, ty) => {
+                let val_layout = layout.field(self.db.upcast(), 0).unwrap();
+                let overflow_layout = layout.field(self.db.upcast(), 1).unwrap();
+                let overflow = self.lower_binop(op, ty, args[0], args[1]).unwrap();
+
+                store(&mut self.code, &val_layout, layout.fields.offset(0).bytes() as u32);
+                self.code.local_get(dest);
+                self.code.local_get(overflow);
+                store(&mut self.code, &overflow_layout, layout.fields.offset(1).bytes() as u32);
+            },
+            | Intrinsic::Unary(op, ty) => {
+                let val_ty = self.scalar_val_type(ty);
+
+                match op {
+                    | UnOp::Neg if ty.is_float() => {
+                        self.code.local_get(args[0]);
+                        self.code.op(if val_ty == ValType::F32 {
+                            op::F32_NEG
+                        } else {
+                            op::F64_NEG
+                        });
+                    },
+                    | UnOp::Neg => {
+                        // locals start out as zero
+                        let zero = self.new_local(val_ty);
+
+                        self.lower_binop(BinOp::Sub, ty, zero, args[0]);
+                    },
+                    | UnOp::Not => {
+                        self.code.local_get(args[0]);
+                        self.code.const_(val_ty, u64::MAX);
+                        self.code.op(int_op(val_ty, BinOp::Xor, false));
+                    },
+                }
+
+                store(&mut self.code, layout, 0);
+            },
+            | Intrinsic::Cmp(op, ty) => {
+                let val_ty = self.scalar_val_type(ty);
+
+                self.code.local_get(args[0]);
+                self.code.local_get(args[1]);
+                self.code.op(cmp_op(val_ty, op, ty.is_signed()));
+                store(&mut self.code, layout, 0);
+            },
+            | Intrinsic::Convert(from, to) => {
+                self.lower_convert(from, to, args[0]);
+                store(&mut self.code, layout, 0);
+            },
+        }
+    }
+
+    /// Pushes the wrapped result and, for operations that can overflow, returns a local that is set when it did.
+    fn lower_binop(&mut self, op: BinOp, ty: ScalarType, lhs: u32, rhs: u32) -> Option<u32> {
+        let val_ty = self.scalar_val_type(ty);
+        let signed = ty.is_signed();
+        let bits = ty.bits(self.pointer_bits);
+
+        if ty.is_float() {
+            let base = if val_ty == ValType::F32 {
+                op::F32_ADD
+            } else {
+                op::F64_ADD
+            };
+            let trunc = if val_ty == ValType::F32 {
+                op::F32_TRUNC
+            } else {
+                op::F64_TRUNC
+            };
+
+            match op {
+                | BinOp::Add => self.binary(lhs, rhs, base),
+                | BinOp::Sub => self.binary(lhs, rhs, base + 1),
+                | BinOp::Mul => self.binary(lhs, rhs, base + 2),
+                | BinOp::Div => self.binary(lhs, rhs, base + 3),
+                | BinOp::Rem => {
+                    // lhs - trunc(lhs / rhs) * rhs
+                    self.code.local_get(lhs);
+                    self.binary(lhs, rhs, base + 3);
+                    self.code.op(trunc);
+                    self.code.local_get(rhs);
+                    self.code.op(base + 2);
+                    self.code.op(base + 1);
+                },
+                | _ => unreachable!(),
+            }
+
+            return None;
+        }
+
+        let res = self.new_local(val_ty);
+
+        self.binary(lhs, rhs, int_op(val_ty, op, signed));
+        self.code.local_set(res);
+
+        let overflow = match op {
+            | BinOp::Add | BinOp::Sub | BinOp::Mul if bits < 32 => {
+                // the operands are extended to 32 bits so the result only overflows if it doesn't fit in the
+                // smaller type
+                self.code.local_get(res);
+                normalize(&mut self.code, bits, signed);
+                self.code.local_get(res);
+                self.code.op(op::I32_NE);
+                true
+            },
+            | BinOp::Add if signed => {
+                // overflow when both operands have a different sign than the result
+                self.binary(lhs, res, int_op(val_ty, BinOp::Xor, false));
+                self.binary(rhs, res, int_op(val_ty, BinOp::Xor, false));
+                self.code.op(int_op(val_ty, BinOp::And, false));
+                self.is_negative(val_ty);
+                true
+            },
+            | BinOp::Add => {
+                self.binary(res, lhs, cmp_op(val_ty, CmpOp::Lt, false));
+                true
+            },
+            | BinOp::Sub if signed => {
+                // overflow when the operands have different signs and the result has the sign of rhs
+                self.binary(lhs, rhs, int_op(val_ty, BinOp::Xor, false));
+                self.binary(lhs, res, int_op(val_ty, BinOp::Xor, false));
+                self.code.op(int_op(val_ty, BinOp::And, false));
+                self.is_negative(val_ty);
+                true
+            },
+            | BinOp::Sub => {
+                self.binary(lhs, rhs, cmp_op(val_ty, CmpOp::Lt, false));
+                true
+            },
+            | BinOp::Mul if val_ty == ValType::I32 => {
+                // multiply at double the width and check that the result still fits
+                let extend = if signed {
+                    op::I64_EXTEND_I32_S
+                } else {
+                    op::I64_EXTEND_I32_U
+                };
+
+                let full = self.new_local(ValType::I64);
+
+                self.code.local_get(lhs);
+                self.code.op(extend);
+                self.code.local_get(rhs);
+                self.code.op(extend);
+                self.code.op(op::I64_MUL);
+                self.code.local_tee(full);
+                self.code.local_get(full);
+                self.code.op(op::I32_WRAP_I64);
+                self.code.op(extend);
+                self.code.op(op::I64_NE);
+                true
+            },
+            | BinOp::Mul => {
+                // the result overflowed if dividing it by lhs doesn't give rhs
+                self.code.local_get(lhs);
+                self.code.op(op::I64_EQZ);
+                self.code.if_(BlockType::Value(ValType::I32));
+                self.code.i32_const(0);
+                self.code.else_();
+
+                if signed {
+                    // dividing by -1 traps when the result is the minimum value
+                    self.code.local_get(lhs);
+                    self.code.i64_const(-1);
+                    self.code.op(op::I64_EQ);
+                    self.code.if_(BlockType::Value(ValType::I32));
+                    self.code.local_get(rhs);
+                    self.code.i64_const(i64::MIN);
+                    self.code.op(op::I64_EQ);
+                    self.code.else_();
+                }
+
+                self.binary(res, lhs, int_op(val_ty, BinOp::Div, signed));
+                self.code.local_get(rhs);
+                self.code.op(op::I64_NE);
+
+                if signed {
+                    self.code.end();
+                }
+
+                self.code.end();
+                true
+            },
+            | _ => false,
+        };
+
+        let overflow = if overflow {
+            let local = self.new_local(ValType::I32);
+
+            self.code.local_set(local);
+            Some(local)
+        } else {
+            None
+        };
+
+        self.code.local_get(res);
+        overflow
+    }
+
+    fn lower_convert(&mut self, from: ScalarType, to: ScalarType, val: u32) {
+        let from_ty = self.scalar_val_type(from);
+        let to_ty = self.scalar_val_type(to);
+
+        self.code.local_get(val);
+
+        match (from.is_float(), to.is_float()) {
+            | (false, false) => cast_int(&mut self.code, from_ty, to_ty, from.is_signed()),
+            | (false, true) => {
+                let base = if to_ty == ValType::F32 {
+                    op::F32_CONVERT_I32_S
+                } else {
+                    op::F64_CONVERT_I32_S
+                };
+
+                let from_i64 = if from_ty == ValType::I64 { 2 } else { 0 };
+
+                self.code.op(base + from_i64 + !from.is_signed() as u8);
+            },
+            | (true, true) if from_ty == to_ty => {},
+            | (true, true) if to_ty == ValType::F64 => self.code.op(op::F64_PROMOTE_F32),
+            | (true, true) => self.code.op(op::F32_DEMOTE_F64),
+            | (true, false) => {
+                // the truncating instructions trap when the value doesn't fit, so saturate it first
+                let bits = to.bits(self.pointer_bits) as i32;
+                let signed = to.is_signed();
+                let (lo, hi, max) = if signed {
+                    (-(2f64.powi(bits - 1)), 2f64.powi(bits - 1), u64::MAX >> (65 - bits))
+                } else {
+                    (-1.0, 2f64.powi(bits), u64::MAX >> (64 - bits))
+                };
+
+                let (lo_cmp, min) = if signed { (CmpOp::Lt, !max) } else { (CmpOp::Le, 0) };
+                let float = |v: f64| {
+                    if from_ty == ValType::F32 {
+                        (v as f32).to_bits() as u64
+                    } else {
+                        v.to_bits()
+                    }
+                };
+
+                let trunc = if to_ty == ValType::I32 {
+                    op::I32_TRUNC_F32_S
+                } else {
+                    op::I64_TRUNC_F32_S
+                };
+
+                let from_f64 = if from_ty == ValType::F64 { 2 } else { 0 };
+
+                self.code.local_get(val);
+                self.code.op(cmp_op(from_ty, CmpOp::Ne, true));
+                self.code.if_(BlockType::Value(to_ty));
+                self.code.const_(to_ty, 0);
+                self.code.else_();
+                self.code.local_get(val);
+                self.code.const_(from_ty, float(hi));
+                self.code.op(cmp_op(from_ty, CmpOp::Ge, true));
+                self.code.if_(BlockType::Value(to_ty));
+                self.code.const_(to_ty, max);
+                self.code.else_();
+                self.code.local_get(val);
+                self.code.const_(from_ty, float(lo));
+                self.code.op(cmp_op(from_ty, lo_cmp, true));
+                self.code.if_(BlockType::Value(to_ty));
+                self.code.const_(to_ty, min);
+                self.code.else_();
+                self.code.local_get(val);
+                self.code.op(trunc + from_f64 + !signed as u8);
+                self.code.end();
+                self.code.end();
+                self.code.end();
+            },
+        }
+    }
+
+    /// Pushes the address of a place.
+    fn lower_place(&mut self, place: &ir::Place) -> PlaceInfo {
+        let mut layout = self.db.layout_of(self.body.locals[place.local].ty.clone());
+        let mut meta = None;
+
+        match self.places[place.local] {
+            | LocalPlace::Param(idx) => self.code.local_get(idx),
+            | LocalPlace::Frame(offset) => self.frame_addr(offset),
+        }
+
+        for elem in &place.elems {
+            match elem {
+                | ir::PlaceElem::Deref => {
+                    let pointee = layout.elem(self.db.upcast()).unwrap();
+
+                    meta = None;
+
+                    if let Abi::ScalarPair(a, b) = &layout.abi {
+                        let triple = self.db.target_triple();
+                        let offset = a.value.size(&triple).align_to(b.value.align(&triple));
+                        let addr = self.new_local(ValType::I32);
+
+                        self.code.local_tee(addr);
+                        self.code.local_get(addr);
+
+                        let ty = load_prim(&mut self.code, b.value, offset.bytes() as u32);
+                        let local = self.new_local(ty);
+
+                        self.code.local_set(local);
+                        meta = Some((local, ty));
+                    }
+
+                    self.code.mem(op::I32_LOAD, 0);
+                    layout = pointee;
+                },
+                | ir::PlaceElem::Field(field) => {
+                    let offset = layout.fields.offset(*field).bytes() as u32;
+
+                    if offset != 0 {
+                        self.code.i32_const(offset as i32);
+                        self.code.op(op::I32_ADD);
+                    }
+
+                    layout = layout.field(self.db.upcast(), *field).unwrap();
+                },
+                | ir::PlaceElem::Index(idx) => {
+                    let elem = layout.elem(self.db.upcast()).unwrap();
+
+                    self.lower_usize(idx);
+                    self.code.i32_const(elem.stride.bytes() as i32);
+                    self.code.op(op::I32_MUL);
+                    self.code.op(op::I32_ADD);
+                    layout = elem;
+                    meta = None;
+                },
+                | ir::PlaceElem::Offset(offset) => {
+                    self.lower_usize(offset);
+                    self.code.op(op::I32_ADD);
+                    meta = None;
+                },
+                | ir::PlaceElem::Downcast(idx) => layout = layout.variant(*idx),
+            }
+        }
+
+        PlaceInfo { layout, meta }
+    }
+
+    /// Pushes the value of a scalar operand.
+    fn lower_scalar(&mut self, op: &ir::Operand) -> ValType {
+        match op {
+            | ir::Operand::Place(place) => {
+                let place = self.lower_place(place);
+
+                load(&mut self.code, &place.layout, 0)
+            },
+            | ir::Operand::Const(c, ty) => {
+                let layout = self.db.layout_of(ty.clone());
+
+                self.lower_const_scalar(c, &layout)
+            },
+            | ir::Operand::Record(_, _) => {
+                self.unsupported("instance record operands");
+                self.code.unreachable();
+                ValType::I32
+            },
+        }
+    }
+
+    fn lower_const_scalar(&mut self, c: &ir::Const, layout: &Layout) -> ValType {
+        let ty = val_type(layout);
+
+        match c {
+            | ir::Const::Undefined => self.code.const_(ty, 0),
+            | ir::Const::Scalar(s) => self.code.const_(ty, normalize_const(layout, *s)),
+            | ir::Const::Tuple(cs) if cs.len() == 1 => return self.lower_const_scalar(&cs[0], layout),
+            | ir::Const::Addr(id) => {
+                let addr = self.body_addr(id);
+
+                self.code.i32_const(addr as i32);
+            },
+            | ir::Const::Ref(to) => {
+                let elem = layout.elem(self.db.upcast()).unwrap();
+                let addr = self.alloc_const(to, &elem);
+
+                self.code.i32_const(addr as i32);
+            },
+            | _ => {
+                // an aggregate with a scalar layout, like a tuple of a scalar and zero-sized fields
+                let temp = self.temp(layout);
+
+                self.store_const(temp, 0, c, layout);
+                self.code.local_get(temp);
+
+                return load(&mut self.code, layout, 0);
+            },
+        }
+
+        ty
+    }
+
+    /// Pushes a pointer sized integer operand as an `i32`.
+    fn lower_usize(&mut self, op: &ir::Operand) {
+        if self.lower_scalar(op) == ValType::I64 {
+            self.code.op(op::I32_WRAP_I64);
+        }
+    }
+
+    /// Pushes the address of an operand, constants are stored in the frame first.
+    fn operand_addr(&mut self, op: &ir::Operand) {
+        match op {
+            | ir::Operand::Place(place) => {
+                self.lower_place(place);
+            },
+            | _ => self.copy_to_temp(op),
+        }
+    }
+
+    /// Pushes the address of a copy of an operand in the frame.
+    fn copy_to_temp(&mut self, op: &ir::Operand) {
+        let layout = self.operand_layout(op);
+        let temp = self.temp(&layout);
+
+        self.write_operand(temp, 0, op);
+        self.code.local_get(temp);
+    }
+
+    fn write_operand(&mut self, dest: u32, offset: u32, op: &ir::Operand) {
+        match op {
+            | ir::Operand::Place(place) => {
+                let src = self.lower_place(place);
+                let layout = src.layout;
+                let src = self.new_local(ValType::I32);
+
+                self.code.local_set(src);
+
+                if let Abi::Scalar(_) = layout.abi {
+                    self.code.local_get(dest);
+                    self.code.local_get(src);
+                    load(&mut self.code, &layout, 0);
+                    store(&mut self.code, &layout, offset);
+                } else if layout.size.bytes() > 0 {
+                    self.code.local_get(dest);
+
+                    if offset != 0 {
+                        self.code.i32_const(offset as i32);
+                        self.code.op(op::I32_ADD);
+                    }
+
+                    self.code.local_get(src);
+                    self.code.i32_const(layout.size.bytes() as i32);
+                    self.code.call(self.rt.memcpy);
+                }
+            },
+            | ir::Operand::Const(c, ty) => {
+                let layout = self.db.layout_of(ty.clone());
+
+                self.store_const(dest, offset, c, &layout);
+            },
+            | ir::Operand::Record(_, _) => self.unsupported("instance record operands"),
+        }
+    }
+
+    fn store_const(&mut self, dest: u32, offset: u32, c: &ir::Const, layout: &Layout) {
+        match c {
+            | ir::Const::Undefined => {},
+            | ir::Const::Scalar(s) => self.store_bytes(dest, offset, *s, layout.size.bytes()),
+            | ir::Const::Tuple(cs) if cs.is_empty() => {},
+            | ir::Const::Tuple(cs) => match &layout.fields {
+                | Fields::Primitive => self.store_const(dest, offset, &cs[0], layout),
+                | fields => {
+                    for (i, c) in cs.iter().enumerate() {
+                        let field = layout.field(self.db.upcast(), i).unwrap();
+                        let field_offset = offset + fields.offset(i).bytes() as u32;
+
+                        self.store_const(dest, field_offset, c, &field);
+                    }
+                },
+            },
+            | ir::Const::Addr(id) => {
+                let addr = self.body_addr(id);
+
+                self.store_bytes(dest, offset, addr as u128, 4);
+            },
+            | ir::Const::Ref(to) => {
+                let elem = layout.elem(self.db.upcast()).unwrap();
+                let addr = self.alloc_const(to, &elem);
+
+                self.store_bytes(dest, offset, addr as u128, 4);
+            },
+            | ir::Const::String(s) => {
+                let ptr = self.alloc_string(s);
+                let len_layout = layout.field(self.db.upcast(), 1).unwrap();
+                let ptr_offset = offset + layout.fields.offset(0).bytes() as u32;
+                let len_offset = offset + layout.fields.offset(1).bytes() as u32;
+
+                self.store_bytes(dest, ptr_offset, ptr as u128, 4);
+                self.store_bytes(dest, len_offset, s.len() as u128, len_layout.size.bytes());
+            },
+            | ir::Const::Ctor(..) => self.unsupported("constructor constants"),
+        }
+    }
+
+    fn store_bytes(&mut self, dest: u32, offset: u32, bits: u128, size: u64) {
+        let (op, ty) = match size {
+            | 0 => return,
+            | 1 => (op::I32_STORE8, ValType::I32),
+            | 2 => (op::I32_STORE16, ValType::I32),
+            | 4 => (op::I32_STORE, ValType::I32),
+            | 8 => (op::I64_STORE, ValType::I64),
+            | 16 => {
+                self.store_bytes(dest, offset, bits, 8);
+                self.store_bytes(dest, offset + 8, bits >> 64, 8);
+                return;
+            },
+            | _ => unreachable!(),
+        };
+
+        self.code.local_get(dest);
+        self.code.const_(ty, bits as u64);
+        self.code.mem(op, offset);
+    }
+
+    fn binary(&mut self, lhs: u32, rhs: u32, op: u8) {
+        self.code.local_get(lhs);
+        self.code.local_get(rhs);
+        self.code.op(op);
+    }
+
+    fn is_negative(&mut self, ty: ValType) {
+        self.code.const_(ty, 0);
+        self.code.op(cmp_op(ty, CmpOp::Lt, true));
+    }
+
+    fn scalar_val_type(&self, ty: ScalarType) -> ValType {
+        match (ty, ty.bits(self.pointer_bits)) {
+            | (ScalarType::F32, _) => ValType::F32,
+            | (ScalarType::F64, _) => ValType::F64,
+            | (_, 128) => unsupported_i128(),
+            | (_, 64) => ValType::I64,
+            | (_, _) => ValType::I32,
+        }
+    }
+
+    fn operand_layout(&self, op: &ir::Operand) -> Arc<Layout> {
+        self.db.layout_of(self.body.operand_type(op))
+    }
+
+    fn new_local(&mut self, ty: ValType) -> u32 {
+        self.locals.push(ty);
+        self.params + self.locals.len() as u32 - 1
+    }
+
+    fn alloc_frame(&mut self, layout: &Layout) -> u32 {
+        let offset = align_to(self.frame_size, layout.align.bytes() as u32);
+
+        self.frame_size = offset + layout.size.bytes() as u32;
+        offset
+    }
+
+    /// Allocates a slot in the frame and returns a local holding its address.
+    fn temp(&mut self, layout: &Layout) -> u32 {
+        let offset = self.alloc_frame(layout);
+        let local = self.new_local(ValType::I32);
+
+        self.frame_addr(offset);
+        self.code.local_set(local);
+        local
+    }
+
+    fn frame_addr(&mut self, offset: u32) {
+        self.code.local_get(self.fp);
+
+        if offset != 0 {
+            self.code.i32_const(offset as i32);
+            self.code.op(op::I32_ADD);
+        }
+    }
+}
+
+impl<'a> std::ops::Deref for FunctionCtx<'a, '_> {
+    type Target = ModuleCtx<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.mcx
+    }
+}
+
+impl<'a> std::ops::DerefMut for FunctionCtx<'a, '_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.mcx
+    }
+}
+
+fn c_pass(layout: &Layout) -> CPass {
+    match &layout.abi {
+        | _ if layout.is_zst() => CPass::Ignore,
+        | Abi::Uninhabited => CPass::Ignore,
+        | Abi::Scalar(s) => CPass::Direct(prim_type(s.value)),
+        | _ => CPass::Indirect,
+    }
+}
+
+fn val_type(layout: &Layout) -> ValType {
+    match &layout.abi {
+        | Abi::Scalar(s) => prim_type(s.value),
+        | _ => panic!("not a scalar: {}", layout),
+    }
+}
+
+fn prim_type(prim: Primitive) -> ValType {
+    match prim {
+        | Primitive::Int(Integer::I128, _) => unsupported_i128(),
+        | Primitive::Int(Integer::I64, _) => ValType::I64,
+        | Primitive::Int(_, _) | Primitive::Pointer => ValType::I32,
+        | Primitive::F32 => ValType::F32,
+        | Primitive::F64 => ValType::F64,
+    }
+}
+
+fn prim_of(ty: ValType) -> Primitive {
+    match ty {
+        | ValType::I32 => Primitive::Int(Integer::I32, false),
+        | ValType::I64 => Primitive::Int(Integer::I64, false),
+        | ValType::F32 => Primitive::F32,
+        | ValType::F64 => Primitive::F64,
+    }
+}
+
+/// Bodies and foreign functions that use 128-bit integers are rejected by `check_i128` before they are lowered.
+fn unsupported_i128() -> ! {
+    unreachable!("128-bit integers are not supported on wasm targets")
+}
+
+/// Whether any scalar in the layout is a 128-bit integer.
+fn has_i128(db: &dyn CodegenDatabase, layout: &Layout) -> bool {
+    let is_i128 = |prim: Primitive| matches!(prim, Primitive::Int(Integer::I128, _));
+
+    match &layout.abi {
+        | Abi::Scalar(s) => return is_i128(s.value),
+        | Abi::ScalarPair(a, b) => return is_i128(a.value) || is_i128(b.value),
+        | _ => {},
+    }
+
+    let fields = match &layout.fields {
+        | Fields::Primitive => false,
+        | Fields::Array { .. } => layout.elem(db.upcast()).map_or(false, |elem| has_i128(db, &elem)),
+        | _ => (0..layout.fields.count()).any(|i| has_i128(db, &layout.field(db.upcast(), i).unwrap())),
+    };
+
+    let variants = match &layout.variants {
+        | Variants::Multiple { variants, .. } => variants.iter().any(|v| has_i128(db, v)),
+        | Variants::Single { .. } => false,
+    };
+
+    fields || variants
+}
+
+/// Loads a scalar from the address on the stack, integers smaller than 32 bits are extended.
+fn load(code: &mut Code, layout: &Layout, offset: u32) -> ValType {
+    match &layout.abi {
+        | Abi::Scalar(s) => load_prim(code, s.value, offset),
+        | _ => panic!("not a scalar: {}", layout),
+    }
+}
+
+fn load_prim(code: &mut Code, prim: Primitive, offset: u32) -> ValType {
+    let op = match prim {
+        | Primitive::Int(Integer::I8, true) => op::I32_LOAD8_S,
+        | Primitive::Int(Integer::I8, false) => op::I32_LOAD8_U,
+        | Primitive::Int(Integer::I16, true) => op::I32_LOAD16_S,
+        | Primitive::Int(Integer::I16, false) => op::I32_LOAD16_U,
+        | Primitive::Int(Integer::I32, _) | Primitive::Pointer => op::I32_LOAD,
+        | Primitive::Int(Integer::I64, _) => op::I64_LOAD,
+        | Primitive::Int(Integer::I128, _) => unsupported_i128(),
+        | Primitive::F32 => op::F32_LOAD,
+        | Primitive::F64 => op::F64_LOAD,
+    };
+
+    code.mem(op, offset);
+    prim_type(prim)
+}
+
+/// Stores the value on the stack to the address below it.
+fn store(code: &mut Code, layout: &Layout, offset: u32) {
+    match &layout.abi {
+        | Abi::Scalar(s) => store_prim(code, s.value, offset),
+        | _ => panic!("not a scalar: {}", layout),
+    }
+}
+
+fn store_prim(code: &mut Code, prim: Primitive, offset: u32) {
+    let op = match prim {
+        | Primitive::Int(Integer::I8, _) => op::I32_STORE8,
+        | Primitive::Int(Integer::I16, _) => op::I32_STORE16,
+        | Primitive::Int(Integer::I32, _) | Primitive::Pointer => op::I32_STORE,
+        | Primitive::Int(Integer::I64, _) => op::I64_STORE,
+        | Primitive::Int(Integer::I128, _) => unsupported_i128(),
+        | Primitive::F32 => op::F32_STORE,
+        | Primitive::F64 => op::F64_STORE,
+    };
+
+    code.mem(op, offset);
+}
+
+fn cast_int(code: &mut Code, from: ValType, to: ValType, signed: bool) {
+    match (from, to) {
+        | (ValType::I32, ValType::I64) if signed => code.op(op::I64_EXTEND_I32_S),
+        | (ValType::I32, ValType::I64) => code.op(op::I64_EXTEND_I32_U),
+        | (ValType::I64, ValType::I32) => code.op(op::I32_WRAP_I64),
+        | _ => {},
+    }
+}
+
+/// Sign or zero extends the lower bits of the `i32` on the stack.
+fn normalize(code: &mut Code, bits: u32, signed: bool) {
+    if signed {
+        code.i32_const(32 - bits as i32);
+        code.op(op::I32_SHL);
+        code.i32_const(32 - bits as i32);
+        code.op(op::I32_SHR_S);
+    } else {
+        code.i32_const(((1u64 << bits) - 1) as i32);
+        code.op(op::I32_AND);
+    }
+}
+
+/// The bits of a constant as they are after loading it, see [`load`].
+fn normalize_const(layout: &Layout, bits: u128) -> u64 {
+    match &layout.abi {
+        | Abi::Scalar(s) => match s.value {
+            | Primitive::Int(Integer::I8, true) => bits as u8 as i8 as i64 as u64,
+            | Primitive::Int(Integer::I16, true) => bits as u16 as i16 as i64 as u64,
+            | _ => bits as u64,
+        },
+        | _ => bits as u64,
+    }
+}
+
+fn int_op(ty: ValType, op: BinOp, signed: bool) -> u8 {
+    let base = if ty == ValType::I64 { op::I64_ADD } else { op::I32_ADD };
+    let unsigned = !signed as u8;
+
+    base + match op {
+        | BinOp::Add => 0,
+        | BinOp::Sub => 1,
+        | BinOp::Mul => 2,
+        | BinOp::Div => 3 + unsigned,
+        | BinOp::Rem => 5 + unsigned,
+        | BinOp::And => 7,
+        | BinOp::Or => 8,
+        | BinOp::Xor => 9,
+        | BinOp::Shl => 10,
+        | BinOp::Shr => 11 + unsigned,
+    }
+}
+
+fn cmp_op(ty: ValType, op: CmpOp, signed: bool) -> u8 {
+    let unsigned = !signed as u8;
+
+    match ty {
+        | ValType::I32 | ValType::I64 => {
+            let base = if ty == ValType::I64 { op::I64_EQ } else { op::I32_EQ };
+
+            base + match op {
+                | CmpOp::Eq => 0,
+                | CmpOp::Ne => 1,
+                | CmpOp::Lt => 2 + unsigned,
+                | CmpOp::Gt => 4 + unsigned,
+                | CmpOp::Le => 6 + unsigned,
+                | CmpOp::Ge => 8 + unsigned,
+            }
+        },
+        | ValType::F32 | ValType::F64 => {
+            let base = if ty == ValType::F64 { op::F64_EQ } else { op::F32_EQ };
+
+            base + match op {
+                | CmpOp::Eq => 0,
+                | CmpOp::Ne => 1,
+                | CmpOp::Lt => 2,
+                | CmpOp::Gt => 3,
+                | CmpOp::Le => 4,
+                | CmpOp::Ge => 5,
+            }
+        },
+    }
+}
+
+fn align_to(val: u32, align: u32) -> u32 {
+    (val + align - 1) / align * align
+}
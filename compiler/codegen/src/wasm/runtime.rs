@@ -0,0 +1,303 @@
+//! The functions that the C library would otherwise provide, implemented on top of WASI.
+
+use super::encode::{op, BlockType, Code, Function, Module, ValType};
+use super::{FREE_LIST, HEAP_END, SP};
+
+/// The number of functions defined by [`define`].
+pub const COUNT: u32 = 5;
+
+/// The function indices of the imported WASI functions and the runtime functions.
+#[derive(Default)]
+pub struct Runtime {
+    pub fd_write: u32,
+    pub proc_exit: u32,
+    pub memcpy: u32,
+    pub malloc: u32,
+    pub free: u32,
+    pub write: u32,
+    pub puts: u32,
+}
+
+impl Runtime {
+    pub fn new(fd_write: u32, proc_exit: u32, base: u32) -> Self {
+        Runtime {
+            fd_write,
+            proc_exit,
+            memcpy: base,
+            malloc: base + 1,
+            free: base + 2,
+            write: base + 3,
+            puts: base + 4,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> u32 {
+        match name {
+            | "write" => self.write,
+            | "puts" => self.puts,
+            | _ => unreachable!(),
+        }
+    }
+}
+
+/// Whether a foreign function is implemented by the runtime instead of being imported.
+pub fn provides(name: &str, params: &[ValType], results: &[ValType]) -> bool {
+    use ValType::*;
+
+    match name {
+        | "write" => params == [I32, I32, I64] && results == [I64],
+        | "puts" => params == [I32] && results == [I32],
+        | _ => false,
+    }
+}
+
+/// Defines the runtime functions in the order of their indices.
+/// `newline` is the address of a `\n` byte in the static data.
+pub fn define(module: &mut Module, rt: &Runtime, newline: u32) {
+    use ValType::*;
+
+    let funcs = vec![
+        (vec![I32, I32, I32], vec![], vec![], memcpy()),
+        (vec![I32], vec![I32], vec![I32, I32], malloc()),
+        (vec![I32], vec![], vec![], free()),
+        (vec![I32, I32, I64], vec![I64], vec![I32], write(rt)),
+        (vec![I32], vec![I32], vec![I32, I32], puts(rt, newline)),
+    ];
+
+    for (params, results, locals, code) in funcs {
+        let ty = module.func_type(params, results);
+
+        module.funcs.push(Function { ty, locals, code });
+    }
+}
+
+/// `memcpy(dest, src, len)`, copies one byte at a time.
+fn memcpy() -> Code {
+    let mut code = Code::default();
+
+    code.block(BlockType::Empty);
+    code.loop_(BlockType::Empty);
+    code.local_get(2);
+    code.op(op::I32_EQZ);
+    code.br_if(1);
+    code.local_get(0);
+    code.local_get(1);
+    code.mem(op::I32_LOAD8_U, 0);
+    code.mem(op::I32_STORE8, 0);
+
+    for local in 0..2 {
+        code.local_get(local);
+        code.i32_const(1);
+        code.op(op::I32_ADD);
+        code.local_set(local);
+    }
+
+    code.local_get(2);
+    code.i32_const(1);
+    code.op(op::I32_SUB);
+    code.local_set(2);
+    code.br(0);
+    code.end();
+    code.end();
+    code
+}
+
+/// `malloc(size)`, every allocation is preceded by a header containing its size and, while it is free,
+/// the next block in the free list. The free list is searched for the first block that is large enough,
+/// otherwise the heap is grown. Returns null when memory is exhausted.
+fn malloc() -> Code {
+    let (size, prev, block) = (0, 1, 2);
+    let mut code = Code::default();
+
+    code.local_get(size);
+    code.i32_const(7);
+    code.op(op::I32_ADD);
+    code.i32_const(-8);
+    code.op(op::I32_AND);
+    code.local_set(size);
+    code.global_get(FREE_LIST);
+    code.local_set(block);
+
+    code.block(BlockType::Empty);
+    code.loop_(BlockType::Empty);
+    code.local_get(block);
+    code.op(op::I32_EQZ);
+    code.br_if(1);
+    code.local_get(block);
+    code.mem(op::I32_LOAD, 0);
+    code.local_get(size);
+    code.op(op::I32_GE_U);
+    code.if_(BlockType::Empty);
+    code.local_get(prev);
+    code.op(op::I32_EQZ);
+    code.if_(BlockType::Empty);
+    code.local_get(block);
+    code.mem(op::I32_LOAD, 4);
+    code.global_set(FREE_LIST);
+    code.else_();
+    code.local_get(prev);
+    code.local_get(block);
+    code.mem(op::I32_LOAD, 4);
+    code.mem(op::I32_STORE, 4);
+    code.end();
+    code.local_get(block);
+    code.i32_const(8);
+    code.op(op::I32_ADD);
+    code.return_();
+    code.end();
+    code.local_get(block);
+    code.local_set(prev);
+    code.local_get(block);
+    code.mem(op::I32_LOAD, 4);
+    code.local_set(block);
+    code.br(0);
+    code.end();
+    code.end();
+
+    // `prev` now holds the new end of the heap
+    code.global_get(HEAP_END);
+    code.local_tee(block);
+    code.local_get(size);
+    code.op(op::I32_ADD);
+    code.i32_const(8);
+    code.op(op::I32_ADD);
+    code.local_tee(prev);
+    code.memory_size();
+    code.i32_const(16);
+    code.op(op::I32_SHL);
+    code.op(op::I32_GT_U);
+    code.if_(BlockType::Empty);
+    code.local_get(prev);
+    code.memory_size();
+    code.i32_const(16);
+    code.op(op::I32_SHL);
+    code.op(op::I32_SUB);
+    code.i32_const(0xFFFF);
+    code.op(op::I32_ADD);
+    code.i32_const(16);
+    code.op(op::I32_SHR_U);
+    code.memory_grow();
+    code.i32_const(-1);
+    code.op(op::I32_EQ);
+    code.if_(BlockType::Empty);
+    code.i32_const(0);
+    code.return_();
+    code.end();
+    code.end();
+    code.local_get(prev);
+    code.global_set(HEAP_END);
+    code.local_get(block);
+    code.local_get(size);
+    code.mem(op::I32_STORE, 0);
+    code.local_get(block);
+    code.i32_const(8);
+    code.op(op::I32_ADD);
+    code
+}
+
+/// `free(ptr)`, pushes the block onto the free list.
+fn free() -> Code {
+    let mut code = Code::default();
+
+    code.local_get(0);
+    code.op(op::I32_EQZ);
+    code.if_(BlockType::Empty);
+    code.return_();
+    code.end();
+    code.local_get(0);
+    code.i32_const(8);
+    code.op(op::I32_SUB);
+    code.local_tee(0);
+    code.global_get(FREE_LIST);
+    code.mem(op::I32_STORE, 4);
+    code.local_get(0);
+    code.global_set(FREE_LIST);
+    code
+}
+
+/// `write(fd, buf, len)`, returns the number of bytes written or -1.
+fn write(rt: &Runtime) -> Code {
+    let iov = 3;
+    let mut code = Code::default();
+
+    // the iovec and the number of written bytes are stored below the stack pointer
+    code.global_get(SP);
+    code.i32_const(16);
+    code.op(op::I32_SUB);
+    code.local_tee(iov);
+    code.local_get(1);
+    code.mem(op::I32_STORE, 0);
+    code.local_get(iov);
+    code.local_get(2);
+    code.op(op::I32_WRAP_I64);
+    code.mem(op::I32_STORE, 4);
+    code.local_get(0);
+    code.local_get(iov);
+    code.i32_const(1);
+    code.local_get(iov);
+    code.i32_const(8);
+    code.op(op::I32_ADD);
+    code.call(rt.fd_write);
+    code.if_(BlockType::Value(ValType::I64));
+    code.i64_const(-1);
+    code.else_();
+    code.local_get(iov);
+    code.mem(op::I64_LOAD32_U, 8);
+    code.end();
+    code
+}
+
+/// `puts(s)`, writes a null terminated string and a newline to stdout.
+fn puts(rt: &Runtime, newline: u32) -> Code {
+    let (len, iov) = (1, 2);
+    let mut code = Code::default();
+
+    code.local_get(0);
+    code.local_set(len);
+    code.block(BlockType::Empty);
+    code.loop_(BlockType::Empty);
+    code.local_get(len);
+    code.mem(op::I32_LOAD8_U, 0);
+    code.op(op::I32_EQZ);
+    code.br_if(1);
+    code.local_get(len);
+    code.i32_const(1);
+    code.op(op::I32_ADD);
+    code.local_set(len);
+    code.br(0);
+    code.end();
+    code.end();
+    code.local_get(len);
+    code.local_get(0);
+    code.op(op::I32_SUB);
+    code.local_set(len);
+
+    code.global_get(SP);
+    code.i32_const(24);
+    code.op(op::I32_SUB);
+    code.local_tee(iov);
+    code.local_get(0);
+    code.mem(op::I32_STORE, 0);
+    code.local_get(iov);
+    code.local_get(len);
+    code.mem(op::I32_STORE, 4);
+    code.local_get(iov);
+    code.i32_const(newline as i32);
+    code.mem(op::I32_STORE, 8);
+    code.local_get(iov);
+    code.i32_const(1);
+    code.mem(op::I32_STORE, 12);
+    code.i32_const(1);
+    code.local_get(iov);
+    code.i32_const(2);
+    code.local_get(iov);
+    code.i32_const(16);
+    code.op(op::I32_ADD);
+    code.call(rt.fd_write);
+    code.if_(BlockType::Value(ValType::I32));
+    code.i32_const(-1);
+    code.else_();
+    code.i32_const(0);
+    code.end();
+    code
+}
@@ -0,0 +1,43 @@
+use super::*;
+
+pub struct FunDepViolation {
+    location: TextRange,
+    other: Option<InFile<TextRange>>,
+}
+
+impl Diagnostic for FunDepViolation {
+    fn title(&self) -> String {
+        "instance violates the functional dependencies of its class".into()
+    }
+
+    fn range(&self) -> TextRange {
+        self.location
+    }
+
+    fn secondary_annotations(&self) -> Vec<SecondaryAnnotation> {
+        self.other
+            .map(|other| SecondaryAnnotation {
+                range: other,
+                message: "conflicts with this instance".into(),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        if self.other.is_some() {
+            vec!["the determining types of both instances match, but the determined types differ".into()]
+        } else {
+            vec!["all type variables in determined types must also appear in the determining types".into()]
+        }
+    }
+}
+
+impl FunDepViolation {
+    pub fn new<DB: hir::db::HirDatabase>(db: &DB, diag: &hir::diagnostic::FunDepViolation) -> Self {
+        let location = instance_head(db, InFile::new(diag.file, diag.src));
+        let other = diag.other.map(|other| other.with_value(instance_head(db, other)));
+
+        Self { location, other }
+    }
+}
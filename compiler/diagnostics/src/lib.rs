@@ -1,10 +1,19 @@
+mod fundep_violation;
+mod managed_type_argument;
 mod mismatched_kind;
 mod mismatched_type;
+mod missing_method;
+mod no_such_field;
+mod orphan_instance;
+mod overlapping_instance;
+mod skolem_escape;
 mod unresolved_operator;
 mod unsolved_constraint;
+mod use_of_moved_value;
 
 use hir::InFile;
-use syntax::TextRange;
+use syntax::ast::{self, AstNode};
+use syntax::{SyntaxNodePtr, TextRange};
 
 pub trait Diagnostic {
     fn title(&self) -> String;
@@ -63,6 +72,22 @@ impl<DB: hir::db::HirDatabase> DiagnosticForWith<DB> for dyn hir::diagnostic::Di
             f(&unsolved_constraint::UnsolvedConstraint::new(with, v))
         } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UnresolvedOperator>() {
             f(&unresolved_operator::UnresolvedOperator::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::OverlappingInstance>() {
+            f(&overlapping_instance::OverlappingInstance::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::OrphanInstance>() {
+            f(&orphan_instance::OrphanInstance::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::FunDepViolation>() {
+            f(&fundep_violation::FunDepViolation::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::MissingMethod>() {
+            f(&missing_method::MissingMethod::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::NoSuchField>() {
+            f(&no_such_field::NoSuchField::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::SkolemEscape>() {
+            f(&skolem_escape::SkolemEscape::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::UseOfMovedValue>() {
+            f(&use_of_moved_value::UseOfMovedValue::new(with, v))
+        } else if let Some(v) = self.as_any().downcast_ref::<hir::diagnostic::ManagedTypeArgument>() {
+            f(&managed_type_argument::ManagedTypeArgument::new(with, v))
         } else {
             f(&GenericDiagnostic { diagnostic: self })
         }
@@ -82,3 +107,24 @@ impl<'d> Diagnostic for GenericDiagnostic<'d> {
         self.diagnostic.display_source().value.range()
     }
 }
+
+/// The range of an instance head, excluding its items.
+fn instance_head(db: &dyn hir::db::HirDatabase, src: InFile<SyntaxNodePtr>) -> TextRange {
+    let parse = db.parse(src.file_id);
+    let node = src.value.to_node(&parse.syntax_node());
+    let range = node.text_range();
+
+    match ast::ItemInstance::cast(node) {
+        | Some(inst) => {
+            let end = inst
+                .types()
+                .last()
+                .map(|t| t.syntax().text_range())
+                .or_else(|| inst.class().map(|c| c.syntax().text_range()))
+                .unwrap_or(range);
+
+            TextRange::new(range.start(), end.end())
+        },
+        | None => range,
+    }
+}
@@ -0,0 +1,31 @@
+use super::*;
+use hir::diagnostic::Diagnostic as _;
+use hir::display::HirDisplay;
+
+pub struct ManagedTypeArgument<'db, 'd, DB: hir::db::HirDatabase> {
+    db: &'db DB,
+    diag: &'d hir::diagnostic::ManagedTypeArgument,
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for ManagedTypeArgument<'db, 'd, DB> {
+    fn title(&self) -> String {
+        format!(
+            "cannot instantiate a type variable with `{}`",
+            self.diag.ty.display(self.db)
+        )
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.display_source().value.range()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        vec!["values of a type variable are not reference counted or dropped".into()]
+    }
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> ManagedTypeArgument<'db, 'd, DB> {
+    pub fn new(db: &'db DB, diag: &'d hir::diagnostic::ManagedTypeArgument) -> Self {
+        Self { db, diag }
+    }
+}
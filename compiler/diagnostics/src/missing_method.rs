@@ -0,0 +1,32 @@
+use super::*;
+use hir::diagnostic::Diagnostic as _;
+
+pub struct MissingMethod<'d> {
+    diag: &'d hir::diagnostic::MissingMethod,
+    location: TextRange,
+}
+
+impl<'d> Diagnostic for MissingMethod<'d> {
+    fn title(&self) -> String {
+        self.diag.message()
+    }
+
+    fn range(&self) -> TextRange {
+        self.location
+    }
+
+    fn notes(&self) -> Vec<String> {
+        vec![format!(
+            "`{}` has no default implementation in the class and must be defined by every instance",
+            self.diag.name
+        )]
+    }
+}
+
+impl<'d> MissingMethod<'d> {
+    pub fn new<DB: hir::db::HirDatabase>(db: &DB, diag: &'d hir::diagnostic::MissingMethod) -> Self {
+        let location = instance_head(db, InFile::new(diag.file, diag.src));
+
+        Self { diag, location }
+    }
+}
@@ -0,0 +1,28 @@
+use super::*;
+use hir::diagnostic::Diagnostic as _;
+use hir::display::HirDisplay;
+
+pub struct NoSuchField<'db, 'd, DB: hir::db::HirDatabase> {
+    db: &'db DB,
+    diag: &'d hir::diagnostic::NoSuchField,
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for NoSuchField<'db, 'd, DB> {
+    fn title(&self) -> String {
+        format!(
+            "no field `{}` on type `{}`",
+            self.diag.field,
+            self.diag.base.display(self.db)
+        )
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.display_source().value.range()
+    }
+}
+
+impl<'db, 'd, DB: hir::db::HirDatabase> NoSuchField<'db, 'd, DB> {
+    pub fn new(db: &'db DB, diag: &'d hir::diagnostic::NoSuchField) -> Self {
+        Self { db, diag }
+    }
+}
@@ -0,0 +1,27 @@
+use super::*;
+
+pub struct OrphanInstance {
+    location: TextRange,
+}
+
+impl Diagnostic for OrphanInstance {
+    fn title(&self) -> String {
+        "orphan instance".into()
+    }
+
+    fn range(&self) -> TextRange {
+        self.location
+    }
+
+    fn notes(&self) -> Vec<String> {
+        vec!["either the class or one of the types must be defined in the current library".into()]
+    }
+}
+
+impl OrphanInstance {
+    pub fn new<DB: hir::db::HirDatabase>(db: &DB, diag: &hir::diagnostic::OrphanInstance) -> Self {
+        let location = instance_head(db, InFile::new(diag.file, diag.src));
+
+        Self { location }
+    }
+}
@@ -0,0 +1,32 @@
+use super::*;
+
+pub struct OverlappingInstance {
+    location: TextRange,
+    other: InFile<TextRange>,
+}
+
+impl Diagnostic for OverlappingInstance {
+    fn title(&self) -> String {
+        "overlapping instances".into()
+    }
+
+    fn range(&self) -> TextRange {
+        self.location
+    }
+
+    fn secondary_annotations(&self) -> Vec<SecondaryAnnotation> {
+        vec![SecondaryAnnotation {
+            range: self.other,
+            message: "overlaps with this instance".into(),
+        }]
+    }
+}
+
+impl OverlappingInstance {
+    pub fn new<DB: hir::db::HirDatabase>(db: &DB, diag: &hir::diagnostic::OverlappingInstance) -> Self {
+        let location = instance_head(db, InFile::new(diag.file, diag.src));
+        let other = diag.other.with_value(instance_head(db, diag.other));
+
+        Self { location, other }
+    }
+}
@@ -0,0 +1,26 @@
+use super::*;
+use hir::diagnostic::Diagnostic as _;
+
+pub struct SkolemEscape<'d> {
+    diag: &'d hir::diagnostic::SkolemEscape,
+}
+
+impl<'d> Diagnostic for SkolemEscape<'d> {
+    fn title(&self) -> String {
+        format!("type variable `{}` would escape its scope", self.diag.skolem)
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.display_source().value.range()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        vec!["a rigid type variable introduced by `for` cannot be unified with a type from outside of its scope".into()]
+    }
+}
+
+impl<'d> SkolemEscape<'d> {
+    pub fn new<DB: hir::db::HirDatabase>(_db: &DB, diag: &'d hir::diagnostic::SkolemEscape) -> Self {
+        Self { diag }
+    }
+}
@@ -1,6 +1,8 @@
 use super::*;
+use hir::class::{CandidateFailure, Explanation};
 use hir::diagnostic::Diagnostic as _;
 use hir::display::HirDisplay;
+use std::collections::BTreeMap;
 
 pub struct UnsolvedConstraint<'db, 'd, DB: hir::db::HirDatabase> {
     db: &'db DB,
@@ -9,16 +11,107 @@ pub struct UnsolvedConstraint<'db, 'd, DB: hir::db::HirDatabase> {
 
 impl<'db, 'd, DB: hir::db::HirDatabase> Diagnostic for UnsolvedConstraint<'db, 'd, DB> {
     fn title(&self) -> String {
-        format!("unsolved constraint `{}`", self.diag.ctnt.display(self.db),)
+        if self.diag.explanation.ambiguous.is_empty() {
+            format!("unsolved constraint `{}`", self.diag.ctnt.display(self.db))
+        } else {
+            format!(
+                "ambiguous type variable in constraint `{}`",
+                self.diag.ctnt.display(self.db)
+            )
+        }
     }
 
     fn range(&self) -> TextRange {
         self.diag.display_source().value.range()
     }
+
+    fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+
+        for ty in self.diag.explanation.ambiguous.iter() {
+            notes.push(format!(
+                "cannot infer the type `{}`, consider adding a type annotation",
+                ty.display(self.db)
+            ));
+        }
+
+        self.explain(&self.diag.explanation, 0, &mut notes);
+        notes
+    }
 }
 
 impl<'db, 'd, DB: hir::db::HirDatabase> UnsolvedConstraint<'db, 'd, DB> {
     pub fn new(db: &'db DB, diag: &'d hir::diagnostic::UnsolvedConstraint) -> Self {
         Self { db, diag }
     }
+
+    fn explain(&self, explanation: &Explanation, depth: usize, notes: &mut Vec<String>) {
+        const MAX_MISMATCHES: usize = 3;
+        let indent = "  ".repeat(depth);
+        let mut mismatches = BTreeMap::<usize, Vec<_>>::new();
+
+        for candidate in explanation.candidates.iter() {
+            if let CandidateFailure::Mismatch { index, expected, found } = &candidate.failure {
+                mismatches.entry(*index).or_default().push((*expected, *found));
+            }
+        }
+
+        // when many instances differ in the same argument, only list the types they do apply to
+        for (index, tys) in mismatches.iter() {
+            if tys.len() > MAX_MISMATCHES {
+                let shown = tys
+                    .iter()
+                    .take(MAX_MISMATCHES)
+                    .map(|(expected, _)| format!("`{}`", expected.display(self.db)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                notes.push(format!(
+                    "{}argument {} is `{}`, but there are only instances for {} and {} other types",
+                    indent,
+                    index + 1,
+                    tys[0].1.display(self.db),
+                    shown,
+                    tys.len() - MAX_MISMATCHES,
+                ));
+            }
+        }
+
+        for candidate in explanation.candidates.iter() {
+            let instance = self.db.lower_instance(candidate.instance);
+            let instance = instance.instance.display(self.db);
+
+            match &candidate.failure {
+                | CandidateFailure::Mismatch { index, .. } if mismatches[index].len() > MAX_MISMATCHES => {},
+                | CandidateFailure::Mismatch { index, expected, found } => notes.push(format!(
+                    "{}`{}` does not apply: argument {} is `{}`, but the instance expects `{}`",
+                    indent,
+                    instance,
+                    index + 1,
+                    found.display(self.db),
+                    expected.display(self.db),
+                )),
+                | CandidateFailure::Undetermined { positions } => notes.push(format!(
+                    "{}`{}` could apply, but argument {} is not determined by the functional dependencies",
+                    indent,
+                    instance,
+                    positions
+                        .iter()
+                        .map(|i| (i + 1).to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )),
+                | CandidateFailure::Constraint { ctnt, explanation } => {
+                    notes.push(format!(
+                        "{}`{}` requires `{}`, which could not be solved",
+                        indent,
+                        instance,
+                        ctnt.display(self.db),
+                    ));
+
+                    self.explain(explanation, depth + 1, notes);
+                },
+            }
+        }
+    }
 }
@@ -0,0 +1,29 @@
+use super::*;
+use hir::diagnostic::Diagnostic as _;
+
+pub struct UseOfMovedValue<'d> {
+    diag: &'d hir::diagnostic::UseOfMovedValue,
+}
+
+impl<'d> Diagnostic for UseOfMovedValue<'d> {
+    fn title(&self) -> String {
+        format!("use of moved value `{}`", self.diag.name)
+    }
+
+    fn range(&self) -> TextRange {
+        self.diag.display_source().value.range()
+    }
+
+    fn secondary_annotations(&self) -> Vec<SecondaryAnnotation> {
+        vec![SecondaryAnnotation {
+            range: InFile::new(self.diag.file, self.diag.moved.range()),
+            message: "value moved here".into(),
+        }]
+    }
+}
+
+impl<'d> UseOfMovedValue<'d> {
+    pub fn new<DB: hir::db::HirDatabase>(_db: &DB, diag: &'d hir::diagnostic::UseOfMovedValue) -> Self {
+        Self { diag }
+    }
+}
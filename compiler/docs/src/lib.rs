@@ -383,9 +383,45 @@ impl<'a> Generator<'a> {
             let mut sec = Section::new("Type");
             let mut entry = Entry::new();
 
+            entry.title(name.to_string());
+
+            let def = entry.code();
+
+            def.keyword("type");
+            def.type_(name.to_string());
+
             sec.entries.push(entry);
             page.sections.push(sec);
 
+            let mut sec = Section::new("Constructors");
+
+            for ctor in type_ctor.ctors(self.hdb) {
+                let mut entry = Entry::new();
+                let name = ctor.name(self.hdb);
+
+                entry.title(name.to_string());
+
+                let def = entry.code();
+
+                def.func(name.to_string());
+                def.text("::");
+                def.type_(ctor.ty(self.hdb).display(self.hdb).to_string());
+
+                for (field, ty) in ctor.field_names(self.hdb).iter().zip(ctor.types(self.hdb)) {
+                    let def = entry.code();
+
+                    def.ident(field.to_string());
+                    def.text("::");
+                    def.type_(ty.display(self.hdb).to_string());
+                }
+
+                sec.entries.push(entry);
+            }
+
+            if !sec.entries.is_empty() {
+                page.sections.push(sec);
+            }
+
             let id = self.db.intern_page(Arc::new(page));
 
             self.type_ctors.insert(type_ctor, id);
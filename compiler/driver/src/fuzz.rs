@@ -0,0 +1,78 @@
+use crate::Driver;
+use base_db::SourceDatabaseExt as _;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+const TOKENS: &[&str] = &[
+    "fun", "type", "class", "instance", "static", "const", "foreign", "infix", "infixl", "infixr", "import", "do",
+    "let", "if", "unless", "then", "else", "case", "of", "while", "until", "loop", "break", "next", "yield", "return",
+    "where", "for", "as", "x", "y", "f", "a", "Int32", "Bool", "True", "False", "Proxy", "Type", "Eq", "Fmt", "+",
+    "-", "*", "==", "$", "=", "::", "->", "=>", "|", ",", ".", ".0", "\\", "_", "@", "(", ")", "{", "}", "[", "]",
+    "0", "1", "255", "99999999999999999999999999999999999999999", "1.5", "'c'", "\"str\"", "\n", "\n    ",
+    "\n        ", " ",
+];
+
+/// Feeds random token streams through parsing and type checking of a module that imports `core`,
+/// reporting every input that makes the compiler panic. Returns the number of panics, or `None`
+/// when `core` cannot be loaded.
+pub fn run(core: &str, iterations: usize, seed: u64) -> Option<usize> {
+    let (mut driver, lib, main_file, _, _) = Driver::interactive();
+    let core = driver.load(core)?;
+
+    driver.add_dep(lib, core);
+
+    let message = Arc::new(Mutex::new(String::new()));
+    let hook_message = message.clone();
+    let old_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        *hook_message.lock().unwrap() = info.to_string();
+    }));
+
+    let mut rng = Rng(seed.max(1));
+    let mut failures = 0;
+
+    for i in 0..iterations {
+        let src = random_module(&mut rng);
+
+        driver.db.set_file_text(main_file, src.clone().into());
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            crate::diagnostics::emit_diagnostics(&driver.db, lib.into(), &mut std::io::sink())
+        }));
+
+        if res.is_err() {
+            failures += 1;
+            eprintln!("\x1B[31mpanic\x1B[0m in iteration {}: {}", i, message.lock().unwrap());
+            eprintln!("{}\n", src);
+        }
+    }
+
+    panic::set_hook(old_hook);
+    Some(failures)
+}
+
+fn random_module(rng: &mut Rng) -> String {
+    let mut src = String::from("module INTERACTIVE =\n\nimport core\n\n");
+    let len = 1 + rng.next() as usize % 64;
+
+    for _ in 0..len {
+        src.push_str(TOKENS[rng.next() as usize % TOKENS.len()]);
+        src.push(' ');
+    }
+
+    src.push('\n');
+    src
+}
+
+/// A xorshift generator, so that failures can be reproduced from the seed alone.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
@@ -1,12 +1,16 @@
 pub mod db;
 pub mod diagnostics;
+pub mod fuzz;
 pub mod manifest;
 
+pub use codegen::jit::{EvalError, JitValue};
+
 use base_db::input::{FileId, SourceRoot, SourceRootId};
 use base_db::libs::{LibId, LibKind, LibSet};
 use base_db::SourceDatabase;
 use base_db::SourceDatabaseExt;
 use codegen::db::CodegenDatabase;
+use codegen::linker::LinkError;
 use mir::db::MirDatabase;
 use rustc_hash::FxHashSet;
 use std::path::PathBuf;
@@ -17,15 +21,88 @@ pub struct Opts<'a> {
     pub input: &'a str,
     pub target: Option<&'a str>,
     pub output: Option<LibKind>,
+    pub emit: Vec<Emit>,
+    pub debug: bool,
+    pub release: bool,
+    pub linker: Option<&'a str>,
+    pub link_args: Vec<&'a str>,
+    pub static_link: bool,
+}
+
+impl Opts<'_> {
+    fn profile_name(&self) -> &'static str {
+        if self.release {
+            "release"
+        } else {
+            "dev"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// A C header declaring the exported functions of every library.
+    Header,
+    /// The tokens of every module, in `target/<lib>/<module>.tokens`.
+    Tokens,
+    /// The concrete syntax tree of every module.
+    Cst,
+    /// The lowered body of every function, in `target/<lib>/<function>.hir`.
+    Hir,
+    /// The inferred types of every expression and pattern of every function.
+    Types,
+    Mir,
+    Clif,
+    /// The disassembled machine code of every function.
+    Asm,
+    /// The object file before linking.
+    Obj,
+}
+
+impl Emit {
+    fn extension(self) -> &'static str {
+        match self {
+            | Emit::Header => "h",
+            | Emit::Tokens => "tokens",
+            | Emit::Cst => "cst",
+            | Emit::Hir => "hir",
+            | Emit::Types => "types",
+            | Emit::Mir => "mir",
+            | Emit::Clif => "clif",
+            | Emit::Asm => "s",
+            | Emit::Obj => "o",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum BuildError {
+    /// Constructs that could not be compiled for the target.
+    Codegen(Vec<String>),
+    Link(LinkError),
+    /// An emitted file could not be written.
+    Emit(PathBuf, std::io::Error),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            | BuildError::Codegen(errors) => write!(f, "{}", errors.join("\n\x1B[1;31merror\x1B[0m: ")),
+            | BuildError::Link(e) => e.fmt(f),
+            | BuildError::Emit(path, e) => write!(f, "cannot write '{}': {}", path.display(), e),
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct Driver {
     pub db: db::RootDatabase,
     target_dir: PathBuf,
+    emit: Vec<Emit>,
     libs: LibSet,
     lib_count: u32,
     file_count: u32,
+    jit: codegen::jit::Jit,
 }
 
 impl Driver {
@@ -33,11 +110,18 @@ impl Driver {
         let mut driver = Driver::default();
         let lib = driver.load(opts.input)?;
 
+        let profile = match manifest::Manifest::load(opts.input.as_ref()) {
+            | Ok(manifest) => manifest.profile(opts.profile_name()),
+            | Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            },
+        };
+
         driver.target_dir = PathBuf::from(opts.input).join("target");
-        driver.db.set_target_triple(match opts.target {
-            | Some(target) => Arc::new(target.parse().unwrap()),
-            | None => Arc::new(mir::target_lexicon::HOST),
-        });
+        driver.emit = opts.emit.clone();
+        driver.set_profile(&opts, profile);
+        driver.set_target(opts.target)?;
 
         Some((driver, lib))
     }
@@ -45,21 +129,25 @@ impl Driver {
     pub fn init_no_manifest(opts: Opts) -> Option<(Self, LibId)> {
         let mut driver = Driver::default();
         let path = std::path::PathBuf::from(opts.input);
-        let lib = manifest::load_normal(
+        let lib = match manifest::load_normal(
             &mut driver.db,
             &mut driver.libs,
             &mut driver.lib_count,
             &mut driver.file_count,
             &path,
             opts.output.unwrap_or(LibKind::Executable),
-        )
-        .ok()?;
+        ) {
+            | Ok(lib) => lib,
+            | Err(e) => {
+                eprintln!("{}", e);
+                return None;
+            },
+        };
 
         driver.target_dir = PathBuf::from(opts.input).join("target");
-        driver.db.set_target_triple(match opts.target {
-            | Some(target) => Arc::new(target.parse().unwrap()),
-            | None => Arc::new(mir::target_lexicon::HOST),
-        });
+        driver.emit = opts.emit.clone();
+        driver.set_profile(&opts, manifest::Profile::builtin(opts.profile_name()));
+        driver.set_target(opts.target)?;
 
         Some((driver, lib))
     }
@@ -80,6 +168,8 @@ impl Driver {
         root.insert_file(resolve_file, "<resolve>");
 
         driver.db.set_target_triple(mir::target_lexicon::HOST.into());
+        driver.db.set_opt_level(mir::opt::OptLevel::None);
+        driver.db.set_codegen_opts(Default::default());
         driver.db.set_libs(driver.libs.clone().into());
         driver.db.set_source_root(root_id, root.into());
         driver.db.set_file_source_root(root_file, root_id);
@@ -99,6 +189,65 @@ impl Driver {
         (driver, lib, root_file, type_file, resolve_file)
     }
 
+    fn set_profile(&mut self, opts: &Opts, profile: manifest::Profile) {
+        let static_link = opts.static_link || profile.static_link.unwrap_or(false);
+
+        self.db
+            .set_opt_level(profile.opt_level.unwrap_or(mir::opt::OptLevel::None));
+        self.db.set_codegen_opts(Arc::new(codegen::db::CodegenOpts {
+            debug_info: opts.debug || profile.debug.unwrap_or(false),
+            verifier: profile.verifier.unwrap_or(true),
+            target_cpu: profile.target_cpu,
+            target_features: profile.target_features.unwrap_or_default(),
+            emit_clif: opts.emit.contains(&Emit::Clif),
+            emit_asm: opts.emit.contains(&Emit::Asm),
+            linker: opts
+                .linker
+                .map(|linker| vec![linker.to_string()])
+                .or_else(|| profile.linker.map(manifest::Linker::into_command)),
+            link_args: profile
+                .link_args
+                .unwrap_or_default()
+                .into_iter()
+                .chain(opts.link_args.iter().map(|arg| arg.to_string()))
+                .collect(),
+            static_link,
+        }));
+
+        // static executables cannot use shared libraries, so the libraries are built as archives instead
+        if static_link {
+            for lib in self.libs.iter().collect::<Vec<_>>() {
+                if self.libs[lib].kind == LibKind::Dynamic {
+                    self.libs.set_kind(lib, LibKind::Static);
+                }
+            }
+
+            self.db.set_libs(self.libs.clone().into());
+        }
+    }
+
+    fn set_target(&mut self, target: Option<&str>) -> Option<()> {
+        let triple = match target {
+            | Some(target) => match target.parse() {
+                | Ok(triple) => triple,
+                | Err(e) => {
+                    eprintln!("\x1B[1;31merror\x1B[0m: invalid target `{}`: {}", target, e);
+                    return None;
+                },
+            },
+            | None => mir::target_lexicon::HOST,
+        };
+
+        self.db.set_target_triple(Arc::new(triple));
+
+        if let Err(e) = codegen::check_target(&self.db) {
+            eprintln!("\x1B[1;31merror\x1B[0m: {}", e);
+            return None;
+        }
+
+        Some(())
+    }
+
     pub fn load(&mut self, input: &str) -> Option<LibId> {
         let path = std::path::PathBuf::from(input);
 
@@ -144,29 +293,34 @@ impl Driver {
     pub fn build(&self) -> bool {
         let start = std::time::Instant::now();
         let db = &self.db;
-        let mut errors = 0;
 
-        for lib in hir::Lib::all(db) {
-            println!("  \x1B[1;32m\x1B[1mCompiling\x1B[0m {}", lib.name(db));
+        if !self.compile() {
+            return false;
+        }
 
-            if let Ok(e) = diagnostics::emit_diagnostics(db, lib, &mut std::io::stderr()) {
-                errors += e;
+        if let Err(e) = std::fs::create_dir_all(&self.target_dir) {
+            eprintln!("\x1B[1;31merror\x1B[0m: cannot create '{}': {}", self.target_dir.display(), e);
+            return false;
+        }
+
+        // written before code generation, so that they are available when it fails
+        for lib in hir::Lib::all(db) {
+            if let Err(e) = self.write_emits(lib) {
+                eprintln!("\x1B[1;31merror\x1B[0m: {}", e);
+                return false;
             }
         }
 
-        if errors == 1 {
-            eprintln!("\x1B[1;31mAborting due to previous error\x1B[0m");
+        if self.emit.contains(&Emit::Header) && !self.write_headers() {
             return false;
-        } else if errors > 1 {
-            eprintln!("\x1B[1;31mAborting due to {} previous errors\x1B[0m", errors);
-            return false;
-        } else {
-            std::fs::create_dir_all(&self.target_dir).unwrap();
+        }
 
-            let mut done = FxHashSet::default();
+        let mut done = FxHashSet::default();
 
-            for lib in hir::Lib::all(db) {
-                self.write_assembly(lib, &mut done).unwrap();
+        for lib in hir::Lib::all(db) {
+            if let Err(e) = self.write_assembly(lib, &mut done) {
+                eprintln!("\x1B[1;31merror\x1B[0m: {}", e);
+                return false;
             }
         }
 
@@ -177,10 +331,67 @@ impl Driver {
         true
     }
 
+    /// Runs the library in this process, without linking it first.
+    pub fn run_jit<'a>(&self, lib: LibId, args: impl Iterator<Item = &'a std::ffi::OsStr>) -> bool {
+        if !self.compile() {
+            return false;
+        }
+
+        let lib: hir::Lib = lib.into();
+        let name = self.target_dir.join(lib.name(&self.db).to_string());
+        let args = std::iter::once(name.as_os_str())
+            .chain(args)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        codegen::jit::run(&self.db, lib, &args) == 0
+    }
+
+    /// Calls a function without parameters in this process, see [`codegen::jit::eval`].
+    /// The compiled code is freed when the driver is dropped.
+    pub fn eval(&mut self, func: hir::Func) -> Result<JitValue, EvalError> {
+        codegen::jit::eval(&self.db, &mut self.jit, func)
+    }
+
+    /// Reports the diagnostics of every library. Returns `false` if there are errors.
+    fn compile(&self) -> bool {
+        let db = &self.db;
+        let mut errors = 0;
+
+        for lib in hir::Lib::all(db) {
+            println!("  \x1B[1;32m\x1B[1mCompiling\x1B[0m {}", lib.name(db));
+
+            if let Ok(e) = diagnostics::emit_diagnostics(db, lib, &mut std::io::stderr()) {
+                errors += e;
+            }
+        }
+
+        if errors == 1 {
+            eprintln!("\x1B[1;31mAborting due to previous error\x1B[0m");
+            false
+        } else if errors > 1 {
+            eprintln!("\x1B[1;31mAborting due to {} previous errors\x1B[0m", errors);
+            false
+        } else {
+            true
+        }
+    }
+
     pub fn run<'a>(&self, lib: LibId, args: impl Iterator<Item = &'a std::ffi::OsStr>) -> bool {
         if self.build() {
             let asm = self.db.lib_assembly(lib.into());
             let path = asm.path(&self.db, &self.target_dir);
+
+            if let mir::target_lexicon::OperatingSystem::Wasi = self.db.target_triple().operating_system {
+                return match codegen::wasm::run(&path) {
+                    | Ok(code) => code == 0,
+                    | Err(e) => {
+                        eprintln!("\x1B[1;31merror\x1B[0m: {}", e);
+                        false
+                    },
+                };
+            }
+
             let mut cmd = std::process::Command::new(path);
 
             cmd.args(args);
@@ -194,19 +405,210 @@ impl Driver {
         docs::generate(&self.db, lib.into(), &self.target_dir).unwrap();
     }
 
-    fn write_assembly(&self, lib: hir::Lib, done: &mut FxHashSet<hir::Lib>) -> std::io::Result<bool> {
+    fn write_headers(&self) -> bool {
+        let db = &self.db;
+        let mut success = true;
+
+        for lib in hir::Lib::all(db) {
+            if db.libs()[lib.into()].kind == LibKind::Executable {
+                continue;
+            }
+
+            if let Some(header) = codegen::header::generate(db, lib) {
+                for error in &header.errors {
+                    eprintln!(
+                        "\x1B[1;31merror\x1B[0m: cannot declare `{}` in a C header: {}, found `{}`",
+                        error.func, error.reason, error.ty
+                    );
+                }
+
+                if !header.errors.is_empty() {
+                    success = false;
+                } else if let Err(e) = self.write_emit(lib, Emit::Header, header.text) {
+                    eprintln!("\x1B[1;31merror\x1B[0m: {}", e);
+                    success = false;
+                }
+            }
+        }
+
+        success
+    }
+
+    fn emit_path(&self, lib: hir::Lib, emit: Emit) -> PathBuf {
+        self.target_dir
+            .join(format!("{}.{}", lib.name(&self.db), emit.extension()))
+    }
+
+    fn write_emit(&self, lib: hir::Lib, emit: Emit, contents: impl AsRef<[u8]>) -> Result<(), BuildError> {
+        let path = self.emit_path(lib, emit);
+
+        std::fs::write(&path, contents).map_err(|e| BuildError::Emit(path, e))
+    }
+
+    /// Writes the intermediate representations requested with `--emit` that do not need code generation.
+    fn write_emits(&self, lib: hir::Lib) -> Result<(), BuildError> {
+        use hir::db::{DefDatabase, HirDatabase};
+        use hir::display::HirDisplay;
+        use std::fmt::Write;
+
+        let db = &self.db;
+        let modules = lib
+            .modules(db)
+            .into_iter()
+            .filter(|m| !m.is_virtual(db))
+            .collect::<Vec<_>>();
+
+        let funcs = modules
+            .iter()
+            .flat_map(|m| {
+                let decls = m.declarations(db).into_iter().filter_map(|def| match def {
+                    | hir::ModuleDef::Func(f) => Some(f),
+                    | _ => None,
+                });
+
+                let items = m.instances(db).into_iter().flat_map(|inst| {
+                    inst.items(db).into_iter().filter_map(|item| match item {
+                        | hir::AssocItem::Func(f) => Some(f),
+                        | _ => None,
+                    })
+                });
+
+                decls.chain(items).collect::<Vec<_>>()
+            })
+            .filter(|f| !f.is_foreign(db))
+            .collect::<Vec<_>>();
+
+        for &emit in &self.emit {
+            // the output of every module or function is written to its own file
+            let mut files = Vec::new();
+
+            match emit {
+                | Emit::Header | Emit::Clif | Emit::Asm | Emit::Obj => continue,
+                | Emit::Tokens => {
+                    for module in &modules {
+                        let text = db.file_text(module.file_id(db));
+                        let (tokens, _) = syntax::parsing::lexer::tokenize(&text);
+                        let mut offset = syntax::TextSize::default();
+                        let mut out = String::new();
+
+                        for token in tokens {
+                            let range = syntax::TextRange::at(offset, token.len);
+
+                            writeln!(out, "{:?}@{:?} {:?}", token.kind, range, &text[range]).unwrap();
+                            offset += token.len;
+                        }
+
+                        files.push((module.name(db).to_string(), out));
+                    }
+                },
+                | Emit::Cst => {
+                    for module in &modules {
+                        let file = module.file_id(db);
+                        let out = format!("{:#?}\n", db.parse(file).syntax_node());
+
+                        files.push((module.name(db).to_string(), out));
+                    }
+                },
+                | Emit::Hir => {
+                    for &func in &funcs {
+                        let def = hir::id::DefWithBodyId::FuncId(func.into());
+                        let out = format!("fun {}{}\n", func.name(db), db.body(def).display(db));
+
+                        files.push((func.link_name(db).to_string(), out));
+                    }
+                },
+                | Emit::Types => {
+                    for &func in &funcs {
+                        let def = hir::id::DefWithBodyId::FuncId(func.into());
+                        let body = db.body(def);
+                        let infer = db.infer(def);
+                        let mut out = String::new();
+
+                        writeln!(out, "fun {} :: {}", func.name(db), func.ty(db).display(db)).unwrap();
+
+                        for (expr, ty) in infer.type_of_expr.iter() {
+                            let expr = hir::display::ExprDisplay(&body, expr);
+
+                            writeln!(out, "    {} :: {}", expr.display(db), ty.display(db)).unwrap();
+                        }
+
+                        for (pat, ty) in infer.type_of_pat.iter() {
+                            let pat = hir::display::PatDisplay(&body, pat);
+
+                            writeln!(out, "    {} :: {}", pat.display(db), ty.display(db)).unwrap();
+                        }
+
+                        files.push((func.link_name(db).to_string(), out));
+                    }
+                },
+                | Emit::Mir => {
+                    for &func in &funcs {
+                        let def = hir::id::DefWithBodyId::FuncId(func.into());
+                        let out = format!("{}\n", db.body_mir(def).display(db));
+
+                        files.push((func.link_name(db).to_string(), out));
+                    }
+                },
+            }
+
+            for (name, out) in files {
+                let path = self
+                    .target_dir
+                    .join(lib.name(db).to_string())
+                    .join(format!("{}.{}", name, emit.extension()));
+
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| BuildError::Emit(dir.to_path_buf(), e))?;
+                }
+
+                std::fs::write(&path, out).map_err(|e| BuildError::Emit(path, e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the output of code generation requested with `--emit`.
+    fn write_codegen_emits(&self, asm: &codegen::assembly::Assembly, lib: hir::Lib) -> Result<(), BuildError> {
+        for &emit in &self.emit {
+            match emit {
+                | Emit::Clif => self.write_emit(lib, emit, asm.clif())?,
+                | Emit::Asm => self.write_emit(lib, emit, asm.asm())?,
+                | Emit::Obj => {
+                    let path = self.emit_path(lib, emit);
+
+                    std::fs::copy(asm.tmp_path(), &path).map_err(|e| BuildError::Emit(path, e))?;
+                },
+                | _ => {},
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_assembly(&self, lib: hir::Lib, done: &mut FxHashSet<hir::Lib>) -> Result<bool, BuildError> {
         if done.contains(&lib) {
             return Ok(false);
         }
 
-        let deps = lib.dependencies(&self.db).into_iter().map(|dep| {
-            let _ = self.write_assembly(dep.lib, done);
-            dep.lib
-        });
+        let deps = lib
+            .dependencies(&self.db)
+            .into_iter()
+            .map(|dep| dep.lib)
+            .collect::<Vec<_>>();
+
+        for &dep in &deps {
+            self.write_assembly(dep, done)?;
+        }
 
         let asm = self.db.lib_assembly(lib);
 
-        asm.link(&self.db, deps, &self.target_dir);
+        if !asm.errors().is_empty() {
+            return Err(BuildError::Codegen(asm.errors().to_vec()));
+        }
+
+        self.write_codegen_emits(&asm, lib)?;
+        asm.link(&self.db, deps.into_iter(), &self.target_dir).map_err(BuildError::Link)?;
         done.insert(lib);
 
         Ok(true)
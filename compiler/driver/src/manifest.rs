@@ -3,6 +3,7 @@ use anyhow::{Context, Result};
 use base_db::input::{FileId, SourceRoot, SourceRootId};
 use base_db::libs::{LibId, LibKind, LibSet};
 use base_db::SourceDatabaseExt;
+use mir::opt::OptLevel;
 use path_slash::PathExt as _;
 use relative_path::RelativePath;
 use serde::{Deserialize, Serialize};
@@ -15,6 +16,9 @@ pub struct Manifest {
 
     #[serde(default)]
     pub dependencies: HashMap<String, Dependency>,
+
+    #[serde(default)]
+    pub profile: HashMap<String, Profile>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +38,40 @@ pub enum Dependency {
     Path { path: PathBuf },
 }
 
+/// The settings used when compiling with `--release` (the `release` profile) or without it (the `dev` profile).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    #[serde(default)]
+    #[serde(with = "opt_level")]
+    pub opt_level: Option<OptLevel>,
+    pub debug: Option<bool>,
+    pub verifier: Option<bool>,
+    pub target_cpu: Option<String>,
+    pub target_features: Option<Vec<String>>,
+    /// `cc`, `clang`, `ld`, `lld` or a custom command.
+    pub linker: Option<Linker>,
+    pub link_args: Option<Vec<String>>,
+    pub static_link: Option<bool>,
+}
+
+/// A linker name or program, or a program followed by its arguments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Linker {
+    Name(String),
+    Command(Vec<String>),
+}
+
+impl Linker {
+    pub fn into_command(self) -> Vec<String> {
+        match self {
+            | Linker::Name(name) => vec![name],
+            | Linker::Command(cmd) => cmd,
+        }
+    }
+}
+
 impl Manifest {
     pub fn load(path: &Path) -> Result<Self> {
         let manifest_path = path.join("shadow.toml");
@@ -43,11 +81,53 @@ impl Manifest {
         toml::from_str(&manifest_src).map_err(Into::into)
     }
 
+    /// Returns the profile with the given name, with unset fields taken from the built-in profile.
+    pub fn profile(&self, name: &str) -> Profile {
+        let default = Profile::builtin(name);
+
+        match self.profile.get(name) {
+            | Some(profile) => profile.clone().or(default),
+            | None => default,
+        }
+    }
+
     pub fn dep_dirs<'a>(&'a self, proj_dir: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
         self.dependencies.values().map(move |d| d.get_dir(proj_dir))
     }
 }
 
+impl Profile {
+    pub fn builtin(name: &str) -> Self {
+        match name {
+            | "release" => Profile {
+                opt_level: Some(OptLevel::Speed),
+                debug: Some(false),
+                verifier: Some(false),
+                ..Profile::default()
+            },
+            | _ => Profile {
+                opt_level: Some(OptLevel::None),
+                debug: Some(false),
+                verifier: Some(true),
+                ..Profile::default()
+            },
+        }
+    }
+
+    fn or(self, other: Self) -> Self {
+        Profile {
+            opt_level: self.opt_level.or(other.opt_level),
+            debug: self.debug.or(other.debug),
+            verifier: self.verifier.or(other.verifier),
+            target_cpu: self.target_cpu.or(other.target_cpu),
+            target_features: self.target_features.or(other.target_features),
+            linker: self.linker.or(other.linker),
+            link_args: self.link_args.or(other.link_args),
+            static_link: self.static_link.or(other.static_link),
+        }
+    }
+}
+
 impl Dependency {
     pub fn get_dir(&self, proj_dir: &Path) -> PathBuf {
         match self {
@@ -87,17 +167,12 @@ pub fn load_project(
 
     *roots += 1;
 
-    load_file(
-        rdb,
-        &mut root,
-        root_id,
-        root_file,
-        lib,
-        files,
-        path.join(&manifest.project.entry).parent().unwrap(),
-        &path.join(&manifest.project.entry),
-        true,
-    )?;
+    let entry = path.join(&manifest.project.entry);
+    let dir = entry.parent().unwrap();
+
+    root.dir = dir.canonicalize().ok();
+
+    load_file(rdb, &mut root, root_id, root_file, lib, files, dir, &entry, true)?;
 
     for dep in manifest.dep_dirs(path) {
         let dep = load_project(rdb, libs, roots, files, &dep)?;
@@ -125,6 +200,7 @@ pub fn load_normal(
     let (lib, _) = libs.add_lib(name, kind, root_id, root_file);
 
     *roots += 1;
+    root.dir = path.parent().unwrap().canonicalize().ok();
 
     load_file(
         rdb,
@@ -228,3 +304,27 @@ mod lib_kind {
         }
     }
 }
+
+mod opt_level {
+    use mir::opt::OptLevel;
+    use serde::de::{Deserialize, Deserializer, Error, Unexpected};
+    use serde::ser::{Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(opt_level: &Option<OptLevel>, serializer: S) -> Result<S::Ok, S::Error> {
+        opt_level.map(OptLevel::name).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<OptLevel>, D::Error> {
+        let s = <&str>::deserialize(deserializer)?;
+
+        match s {
+            | "none" => Ok(Some(OptLevel::None)),
+            | "speed" => Ok(Some(OptLevel::Speed)),
+            | "speed_and_size" => Ok(Some(OptLevel::SpeedAndSize)),
+            | _ => Err(Error::invalid_value(
+                Unexpected::Str(s),
+                &"none, speed or speed_and_size",
+            )),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn cyclic_alias() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\ntype A = *B\n\ntype B = A\n");
+
+    assert!(out.contains("cyclic type alias"), "{}", out);
+    assert!(!out.contains("depends on itself"), "{}", out);
+}
+
+#[test]
+fn alias_in_recursive_types() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Tree =\n    | Node Int32 Children\n\ntype Children = \
+         *Forest\n\ntype Forest =\n    | Nil\n    | Cons *Tree Children\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
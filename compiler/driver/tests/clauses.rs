@@ -0,0 +1,32 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn arity_mismatch() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun pick (a :: Int32) 0 = a\nfun pick a = a\n");
+
+    assert!(out.contains("this clause has 1 parameters, but the first clause has 2"), "{}", out);
+}
+
+#[test]
+fn generics_without_constraints() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun id (x :: a) | a = x\n");
+
+    assert!(out.is_empty(), "{}", out);
+}
+
+#[test]
+fn kinded_generics_without_constraints() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun first (x :: a) (y :: b) | a (b :: Type) = x\n");
+
+    assert!(!out.contains("unknown value"), "{}", out);
+    assert!(!out.contains("expected"), "{}", out);
+}
+
+#[test]
+fn float_pattern() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun half 0.5 = 1 :: Int32\nfun half _ = 0\n");
+
+    assert!(out.contains("float literals cannot be used as patterns"), "{}", out);
+}
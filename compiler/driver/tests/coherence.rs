@@ -0,0 +1,33 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn overlap_with_dependency() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Local = Local\n\ninstance Fmt (Local, Local) =\n    fun fmt w _ = \
+         w\n",
+    );
+
+    assert!(out.contains("overlapping instances"), "{}", out);
+    assert!(!out.contains("orphan instance"), "{}", out);
+}
+
+#[test]
+fn overlap_in_same_lib() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nclass Named a =\n    fun name :: a -> Str\n\ninstance Named a =\n    \
+         fun name _ = \"any\"\n\ninstance Named Int32 =\n    fun name _ = \"int\"\n",
+    );
+
+    assert_eq!(out.matches("overlapping instances").count(), 1, "{}", out);
+}
+
+#[test]
+fn orphan() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ninstance Mul Str Str Str =\n    fun mul lhs _ = lhs\n",
+    );
+
+    assert!(out.contains("orphan instance"), "{}", out);
+}
@@ -0,0 +1,47 @@
+#![allow(dead_code)]
+
+use base_db::input::FileId;
+use base_db::libs::LibId;
+use base_db::SourceDatabaseExt as _;
+use driver::Driver;
+use std::path::PathBuf;
+
+/// Loads `src` as a module that imports `core`, the returned file contains the module.
+pub fn load(src: &str) -> (Driver, LibId, FileId) {
+    let (mut driver, lib, main_file, _, _) = Driver::interactive();
+    let core = driver
+        .load(concat!(env!("CARGO_MANIFEST_DIR"), "/../../lib/core"))
+        .unwrap();
+
+    driver.add_dep(lib, core);
+    driver.db.set_file_text(main_file, src.to_string().into());
+
+    (driver, lib, main_file)
+}
+
+/// Type checks `src` as a module that imports `core` and returns the emitted diagnostics.
+pub fn check(src: &str) -> String {
+    let (driver, lib, _) = load(src);
+    let mut out = Vec::new();
+
+    driver::diagnostics::emit_diagnostics(&driver.db, lib.into(), &mut out).unwrap();
+
+    String::from_utf8(out).unwrap()
+}
+
+/// Writes an executable project named `name` that depends on `core` to a temporary directory.
+pub fn project(name: &str, main: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shade-{}-{}", name, std::process::id()));
+    let manifest = format!(
+        "[project]\nname = \"{}\"\nversion = \"0.1.0\"\nentry = \"src/main.shade\"\noutput = \"executable\"\n\n\
+         [dependencies]\ncore = {{ path = \"{}\" }}\n",
+        name,
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../../lib/core"),
+    );
+
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("shadow.toml"), manifest).unwrap();
+    std::fs::write(dir.join("src/main.shade"), main).unwrap();
+
+    dir
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::project;
+use driver::{Driver, Emit, Opts};
+use std::process::Command;
+
+#[test]
+fn locals_on_the_stack_have_a_location() {
+    let dir = project(
+        "debuginfo",
+        "module main =\n\nimport core\n\nfun main = do\n    let triple = (1 :: Int32, 2 :: Int32, 3 :: Int32)\n    \
+         let (a, _, c) = triple\n\n    io/print_i32 (a + c)\n",
+    );
+
+    let (driver, _) = Driver::init(Opts {
+        input: dir.to_str().unwrap(),
+        emit: vec![Emit::Obj],
+        debug: true,
+        ..Opts::default()
+    })
+    .unwrap();
+
+    assert!(driver.build());
+
+    let out = Command::new("readelf")
+        .arg("--debug-dump=info,line")
+        .arg(dir.join("target/debuginfo.o"))
+        .output()
+        .unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let triple = out.find("triple").expect(&out);
+    let location = out[triple..].find("DW_AT_location").expect(&out);
+
+    assert!(out.contains("DW_AT_frame_base"), "{}", out);
+    assert!(out[triple..triple + location].find("DW_TAG").is_none(), "{}", out);
+    assert!(out[triple + location..].contains("DW_OP_fbreg"), "{}", out);
+    assert!(out.contains("main.shade"), "{}", out);
+}
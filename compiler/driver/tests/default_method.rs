@@ -0,0 +1,14 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn default_without_signature() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nclass Named a =\n    fun name :: a -> Str\n    fun describe x = \
+         \"thing\"\n\ninstance Named Int32 =\n    fun name _ = \"int\"\n",
+    );
+
+    assert!(out.contains("default implementation of `describe` has no signature"), "{}", out);
+    assert!(!out.contains("`name`"), "{}", out);
+}
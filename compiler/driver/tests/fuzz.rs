@@ -0,0 +1,7 @@
+#[test]
+fn no_panics() {
+    let core = concat!(env!("CARGO_MANIFEST_DIR"), "/../../lib/core");
+    let failures = driver::fuzz::run(core, 200, 0x5eed);
+
+    assert_eq!(failures, Some(0));
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::load;
+use std::process::Command;
+
+#[test]
+fn header_compiles() {
+    let (driver, lib, _) = load(
+        "module INTERACTIVE =\n\nimport core\n\ntype Point = { x :: Int32, y :: Int32 }\n\ntype Dir =\n    | Left\n    \
+         | Right\n\n@export\nfun move (p :: Point) (d :: Dir) = p\n\n@export = \"shade_add\"\nfun add (a :: Int32) \
+         (b :: Float64) = a\n",
+    );
+    let header = codegen::header::generate(&driver.db, lib.into()).unwrap();
+
+    assert!(header.errors.is_empty());
+
+    let path = std::env::temp_dir().join(format!("shade-header-{}.h", std::process::id()));
+
+    std::fs::write(&path, &header.text).unwrap();
+
+    let status = Command::new("cc").arg("-fsyntax-only").arg(&path).status().unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(status.success(), "{}", header.text);
+}
+
+#[test]
+fn empty_record() {
+    let (driver, lib, _) = load("module INTERACTIVE =\n\nimport core\n\n@export\nfun empty (r :: {}) = r\n");
+    let header = codegen::header::generate(&driver.db, lib.into()).unwrap();
+
+    assert_eq!(header.errors.len(), 1);
+    assert!(!header.text.contains("struct"), "{}", header.text);
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn escaped_braces_are_not_interpolated() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun escaped = \"\\{x\\}\" :: Str\n\nfun raw = r\"{x}\" :: Str\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
+
+#[test]
+fn interpolated_string_is_a_writer() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun writer (x :: Int32) = \"x = {x}\" :: io/Stdout -> io/Stdout\n\nfun \
+         string (x :: Int32) = \"x = {x}\" :: Str\n",
+    );
+
+    assert_eq!(out.matches("expected `").count(), 1, "{}", out);
+}
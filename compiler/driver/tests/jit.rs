@@ -0,0 +1,50 @@
+mod common;
+
+use base_db::libs::LibId;
+use base_db::SourceDatabaseExt as _;
+use common::load;
+use driver::{Driver, EvalError, JitValue};
+
+fn it(driver: &Driver, lib: LibId) -> hir::Func {
+    let db = &driver.db;
+    let lib: hir::Lib = lib.into();
+
+    lib.root_module(db)
+        .declarations(db)
+        .into_iter()
+        .find_map(|def| match def {
+            | hir::ModuleDef::Func(f) if f.name(db).to_string() == "it" => Some(f),
+            | _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn eval_adds_new_declarations() {
+    let src = "module INTERACTIVE =\n\nimport core\n\nfun double (x :: Int32) = x * 2\n\n";
+    let (mut driver, lib, file) = load(&format!("{}fun it = double 21\n", src));
+
+    assert_eq!(driver.eval(it(&driver, lib)), Ok(JitValue::Int(42)));
+
+    let src = format!("{}fun triple (x :: Int32) = x * 3\n\n", src);
+
+    driver
+        .db
+        .set_file_text(file, format!("{}fun it = double (triple 2)\n", src).into());
+
+    assert_eq!(driver.eval(it(&driver, lib)), Ok(JitValue::Int(12)));
+}
+
+#[test]
+fn eval_polymorphic() {
+    let (mut driver, lib, _) = load("module INTERACTIVE =\n\nimport core\n\nfun it = do |x| x\n");
+
+    assert_eq!(driver.eval(it(&driver, lib)), Err(EvalError::Polymorphic));
+}
+
+#[test]
+fn run_jit() {
+    let (driver, lib, _) = load("module INTERACTIVE =\n\nimport core\n\nfun main = print \"jit\\n\"\n");
+
+    assert!(driver.run_jit(lib, std::iter::empty()));
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::project;
+use driver::{Driver, Opts};
+use std::io::Write as _;
+
+#[test]
+fn linker_command_and_args() {
+    let dir = project("link", "module main =\n\nimport core\n\nfun main = print \"linked\\n\"\n");
+    let args = dir.join("linker args.txt");
+    let mut manifest = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join("shadow.toml"))
+        .unwrap();
+
+    // the script contains spaces, so it is only passed correctly when the command is not split
+    writeln!(
+        manifest,
+        "\n[profile.dev]\nlinker = [\"sh\", \"-c\", \"echo \\\"$@\\\" > '{}' && exec cc \\\"$@\\\"\", \"linker\"]\nlink-args \
+         = [\"-Wl,-z,now\"]",
+        args.display()
+    )
+    .unwrap();
+
+    let (driver, lib) = Driver::init(Opts {
+        input: dir.to_str().unwrap(),
+        ..Opts::default()
+    })
+    .unwrap();
+
+    let success = driver.run(lib, std::iter::empty());
+    let args = std::fs::read_to_string(&args).unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(success);
+    assert!(args.contains("-Wl,-z,now"), "{}", args);
+}
@@ -0,0 +1,13 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn generalized_local_function() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun pair = do\n    let id x = x\n\n    (id (1 :: Int32), id \
+         \"one\")\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
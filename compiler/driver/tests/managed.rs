@@ -0,0 +1,35 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn generic_copy_of_rc() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun dup x = (x, x)\n\nfun main = do\n    let r = intrinsics/rc_new \
+         (1 :: Int32)\n    let _ = dup r\n    ()\n",
+    );
+
+    assert_eq!(out.matches("cannot instantiate a type variable").count(), 1, "{}", out);
+    assert!(out.contains("Rc Int32"), "{}", out);
+}
+
+#[test]
+fn generic_copy_of_drop_type() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Handle = Handle\n\ninstance Drop Handle =\n    fun drop _ = \
+         ()\n\ntype Pair = Pair Int32 Handle\n\nfun main = do\n    let dup x = (x, x)\n    let _ = dup (Pair 0 \
+         Handle)\n    ()\n",
+    );
+
+    assert_eq!(out.matches("cannot instantiate a type variable").count(), 1, "{}", out);
+}
+
+#[test]
+fn direct_copy_of_rc() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun main = do\n    let r = intrinsics/rc_new (1 :: Int32)\n    let \
+         s = r\n    let _ = (r, s)\n    ()\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn fields_of_the_same_record() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun sum r = r.x + r.y\n\nfun main = sum { x: 1 :: Int32, y: 2 :: \
+         Int32, z: \"extra\" }\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
+
+#[test]
+fn missing_field() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun sum r = r.x + r.y\n\nfun main = sum { x: 1 :: Int32 }\n",
+    );
+
+    assert!(!out.is_empty(), "{}", out);
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::project;
+use driver::{Driver, Opts};
+use std::process::Command;
+
+#[test]
+fn copy_in_nested_scope() {
+    let dir = project(
+        "nested_copy",
+        "module main =\n\nimport core\n\ntype Handle = Handle\n\ninstance Drop Handle =\n    fun drop _ = print \
+         \"dropped\\n\"\n\nfun main = do\n    let r = intrinsics/rc_new Handle\n    let u = do\n        let t \
+         = r\n        print \"inner\\n\"\n        t\n\n    print \"outer\\n\"\n    let _ = (r, u)\n    ()\n",
+    );
+
+    let (driver, _) = Driver::init(Opts {
+        input: dir.to_str().unwrap(),
+        release: true,
+        ..Opts::default()
+    })
+    .unwrap();
+
+    assert!(driver.build());
+
+    let out = Command::new(dir.join("target/nested_copy")).output().unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(out.status.success(), "{:?}", out.status);
+    assert_eq!(stdout, "inner\nouter\ndropped\n");
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::project;
+use driver::{Driver, Opts};
+use std::process::Command;
+
+#[test]
+fn crash_message() {
+    let dir = project(
+        "crash",
+        "module main =\n\nimport core\n\nfun main = intrinsics/unsafePartial $ intrinsics/crash \"out of cheese\"\n",
+    );
+
+    let (driver, _) = Driver::init(Opts {
+        input: dir.to_str().unwrap(),
+        ..Opts::default()
+    })
+    .unwrap();
+
+    assert!(driver.build());
+
+    let out = Command::new(dir.join("target/crash"))
+        .env("SHADE_BACKTRACE", "1")
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(out.stderr).unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(out.status.code(), Some(101));
+    assert!(stderr.contains("panicked at 'out of cheese'"), "{}", stderr);
+    assert!(stderr.contains("main.shade:5"), "{}", stderr);
+    assert!(stderr.contains("backtrace:") || stderr.contains("not supported"), "{}", stderr);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn named_ctor_fields() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Point = Point { x :: Int32, y :: Int32 }\n\nfun main = do\n    \
+         let p = Point { y: 2, x: 1 }\n    let q = { p | x: 10 }\n    case q of\n        Point { x, y } -> x + \
+         y + p.y\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
+
+#[test]
+fn open_record_as_positional_field() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Wrap r = Wrap { x :: Int32 | r }\n\nfun get w = case w of\n    \
+         Wrap rec -> rec.x\n\nfun main = get (Wrap { x: 1, y: \"extra\" })\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
+
+#[test]
+fn closed_record_as_positional_field() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Box = Box ({ x :: Int32 })\n\nfun get b = case b of\n    Box \
+         rec -> rec.x\n\nfun main = get (Box { x: 1 })\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn chained_non_associative_operator() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun chained (a :: Int32) b c = a == b == c\n");
+
+    assert!(out.contains("operator `==` is not associative and cannot be chained"), "{}", out);
+}
+
+#[test]
+fn parenthesized_non_associative_operator() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun nested (a :: Int32) b (c :: Bool) = (a == b) == c\n");
+
+    assert!(!out.contains("not associative"), "{}", out);
+}
+
+#[test]
+fn operator_sections() {
+    let out = check("module INTERACTIVE =\n\nimport core\n\nfun sections (a :: Int32) = ((+ 1) a, (1 +) a, (+) a a)\n");
+
+    assert!(out.is_empty(), "{}", out);
+}
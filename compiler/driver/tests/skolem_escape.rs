@@ -0,0 +1,24 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn unknown_from_outer_scope() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun apply (f :: for a. a -> a) = 0 :: Int32\n\nfun leak y = apply (do \
+         |x| y)\n",
+    );
+
+    assert!(out.contains("would escape its scope"), "{}", out);
+    assert!(!out.contains("mismatched"), "{}", out);
+}
+
+#[test]
+fn polymorphic_argument() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nfun apply (f :: for a. a -> a) = f (0 :: Int32)\n\nfun ok = apply (do \
+         |x| x)\n",
+    );
+
+    assert!(out.is_empty(), "{}", out);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::check;
+
+#[test]
+fn single_argument_class() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Opaque = Opaque\n\nfun main = printf \"{Opaque}\"\n",
+    );
+
+    assert!(out.contains("unsolved constraint"), "{}", out);
+    assert!(out.contains("argument 1 is `"), "{}", out);
+    assert!(out.contains("but there are only instances for"), "{}", out);
+}
+
+#[test]
+fn missing_superclass_instance() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Opaque = Opaque\n\ninstance Ord Opaque\n\nfun ordered (x :: t) \
+         | t : Ord t = x\n\nfun main = ordered Opaque\n",
+    );
+
+    assert_eq!(out.matches("unsolved constraint").count(), 1, "{}", out);
+    assert!(out.contains("unsolved constraint `PartialOrd"), "{}", out);
+}
+
+#[test]
+fn instance_constraint_chain() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\ntype Opaque = Opaque\n\ntype Wrap a = Wrap a\n\ninstance Fmt (Wrap \
+         a) : Fmt a =\n    fun fmt w _ = w\n\nfun main = printf \"{Wrap Opaque}\"\n",
+    );
+
+    assert!(out.contains("unsolved constraint"), "{}", out);
+    assert!(out.contains("requires `"), "{}", out);
+}
+
+#[test]
+fn cyclic_superclass_and_instance_constraint() {
+    let out = check(
+        "module INTERACTIVE =\n\nimport core\n\nclass Like a b\n\nclass Same a : Like a a\n\ntype X = X\n\ninstance \
+         Like X X : Same X\n\ninstance Same X\n\nfun same (x :: t) | t : Same t = x\n\nfun main = same X\n",
+    );
+
+    assert!(!out.contains("unsolved constraint"), "{}", out);
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use common::project;
+use driver::{Driver, Opts};
+
+#[test]
+fn run_core_program() {
+    let dir = project(
+        "wasm",
+        "module main =\n\nimport core\n\nfun main = do\n    let x = 40 :: Int32\n\n    io/print_i32 (x + 2)\n    \
+         print \"\\n\"\n",
+    );
+
+    let (driver, lib) = Driver::init(Opts {
+        input: dir.to_str().unwrap(),
+        target: Some("wasm32-wasi"),
+        ..Opts::default()
+    })
+    .unwrap();
+
+    let success = driver.run(lib, std::iter::empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(success);
+}
@@ -10,12 +10,12 @@ use hir_ty::display::HirDisplay;
 
 use base_db::input::FileId;
 use base_db::libs::LibId;
-pub use hir_def::body::Body;
+pub use hir_def::body::{Body, BodySourceMap};
 use hir_def::diagnostic::DiagnosticSink;
 pub use hir_def::expr::{CaseArm, Expr, ExprId, Literal, Stmt};
 use hir_def::id::*;
 pub use hir_def::in_file::InFile;
-pub use hir_def::item_tree::{Assoc, Prec};
+pub use hir_def::item_tree::{Abi, Assoc, Prec};
 pub use hir_def::name::{AsName, Name};
 pub use hir_def::pat::{Pat, PatId};
 pub use hir_def::path::Path;
@@ -23,7 +23,7 @@ pub use hir_def::{arena, attrs, id};
 use hir_ty::db::HirDatabase;
 pub use hir_ty::infer::{InferenceResult, MethodSource};
 use hir_ty::lower::LowerResult;
-pub use hir_ty::{display, ty};
+pub use hir_ty::{class, display, ty};
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -362,7 +362,9 @@ impl Func {
     }
 
     pub fn link_name(self, db: &dyn HirDatabase) -> Name {
-        if self.is_foreign(db) {
+        if let Some(name) = self.export_name(db) {
+            name
+        } else if self.is_foreign(db) {
             self.name(db)
         } else {
             let mut path = self.path(db);
@@ -386,8 +388,19 @@ impl Func {
         }
     }
 
+    pub fn abi(self, db: &dyn HirDatabase) -> Abi {
+        db.func_data(self.id).abi
+    }
+
+    /// The symbol name given by `@export`.
+    pub fn export_name(self, db: &dyn HirDatabase) -> Option<Name> {
+        db.func_data(self.id).export.clone()
+    }
+
     pub fn is_exported(self, db: &dyn HirDatabase) -> bool {
-        if let Some(assoc) = self.as_assoc_item(db) {
+        if self.export_name(db).is_some() {
+            true
+        } else if let Some(assoc) = self.as_assoc_item(db) {
             matches!(assoc.container(db), AssocItemContainer::Instance(_))
         } else {
             self.module(db).is_exported(db, self.name(db))
@@ -412,6 +425,12 @@ impl Func {
         //
         // eprintln!();
 
+        let (_, source_map) = db.body_source_map(self.id.into());
+
+        for diag in source_map.diagnostics() {
+            diag.add_to(sink);
+        }
+
         infer.add_diagnostics(db, self.id.into(), sink);
         lower.add_diagnostics(db, TypeVarOwner::TypedDefId(self.id.into()), sink);
     }
@@ -446,7 +465,9 @@ impl Static {
     }
 
     pub fn link_name(self, db: &dyn HirDatabase) -> Name {
-        if self.is_foreign(db) {
+        if let Some(name) = self.export_name(db) {
+            name
+        } else if self.is_foreign(db) {
             self.name(db)
         } else {
             let mut path = self.path(db);
@@ -474,8 +495,18 @@ impl Static {
         db.static_data(self.id).is_foreign
     }
 
+    pub fn abi(self, db: &dyn HirDatabase) -> Abi {
+        db.static_data(self.id).abi
+    }
+
+    pub fn export_name(self, db: &dyn HirDatabase) -> Option<Name> {
+        db.static_data(self.id).export.clone()
+    }
+
     pub fn is_exported(self, db: &dyn HirDatabase) -> bool {
-        if let Some(assoc) = self.as_assoc_item(db) {
+        if self.export_name(db).is_some() {
+            true
+        } else if let Some(assoc) = self.as_assoc_item(db) {
             matches!(assoc.container(db), AssocItemContainer::Instance(_))
         } else {
             self.module(db).is_exported(db, self.name(db))
@@ -552,6 +583,10 @@ impl TypeAlias {
         self.module(db).path_to_name(db, self.name(db))
     }
 
+    pub fn ty(self, db: &dyn HirDatabase) -> ty::Ty {
+        db.type_for_alias(self.id).ty
+    }
+
     pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
         let lower = db.type_for_alias(self.id);
 
@@ -583,6 +618,14 @@ impl TypeCtor {
         db.type_ctor_data(self.id).name.clone()
     }
 
+    pub fn ctors(self, db: &dyn HirDatabase) -> Vec<Ctor> {
+        db.type_ctor_data(self.id)
+            .ctors
+            .iter()
+            .map(|(id, _)| Ctor { parent: self, id })
+            .collect()
+    }
+
     pub fn path(self, db: &dyn HirDatabase) -> Path {
         self.module(db).path_to_name(db, self.name(db))
     }
@@ -625,6 +668,36 @@ impl Ctor {
         self.parent
     }
 
+    pub fn ty(self, db: &dyn HirDatabase) -> ty::Ty {
+        let id = CtorId {
+            parent: self.parent.id,
+            local_id: self.id,
+        };
+
+        db.value_ty(id.into())
+    }
+
+    /// The types of the fields of this constructor, in the order they were declared.
+    pub fn types(self, db: &dyn HirDatabase) -> Vec<ty::Ty> {
+        let id = CtorId {
+            parent: self.parent.id,
+            local_id: self.id,
+        };
+
+        let lower = db.ctor_ty(id);
+
+        db.type_ctor_data(self.parent.id).ctors[self.id]
+            .types
+            .iter()
+            .map(|&t| lower.types[t])
+            .collect()
+    }
+
+    /// The names of the fields of a constructor declared with record syntax, empty otherwise.
+    pub fn field_names(self, db: &dyn HirDatabase) -> Box<[Name]> {
+        db.type_ctor_data(self.parent.id).ctors[self.id].fields.clone()
+    }
+
     pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
         let id = CtorId {
             parent: self.parent.id,
@@ -733,8 +806,10 @@ impl Instance {
 
     pub fn diagnostics(self, db: &dyn HirDatabase, sink: &mut DiagnosticSink) {
         let lower = db.lower_instance(self.id);
+        let coherence = db.coherence(self.id);
 
         lower.add_diagnostics(db, TypeVarOwner::TypedDefId(self.id.into()), sink);
+        coherence.add_diagnostics(db, TypeVarOwner::TypedDefId(self.id.into()), sink);
 
         for item in self.items(db) {
             item.diagnostics(db, sink);
@@ -247,6 +247,13 @@ impl AttrInputGroup {
         self.0.iter().any(|i| i.ident(name))
     }
 
+    pub fn idents(&self) -> impl Iterator<Item = &Name> {
+        self.0.iter().filter_map(|i| match i {
+            | AttrInput::Ident(name) => Some(name),
+            | _ => None,
+        })
+    }
+
     pub fn string(&self) -> Option<&str> {
         self.0.iter().find_map(AttrInput::string)
     }
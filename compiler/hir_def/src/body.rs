@@ -1,15 +1,21 @@
+mod derive;
 mod lower;
 
 use crate::arena::{Arena, ArenaMap};
+use crate::ast_id::AstId;
 use crate::db::DefDatabase;
+use crate::diagnostic::DiagnosticSink;
+use crate::diagnostics;
 use crate::expr::{Expr, ExprId};
 use crate::id::{DefWithBodyId, HasModule, HasSource, Lookup, ModuleId};
 use crate::in_file::InFile;
+use crate::name::Name;
 use crate::pat::{Pat, PatId};
 use crate::type_ref::{LocalTypeRefId, TypeMap, TypeRef, TypeSourceMap};
 use base_db::input::FileId;
 use rustc_hash::FxHashMap;
 use std::sync::Arc;
+use syntax::ptr::SyntaxNodePtr;
 use syntax::{ast, AstPtr};
 
 #[derive(Debug, PartialEq, Eq)]
@@ -36,6 +42,34 @@ pub struct BodySourceMap {
     pat_map_back: ArenaMap<PatId, Result<PatSource, SyntheticSyntax>>,
 
     type_source_map: TypeSourceMap,
+    synthetic_origin: Option<SyntaxNodePtr>,
+    diagnostics: Vec<BodyDiagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyDiagnostic {
+    MismatchedClauseArity {
+        name: InFile<AstPtr<ast::Name>>,
+        expected: usize,
+        found: usize,
+    },
+    InvalidLiteral {
+        src: InFile<SyntaxNodePtr>,
+    },
+    NonAssociativeOperator {
+        src: InFile<SyntaxNodePtr>,
+        op: Name,
+    },
+    NoSuchCtorField {
+        src: InFile<SyntaxNodePtr>,
+        ctor: Name,
+        field: Name,
+    },
+    MissingCtorFields {
+        src: InFile<SyntaxNodePtr>,
+        ctor: Name,
+        fields: Vec<Name>,
+    },
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,13 +78,32 @@ pub struct SyntheticSyntax;
 impl Body {
     pub fn body_source_map_query(db: &dyn DefDatabase, def: DefWithBodyId) -> (Arc<Body>, Arc<BodySourceMap>) {
         let mut params = None;
+        let mut where_clause = None;
 
         let (file_id, module, body) = match def {
+            | DefWithBodyId::FuncId(id) if derive::is_derived(db, id) => {
+                let (body, source_map) = derive::lower(db, id);
+
+                return (Arc::new(body), Arc::new(source_map));
+            },
             | DefWithBodyId::FuncId(f) => {
                 let f = f.lookup(db);
                 let src = f.source(db);
+                let item_tree = db.item_tree(f.id.file_id);
+                let clauses = &item_tree[f.id.value].clauses;
+
+                if !clauses.is_empty() {
+                    let clauses = clauses
+                        .iter()
+                        .map(|&ast_id| AstId::new(src.file_id, ast_id).to_node(db))
+                        .collect();
+                    let (body, source_map) = lower::lower_clauses(db, clauses, src.file_id, f.module(db));
+
+                    return (Arc::new(body), Arc::new(source_map));
+                }
 
                 params = Some(src.value.args());
+                where_clause = src.value.where_clause();
                 (src.file_id, f.module(db), src.value.body())
             },
             | DefWithBodyId::ConstId(c) => {
@@ -67,7 +120,7 @@ impl Body {
             },
         };
 
-        let (body, source_map) = Body::new(db, params, body, file_id, module);
+        let (body, source_map) = Body::new(db, params, body, where_clause, file_id, module);
 
         (Arc::new(body), Arc::new(source_map))
     }
@@ -80,10 +133,11 @@ impl Body {
         db: &dyn DefDatabase,
         params: Option<ast::AstChildren<ast::Pat>>,
         body: Option<ast::Expr>,
+        where_clause: Option<ast::WhereClause>,
         file_id: FileId,
         module: ModuleId,
     ) -> (Body, BodySourceMap) {
-        lower::lower(db, params, body, file_id, module)
+        lower::lower(db, params, body, where_clause, file_id, module)
     }
 
     pub fn params(&self) -> &[PatId] {
@@ -117,6 +171,59 @@ impl BodySourceMap {
         let src = node.map(AstPtr::new);
         self.pat_map.get(&src).cloned()
     }
+
+    /// The node that synthesized expressions and patterns should be reported at.
+    pub fn synthetic_origin(&self) -> Option<SyntaxNodePtr> {
+        self.synthetic_origin
+    }
+
+    pub fn diagnostics(&self) -> &[BodyDiagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl BodyDiagnostic {
+    pub fn add_to(&self, sink: &mut DiagnosticSink) {
+        match self {
+            | BodyDiagnostic::MismatchedClauseArity { name, expected, found } => {
+                sink.push(diagnostics::MismatchedClauseArity {
+                    file: name.file_id,
+                    node: name.value.clone(),
+                    expected: *expected,
+                    found: *found,
+                });
+            },
+            | BodyDiagnostic::InvalidLiteral { src } => {
+                sink.push(diagnostics::InvalidLiteral {
+                    file: src.file_id,
+                    src: src.value,
+                });
+            },
+            | BodyDiagnostic::NonAssociativeOperator { src, op } => {
+                sink.push(diagnostics::NonAssociativeOperator {
+                    file: src.file_id,
+                    src: src.value,
+                    op: op.clone(),
+                });
+            },
+            | BodyDiagnostic::NoSuchCtorField { src, ctor, field } => {
+                sink.push(diagnostics::NoSuchCtorField {
+                    file: src.file_id,
+                    src: src.value,
+                    ctor: ctor.clone(),
+                    field: field.clone(),
+                });
+            },
+            | BodyDiagnostic::MissingCtorFields { src, ctor, fields } => {
+                sink.push(diagnostics::MissingCtorFields {
+                    file: src.file_id,
+                    src: src.value,
+                    ctor: ctor.clone(),
+                    fields: fields.clone(),
+                });
+            },
+        }
+    }
 }
 
 impl std::ops::Deref for BodySourceMap {
@@ -0,0 +1,347 @@
+use crate::arena::Arena;
+use crate::body::{Body, BodySourceMap, SyntheticSyntax};
+use crate::data::{DeriveData, DeriveKind, TypeCtorData};
+use crate::db::DefDatabase;
+use crate::expr::{dummy_expr_id, CaseArm, Expr, ExprId, Literal};
+use crate::id::{ContainerId, FuncId, Lookup};
+use crate::name::{AsName, Name};
+use crate::pat::{Pat, PatId};
+use crate::path::Path;
+use crate::type_ref::TypeMap;
+
+pub(super) fn is_derived(db: &dyn DefDatabase, id: FuncId) -> bool {
+    let loc = id.lookup(db);
+    let item_tree = db.item_tree(loc.id.file_id);
+
+    item_tree[loc.id.value].is_derived
+}
+
+pub(super) fn lower(db: &dyn DefDatabase, id: FuncId) -> (Body, BodySourceMap) {
+    let loc = id.lookup(db);
+    let instance = match loc.container {
+        | ContainerId::Instance(id) => id,
+        | _ => unreachable!(),
+    };
+
+    let item_tree = db.item_tree(loc.id.file_id);
+    let name = item_tree[loc.id.value].name.to_string();
+    let data = db.instance_data(instance);
+    let derive = data.derive.as_ref().unwrap();
+    let type_ctor = db.type_ctor_data(derive.type_ctor);
+    let type_loc = derive.type_ctor.lookup(db);
+    let ast_id = item_tree[type_loc.id.value].ast_id;
+    let origin = db.ast_id_map(loc.id.file_id).get(ast_id).syntax_node_ptr();
+    let mut ctx = DeriveCtx {
+        type_ctor: &type_ctor,
+        body: Body {
+            exprs: Arena::default(),
+            pats: Arena::default(),
+            params: Vec::new(),
+            body_expr: dummy_expr_id(),
+            type_map: TypeMap::default(),
+        },
+        source_map: BodySourceMap {
+            synthetic_origin: Some(origin),
+            ..BodySourceMap::default()
+        },
+    };
+
+    ctx.body.body_expr = match (derive.kind, name.as_str()) {
+        | (DeriveKind::PartialEq, "eq") => ctx.derive_eq(),
+        | (DeriveKind::PartialOrd, "cmp") => ctx.derive_cmp(),
+        | (DeriveKind::Fmt, "fmt") => ctx.derive_fmt(),
+        | _ => ctx.alloc_expr(Expr::Missing),
+    };
+
+    (ctx.body, ctx.source_map)
+}
+
+struct DeriveCtx<'a> {
+    type_ctor: &'a TypeCtorData,
+    body: Body,
+    source_map: BodySourceMap,
+}
+
+impl DeriveCtx<'_> {
+    /// ```shade
+    /// fun eq lhs rhs =
+    ///     case lhs of
+    ///         C x0 x1 -> case rhs of
+    ///             C y0 y1 -> if eq x0 y0 then eq x1 y1 else False
+    ///             _ -> False
+    /// ```
+    fn derive_eq(&mut self) -> ExprId {
+        let lhs = self.param("lhs");
+        let rhs = self.param("rhs");
+
+        if self.type_ctor.ctors.is_empty() {
+            return self.scope_path("true");
+        }
+
+        let multiple = self.type_ctor.ctors.len() > 1;
+        let ctors = self.ctors();
+        let arms = ctors
+            .into_iter()
+            .map(|(ctor, fields)| {
+                let (lhs_pat, xs) = self.ctor_pat(&ctor, fields, "x");
+                let (rhs_pat, ys) = self.ctor_pat(&ctor, fields, "y");
+                let fields = xs.into_iter().zip(ys).collect::<Vec<_>>();
+                let expr = match fields.split_last() {
+                    | None => self.scope_path("true"),
+                    | Some((&(x, y), rest)) => {
+                        let last = self.call("eq", vec![x, y]);
+
+                        rest.iter().rev().fold(last, |then, &(x, y)| {
+                            let cond = self.call("eq", vec![x, y]);
+                            let else_ = self.scope_path("false");
+
+                            self.alloc_expr(Expr::If {
+                                cond,
+                                then,
+                                else_: Some(else_),
+                                inverse: false,
+                            })
+                        })
+                    },
+                };
+
+                let mut arms = vec![self.arm(rhs_pat, expr)];
+
+                if multiple {
+                    let wildcard = self.alloc_pat(Pat::Wildcard);
+                    let expr = self.scope_path("false");
+
+                    arms.push(self.arm(wildcard, expr));
+                }
+
+                let pred = self.path(rhs.clone());
+                let expr = self.alloc_expr(Expr::Case { pred, arms });
+
+                self.arm(lhs_pat, expr)
+            })
+            .collect();
+
+        let pred = self.path(lhs);
+
+        self.alloc_expr(Expr::Case { pred, arms })
+    }
+
+    /// ```shade
+    /// fun cmp lhs rhs =
+    ///     case lhs of
+    ///         A x0 x1 -> case rhs of
+    ///             A y0 y1 -> case cmp x0 y0 of
+    ///                 Equal -> cmp x1 y1
+    ///                 o -> o
+    ///             _ -> Less
+    ///         B -> case rhs of
+    ///             B -> Equal
+    ///             _ -> Greater
+    /// ```
+    fn derive_cmp(&mut self) -> ExprId {
+        let lhs = self.param("lhs");
+        let rhs = self.param("rhs");
+
+        if self.type_ctor.ctors.is_empty() {
+            return self.scope_path("equal");
+        }
+
+        let ctors = self.ctors();
+        let last_ctor = ctors.len() - 1;
+        let arms = ctors
+            .iter()
+            .enumerate()
+            .map(|(i, (ctor, fields))| {
+                let (lhs_pat, xs) = self.ctor_pat(ctor, *fields, "x");
+                let (rhs_pat, ys) = self.ctor_pat(ctor, *fields, "y");
+                let fields = xs.into_iter().zip(ys).collect::<Vec<_>>();
+                let expr = match fields.split_last() {
+                    | None => self.scope_path("equal"),
+                    | Some((&(x, y), rest)) => {
+                        let last = self.call("cmp", vec![x, y]);
+
+                        rest.iter().rev().fold(last, |rest, &(x, y)| {
+                            let pred = self.call("cmp", vec![x, y]);
+                            let equal = Path::from(DeriveData::scope_name("equal"));
+                            let equal = self.alloc_pat(Pat::Path { path: equal });
+                            let other = self.bind("o");
+                            let other_expr = self.path("o".as_name());
+                            let arms = vec![self.arm(equal, rest), self.arm(other, other_expr)];
+
+                            self.alloc_expr(Expr::Case { pred, arms })
+                        })
+                    },
+                };
+
+                let mut arms = vec![self.arm(rhs_pat, expr)];
+
+                if i == last_ctor {
+                    if i != 0 {
+                        let wildcard = self.alloc_pat(Pat::Wildcard);
+                        let expr = self.scope_path("greater");
+
+                        arms.push(self.arm(wildcard, expr));
+                    }
+                } else {
+                    for (other, fields) in &ctors[..i] {
+                        let (pat, _) = self.ctor_pat(other, *fields, "_");
+                        let expr = self.scope_path("greater");
+
+                        arms.push(self.arm(pat, expr));
+                    }
+
+                    let wildcard = self.alloc_pat(Pat::Wildcard);
+                    let expr = self.scope_path("less");
+
+                    arms.push(self.arm(wildcard, expr));
+                }
+
+                let pred = self.path(rhs.clone());
+                let expr = self.alloc_expr(Expr::Case { pred, arms });
+
+                self.arm(lhs_pat, expr)
+            })
+            .collect();
+
+        let pred = self.path(lhs);
+
+        self.alloc_expr(Expr::Case { pred, arms })
+    }
+
+    /// ```shade
+    /// fun fmt w value =
+    ///     case value of
+    ///         C x0 x1 -> fmt (fmt (fmt (fmt (fmt w "(C") " ") x0) " ") x1) ")"
+    ///         D -> fmt w "D"
+    /// ```
+    fn derive_fmt(&mut self) -> ExprId {
+        let writer = self.param("w");
+        let value = self.param("value");
+        let ctors = self.ctors();
+
+        if ctors.is_empty() {
+            return self.path(writer);
+        }
+
+        let arms = ctors
+            .into_iter()
+            .map(|(ctor, fields)| {
+                let (pat, xs) = self.ctor_pat(&ctor, fields, "x");
+                let w = self.path(writer.clone());
+                let expr = if xs.is_empty() {
+                    let name = self.string(ctor.to_string());
+
+                    self.call("fmt", vec![w, name])
+                } else {
+                    let name = self.string(format!("({}", ctor));
+                    let start = self.call("fmt", vec![w, name]);
+                    let fields = xs.into_iter().fold(start, |w, x| {
+                        let space = self.string(" ".into());
+                        let w = self.call("fmt", vec![w, space]);
+
+                        self.call("fmt", vec![w, x])
+                    });
+
+                    let end = self.string(")".into());
+
+                    self.call("fmt", vec![fields, end])
+                };
+
+                self.arm(pat, expr)
+            })
+            .collect();
+
+        let pred = self.path(value);
+
+        self.alloc_expr(Expr::Case { pred, arms })
+    }
+
+    fn ctors(&self) -> Vec<(Name, usize)> {
+        self.type_ctor
+            .ctors
+            .iter()
+            .map(|(_, ctor)| (ctor.name.clone(), ctor.types.len()))
+            .collect()
+    }
+
+    /// Creates a pattern matching the constructor and binding its fields.
+    /// Fields are matched by wildcards if `prefix` is `_`.
+    fn ctor_pat(&mut self, ctor: &Name, fields: usize, prefix: &str) -> (PatId, Vec<ExprId>) {
+        let base = self.alloc_pat(Pat::Path {
+            path: Path::from(ctor.clone()),
+        });
+
+        if fields == 0 {
+            return (base, Vec::new());
+        }
+
+        let mut args = Vec::with_capacity(fields);
+        let mut exprs = Vec::with_capacity(fields);
+
+        for i in 0..fields {
+            if prefix == "_" {
+                args.push(self.alloc_pat(Pat::Wildcard));
+            } else {
+                let name = format!("{}{}", prefix, i);
+
+                args.push(self.bind(&name));
+                exprs.push(self.path(name.as_name()));
+            }
+        }
+
+        (self.alloc_pat(Pat::App { base, args }), exprs)
+    }
+
+    fn param(&mut self, name: &str) -> Name {
+        let pat = self.bind(name);
+
+        self.body.params.push(pat);
+        name.as_name()
+    }
+
+    fn bind(&mut self, name: &str) -> PatId {
+        self.alloc_pat(Pat::Bind {
+            name: name.as_name(),
+            subpat: None,
+        })
+    }
+
+    fn arm(&mut self, pat: PatId, expr: ExprId) -> CaseArm {
+        CaseArm { pat, guard: None, expr }
+    }
+
+    fn call(&mut self, method: &str, args: Vec<ExprId>) -> ExprId {
+        let base = self.scope_path(method);
+
+        args.into_iter()
+            .fold(base, |base, arg| self.alloc_expr(Expr::App { base, arg }))
+    }
+
+    fn scope_path(&mut self, name: &str) -> ExprId {
+        self.path(DeriveData::scope_name(name))
+    }
+
+    fn path(&mut self, name: Name) -> ExprId {
+        self.alloc_expr(Expr::Path { path: Path::from(name) })
+    }
+
+    fn string(&mut self, s: String) -> ExprId {
+        self.alloc_expr(Expr::Lit {
+            lit: Literal::String(s),
+        })
+    }
+
+    fn alloc_expr(&mut self, expr: Expr) -> ExprId {
+        let id = self.body.exprs.alloc(expr);
+
+        self.source_map.expr_map_back.insert(id, Err(SyntheticSyntax));
+        id
+    }
+
+    fn alloc_pat(&mut self, pat: Pat) -> PatId {
+        let id = self.body.pats.alloc(pat);
+
+        self.source_map.pat_map_back.insert(id, Err(SyntheticSyntax));
+        id
+    }
+}
@@ -1,11 +1,11 @@
 use crate::arena::Arena;
 use crate::ast_id::{AstIdMap, FileAstId};
-use crate::body::{Body, BodySourceMap, ExprPtr, ExprSource, PatPtr, PatSource, SyntheticSyntax};
-use crate::data::FixityData;
+use crate::body::{Body, BodyDiagnostic, BodySourceMap, ExprPtr, ExprSource, PatPtr, PatSource, SyntheticSyntax};
+use crate::data::{CtorData, FixityData};
 use crate::db::DefDatabase;
 use crate::def_map::DefMap;
 use crate::expr::{dummy_expr_id, CaseArm, Expr, ExprId, Literal, RecordField, Stmt};
-use crate::id::{FixityId, LocalModuleId, ModuleDefId, ModuleId};
+use crate::id::{CtorId, FixityId, LocalModuleId, ModuleDefId, ModuleId};
 use crate::in_file::InFile;
 use crate::item_tree::Assoc;
 use crate::name::{AsName, Name};
@@ -14,7 +14,9 @@ use crate::path::Path;
 use crate::type_ref::{TypeMap, TypeMapBuilder};
 use base_db::input::FileId;
 use std::sync::Arc;
-use syntax::{ast, AstPtr};
+use syntax::ast::{self, AstNode, NameOwner};
+use syntax::ptr::SyntaxNodePtr;
+use syntax::AstPtr;
 
 pub struct LowerCtx {
     file_id: FileId,
@@ -46,25 +48,20 @@ pub(super) fn lower(
     db: &dyn DefDatabase,
     params: Option<ast::AstChildren<ast::Pat>>,
     body: Option<ast::Expr>,
+    where_clause: Option<ast::WhereClause>,
     file_id: FileId,
     module: ModuleId,
 ) -> (Body, BodySourceMap) {
-    ExprCollector {
-        db,
-        file_id,
-        module: module.local_id,
-        def_map: db.def_map(module.lib),
-        source_map: BodySourceMap::default(),
-        body: Body {
-            exprs: Arena::default(),
-            pats: Arena::default(),
-            params: Vec::new(),
-            body_expr: dummy_expr_id(),
-            type_map: TypeMap::default(),
-        },
-        type_builder: TypeMapBuilder::default(),
-    }
-    .collect(params, body)
+    ExprCollector::new(db, file_id, module).collect(params, body, where_clause)
+}
+
+pub(super) fn lower_clauses(
+    db: &dyn DefDatabase,
+    clauses: Vec<ast::ItemFun>,
+    file_id: FileId,
+    module: ModuleId,
+) -> (Body, BodySourceMap) {
+    ExprCollector::new(db, file_id, module).collect_clauses(clauses)
 }
 
 struct ExprCollector<'a> {
@@ -78,7 +75,30 @@ struct ExprCollector<'a> {
 }
 
 impl<'a> ExprCollector<'a> {
-    fn collect(mut self, params: Option<ast::AstChildren<ast::Pat>>, body: Option<ast::Expr>) -> (Body, BodySourceMap) {
+    fn new(db: &'a dyn DefDatabase, file_id: FileId, module: ModuleId) -> Self {
+        ExprCollector {
+            db,
+            file_id,
+            module: module.local_id,
+            def_map: db.def_map(module.lib),
+            source_map: BodySourceMap::default(),
+            body: Body {
+                exprs: Arena::default(),
+                pats: Arena::default(),
+                params: Vec::new(),
+                body_expr: dummy_expr_id(),
+                type_map: TypeMap::default(),
+            },
+            type_builder: TypeMapBuilder::default(),
+        }
+    }
+
+    fn collect(
+        mut self,
+        params: Option<ast::AstChildren<ast::Pat>>,
+        body: Option<ast::Expr>,
+        where_clause: Option<ast::WhereClause>,
+    ) -> (Body, BodySourceMap) {
         if let Some(params) = params {
             for param in params {
                 let pat = self.collect_pat(param);
@@ -87,8 +107,122 @@ impl<'a> ExprCollector<'a> {
             }
         }
 
-        self.body.body_expr = self.collect_expr_opt(body);
+        self.body.body_expr = self.collect_body(body, where_clause);
+        self.finish()
+    }
+
+    /// Lowers the equations of a function into a single case expression over its parameters.
+    ///
+    /// ```shade
+    /// fun f 0 y = a
+    /// fun f x y | x > y = b
+    /// ```
+    ///
+    /// becomes
+    ///
+    /// ```shade
+    /// fun f $arg0 $arg1 =
+    ///     case ($arg0, $arg1) of
+    ///         (0, y) -> a
+    ///         (x, y) | x > y -> b
+    /// ```
+    ///
+    /// A single equation without a guard whose parameters are all irrefutable is lowered as is.
+    fn collect_clauses(mut self, clauses: Vec<ast::ItemFun>) -> (Body, BodySourceMap) {
+        let arity = clauses[0].args().count();
+        let clause_pats = clauses
+            .iter()
+            .map(|clause| clause.args().map(|p| self.collect_pat(p)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        if let ([clause], [pats]) = (&clauses[..], &clause_pats[..]) {
+            if clause.guard().is_none() && !pats.iter().any(|&p| self.is_refutable(p)) {
+                self.body.params = pats.clone();
+                self.body.body_expr = self.collect_body(clause.body(), clause.where_clause());
+
+                return self.finish();
+            }
+        }
+
+        let mut args = Vec::with_capacity(arity);
+
+        for i in 0..arity {
+            let name = format!("$arg{}", i).as_name();
+            let pat = self.alloc_pat_desugared(Pat::Bind {
+                name: name.clone(),
+                subpat: None,
+            });
+
+            self.body.params.push(pat);
+            args.push(self.alloc_expr_desugared(Expr::Path { path: Path::from(name) }));
+        }
+
+        let pred = if arity == 1 {
+            args[0]
+        } else {
+            self.alloc_expr_desugared(Expr::Tuple { exprs: args })
+        };
+
+        let mut arms = Vec::with_capacity(clauses.len());
+
+        for (clause, mut pats) in clauses.iter().zip(clause_pats) {
+            let pat = if pats.len() != arity {
+                if let Some(name) = clause.name() {
+                    let name = self.to_source(AstPtr::new(&name));
+
+                    self.source_map.diagnostics.push(BodyDiagnostic::MismatchedClauseArity {
+                        name,
+                        expected: arity,
+                        found: pats.len(),
+                    });
+                }
+
+                self.missing_pat()
+            } else if arity == 1 {
+                pats.pop().unwrap()
+            } else {
+                self.alloc_pat_desugared(Pat::Tuple { pats })
+            };
+
+            let guard = clause.guard().map(|g| self.collect_expr_opt(g.expr()));
+            let expr = self.collect_body(clause.body(), clause.where_clause());
+
+            arms.push(CaseArm { pat, guard, expr });
+        }
+
+        self.source_map.synthetic_origin = Some(SyntaxNodePtr::new(clauses[0].syntax()));
+        self.body.body_expr = self.alloc_expr_desugared(Expr::Case { pred, arms });
+        self.finish()
+    }
+
+    /// Lowers the body of a function. The bindings in a `where` clause scope over the body like `let`
+    /// statements in a `do` block, so
+    ///
+    /// ```shade
+    /// fun f x = g x
+    ///     where g y = y + 1
+    /// ```
+    ///
+    /// becomes
+    ///
+    /// ```shade
+    /// fun f x = do
+    ///     let g y = y + 1
+    ///     g x
+    /// ```
+    fn collect_body(&mut self, body: Option<ast::Expr>, where_clause: Option<ast::WhereClause>) -> ExprId {
+        if let Some(where_clause) = where_clause {
+            let mut stmts = where_clause.bindings().map(|b| self.collect_let(b)).collect::<Vec<_>>();
+            let expr = self.collect_expr_opt(body);
+
+            stmts.push(Stmt::Expr { expr });
+            self.alloc_expr_desugared(Expr::Do { stmts })
+        } else {
+            self.collect_expr_opt(body)
+        }
+    }
 
+    fn finish(mut self) -> (Body, BodySourceMap) {
         let (type_map, type_source_map) = self.type_builder.finish();
 
         self.body.type_map = type_map;
@@ -166,6 +300,16 @@ impl<'a> ExprCollector<'a> {
                 self.alloc_expr(Expr::Typed { expr, ty }, syntax_ptr)
             },
             | ast::Expr::App(e) => {
+                if let (Some(ast::Expr::Path(base)), Some(ast::Expr::Record(arg))) = (e.base(), e.arg()) {
+                    let path = base.path().map(Path::lower);
+
+                    if let Some(ctor) = path.as_ref().and_then(|p| self.record_ctor(p)) {
+                        if arg.base().is_none() {
+                            return Some(self.collect_ctor_record(ctor, path.unwrap(), arg, syntax_ptr));
+                        }
+                    }
+                }
+
                 let base = self.collect_expr_opt(e.base());
                 let arg = self.collect_expr_opt(e.arg());
 
@@ -181,6 +325,12 @@ impl<'a> ExprCollector<'a> {
 
                 self.alloc_expr(Expr::Field { base, field }, syntax_ptr)
             },
+            | ast::Expr::Index(e) => {
+                let base = self.collect_expr_opt(e.base());
+                let index = self.collect_expr_opt(e.index());
+
+                self.alloc_expr(Expr::Index { base, index }, syntax_ptr)
+            },
             | ast::Expr::Path(e) => {
                 let path = e
                     .path()
@@ -190,16 +340,16 @@ impl<'a> ExprCollector<'a> {
 
                 self.alloc_expr(path, syntax_ptr)
             },
-            | ast::Expr::Lit(e) => {
-                let lit = match e.literal()? {
-                    | ast::Literal::Int(l) => Literal::Int(l.value()?),
-                    | ast::Literal::Float(l) => Literal::Float(l.value()?.to_bits()),
-                    | ast::Literal::Char(l) => Literal::Char(l.value()?),
-                    | ast::Literal::String(l) => Literal::String(l.value()?),
-                };
-
-                self.alloc_expr(Expr::Lit { lit }, syntax_ptr)
+            | ast::Expr::Lit(e) => match e.literal().and_then(lower_literal) {
+                | Some(lit) => self.alloc_expr(Expr::Lit { lit }, syntax_ptr),
+                | None => {
+                    let src = self.to_source(SyntaxNodePtr::new(e.syntax()));
+
+                    self.source_map.diagnostics.push(BodyDiagnostic::InvalidLiteral { src });
+                    self.alloc_expr(Expr::Missing, syntax_ptr)
+                },
             },
+            | ast::Expr::Interp(e) => self.collect_interp(e, syntax_ptr),
             | ast::Expr::Infix(e) => {
                 if let Some(path) = e.path() {
                     let path = Path::lower(path);
@@ -245,11 +395,26 @@ impl<'a> ExprCollector<'a> {
                             let left = if let Some((id, fixity)) = fixities.next().unwrap() {
                                 if let Some(next) = fixities.peek() {
                                     if let Some((id2, fixity2)) = next {
-                                        if id == *id2 {
+                                        if fixity.prec == fixity2.prec
+                                            && (fixity.assoc == Assoc::None || fixity2.assoc == Assoc::None)
+                                        {
+                                            let src = collector.to_source(syntax_ptr.syntax_node_ptr());
+                                            let op = if fixity.assoc == Assoc::None {
+                                                fixity.name.clone()
+                                            } else {
+                                                fixity2.name.clone()
+                                            };
+
+                                            collector
+                                                .source_map
+                                                .diagnostics
+                                                .push(BodyDiagnostic::NonAssociativeOperator { src, op });
+
+                                            true
+                                        } else if id == *id2 {
                                             match fixity.assoc {
-                                                | Assoc::Left => true,
+                                                | Assoc::Left | Assoc::None => true,
                                                 | Assoc::Right => false,
-                                                | Assoc::None => true, // @TODO: this should be an error
                                             }
                                         } else if fixity.prec >= fixity2.prec {
                                             true
@@ -295,32 +460,23 @@ impl<'a> ExprCollector<'a> {
                 self.source_map.expr_map.insert(src, inner);
                 inner
             },
+            | ast::Expr::Section(e) => self.collect_section(e, syntax_ptr),
             | ast::Expr::Tuple(e) => {
                 let exprs = e.exprs().map(|e| self.collect_expr(e)).collect();
 
                 self.alloc_expr(Expr::Tuple { exprs }, syntax_ptr)
             },
             | ast::Expr::Record(e) => {
+                let base = e.base().map(|e| self.collect_expr(e));
                 let fields = e
                     .fields()
-                    .filter_map(|f| {
-                        Some(match f {
-                            | ast::Field::Normal(f) => RecordField {
-                                name: f.name()?.as_name(),
-                                val: self.collect_expr_opt(f.expr()),
-                            },
-                            | ast::Field::Pun(f) => {
-                                let name = f.name()?.as_name();
-                                let path = Path::from(name.clone());
-                                let val = self.alloc_expr(Expr::Path { path }, syntax_ptr.clone());
-
-                                RecordField { name, val }
-                            },
-                        })
-                    })
+                    .filter_map(|f| self.collect_record_field(f, syntax_ptr.clone()))
                     .collect();
 
-                self.alloc_expr(Expr::Record { fields }, syntax_ptr)
+                match base {
+                    | Some(base) => self.alloc_expr(Expr::Update { base, fields }, syntax_ptr),
+                    | None => self.alloc_expr(Expr::Record { fields }, syntax_ptr),
+                }
             },
             | ast::Expr::Array(e) => {
                 let exprs = e.exprs().map(|e| self.collect_expr(e)).collect();
@@ -406,10 +562,196 @@ impl<'a> ExprCollector<'a> {
 
                 self.alloc_expr(Expr::Return { expr }, syntax_ptr)
             },
-            | _ => unimplemented!("{:?}", expr),
         })
     }
 
+    /// Desugars an interpolated string into a closure that formats every part into a writer:
+    ///
+    /// ```shade
+    /// "x = {x}!"
+    /// ```
+    ///
+    /// becomes
+    ///
+    /// ```shade
+    /// do |$w| fmt (fmt (fmt $w "x = ") x) "!"
+    /// ```
+    ///
+    /// where `fmt` is the `fmt-arg` lang item.
+    fn collect_interp(&mut self, e: ast::ExprInterp, ptr: ExprPtr) -> ExprId {
+        let name = "$w".as_name();
+        let pat = self.alloc_pat_desugared(Pat::Bind {
+            name: name.clone(),
+            subpat: None,
+        });
+
+        let mut expr = self.alloc_expr(Expr::Path { path: Path::from(name) }, ptr.clone());
+
+        for part in e.parts() {
+            let arg = match part {
+                | ast::InterpPart::Text(text) if text.is_empty() => continue,
+                | ast::InterpPart::Text(text) => self.alloc_expr(
+                    Expr::Lit {
+                        lit: Literal::String(text),
+                    },
+                    ptr.clone(),
+                ),
+                | ast::InterpPart::Expr(e) => self.collect_expr(e),
+            };
+
+            // diagnostics about missing `Fmt` instances point at the whole string
+            let fmt = self.alloc_expr(
+                Expr::Path {
+                    path: Path::lang("fmt-arg"),
+                },
+                ptr.clone(),
+            );
+
+            let base = self.alloc_expr(Expr::App { base: fmt, arg: expr }, ptr.clone());
+
+            expr = self.alloc_expr(Expr::App { base, arg }, ptr.clone());
+        }
+
+        self.alloc_expr(
+            Expr::Clos {
+                pats: vec![pat],
+                stmts: vec![Stmt::Expr { expr }],
+            },
+            ptr,
+        )
+    }
+
+    /// Desugars an operator section into a function:
+    ///
+    /// ```shade
+    /// (+)   -- the function of the operator
+    /// (1 +) -- (+) 1
+    /// (+ 1) -- do |$x| $x + 1
+    /// ```
+    fn collect_section(&mut self, e: ast::ExprSection, ptr: ExprPtr) -> ExprId {
+        let op = match e.op() {
+            | Some(op) => Path::from(op.as_name()),
+            | None => return self.alloc_expr(Expr::Missing, ptr),
+        };
+
+        if let Some(rhs) = e.rhs() {
+            let name = "$x".as_name();
+            let pat = self.alloc_pat_desugared(Pat::Bind {
+                name: name.clone(),
+                subpat: None,
+            });
+
+            let lhs = self.alloc_expr(Expr::Path { path: Path::from(name) }, ptr.clone());
+            let rhs = self.collect_expr(rhs);
+            let expr = self.alloc_expr(Expr::Infix { op, lhs, rhs }, ptr.clone());
+
+            self.alloc_expr(
+                Expr::Clos {
+                    pats: vec![pat],
+                    stmts: vec![Stmt::Expr { expr }],
+                },
+                ptr,
+            )
+        } else if let Some(lhs) = e.lhs() {
+            let arg = self.collect_expr(lhs);
+            let base = self.alloc_expr(Expr::Path { path: op }, ptr.clone());
+
+            self.alloc_expr(Expr::App { base, arg }, ptr)
+        } else {
+            self.alloc_expr(Expr::Path { path: op }, ptr)
+        }
+    }
+
+    fn collect_record_field(&mut self, field: ast::Field, ptr: ExprPtr) -> Option<RecordField<ExprId>> {
+        Some(match field {
+            | ast::Field::Normal(f) => RecordField {
+                name: f.name()?.as_name(),
+                val: self.collect_expr_opt(f.expr()),
+            },
+            | ast::Field::Pun(f) => {
+                let name = f.name()?.as_name();
+                let path = Path::from(name.clone());
+                let val = self.alloc_expr(Expr::Path { path }, ptr);
+
+                RecordField { name, val }
+            },
+        })
+    }
+
+    /// The constructor `path` refers to, if it was declared with named fields.
+    fn record_ctor(&self, path: &Path) -> Option<CtorId> {
+        let (resolved, _) = self.def_map.resolve_path(self.db, self.module, path);
+
+        match resolved.values {
+            | Some(ModuleDefId::CtorId(id)) => {
+                let data = self.db.type_ctor_data(id.parent);
+
+                if data.ctors[id.local_id].fields.is_empty() {
+                    None
+                } else {
+                    Some(id)
+                }
+            },
+            | _ => None,
+        }
+    }
+
+    /// Desugars `Point { y: 2, x: 1 }` into `Point 1 2`, following the order in which the fields were declared.
+    fn collect_ctor_record(&mut self, id: CtorId, path: Path, record: ast::ExprRecord, ptr: ExprPtr) -> ExprId {
+        let data = self.db.type_ctor_data(id.parent);
+        let ctor = &data.ctors[id.local_id];
+        let mut args = vec![None; ctor.fields.len()];
+
+        for field in record.fields() {
+            let src = self.to_source(SyntaxNodePtr::new(field.syntax()));
+            let field = match self.collect_record_field(field, ptr.clone()) {
+                | Some(field) => field,
+                | None => continue,
+            };
+
+            match ctor.field(&field.name) {
+                | Some(idx) => args[idx] = Some(field.val),
+                | None => self.source_map.diagnostics.push(BodyDiagnostic::NoSuchCtorField {
+                    src,
+                    ctor: ctor.name.clone(),
+                    field: field.name,
+                }),
+            }
+        }
+
+        self.check_missing_fields(ctor, &args, ptr.syntax_node_ptr());
+
+        let mut expr = self.alloc_expr(Expr::Path { path }, ptr.clone());
+
+        for arg in args {
+            let arg = arg.unwrap_or_else(|| self.missing_expr());
+
+            expr = self.alloc_expr(Expr::App { base: expr, arg }, ptr.clone());
+        }
+
+        expr
+    }
+
+    fn check_missing_fields<T>(&mut self, ctor: &CtorData, args: &[Option<T>], ptr: SyntaxNodePtr) {
+        let fields = ctor
+            .fields
+            .iter()
+            .zip(args)
+            .filter(|(_, arg)| arg.is_none())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        if !fields.is_empty() {
+            let src = self.to_source(ptr);
+
+            self.source_map.diagnostics.push(BodyDiagnostic::MissingCtorFields {
+                src,
+                ctor: ctor.name.clone(),
+                fields,
+            });
+        }
+    }
+
     fn collect_expr_opt(&mut self, expr: Option<ast::Expr>) -> ExprId {
         if let Some(expr) = expr {
             self.collect_expr(expr)
@@ -420,12 +762,7 @@ impl<'a> ExprCollector<'a> {
 
     fn collect_stmt(&mut self, stmt: ast::Stmt) -> Stmt {
         match stmt {
-            | ast::Stmt::Let(stmt) => {
-                let pat = self.collect_pat_opt(stmt.pat());
-                let val = self.collect_expr_opt(stmt.expr());
-
-                Stmt::Let { pat, val }
-            },
+            | ast::Stmt::Let(stmt) => self.collect_let(stmt),
             | ast::Stmt::Bind(stmt) => {
                 let pat = self.collect_pat_opt(stmt.pat());
                 let val = self.collect_expr_opt(stmt.expr());
@@ -440,6 +777,48 @@ impl<'a> ExprCollector<'a> {
         }
     }
 
+    fn collect_let(&mut self, stmt: ast::StmtLet) -> Stmt {
+        if let Some(stmt) = self.collect_local_fun(&stmt) {
+            return stmt;
+        }
+
+        let pat = self.collect_pat_opt(stmt.pat());
+        let val = self.collect_expr_opt(stmt.expr());
+
+        Stmt::Let { pat, val }
+    }
+
+    /// Lowers `let f x y = e` to a local function `f` bound to the closure `\x y -> e`,
+    /// unless `f` names a constructor or constant, in which case it is a regular pattern binding.
+    fn collect_local_fun(&mut self, stmt: &ast::StmtLet) -> Option<Stmt> {
+        let app = match stmt.pat()? {
+            | ast::Pat::App(app) => app,
+            | _ => return None,
+        };
+
+        let base = match app.base()? {
+            | ast::Pat::Bind(base) if base.subpat().is_none() => base,
+            | _ => return None,
+        };
+
+        let name = base.name()?.as_name();
+        let (resolved, _) = self.def_map.resolve_path(self.db, self.module, &name.clone().into());
+
+        if let Some(ModuleDefId::ConstId(_)) | Some(ModuleDefId::CtorId(_)) = resolved.values {
+            return None;
+        }
+
+        let pat = self.alloc_pat(Pat::Bind { name, subpat: None }, AstPtr::new(&ast::Pat::Bind(base)));
+        let pats = app.args().map(|p| self.collect_pat(p)).collect();
+        let expr = self.collect_expr_opt(stmt.expr());
+        let val = self.alloc_expr_desugared(Expr::Clos {
+            pats,
+            stmts: vec![Stmt::Expr { expr }],
+        });
+
+        Some(Stmt::Fun { pat, val })
+    }
+
     fn collect_pat(&mut self, pat: ast::Pat) -> PatId {
         let ptr = AstPtr::new(&pat);
         let pattern = match pat {
@@ -450,6 +829,21 @@ impl<'a> ExprCollector<'a> {
                 Pat::Typed { pat, ty }
             },
             | ast::Pat::Wildcard(_) => Pat::Wildcard,
+            | ast::Pat::Lit(p) => match p.literal().and_then(lower_literal) {
+                | Some(lit) => Pat::Lit { lit },
+                | None => {
+                    let src = self.to_source(SyntaxNodePtr::new(p.syntax()));
+
+                    self.source_map.diagnostics.push(BodyDiagnostic::InvalidLiteral { src });
+                    Pat::Missing
+                },
+            },
+            | ast::Pat::Ctor(p) => match p.path() {
+                | Some(path) => Pat::Path {
+                    path: Path::lower(path),
+                },
+                | None => Pat::Missing,
+            },
             | ast::Pat::Bind(pat) => {
                 let name = pat.name().map(|n| n.as_name()).unwrap_or_else(Name::missing);
                 let subpat = pat.subpat().map(|sp| self.collect_pat(sp));
@@ -469,7 +863,20 @@ impl<'a> ExprCollector<'a> {
             },
             | ast::Pat::App(p) => {
                 let base = self.collect_pat_opt(p.base());
-                let args = p.args().map(|p| self.collect_pat(p)).collect();
+                let args = p.args().collect::<Vec<_>>();
+
+                let ctor = match (&self.body[base], &args[..]) {
+                    | (Pat::Path { path }, [ast::Pat::Record(_)]) => self.record_ctor(path),
+                    | _ => None,
+                };
+
+                if let (Some(ctor), Some(ast::Pat::Record(record))) = (ctor, args.last().cloned()) {
+                    let args = self.collect_ctor_record_pat(ctor, record);
+
+                    return self.alloc_pat(Pat::App { base, args }, ptr);
+                }
+
+                let args = args.into_iter().map(|p| self.collect_pat(p)).collect();
 
                 Pat::App { base, args }
             },
@@ -489,38 +896,72 @@ impl<'a> ExprCollector<'a> {
             | ast::Pat::Record(p) => {
                 let fields = p
                     .fields()
-                    .filter_map(|f| {
-                        Some(match f {
-                            | ast::Field::Normal(f) => RecordField {
-                                name: f.name()?.as_name(),
-                                val: self.collect_pat_opt(f.pat()),
-                            },
-                            | ast::Field::Pun(f) => {
-                                let name = f.name()?.as_name();
-                                let val = self.alloc_pat(
-                                    Pat::Bind {
-                                        name: name.clone(),
-                                        subpat: None,
-                                    },
-                                    ptr.clone(),
-                                );
-
-                                RecordField { name, val }
-                            },
-                        })
-                    })
+                    .filter_map(|f| self.collect_record_field_pat(f, ptr.clone()))
                     .collect();
 
                 let has_rest = p.has_rest();
 
                 Pat::Record { fields, has_rest }
             },
-            | _ => unimplemented!("{:?}", pat),
         };
 
         self.alloc_pat(pattern, ptr)
     }
 
+    fn collect_record_field_pat(&mut self, field: ast::Field, ptr: PatPtr) -> Option<RecordField<PatId>> {
+        Some(match field {
+            | ast::Field::Normal(f) => RecordField {
+                name: f.name()?.as_name(),
+                val: self.collect_pat_opt(f.pat()),
+            },
+            | ast::Field::Pun(f) => {
+                let name = f.name()?.as_name();
+                let val = self.alloc_pat(
+                    Pat::Bind {
+                        name: name.clone(),
+                        subpat: None,
+                    },
+                    ptr,
+                );
+
+                RecordField { name, val }
+            },
+        })
+    }
+
+    /// Desugars the fields of `Point { x, .. }` into the arguments of `Point x _`.
+    fn collect_ctor_record_pat(&mut self, id: CtorId, record: ast::PatRecord) -> Vec<PatId> {
+        let data = self.db.type_ctor_data(id.parent);
+        let ctor = &data.ctors[id.local_id];
+        let ptr = AstPtr::new(&ast::Pat::Record(record.clone()));
+        let mut args = vec![None; ctor.fields.len()];
+
+        for field in record.fields() {
+            let src = self.to_source(SyntaxNodePtr::new(field.syntax()));
+            let field = match self.collect_record_field_pat(field, ptr.clone()) {
+                | Some(field) => field,
+                | None => continue,
+            };
+
+            match ctor.field(&field.name) {
+                | Some(idx) => args[idx] = Some(field.val),
+                | None => self.source_map.diagnostics.push(BodyDiagnostic::NoSuchCtorField {
+                    src,
+                    ctor: ctor.name.clone(),
+                    field: field.name,
+                }),
+            }
+        }
+
+        if !record.has_rest() {
+            self.check_missing_fields(ctor, &args, ptr.syntax_node_ptr());
+        }
+
+        args.into_iter()
+            .map(|arg| arg.unwrap_or_else(|| self.alloc_pat_desugared(Pat::Wildcard)))
+            .collect()
+    }
+
     fn collect_pat_opt(&mut self, pat: Option<ast::Pat>) -> PatId {
         if let Some(pat) = pat {
             self.collect_pat(pat)
@@ -528,4 +969,26 @@ impl<'a> ExprCollector<'a> {
             self.missing_pat()
         }
     }
+
+    /// Whether a pattern might not match, constructors are assumed to have siblings.
+    fn is_refutable(&self, pat: PatId) -> bool {
+        match &self.body[pat] {
+            | Pat::Lit { .. } | Pat::Path { .. } | Pat::App { .. } => true,
+            | p => {
+                let mut refutable = false;
+
+                p.walk(|p| refutable |= self.is_refutable(p));
+                refutable
+            },
+        }
+    }
+}
+
+fn lower_literal(lit: ast::Literal) -> Option<Literal> {
+    Some(match lit {
+        | ast::Literal::Int(l) => Literal::Int(l.value()?),
+        | ast::Literal::Float(l) => Literal::Float(l.value()?.to_bits()),
+        | ast::Literal::Char(l) => Literal::Char(l.value()?),
+        | ast::Literal::String(l) => Literal::String(l.value()?),
+    })
 }
@@ -1,12 +1,15 @@
 use crate::arena::Arena;
+use crate::attrs::Attrs;
 use crate::db::DefDatabase;
 use crate::id::*;
-pub use crate::item_tree::{Assoc, FunDep, Prec};
-use crate::item_tree::{AssocItem, ItemTreeId};
-use crate::name::Name;
+pub use crate::item_tree::{Abi, Assoc, DeriveKind, FunDep, Prec};
+use crate::item_tree::{AssocItem, Derive, Instance, Item, ItemTreeId};
+use crate::name::{AsName, Name};
 use crate::path::Path;
-use crate::type_ref::{Constraint, LocalTypeRefId, LocalTypeVarId, TypeMap, TypeRef, TypeSourceMap};
+use crate::resolver::TypeNs;
+use crate::type_ref::{Constraint, LocalTypeRefId, LocalTypeVarId, TypeMap, TypeRef, TypeSourceMap, TypeVar};
 use base_db::input::FileId;
+use rustc_hash::FxHashMap;
 use std::sync::Arc;
 use syntax::{ast, AstPtr};
 
@@ -26,6 +29,9 @@ pub struct FuncData {
     pub constraints: Box<[Constraint]>,
     pub has_body: bool,
     pub is_foreign: bool,
+    pub abi: Abi,
+    /// The symbol name given by `@export`.
+    pub export: Option<Name>,
     type_map: TypeMap,
     type_source_map: TypeSourceMap,
 }
@@ -35,6 +41,8 @@ pub struct StaticData {
     pub name: Name,
     pub ty: Option<LocalTypeRefId>,
     pub is_foreign: bool,
+    pub abi: Abi,
+    pub export: Option<Name>,
     type_map: TypeMap,
     type_source_map: TypeSourceMap,
 }
@@ -67,6 +75,8 @@ pub struct TypeCtorData {
 pub struct CtorData {
     pub name: Name,
     pub types: Box<[LocalTypeRefId]>,
+    /// The names of the fields of a constructor declared with record syntax, empty otherwise.
+    pub fields: Box<[Name]>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -76,6 +86,7 @@ pub struct ClassData {
     pub fundeps: Box<[FunDep]>,
     pub constraints: Box<[Constraint]>,
     pub items: Box<[(Name, AssocItemId)]>,
+    pub defaults: Box<[(Name, FuncId)]>,
     type_map: TypeMap,
     type_source_map: TypeSourceMap,
 }
@@ -87,10 +98,19 @@ pub struct InstanceData {
     pub types: Box<[LocalTypeRefId]>,
     pub constraints: Box<[Constraint]>,
     pub items: Box<[(Name, AssocItemId)]>,
+    pub derive: Option<DeriveData>,
+    defaults: FxHashMap<FuncId, FuncId>,
     type_map: TypeMap,
     type_source_map: TypeSourceMap,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeriveData {
+    pub kind: DeriveKind,
+    pub type_ctor: TypeCtorId,
+    scope: FxHashMap<Name, ModuleDefId>,
+}
+
 impl FixityData {
     pub fn query(db: &dyn DefDatabase, id: FixityId) -> Arc<Self> {
         let loc = id.lookup(db);
@@ -111,6 +131,23 @@ impl FuncData {
         let loc = id.lookup(db);
         let item_tree = db.item_tree(loc.id.file_id);
         let it = &item_tree[loc.id.value];
+
+        if it.is_derived {
+            // the type of a derived method is taken from the class
+            return Arc::new(FuncData {
+                name: it.name.clone(),
+                has_body: true,
+                is_foreign: false,
+                abi: Abi::Shade,
+                export: None,
+                ty: None,
+                vars: Box::new([]),
+                constraints: Box::new([]),
+                type_map: TypeMap::default(),
+                type_source_map: TypeSourceMap::default(),
+            });
+        }
+
         let src = loc.source(db);
         let mut type_builder = TypeMap::builder();
         let ty = src.value.ty().map(|t| type_builder.alloc_type_ref(t));
@@ -127,11 +164,15 @@ impl FuncData {
         };
 
         let (type_map, type_source_map) = type_builder.finish();
+        let attrs = item_tree.attrs(Item::Func(loc.id.value).into());
+        let (export, abi) = export_and_abi(&attrs, &it.name, it.is_foreign);
 
         Arc::new(FuncData {
             name: it.name.clone(),
             has_body: it.has_body,
             is_foreign: it.is_foreign,
+            abi,
+            export,
             ty,
             vars,
             constraints,
@@ -149,6 +190,25 @@ impl FuncData {
     }
 }
 
+/// Reads the `@export` and `@abi` attributes of a function or static.
+fn export_and_abi(attrs: &Attrs, name: &Name, is_foreign: bool) -> (Option<Name>, Abi) {
+    let export = attrs.by_key("export").attrs().next().map(|attr| {
+        attr.string_value()
+            .or_else(|| attr.group()?.string())
+            .map(|s| s.as_name())
+            .unwrap_or_else(|| name.clone())
+    });
+
+    // foreign and exported items use the C abi unless specified otherwise
+    let abi = match Abi::from_attrs(attrs) {
+        | Some(Ok(abi)) => abi,
+        | _ if is_foreign || export.is_some() => Abi::C,
+        | _ => Abi::Shade,
+    };
+
+    (export, abi)
+}
+
 impl StaticData {
     pub fn query(db: &dyn DefDatabase, id: StaticId) -> Arc<Self> {
         let loc = id.lookup(db);
@@ -158,10 +218,14 @@ impl StaticData {
         let mut type_builder = TypeMap::builder();
         let ty = src.value.ty().map(|t| type_builder.alloc_type_ref(t));
         let (type_map, type_source_map) = type_builder.finish();
+        let attrs = item_tree.attrs(Item::Static(loc.id.value).into());
+        let (export, abi) = export_and_abi(&attrs, &it.name, it.is_foreign);
 
         Arc::new(StaticData {
             name: it.name.clone(),
             is_foreign: it.is_foreign,
+            abi,
+            export,
             ty,
             type_map,
             type_source_map,
@@ -239,9 +303,18 @@ impl TypeCtorData {
             .collect();
 
         for ctor in src.value.ctors() {
+            let mut types = ctor.types().map(|t| type_builder.alloc_type_ref(t)).collect::<Vec<_>>();
+            let mut fields = Vec::new();
+
+            for field in ctor.fields() {
+                fields.push(field.name().map(|n| n.as_name()).unwrap_or_else(Name::missing));
+                types.push(type_builder.alloc_type_ref_opt(field.ty()));
+            }
+
             ctors.alloc(CtorData {
                 name: ctor.name().unwrap().as_name(),
-                types: ctor.types().map(|t| type_builder.alloc_type_ref(t)).collect(),
+                types: types.into(),
+                fields: fields.into(),
             });
         }
 
@@ -271,6 +344,13 @@ impl TypeCtorData {
     }
 }
 
+impl CtorData {
+    /// The index of a named field.
+    pub fn field(&self, name: &Name) -> Option<usize> {
+        self.fields.iter().position(|f| f == name)
+    }
+}
+
 impl ClassData {
     pub fn query(db: &dyn DefDatabase, id: ClassId) -> Arc<Self> {
         let loc = id.lookup(db);
@@ -290,14 +370,28 @@ impl ClassData {
             .filter_map(|c| type_builder.lower_constraint(c))
             .collect();
 
+        let (defaults, items) = it.items.iter().copied().partition::<Vec<_>, _>(|item| match *item {
+            | AssocItem::Func(id) => item_tree[id].has_body,
+            | AssocItem::Static(_) => false,
+        });
+
         let container = ContainerId::Class(id);
-        let items = collect_assoc_items(db, loc.id.file_id, it.items.iter().copied(), container);
+        let items = collect_assoc_items(db, loc.id.file_id, items.into_iter(), container);
+        let defaults = collect_assoc_items(db, loc.id.file_id, defaults.into_iter(), container)
+            .into_iter()
+            .filter_map(|(name, id)| match id {
+                | AssocItemId::FuncId(id) => Some((name, id)),
+                | AssocItemId::StaticId(_) => None,
+            })
+            .collect();
+
         let (type_map, type_source_map) = type_builder.finish();
 
         Arc::new(ClassData {
             name: it.name.clone(),
             fundeps: it.fundeps.clone(),
             items: items.into(),
+            defaults,
             vars,
             constraints,
             type_map,
@@ -318,6 +412,12 @@ impl ClassData {
             .iter()
             .find_map(|(n, id)| if n == name { Some(*id) } else { None })
     }
+
+    pub fn default(&self, name: &Name) -> Option<FuncId> {
+        self.defaults
+            .iter()
+            .find_map(|(n, id)| if n == name { Some(*id) } else { None })
+    }
 }
 
 impl InstanceData {
@@ -326,6 +426,11 @@ impl InstanceData {
         let loc = id.lookup(db);
         let item_tree = db.item_tree(loc.id.file_id);
         let it = &item_tree[loc.id.value];
+
+        if let Some(derive) = it.derive {
+            return Self::derived(db, id, it, derive);
+        }
+
         let src = loc.source(db);
         let mut type_builder = TypeMap::builder();
         let types = src.value.types().map(|t| type_builder.alloc_type_ref(t)).collect();
@@ -355,12 +460,15 @@ impl InstanceData {
             .collect();
 
         let container = ContainerId::Instance(id);
-        let items = collect_assoc_items(db, loc.id.file_id, it.items.iter().copied(), container);
+        let mut items = collect_assoc_items(db, loc.id.file_id, it.items.iter().copied(), container);
+        let defaults = collect_default_items(db, id, &it.class, &mut items);
         let (type_map, type_source_map) = type_builder.finish();
 
         Arc::new(InstanceData {
             class: it.class.clone(),
             items: items.into(),
+            derive: None,
+            defaults,
             types,
             vars,
             constraints,
@@ -369,6 +477,93 @@ impl InstanceData {
         })
     }
 
+    fn derived(db: &dyn DefDatabase, id: InstanceId, it: &Instance, derive: Derive) -> Arc<Self> {
+        use crate::resolver::HasResolver;
+        let loc = id.lookup(db);
+        let type_ctor = TypeCtorLoc {
+            module: loc.module,
+            id: ItemTreeId::new(loc.id.file_id, derive.type_ctor),
+        }
+        .intern(db);
+
+        let data = db.type_ctor_data(type_ctor);
+        let mut type_builder = TypeMap::builder();
+        let mut ty = type_builder.alloc_type_ref_desugared(TypeRef::Path(Path::from(data.name.clone())));
+        let mut vars = Vec::with_capacity(data.vars.len());
+        let mut constraints = Vec::with_capacity(data.vars.len());
+
+        for &var in data.vars.iter() {
+            let name = data.type_map()[var].name.clone();
+            let var = type_builder.alloc_type_ref_desugared(TypeRef::Path(Path::from(name.clone())));
+
+            vars.push(type_builder.alloc_type_var_desugared(TypeVar { name, kind: None }));
+            constraints.push(Constraint {
+                class: it.class.clone(),
+                types: vec![var; derive.kind.arity()].into(),
+            });
+
+            ty = type_builder.alloc_type_ref_desugared(TypeRef::App(ty, var));
+        }
+
+        let resolver = loc.module.resolver(db);
+        let mut scope = FxHashMap::default();
+
+        if let Some((TypeNs::Class(class), None)) = resolver.resolve_type(db, &it.class) {
+            let class_data = db.class_data(class);
+
+            for method in derive.kind.methods() {
+                if let Some(AssocItemId::FuncId(func)) = class_data.item(&method.as_name()) {
+                    scope.insert(DeriveData::scope_name(method), ModuleDefId::FuncId(func));
+                }
+            }
+        }
+
+        let lang_ctors: &[&str] = match derive.kind {
+            | DeriveKind::PartialEq => &["bool-type", "True", "False"],
+            | DeriveKind::PartialOrd => &["ordering-type", "Less", "Equal", "Greater"],
+            | _ => &[],
+        };
+
+        if let [lang, ctors @ ..] = lang_ctors {
+            let lang = db
+                .lang_item(loc.module.lib, (*lang).into())
+                .and_then(|l| l.as_type_ctor());
+
+            if let Some(parent) = lang {
+                let lang_data = db.type_ctor_data(parent);
+
+                for ctor in ctors {
+                    if let Some(local_id) = lang_data.ctor(&ctor.as_name()) {
+                        let id = ModuleDefId::CtorId(CtorId { parent, local_id });
+
+                        scope.insert(DeriveData::scope_name(&ctor.to_lowercase()), id);
+                    }
+                }
+            }
+        }
+
+        let container = ContainerId::Instance(id);
+        let mut items = collect_assoc_items(db, loc.id.file_id, it.items.iter().copied(), container);
+        let defaults = collect_default_items(db, id, &it.class, &mut items);
+        let (type_map, type_source_map) = type_builder.finish();
+
+        Arc::new(InstanceData {
+            class: it.class.clone(),
+            items: items.into(),
+            defaults,
+            vars: vars.into(),
+            types: vec![ty; derive.kind.arity()].into(),
+            constraints: constraints.into(),
+            derive: Some(DeriveData {
+                kind: derive.kind,
+                type_ctor,
+                scope,
+            }),
+            type_map,
+            type_source_map,
+        })
+    }
+
     pub fn type_map(&self) -> &TypeMap {
         &self.type_map
     }
@@ -382,6 +577,58 @@ impl InstanceData {
             .iter()
             .find_map(|(n, id)| if n == name { Some(*id) } else { None })
     }
+
+    /// The default method in the class that the given instance item was copied from.
+    pub fn default_of(&self, func: FuncId) -> Option<FuncId> {
+        self.defaults.get(&func).copied()
+    }
+}
+
+impl DeriveData {
+    /// Derived bodies refer to the class methods and lang items they need through
+    /// names that cannot be written in source code, so they can never be shadowed.
+    pub fn scope_name(name: &str) -> Name {
+        format!("${}", name).as_name()
+    }
+
+    pub fn resolve(&self, name: &Name) -> Option<ModuleDefId> {
+        self.scope.get(name).copied()
+    }
+}
+
+/// Copies the default methods of the class into the instance for every method the instance does not define.
+/// The copies share their syntax with the class default, but are checked and lowered as instance items.
+fn collect_default_items(
+    db: &dyn DefDatabase,
+    id: InstanceId,
+    class: &Path,
+    items: &mut Vec<(Name, AssocItemId)>,
+) -> FxHashMap<FuncId, FuncId> {
+    use crate::resolver::HasResolver;
+    let resolver = id.lookup(db).module.resolver(db);
+    let mut defaults = FxHashMap::default();
+
+    if let Some((TypeNs::Class(class), None)) = resolver.resolve_type(db, class) {
+        let class_data = db.class_data(class);
+
+        // defaults without a signature are reported by the class
+        for (name, default) in class_data.defaults.iter() {
+            if class_data.item(name).is_none() || items.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+
+            let func = FuncLoc {
+                container: ContainerId::Instance(id),
+                id: default.lookup(db).id,
+            }
+            .intern(db);
+
+            defaults.insert(func, *default);
+            items.push((name.clone(), AssocItemId::FuncId(func)));
+        }
+    }
+
+    defaults
 }
 
 fn collect_assoc_items(
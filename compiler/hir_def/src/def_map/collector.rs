@@ -432,6 +432,14 @@ impl<'a, 'b> ModCollector<'a, 'b> {
                 },
                 | Item::Func(id) => {
                     let it = &self.item_tree[id];
+                    let attrs = self.item_tree.attrs(Item::Func(id).into());
+
+                    if let Some(Err(abi)) = item_tree::Abi::from_attrs(&attrs) {
+                        let ast = InFile::new(self.file_id, it.ast_id);
+                        let diag = DefDiagnostic::unknown_abi(self.module_id, ast, abi.to_string());
+
+                        self.def_collector.def_map.diagnostics.push(diag);
+                    }
 
                     def = Some(DefData {
                         id: ModuleDefId::FuncId(
@@ -495,6 +503,21 @@ impl<'a, 'b> ModCollector<'a, 'b> {
                     .intern(self.def_collector.db);
 
                     let data = self.def_collector.db.type_ctor_data(new_id);
+                    let attrs = self.item_tree.attrs(Item::TypeCtor(id).into());
+
+                    for name in attrs
+                        .by_key("derive")
+                        .attrs()
+                        .filter_map(|attr| attr.group())
+                        .flat_map(|group| group.idents())
+                    {
+                        if item_tree::DeriveKind::from_name(name).is_none() {
+                            let ast = InFile::new(self.file_id, it.ast_id);
+                            let diag = DefDiagnostic::unknown_derive(self.module_id, ast, name.clone());
+
+                            self.def_collector.def_map.diagnostics.push(diag);
+                        }
+                    }
 
                     for (local_id, data) in data.ctors.iter() {
                         let id = CtorId {
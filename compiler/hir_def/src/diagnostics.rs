@@ -3,9 +3,10 @@ use crate::db::DefDatabase;
 use crate::diagnostic::{Diagnostic, DiagnosticSink};
 use crate::id::LocalModuleId;
 use crate::in_file::InFile;
+use crate::name::Name;
 use base_db::input::FileId;
 use std::any::Any;
-use syntax::ast;
+use syntax::ast::{self, NameOwner};
 use syntax::ptr::{AstPtr, SyntaxNodePtr};
 
 #[derive(Debug, PartialEq, Eq)]
@@ -17,6 +18,8 @@ pub struct DefDiagnostic {
 #[derive(Debug, PartialEq, Eq)]
 pub enum DefDiagnosticKind {
     UnresolvedImport { ast: AstId<ast::ItemImport>, index: usize },
+    UnknownDerive { ast: AstId<ast::ItemType>, name: Name },
+    UnknownAbi { ast: AstId<ast::ItemFun>, abi: String },
 }
 
 impl DefDiagnostic {
@@ -27,6 +30,20 @@ impl DefDiagnostic {
         }
     }
 
+    pub fn unknown_derive(container: LocalModuleId, ast: AstId<ast::ItemType>, name: Name) -> Self {
+        DefDiagnostic {
+            in_module: container,
+            kind: DefDiagnosticKind::UnknownDerive { ast, name },
+        }
+    }
+
+    pub fn unknown_abi(container: LocalModuleId, ast: AstId<ast::ItemFun>, abi: String) -> Self {
+        DefDiagnostic {
+            in_module: container,
+            kind: DefDiagnosticKind::UnknownAbi { ast, abi },
+        }
+    }
+
     pub fn add_to(&self, db: &dyn DefDatabase, module: LocalModuleId, sink: &mut DiagnosticSink) {
         if self.in_module != module {
             return;
@@ -58,6 +75,24 @@ impl DefDiagnostic {
                     });
                 }
             },
+            | DefDiagnosticKind::UnknownDerive { ast, name } => {
+                let item = ast.to_node(db);
+
+                sink.push(UnknownDerive {
+                    file: ast.file_id,
+                    node: AstPtr::new(&item.name().unwrap()),
+                    name: name.clone(),
+                });
+            },
+            | DefDiagnosticKind::UnknownAbi { ast, abi } => {
+                let item = ast.to_node(db);
+
+                sink.push(UnknownAbi {
+                    file: ast.file_id,
+                    node: AstPtr::new(&item.name().unwrap()),
+                    abi: abi.clone(),
+                });
+            },
         }
     }
 }
@@ -81,3 +116,162 @@ impl Diagnostic for UnresolvedImport {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct UnknownDerive {
+    pub file: FileId,
+    pub node: AstPtr<ast::Name>,
+    pub name: Name,
+}
+
+impl Diagnostic for UnknownDerive {
+    fn message(&self) -> String {
+        format!("cannot derive an instance of unknown class `{}`", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.node.clone().into())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownAbi {
+    pub file: FileId,
+    pub node: AstPtr<ast::Name>,
+    pub abi: String,
+}
+
+impl Diagnostic for UnknownAbi {
+    fn message(&self) -> String {
+        format!("unknown abi `{}`", self.abi)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.node.clone().into())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MismatchedClauseArity {
+    pub file: FileId,
+    pub node: AstPtr<ast::Name>,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl Diagnostic for MismatchedClauseArity {
+    fn message(&self) -> String {
+        format!(
+            "this clause has {} parameters, but the first clause has {}",
+            self.found, self.expected
+        )
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.node.clone().into())
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidLiteral {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+}
+
+impl Diagnostic for InvalidLiteral {
+    fn message(&self) -> String {
+        "invalid literal".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct NonAssociativeOperator {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub op: Name,
+}
+
+impl Diagnostic for NonAssociativeOperator {
+    fn message(&self) -> String {
+        format!("operator `{}` is not associative and cannot be chained", self.op)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct NoSuchCtorField {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub ctor: Name,
+    pub field: Name,
+}
+
+impl Diagnostic for NoSuchCtorField {
+    fn message(&self) -> String {
+        format!("constructor `{}` has no field named `{}`", self.ctor, self.field)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingCtorFields {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub ctor: Name,
+    pub fields: Vec<Name>,
+}
+
+impl Diagnostic for MissingCtorFields {
+    fn message(&self) -> String {
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| format!("`{}`", f))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!("missing fields {} in constructor `{}`", fields, self.ctor)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
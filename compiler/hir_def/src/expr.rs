@@ -46,6 +46,11 @@ pub enum Expr {
     Record {
         fields: Vec<RecordField<ExprId>>,
     },
+    /// `{ base | field: val }`, a copy of `base` with some of its named fields replaced.
+    Update {
+        base: ExprId,
+        fields: Vec<RecordField<ExprId>>,
+    },
     Array {
         exprs: Vec<ExprId>,
     },
@@ -106,6 +111,8 @@ pub struct RecordField<ID> {
 pub enum Stmt {
     Let { pat: PatId, val: ExprId },
     Bind { pat: PatId, val: ExprId },
+    /// A local function, `val` is always a closure. Its name is in scope in its own body.
+    Fun { pat: PatId, val: ExprId },
     Expr { expr: ExprId },
 }
 
@@ -140,10 +147,15 @@ impl Expr {
             | Expr::Record { fields } => {
                 fields.iter().for_each(|i| f(i.val));
             },
+            | Expr::Update { base, fields } => {
+                f(*base);
+                fields.iter().for_each(|i| f(i.val));
+            },
             | Expr::Do { stmts } => {
                 stmts.iter().for_each(|stmt| match stmt {
                     | Stmt::Let { val, .. } => f(*val),
                     | Stmt::Bind { val, .. } => f(*val),
+                    | Stmt::Fun { val, .. } => f(*val),
                     | Stmt::Expr { expr } => f(*expr),
                 });
             },
@@ -151,6 +163,7 @@ impl Expr {
                 stmts.iter().for_each(|stmt| match stmt {
                     | Stmt::Let { val, .. } => f(*val),
                     | Stmt::Bind { val, .. } => f(*val),
+                    | Stmt::Fun { val, .. } => f(*val),
                     | Stmt::Expr { expr } => f(*expr),
                 });
             },
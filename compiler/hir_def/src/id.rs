@@ -4,6 +4,7 @@ use crate::def_map::ModuleData;
 use crate::in_file::InFile;
 use crate::item_tree::*;
 use crate::type_ref::{LocalTypeVarId, TypeMap, TypeSourceMap};
+use base_db::input::FileId;
 use base_db::libs::LibId;
 use std::hash::{Hash, Hasher};
 
@@ -317,6 +318,13 @@ impl TypeVarOwner {
             | TypeVarOwner::TypedDefId(id) => id.container(db),
         }
     }
+
+    pub fn file_id(self, db: &dyn DefDatabase) -> FileId {
+        match self {
+            | TypeVarOwner::DefWithBodyId(id) => id.file_id(db),
+            | TypeVarOwner::TypedDefId(id) => id.file_id(db),
+        }
+    }
 }
 
 impl DefWithBodyId {
@@ -328,6 +336,14 @@ impl DefWithBodyId {
         }
     }
 
+    pub fn file_id(self, db: &dyn DefDatabase) -> FileId {
+        match self {
+            | DefWithBodyId::FuncId(id) => id.lookup(db).id.file_id,
+            | DefWithBodyId::StaticId(id) => id.lookup(db).id.file_id,
+            | DefWithBodyId::ConstId(id) => id.lookup(db).id.file_id,
+        }
+    }
+
     pub fn has_body(self, db: &dyn DefDatabase) -> bool {
         match self {
             | DefWithBodyId::FuncId(id) => db.func_data(id).has_body,
@@ -349,6 +365,18 @@ impl TypedDefId {
             | TypedDefId::InstanceId(id) => ContainerId::Module(id.lookup(db).module),
         }
     }
+
+    pub fn file_id(self, db: &dyn DefDatabase) -> FileId {
+        match self {
+            | TypedDefId::FuncId(id) => id.lookup(db).id.file_id,
+            | TypedDefId::StaticId(id) => id.lookup(db).id.file_id,
+            | TypedDefId::TypeAliasId(id) => id.lookup(db).id.file_id,
+            | TypedDefId::TypeCtorId(id) => id.lookup(db).id.file_id,
+            | TypedDefId::CtorId(id) => id.parent.lookup(db).id.file_id,
+            | TypedDefId::ClassId(id) => id.lookup(db).id.file_id,
+            | TypedDefId::InstanceId(id) => id.lookup(db).id.file_id,
+        }
+    }
 }
 
 impl AssocItemId {
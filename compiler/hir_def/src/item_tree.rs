@@ -204,6 +204,9 @@ pub struct Func {
     pub name: Name,
     pub has_body: bool,
     pub is_foreign: bool,
+    pub is_derived: bool,
+    /// The equations defining this function, starting with `ast_id`.
+    pub clauses: Box<[FileAstId<ast::ItemFun>]>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -257,6 +260,31 @@ pub struct Instance {
     pub ast_id: FileAstId<ast::ItemInstance>,
     pub class: Path,
     pub items: Box<[AssocItem]>,
+    pub derive: Option<Derive>,
+}
+
+/// An instance generated by a `@derive(...)` attribute on a type.
+/// Derived instances and their items have no syntax of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Derive {
+    pub type_ctor: LocalItemTreeId<TypeCtor>,
+    pub kind: DeriveKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeriveKind {
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Fmt,
+}
+
+/// The calling convention of a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Abi {
+    Shade,
+    C,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -288,6 +316,56 @@ impl From<Idx<Ctor>> for AttrOwner {
     }
 }
 
+impl Abi {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            | "Shade" => Some(Abi::Shade),
+            | "C" => Some(Abi::C),
+            | _ => None,
+        }
+    }
+
+    /// The abi given by an `@abi("C")` or `@abi = "C"` attribute.
+    pub fn from_attrs(attrs: &Attrs) -> Option<Result<Self, &str>> {
+        let abi = attrs
+            .by_key("abi")
+            .attrs()
+            .find_map(|attr| attr.string_value().or_else(|| attr.group()?.string()))?;
+
+        Some(Self::from_name(abi).ok_or(abi))
+    }
+}
+
+impl DeriveKind {
+    pub fn from_name(name: &Name) -> Option<Self> {
+        match name.to_string().as_str() {
+            | "PartialEq" => Some(DeriveKind::PartialEq),
+            | "Eq" => Some(DeriveKind::Eq),
+            | "PartialOrd" => Some(DeriveKind::PartialOrd),
+            | "Ord" => Some(DeriveKind::Ord),
+            | "Fmt" => Some(DeriveKind::Fmt),
+            | _ => None,
+        }
+    }
+
+    /// The number of types the class is applied to.
+    pub fn arity(self) -> usize {
+        match self {
+            | DeriveKind::PartialEq | DeriveKind::PartialOrd => 2,
+            | DeriveKind::Eq | DeriveKind::Ord | DeriveKind::Fmt => 1,
+        }
+    }
+
+    pub fn methods(self) -> &'static [&'static str] {
+        match self {
+            | DeriveKind::PartialEq => &["eq"],
+            | DeriveKind::PartialOrd => &["cmp"],
+            | DeriveKind::Fmt => &["fmt"],
+            | DeriveKind::Eq | DeriveKind::Ord => &[],
+        }
+    }
+}
+
 impl<T> IdRange<T> {
     fn new(range: Range<Idx<T>>) -> Self {
         IdRange {
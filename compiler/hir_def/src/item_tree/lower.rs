@@ -46,20 +46,25 @@ impl<'db> Ctx<'db> {
     }
 
     pub fn lower_items(mut self, module: &ast::Module) -> ItemTree {
-        self.tree.top_level = module
-            .items()
-            .flat_map(|item| self.lower_item(&item))
+        let items = group_clauses(module.items(), |item| match item {
+            | ast::Item::Fun(it) => Some(it),
+            | _ => None,
+        });
+
+        self.tree.top_level = items
+            .into_iter()
+            .flat_map(|(item, clauses)| self.lower_item(&item, &clauses))
             .flat_map(|item| item.0)
             .collect();
         self.tree
     }
 
-    fn lower_item(&mut self, item: &ast::Item) -> Option<Items> {
+    fn lower_item(&mut self, item: &ast::Item, clauses: &[ast::ItemFun]) -> Option<Items> {
         let attrs = RawAttrs::new(item);
         let items = match item {
             | ast::Item::Import(ast) => Some(Items(self.lower_import(ast).into_iter().map(Into::into).collect())),
             | ast::Item::Fixity(ast) => self.lower_fixity(ast).map(Into::into),
-            | ast::Item::Fun(ast) => self.lower_fun(ast).map(Into::into),
+            | ast::Item::Fun(ast) => self.lower_fun(ast, clauses).map(Into::into),
             | ast::Item::Static(ast) => self.lower_static(ast).map(Into::into),
             | ast::Item::Const(ast) => self.lower_const(ast).map(Into::into),
             | ast::Item::Type(ast) => self.lower_type(ast),
@@ -74,6 +79,59 @@ impl<'db> Ctx<'db> {
             }
         }
 
+        items.map(|items| self.lower_derives(&attrs, items))
+    }
+
+    fn lower_derives(&mut self, attrs: &RawAttrs, mut items: Items) -> Items {
+        let type_ctors = items
+            .0
+            .iter()
+            .filter_map(|&item| match item {
+                | Item::TypeCtor(id) => Some(id),
+                | _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let attrs = Attrs(attrs.clone());
+        let names = attrs
+            .by_key("derive")
+            .attrs()
+            .filter_map(|attr| attr.group())
+            .flat_map(|group| group.idents())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for type_ctor in type_ctors {
+            for name in &names {
+                if let Some(kind) = DeriveKind::from_name(name) {
+                    let derive = Derive { type_ctor, kind };
+                    let items = kind
+                        .methods()
+                        .iter()
+                        .map(|method| {
+                            AssocItem::Func(id(self.tree.data.funcs.alloc(Func {
+                                ast_id: FileAstId::DUMMY,
+                                name: method.as_name(),
+                                has_body: true,
+                                is_foreign: false,
+                                is_derived: true,
+                                clauses: Box::new([]),
+                            })))
+                        })
+                        .collect();
+
+                    let inst = id(self.tree.data.instances.alloc(Instance {
+                        ast_id: FileAstId::DUMMY,
+                        class: Path::from(name.clone()),
+                        derive: Some(derive),
+                        items,
+                    }));
+
+                    items.0.push(inst.into());
+                }
+            }
+        }
+
         items
     }
 
@@ -114,17 +172,26 @@ impl<'db> Ctx<'db> {
         })))
     }
 
-    fn lower_fun(&mut self, item: &ast::ItemFun) -> Option<LocalItemTreeId<Func>> {
+    fn lower_fun(&mut self, item: &ast::ItemFun, clauses: &[ast::ItemFun]) -> Option<LocalItemTreeId<Func>> {
         let ast_id = self.ast_id_map.ast_id(item);
         let name = item.name()?.as_name();
         let has_body = item.body().is_some();
         let is_foreign = item.is_foreign();
+        let clauses = if has_body {
+            std::iter::once(ast_id)
+                .chain(clauses.iter().map(|c| self.ast_id_map.ast_id(c)))
+                .collect()
+        } else {
+            Box::new([]) as Box<[_]>
+        };
 
         Some(id(self.tree.data.funcs.alloc(Func {
             name,
             ast_id,
             has_body,
             is_foreign,
+            is_derived: false,
+            clauses,
         })))
     }
 
@@ -182,7 +249,7 @@ impl<'db> Ctx<'db> {
         let ast_id = self.ast_id_map.ast_id(item);
         let name = item.name()?.as_name();
         let fundeps = item.fundeps().filter_map(|f| self.lower_fun_dep(f)).collect();
-        let items = item.items().filter_map(|item| self.lower_assoc_item(item)).collect();
+        let items = self.lower_assoc_items(item.items());
 
         Some(id(self.tree.data.classes.alloc(Class {
             name,
@@ -209,14 +276,31 @@ impl<'db> Ctx<'db> {
     fn lower_instance(&mut self, item: &ast::ItemInstance) -> Option<LocalItemTreeId<Instance>> {
         let ast_id = self.ast_id_map.ast_id(item);
         let class = Path::lower(item.class()?);
-        let items = item.items().filter_map(|item| self.lower_assoc_item(item)).collect();
+        let items = self.lower_assoc_items(item.items());
 
-        Some(id(self.tree.data.instances.alloc(Instance { ast_id, class, items })))
+        Some(id(self.tree.data.instances.alloc(Instance {
+            ast_id,
+            class,
+            items,
+            derive: None,
+        })))
     }
 
-    fn lower_assoc_item(&mut self, item: ast::AssocItem) -> Option<AssocItem> {
+    fn lower_assoc_items(&mut self, items: impl Iterator<Item = ast::AssocItem>) -> Box<[AssocItem]> {
+        let items = group_clauses(items, |item| match item {
+            | ast::AssocItem::Fun(it) => Some(it),
+            | _ => None,
+        });
+
+        items
+            .into_iter()
+            .filter_map(|(item, clauses)| self.lower_assoc_item(item, &clauses))
+            .collect()
+    }
+
+    fn lower_assoc_item(&mut self, item: ast::AssocItem, clauses: &[ast::ItemFun]) -> Option<AssocItem> {
         match item {
-            | ast::AssocItem::Fun(it) => self.lower_fun(&it).map(AssocItem::Func),
+            | ast::AssocItem::Fun(it) => self.lower_fun(&it, clauses).map(AssocItem::Func),
             | ast::AssocItem::Static(it) => self.lower_static(&it).map(AssocItem::Static),
         }
     }
@@ -225,3 +309,36 @@ impl<'db> Ctx<'db> {
         Idx::from_raw(RawIdx::from(self.tree.data.ctors.len() as u32))
     }
 }
+
+/// Groups consecutive equations of the same function, each item is paired with the equations following it.
+/// Signatures are never grouped, so a class method keeps its signature and default body apart.
+fn group_clauses<T>(
+    items: impl Iterator<Item = T>,
+    as_fun: impl Fn(&T) -> Option<&ast::ItemFun>,
+) -> Vec<(T, Vec<ast::ItemFun>)> {
+    let mut groups: Vec<(T, Vec<ast::ItemFun>)> = Vec::new();
+
+    for item in items {
+        if let Some(fun) = as_fun(&item) {
+            if let Some((first, clauses)) = groups.last_mut() {
+                if let Some(first) = as_fun(first) {
+                    if is_equation(first)
+                        && is_equation(fun)
+                        && first.name().map(|n| n.as_name()) == fun.name().map(|n| n.as_name())
+                    {
+                        clauses.push(fun.clone());
+                        continue;
+                    }
+                }
+            }
+        }
+
+        groups.push((item, Vec::new()));
+    }
+
+    groups
+}
+
+fn is_equation(fun: &ast::ItemFun) -> bool {
+    fun.body().is_some() && !fun.is_foreign()
+}
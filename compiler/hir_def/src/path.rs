@@ -16,6 +16,21 @@ impl Path {
         }
     }
 
+    /// A path to a lang item, used by desugared code so that it does not depend on what is in scope.
+    /// The first segment cannot be written in source code.
+    pub fn lang(item: &str) -> Self {
+        Path {
+            segments: vec!["$lang".as_name(), item.as_name()],
+        }
+    }
+
+    pub fn as_lang_item(&self) -> Option<&Name> {
+        match &*self.segments {
+            | [lang, item] if lang == "$lang" => Some(item),
+            | _ => None,
+        }
+    }
+
     pub fn segments(&self) -> &[Name] {
         &self.segments
     }
@@ -1,7 +1,9 @@
+use crate::data::InstanceData;
 use crate::db::DefDatabase;
 use crate::def_map::DefMap;
 use crate::expr::ExprId;
 use crate::id::*;
+use crate::lang_item::LangItem;
 use crate::name::Name;
 use crate::pat::PatId;
 use crate::path::Path;
@@ -19,6 +21,7 @@ pub struct Resolver {
 #[derive(Debug, Clone)]
 enum Scope {
     ModuleScope(ModuleItemMap),
+    DeriveScope(Arc<InstanceData>),
     ExprScope(ExprScope),
     TypeScope(TypeScope),
 }
@@ -99,6 +102,7 @@ impl Resolver {
                 },
                 | Scope::TypeScope(_) => continue,
                 | Scope::ExprScope(_) => continue,
+                | Scope::DeriveScope(_) => continue,
                 | Scope::ModuleScope(m) => {
                     if let Some(res) = m.resolve_type(db, path) {
                         return Some(res);
@@ -111,6 +115,16 @@ impl Resolver {
     }
 
     pub fn resolve_value(&self, db: &dyn DefDatabase, path: &Path) -> Option<(ValueNs, Option<usize>)> {
+        if let Some(item) = path.as_lang_item() {
+            let res = match db.lang_item(self.lib()?, item.to_string().into())? {
+                | LangItem::FuncId(id) => ValueNs::Func(id),
+                | LangItem::StaticId(id) => ValueNs::Static(id),
+                | _ => return None,
+            };
+
+            return Some((res, None));
+        }
+
         let n_segments = path.segments().len();
         let first_name = path.segments().first()?;
 
@@ -129,6 +143,14 @@ impl Resolver {
                 },
                 | Scope::ExprScope(_) => continue,
                 | Scope::TypeScope(_) => continue,
+                | Scope::DeriveScope(data) if n_segments <= 1 => {
+                    let def = data.derive.as_ref().and_then(|d| d.resolve(first_name));
+
+                    if let Some(def) = def.and_then(|def| to_value_ns(PerNs::values(def))) {
+                        return Some((def, None));
+                    }
+                },
+                | Scope::DeriveScope(_) => continue,
                 | Scope::ModuleScope(m) => {
                     if let Some(def) = m.resolve_value(db, path) {
                         return Some(def);
@@ -243,6 +265,10 @@ impl Resolver {
         self.push_scope(Scope::ModuleScope(ModuleItemMap { def_map, module_id }))
     }
 
+    fn push_derive_scope(self, data: Arc<InstanceData>) -> Self {
+        self.push_scope(Scope::DeriveScope(data))
+    }
+
     fn push_expr_scope(self, owner: DefWithBodyId, expr_scopes: Arc<ExprScopes>, scope_id: ExprScopeId) -> Self {
         self.push_scope(Scope::ExprScope(ExprScope {
             owner,
@@ -313,8 +339,16 @@ impl HasResolver for FixityId {
 impl HasResolver for FuncId {
     fn resolver(self, db: &dyn DefDatabase) -> Resolver {
         let data = db.func_data(self);
+        let container = match self.lookup(db).container {
+            // default methods copied into an instance resolve names in the scope of their class
+            | ContainerId::Instance(inst) => match db.instance_data(inst).default_of(self) {
+                | Some(default) => default.lookup(db).container,
+                | None => ContainerId::Instance(inst),
+            },
+            | container => container,
+        };
 
-        self.lookup(db).container.resolver(db).with_type_vars(
+        container.resolver(db).with_type_vars(
             data.type_map(),
             TypedDefId::FuncId(self).into(),
             data.vars.iter().copied(),
@@ -389,12 +423,17 @@ impl HasResolver for ClassId {
 impl HasResolver for InstanceId {
     fn resolver(self, db: &dyn DefDatabase) -> Resolver {
         let data = db.instance_data(self);
-
-        self.lookup(db).module.resolver(db).with_type_vars(
+        let resolver = self.lookup(db).module.resolver(db).with_type_vars(
             data.type_map(),
             TypedDefId::InstanceId(self).into(),
             data.vars.iter().copied(),
-        )
+        );
+
+        if data.derive.is_some() {
+            resolver.push_derive_scope(data)
+        } else {
+            resolver
+        }
     }
 }
 
@@ -270,6 +270,11 @@ fn compute_block_scopes(stmts: &[Stmt], body: &Body, scopes: &mut ExprScopes, mu
                 scope = scopes.new_scope(scope);
                 scopes.add_bindings(body, scope, *pat);
             },
+            | Stmt::Fun { pat, val } => {
+                scope = scopes.new_scope(scope);
+                scopes.add_bindings(body, scope, *pat);
+                compute_expr_scopes(*val, body, scopes, scope);
+            },
             | Stmt::Expr { expr } => {
                 compute_expr_scopes(*expr, body, scopes, scope);
             },
@@ -266,6 +266,14 @@ impl TypeMapBuilder {
         Some(self.alloc_type_var_impl(type_var, Either::Right(ptr)))
     }
 
+    pub fn alloc_type_ref_desugared(&mut self, type_ref: TypeRef) -> LocalTypeRefId {
+        self.map.type_refs.alloc(type_ref)
+    }
+
+    pub fn alloc_type_var_desugared(&mut self, type_var: TypeVar) -> LocalTypeVarId {
+        self.map.type_vars.alloc(type_var)
+    }
+
     pub fn lower_constraint(&mut self, ctnt: ast::Constraint) -> Option<Constraint> {
         let class = Path::lower(ctnt.class()?);
         let types = ctnt.types().map(|t| self.alloc_type_ref(t)).collect();
@@ -1,12 +1,16 @@
 use crate::db::HirDatabase;
 use crate::display::HirDisplay;
+use crate::infer::diagnostics::InferenceDiagnostic;
 use crate::infer::InferenceContext;
 use crate::lower::InstanceLowerResult;
-use crate::ty::{Constraint, Ty, TyKind, TypeVar, Unknown};
+use crate::ty::{Constraint, DebruijnIndex, Ty, TyKind, TypeVar, Unknown};
+use base_db::libs::LibId;
 use hir_def::arena::{Arena, Idx};
-use hir_def::id::{ClassId, InstanceId, Lookup, TypedDefId};
+use hir_def::diagnostic::DiagnosticSink;
+use hir_def::id::{ClassId, InstanceId, Lookup, TypeVarOwner, TypedDefId};
 use hir_def::resolver::Resolver;
 use rustc_hash::{FxHashMap, FxHashSet};
+use salsa::InternKey;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
@@ -15,6 +19,7 @@ pub struct Class {
     pub id: ClassId,
     pub vars: Box<[Ty]>,
     pub fundeps: Box<[FunDep]>,
+    pub superclasses: Box<[Constraint]>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -38,6 +43,42 @@ pub struct Instances {
     deps: Box<[FunDep]>,
 }
 
+/// Why a constraint could not be solved.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// Unknown types in the constraint that prevented picking an instance.
+    pub ambiguous: Vec<Ty>,
+    pub candidates: Vec<Candidate>,
+}
+
+/// An instance that nearly matched an unsolved constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub instance: InstanceId,
+    pub failure: CandidateFailure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CandidateFailure {
+    Mismatch {
+        index: usize,
+        expected: Ty,
+        found: Ty,
+    },
+    Undetermined {
+        positions: Vec<usize>,
+    },
+    Constraint {
+        ctnt: Constraint,
+        explanation: Box<Explanation>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CoherenceResult {
+    pub(crate) diagnostics: Vec<InferenceDiagnostic>,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct InstanceMatchResult {
     pub instance: InstanceId,
@@ -109,6 +150,194 @@ impl Instances {
         instances.matches(db, constraint).map(Arc::new)
     }
 
+    pub(crate) fn coherence_query(db: &dyn HirDatabase, id: InstanceId) -> Arc<CoherenceResult> {
+        let lower = db.lower_instance(id);
+        let inst = &lower.instance;
+        let mut diagnostics = Vec::new();
+
+        if inst.class == ClassId::dummy() {
+            return Arc::new(CoherenceResult { diagnostics });
+        }
+
+        if inst.is_orphan(db) {
+            diagnostics.push(InferenceDiagnostic::OrphanInstance);
+        }
+
+        let class = db.lower_class(inst.class);
+
+        if !inst.covers(db, &class.class.fundeps) {
+            diagnostics.push(InferenceDiagnostic::FunDepViolation { other: None });
+        }
+
+        // superclasses are checked where the instance is declared rather than where it is used, the
+        // instance context is not known here so only instances with fully known types are checked
+        let head = Constraint {
+            class: inst.class,
+            types: inst.types.iter().copied().collect(),
+        };
+
+        if head.types.iter().all(|&ty| is_ground(db, ty)) {
+            for sup in Self::superclasses(db, &head) {
+                if db.solve_constraint(sup.clone()).is_none() {
+                    diagnostics.push(InferenceDiagnostic::UnsolvedSuperclass { ctnt: sup });
+                }
+            }
+        }
+
+        if inst.is_default(db) {
+            return Arc::new(CoherenceResult { diagnostics });
+        }
+
+        // only the instance that was defined last reports the conflict, instances from dependencies
+        // come first so that the conflict is reported in the library that introduced it
+        let libs = db.libs().toposort();
+        let order = |id: InstanceId| {
+            let lib = id.lookup(db.upcast()).module.lib;
+
+            (libs.iter().position(|&l| l == lib), id.as_intern_id())
+        };
+
+        let instances = db.instances(inst.class);
+        let earlier = instances
+            .matchers
+            .iter()
+            .map(|m| &m.instance)
+            .filter(|other| order(other.id) < order(id) && !other.is_default(db));
+
+        for other in earlier {
+            if unify_heads(db, inst.types.iter().zip(other.types.iter())) {
+                diagnostics.push(InferenceDiagnostic::OverlappingInstance { other: other.id });
+                continue;
+            }
+
+            for dep in class.class.fundeps.iter() {
+                let determiners = dep
+                    .determiners
+                    .iter()
+                    .map(|d| d.debruijn().depth() as usize)
+                    .map(|i| (&inst.types[i], &other.types[i]));
+
+                let determined = dep
+                    .determined
+                    .iter()
+                    .map(|d| d.debruijn().depth() as usize)
+                    .map(|i| (&inst.types[i], &other.types[i]));
+
+                if unify_heads(db, determiners) && !unify_heads(db, determined) {
+                    diagnostics.push(InferenceDiagnostic::FunDepViolation { other: Some(other.id) });
+                    break;
+                }
+            }
+        }
+
+        Arc::new(CoherenceResult { diagnostics })
+    }
+
+    pub(crate) fn explain(db: &dyn HirDatabase, ctnt: &Constraint) -> Explanation {
+        Self::explain_impl(db, ctnt, 0)
+    }
+
+    fn explain_impl(db: &dyn HirDatabase, ctnt: &Constraint, depth: usize) -> Explanation {
+        const MAX_DEPTH: usize = 4;
+        let instances = db.instances(ctnt.class);
+        let mut ambiguous = Vec::new();
+        let mut candidates = Vec::new();
+
+        for m in instances.matchers.iter() {
+            let inst = &m.instance;
+            let mut subst = FxHashMap::default();
+            let mut vars = BTreeMap::default();
+            let matches = ctnt
+                .types
+                .iter()
+                .zip(inst.types.iter())
+                .map(|(&ty, &with)| match_type(db, ty, with, &mut subst, &mut vars))
+                .collect::<Vec<_>>();
+
+            let apart = matches
+                .iter()
+                .enumerate()
+                .filter(|(_, m)| **m == Matched::Apart)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+
+            let failure = match apart[..] {
+                | [index, ..] => CandidateFailure::Mismatch {
+                    index,
+                    expected: inst.types[index],
+                    found: ctnt.types[index],
+                },
+                | [] if !verify(&matches, &instances.deps) => {
+                    let determined = determined(&matches, &instances.deps);
+                    let positions = (0..matches.len())
+                        .filter(|i| !determined.contains(i))
+                        .filter(|&i| collect_unknowns(db, ctnt.types[i], &mut ambiguous))
+                        .collect::<Vec<_>>();
+
+                    if positions.is_empty() {
+                        continue;
+                    }
+
+                    CandidateFailure::Undetermined { positions }
+                },
+                | [] => {
+                    let unsolved = inst
+                        .constraints
+                        .iter()
+                        .map(|c| Constraint {
+                            class: c.class,
+                            types: c.types.iter().map(|&t| subst_vars(db, t, &vars)).collect(),
+                        })
+                        .find(|c| db.solve_constraint(c.clone()).is_none());
+
+                    match unsolved {
+                        | Some(ctnt) => {
+                            let explanation = if depth < MAX_DEPTH {
+                                Self::explain_impl(db, &ctnt, depth + 1)
+                            } else {
+                                Explanation::default()
+                            };
+
+                            CandidateFailure::Constraint {
+                                ctnt,
+                                explanation: Box::new(explanation),
+                            }
+                        },
+                        | None => continue,
+                    }
+                },
+            };
+
+            candidates.push(Candidate {
+                instance: inst.id,
+                failure,
+            });
+        }
+
+        Explanation { ambiguous, candidates }
+    }
+
+    /// The superclass constraints of the class of `ctnt`, applied to the types in `ctnt`.
+    fn superclasses(db: &dyn HirDatabase, ctnt: &Constraint) -> Vec<Constraint> {
+        let lower = db.lower_class(ctnt.class);
+        let vars = ctnt
+            .types
+            .iter()
+            .enumerate()
+            .map(|(i, &ty)| (TypeVar::new(DebruijnIndex::new(i as u32)), ty))
+            .collect::<BTreeMap<_, _>>();
+
+        lower
+            .class
+            .superclasses
+            .iter()
+            .map(|c| Constraint {
+                class: c.class,
+                types: c.types.iter().map(|&t| subst_vars(db, t, &vars)).collect(),
+            })
+            .collect()
+    }
+
     pub(crate) fn matches(&self, db: &dyn HirDatabase, ctnt: Constraint) -> Option<InstanceMatchResult> {
         self.matchers
             .iter()
@@ -117,6 +346,12 @@ impl Instances {
     }
 }
 
+impl CoherenceResult {
+    pub fn add_diagnostics(&self, db: &dyn HirDatabase, owner: TypeVarOwner, sink: &mut DiagnosticSink) {
+        self.diagnostics.iter().for_each(|it| it.add_to(db, owner, sink));
+    }
+}
+
 impl InstanceMatchResult {
     pub(crate) fn apply(&self, icx: &mut InferenceContext) {
         for (&u, &ty) in self.subst.iter() {
@@ -148,13 +383,7 @@ impl Instance {
 
         // @TODO: check if this is always the right thing to do
         for ty in subst.values_mut() {
-            *ty = ty.everywhere(db, &mut |t| match t.lookup(db) {
-                | TyKind::TypeVar(v) => match vars.get(&v) {
-                    | Some(ty) => *ty,
-                    | None => t,
-                },
-                | _ => t,
-            });
+            *ty = subst_vars(db, *ty, &vars);
         }
 
         Some(InstanceMatchResult {
@@ -163,10 +392,42 @@ impl Instance {
         })
     }
 
-    fn priority(&self, db: &dyn HirDatabase) -> isize {
-        let attrs = db.attrs(self.id.into());
+    fn is_default(&self, db: &dyn HirDatabase) -> bool {
+        db.attrs(self.id.into()).by_key("default").exists()
+    }
+
+    /// An instance is an orphan when neither its class nor any of the types
+    /// in its head are defined in the library containing the instance.
+    fn is_orphan(&self, db: &dyn HirDatabase) -> bool {
+        let lib = self.id.lookup(db.upcast()).module.lib;
+
+        if self.class.lookup(db.upcast()).module.lib == lib {
+            return false;
+        }
+
+        !self.types.iter().any(|&ty| contains_local_ctor(db, ty, lib))
+    }
+
+    /// The type variables in determined positions must all appear in the determining positions.
+    fn covers(&self, db: &dyn HirDatabase, deps: &[FunDep]) -> bool {
+        deps.iter().all(|dep| {
+            let mut determiners = FxHashSet::default();
+            let mut determined = FxHashSet::default();
+
+            for d in dep.determiners.iter() {
+                collect_type_vars(db, self.types[d.debruijn().depth() as usize], &mut determiners);
+            }
+
+            for d in dep.determined.iter() {
+                collect_type_vars(db, self.types[d.debruijn().depth() as usize], &mut determined);
+            }
+
+            determined.is_subset(&determiners)
+        })
+    }
 
-        if attrs.by_key("default").exists() {
+    fn priority(&self, db: &dyn HirDatabase) -> isize {
+        if self.is_default(db) {
             isize::min_value()
         } else {
             let mut score = self.vars.len() as isize * 10;
@@ -259,6 +520,12 @@ impl Ty {
 
 fn verify(matches: &[Matched<()>], deps: &[FunDep]) -> bool {
     let expected = (0..matches.len()).collect::<FxHashSet<_>>();
+
+    determined(matches, deps) == expected
+}
+
+/// The argument positions that either matched or are determined through the functional dependencies.
+fn determined(matches: &[Matched<()>], deps: &[FunDep]) -> FxHashSet<usize> {
     let initial_set = matches
         .iter()
         .enumerate()
@@ -294,7 +561,46 @@ fn verify(matches: &[Matched<()>], deps: &[FunDep]) -> bool {
         })
     }
 
-    until_fixed_point(deps, initial_set) == expected
+    until_fixed_point(deps, initial_set)
+}
+
+fn subst_vars(db: &dyn HirDatabase, ty: Ty, vars: &BTreeMap<TypeVar, Ty>) -> Ty {
+    ty.everywhere(db, &mut |t| match t.lookup(db) {
+        | TyKind::TypeVar(v) => match vars.get(&v) {
+            | Some(ty) => *ty,
+            | None => t,
+        },
+        | _ => t,
+    })
+}
+
+/// Whether `ty` contains no unknown types, skolems or type variables.
+fn is_ground(db: &dyn HirDatabase, ty: Ty) -> bool {
+    let mut ground = true;
+
+    ty.everything(db, &mut |t| match t.lookup(db) {
+        | TyKind::Unknown(_) | TyKind::Skolem(_, _) | TyKind::TypeVar(_) => ground = false,
+        | _ => {},
+    });
+
+    ground
+}
+
+/// Adds the unknown types in `ty` to `unknowns`, returns whether there were any.
+fn collect_unknowns(db: &dyn HirDatabase, ty: Ty, unknowns: &mut Vec<Ty>) -> bool {
+    let mut found = false;
+
+    ty.everything(db, &mut |t| {
+        if let TyKind::Unknown(_) = t.lookup(db) {
+            found = true;
+
+            if !unknowns.contains(&t) {
+                unknowns.push(t);
+            }
+        }
+    });
+
+    found
 }
 
 fn match_type(
@@ -334,6 +640,77 @@ fn match_type(
     }
 }
 
+/// Checks whether two instance heads could match the same constraint.
+/// The type variables of both heads are kept apart, a variable that occurs more than once
+/// is only checked when it is bound to a type without variables.
+fn unify_heads<'a>(db: &dyn HirDatabase, mut heads: impl Iterator<Item = (&'a Ty, &'a Ty)>) -> bool {
+    let mut lhs = BTreeMap::new();
+    let mut rhs = BTreeMap::new();
+
+    heads.all(|(&a, &b)| unify_head(db, a, b, &mut lhs, &mut rhs))
+}
+
+fn unify_head(
+    db: &dyn HirDatabase,
+    a: Ty,
+    b: Ty,
+    lhs: &mut BTreeMap<TypeVar, Ty>,
+    rhs: &mut BTreeMap<TypeVar, Ty>,
+) -> bool {
+    match (a.lookup(db), b.lookup(db)) {
+        | (TyKind::Error, _) | (_, TyKind::Error) => false,
+        | (TyKind::TypeVar(v), _) => match lhs.get(&v) {
+            | Some(&t) => t == b || has_type_vars(db, t) || has_type_vars(db, b),
+            | None => {
+                lhs.insert(v, b);
+                true
+            },
+        },
+        | (_, TyKind::TypeVar(v)) => match rhs.get(&v) {
+            | Some(&t) => t == a || has_type_vars(db, t) || has_type_vars(db, a),
+            | None => {
+                rhs.insert(v, a);
+                true
+            },
+        },
+        | (TyKind::Tuple(t1), TyKind::Tuple(t2)) if t1.len() == t2.len() => t1
+            .iter()
+            .zip(t2.iter())
+            .all(|(&t1, &t2)| unify_head(db, t1, t2, lhs, rhs)),
+        | (TyKind::App(a1, a2), TyKind::App(b1, b2)) => {
+            unify_head(db, a1, b1, lhs, rhs) && unify_head(db, a2, b2, lhs, rhs)
+        },
+        | (_, _) => a == b,
+    }
+}
+
+fn has_type_vars(db: &dyn HirDatabase, ty: Ty) -> bool {
+    let mut vars = FxHashSet::default();
+
+    collect_type_vars(db, ty, &mut vars);
+    !vars.is_empty()
+}
+
+fn collect_type_vars(db: &dyn HirDatabase, ty: Ty, vars: &mut FxHashSet<TypeVar>) {
+    ty.everything(db, &mut |t| {
+        if let TyKind::TypeVar(v) = t.lookup(db) {
+            vars.insert(v);
+        }
+    });
+}
+
+fn contains_local_ctor(db: &dyn HirDatabase, ty: Ty, lib: LibId) -> bool {
+    let mut found = false;
+
+    ty.everything(db, &mut |t| {
+        if let TyKind::Ctor(id) = t.lookup(db) {
+            found |= id.lookup(db.upcast()).module.lib == lib;
+        }
+    });
+
+    found
+}
+
 fn type_score(db: &dyn HirDatabase, ty: Ty) -> isize {
     match ty.lookup(db) {
         | TyKind::TypeVar(_) => 5,
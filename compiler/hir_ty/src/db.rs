@@ -1,10 +1,10 @@
-use crate::class::{InstanceMatchResult, Instances};
+use crate::class::{CoherenceResult, InstanceMatchResult, Instances};
 use crate::infer::InferenceResult;
-use crate::lower::{ClassLowerResult, InstanceLowerResult, LowerResult};
+use crate::lower::{ClassLowerResult, InstanceLowerResult, LowerResult, TypeGroup};
 use crate::ty::{Constraint, Ty, TyKind};
 use base_db::Upcast;
 use hir_def::db::DefDatabase;
-use hir_def::id::{ClassId, CtorId, DefWithBodyId, InstanceId, TypeAliasId, TypeCtorId, ValueTyDefId};
+use hir_def::id::{ClassId, CtorId, DefWithBodyId, InstanceId, TypeAliasId, TypeCtorId, TypedDefId, ValueTyDefId};
 use std::sync::Arc;
 
 #[salsa::query_group(HirDatabaseStorage)]
@@ -21,6 +21,9 @@ pub trait HirDatabase: DefDatabase + Upcast<dyn DefDatabase> {
     #[salsa::interned]
     fn intern_ty(&self, ty: TyKind) -> Ty;
 
+    #[salsa::invoke(crate::lower::type_group_query)]
+    fn type_group(&self, id: TypedDefId) -> Arc<TypeGroup>;
+
     #[salsa::invoke(crate::lower::type_for_alias)]
     #[salsa::cycle(crate::lower::type_for_alias_recover)]
     fn type_for_alias(&self, id: TypeAliasId) -> Arc<LowerResult>;
@@ -42,6 +45,9 @@ pub trait HirDatabase: DefDatabase + Upcast<dyn DefDatabase> {
     #[salsa::invoke(Instances::instances_query)]
     fn instances(&self, id: ClassId) -> Arc<Instances>;
 
+    #[salsa::invoke(Instances::coherence_query)]
+    fn coherence(&self, id: InstanceId) -> Arc<CoherenceResult>;
+
     #[salsa::invoke(Instances::solve_constraint_query)]
     fn solve_constraint(&self, ctnt: Constraint) -> Option<Arc<InstanceMatchResult>>;
 }
@@ -1,7 +1,9 @@
-use crate::ty::{Constraint, Ty};
+use crate::class::Explanation;
+use crate::ty::{Constraint, Skolem, Ty};
 use base_db::input::FileId;
 use hir_def::diagnostic::Diagnostic;
 use hir_def::in_file::InFile;
+use hir_def::name::Name;
 use std::any::Any;
 use syntax::{ast, AstPtr, SyntaxNodePtr};
 
@@ -134,11 +136,37 @@ pub struct UnsolvedConstraint {
     pub file: FileId,
     pub src: SyntaxNodePtr,
     pub ctnt: Constraint,
+    pub explanation: Explanation,
 }
 
 impl Diagnostic for UnsolvedConstraint {
     fn message(&self) -> String {
-        "unresolved constraint".into()
+        if self.explanation.ambiguous.is_empty() {
+            "unresolved constraint".into()
+        } else {
+            "ambiguous type variable".into()
+        }
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct SkolemEscape {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub skolem: Skolem,
+}
+
+impl Diagnostic for SkolemEscape {
+    fn message(&self) -> String {
+        format!("type variable `{}` would escape its scope", self.skolem)
     }
 
     fn display_source(&self) -> InFile<SyntaxNodePtr> {
@@ -229,3 +257,276 @@ impl Diagnostic for CannotNextWithValue {
         self
     }
 }
+
+#[derive(Debug)]
+pub struct OverlappingInstance {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub other: InFile<SyntaxNodePtr>,
+}
+
+impl Diagnostic for OverlappingInstance {
+    fn message(&self) -> String {
+        "overlapping instances".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct OrphanInstance {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+}
+
+impl Diagnostic for OrphanInstance {
+    fn message(&self) -> String {
+        "orphan instance".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct FunDepViolation {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub other: Option<InFile<SyntaxNodePtr>>,
+}
+
+impl Diagnostic for FunDepViolation {
+    fn message(&self) -> String {
+        "instance violates the functional dependencies of its class".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingMethod {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub name: Name,
+}
+
+impl Diagnostic for MissingMethod {
+    fn message(&self) -> String {
+        format!("missing method `{}` in instance", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct DefaultWithoutSignature {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub name: Name,
+}
+
+impl Diagnostic for DefaultWithoutSignature {
+    fn message(&self) -> String {
+        format!("default implementation of `{}` has no signature in the class", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct CyclicTypeAlias {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub path: Vec<Name>,
+}
+
+impl Diagnostic for CyclicTypeAlias {
+    fn message(&self) -> String {
+        let path = self.path.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>();
+
+        format!("cyclic type alias: {}", path.join(" -> "))
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct CyclicType {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+}
+
+impl Diagnostic for CyclicType {
+    fn message(&self) -> String {
+        "the type of this item depends on itself".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct NoSuchField {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub base: Ty,
+    pub field: Name,
+}
+
+impl Diagnostic for NoSuchField {
+    fn message(&self) -> String {
+        format!("no field `{}`", self.field)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UnsupportedExpr {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+}
+
+impl Diagnostic for UnsupportedExpr {
+    fn message(&self) -> String {
+        "this expression is not supported yet".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct FloatPattern {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+}
+
+impl Diagnostic for FloatPattern {
+    fn message(&self) -> String {
+        "float literals cannot be used as patterns".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct MissingLangItem {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub name: &'static str,
+}
+
+impl Diagnostic for MissingLangItem {
+    fn message(&self) -> String {
+        format!("missing the `{}` lang item", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct UseOfMovedValue {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub moved: SyntaxNodePtr,
+    pub name: Name,
+}
+
+impl Diagnostic for UseOfMovedValue {
+    fn message(&self) -> String {
+        format!("use of moved value `{}`", self.name)
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct ManagedTypeArgument {
+    pub file: FileId,
+    pub src: SyntaxNodePtr,
+    pub ty: Ty,
+}
+
+impl Diagnostic for ManagedTypeArgument {
+    fn message(&self) -> String {
+        "type variables cannot be instantiated with reference counted or dropped types".into()
+    }
+
+    fn display_source(&self) -> InFile<SyntaxNodePtr> {
+        InFile::new(self.file, self.src)
+    }
+
+    fn as_any(&self) -> &(dyn Any + Send + 'static) {
+        self
+    }
+}
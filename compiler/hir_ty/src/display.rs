@@ -6,6 +6,10 @@ use hir_def::id::Lookup;
 use hir_def::lang_item::LangItem;
 use std::fmt;
 
+mod body;
+
+pub use body::{ExprDisplay, PatDisplay};
+
 pub trait HirDisplay {
     fn hir_fmt(&self, f: &mut HirFormatter) -> fmt::Result;
 
@@ -156,6 +160,12 @@ impl fmt::Display for TypeVar {
     }
 }
 
+impl fmt::Display for Skolem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.var.fmt(f)
+    }
+}
+
 impl fmt::Display for Unknown {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "?{}", self.raw())
@@ -318,7 +328,6 @@ impl HirDisplay for Ty {
                 write!(f, ". ")?;
                 ty.hir_fmt(f)
             },
-            | _ => unimplemented!(),
         }
     }
 }
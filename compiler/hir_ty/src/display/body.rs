@@ -0,0 +1,464 @@
+//! Displays lowered bodies as source code, which is used by `--emit=hir`.
+
+use super::*;
+use hir_def::body::Body;
+use hir_def::expr::{Expr, ExprId, Literal, RecordField, Stmt};
+use hir_def::pat::{Pat, PatId};
+use hir_def::type_ref::{LocalTypeRefId, PtrLen, TypeRef};
+
+impl HirDisplay for Body {
+    fn hir_fmt(&self, f: &mut HirFormatter) -> fmt::Result {
+        let printer = BodyPrinter { body: self };
+        let f: &mut dyn Write = f;
+
+        for &param in self.params() {
+            write!(f, " ")?;
+            printer.pat_atom(param, f)?;
+        }
+
+        write!(f, " = ")?;
+        printer.expr(self.body_expr(), f)
+    }
+}
+
+/// A single expression of a body, displayed as source code.
+pub struct ExprDisplay<'a>(pub &'a Body, pub ExprId);
+
+/// A single pattern of a body, displayed as source code.
+pub struct PatDisplay<'a>(pub &'a Body, pub PatId);
+
+impl HirDisplay for ExprDisplay<'_> {
+    fn hir_fmt(&self, f: &mut HirFormatter) -> fmt::Result {
+        BodyPrinter { body: self.0 }.expr(self.1, f)
+    }
+}
+
+impl HirDisplay for PatDisplay<'_> {
+    fn hir_fmt(&self, f: &mut HirFormatter) -> fmt::Result {
+        BodyPrinter { body: self.0 }.pat(self.1, f)
+    }
+}
+
+struct BodyPrinter<'a> {
+    body: &'a Body,
+}
+
+impl BodyPrinter<'_> {
+    fn expr(&self, id: ExprId, f: &mut dyn Write) -> fmt::Result {
+        match &self.body[id] {
+            | Expr::Typed { expr, ty } => {
+                self.expr_atom(*expr, f)?;
+                write!(f, " :: ")?;
+                self.ty(*ty, f)
+            },
+            | Expr::Infix { op, lhs, rhs } => {
+                self.expr_atom(*lhs, f)?;
+                write!(f, " {} ", op)?;
+                self.expr_atom(*rhs, f)
+            },
+            | Expr::App { base, arg } => {
+                match self.body[*base] {
+                    | Expr::App { .. } => self.expr(*base, f)?,
+                    | _ => self.expr_atom(*base, f)?,
+                }
+
+                write!(f, " ")?;
+                self.expr_atom(*arg, f)
+            },
+            | Expr::Do { stmts } => {
+                write!(f, "do")?;
+                self.block(stmts, f)
+            },
+            | Expr::Clos { pats, stmts } => {
+                write!(f, "do |")?;
+
+                for (i, &pat) in pats.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    self.pat(pat, f)?;
+                }
+
+                write!(f, "|")?;
+                self.block(stmts, f)
+            },
+            | Expr::If {
+                cond,
+                then,
+                else_,
+                inverse,
+            } => {
+                write!(f, "{} ", if *inverse { "unless" } else { "if" })?;
+                self.expr(*cond, f)?;
+                write!(f, " then ")?;
+                self.expr(*then, f)?;
+
+                if let Some(else_) = else_ {
+                    write!(f, " else ")?;
+                    self.expr(*else_, f)?;
+                }
+
+                Ok(())
+            },
+            | Expr::Case { pred, arms } => {
+                write!(f, "case ")?;
+                self.expr(*pred, f)?;
+                write!(f, " of")?;
+
+                let mut f: &mut dyn Write = f;
+                let mut f = indent(&mut f);
+
+                for arm in arms {
+                    writeln!(f)?;
+                    self.pat(arm.pat, &mut f)?;
+
+                    if let Some(guard) = arm.guard {
+                        write!(f, " | ")?;
+                        self.expr(guard, &mut f)?;
+                    }
+
+                    write!(f, " -> ")?;
+                    self.expr(arm.expr, &mut f)?;
+                }
+
+                Ok(())
+            },
+            | Expr::While { cond, body, inverse } => {
+                write!(f, "{} ", if *inverse { "until" } else { "while" })?;
+                self.expr(*cond, f)?;
+                write!(f, " ")?;
+                self.expr(*body, f)
+            },
+            | Expr::Loop { body } => {
+                write!(f, "loop ")?;
+                self.expr(*body, f)
+            },
+            | Expr::Next { expr } => self.keyword("next", *expr, f),
+            | Expr::Break { expr } => self.keyword("break", *expr, f),
+            | Expr::Return { expr } => self.keyword("return", *expr, f),
+            | Expr::Yield { exprs } => {
+                write!(f, "yield")?;
+
+                for &expr in exprs {
+                    write!(f, " ")?;
+                    self.expr_atom(expr, f)?;
+                }
+
+                Ok(())
+            },
+            | _ => self.expr_atom(id, f),
+        }
+    }
+
+    fn expr_atom(&self, id: ExprId, f: &mut dyn Write) -> fmt::Result {
+        match &self.body[id] {
+            | Expr::Missing => write!(f, "{{missing}}"),
+            | Expr::Path { path } => write!(f, "{}", path),
+            | Expr::Lit { lit } => literal(lit, f),
+            | Expr::Field { base, field } => {
+                self.expr_atom(*base, f)?;
+                write!(f, ".{}", field)
+            },
+            | Expr::Index { base, index } => {
+                self.expr_atom(*base, f)?;
+                write!(f, "[")?;
+                self.expr(*index, f)?;
+                write!(f, "]")
+            },
+            | Expr::Tuple { exprs } => {
+                write!(f, "(")?;
+                self.exprs(exprs, f)?;
+                write!(f, ")")
+            },
+            | Expr::Array { exprs } => {
+                write!(f, "[")?;
+                self.exprs(exprs, f)?;
+                write!(f, "]")
+            },
+            | Expr::Record { fields } => {
+                write!(f, "{{ ")?;
+                self.fields(fields, |id, f| self.expr(id, f), f)?;
+                write!(f, " }}")
+            },
+            | Expr::Update { base, fields } => {
+                write!(f, "{{ ")?;
+                self.expr(*base, f)?;
+                write!(f, " | ")?;
+                self.fields(fields, |id, f| self.expr(id, f), f)?;
+                write!(f, " }}")
+            },
+            | _ => {
+                write!(f, "(")?;
+                self.expr(id, f)?;
+                write!(f, ")")
+            },
+        }
+    }
+
+    fn exprs(&self, exprs: &[ExprId], f: &mut dyn Write) -> fmt::Result {
+        for (i, &expr) in exprs.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+
+            self.expr(expr, f)?;
+        }
+
+        Ok(())
+    }
+
+    fn keyword(&self, keyword: &str, expr: Option<ExprId>, f: &mut dyn Write) -> fmt::Result {
+        write!(f, "{}", keyword)?;
+
+        if let Some(expr) = expr {
+            write!(f, " ")?;
+            self.expr(expr, f)?;
+        }
+
+        Ok(())
+    }
+
+    fn block(&self, stmts: &[Stmt], f: &mut dyn Write) -> fmt::Result {
+        let mut f: &mut dyn Write = f;
+        let mut f = indent(&mut f);
+
+        for stmt in stmts {
+            writeln!(f)?;
+
+            match *stmt {
+                | Stmt::Let { pat, val } | Stmt::Fun { pat, val } => {
+                    write!(f, "let ")?;
+                    self.pat(pat, &mut f)?;
+                    write!(f, " = ")?;
+                    self.expr(val, &mut f)?;
+                },
+                | Stmt::Bind { pat, val } => {
+                    self.pat(pat, &mut f)?;
+                    write!(f, " <- ")?;
+                    self.expr(val, &mut f)?;
+                },
+                | Stmt::Expr { expr } => self.expr(expr, &mut f)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pat(&self, id: PatId, f: &mut dyn Write) -> fmt::Result {
+        match &self.body[id] {
+            | Pat::Typed { pat, ty } => {
+                self.pat_atom(*pat, f)?;
+                write!(f, " :: ")?;
+                self.ty(*ty, f)
+            },
+            | Pat::App { base, args } => {
+                self.pat_atom(*base, f)?;
+
+                for &arg in args {
+                    write!(f, " ")?;
+                    self.pat_atom(arg, f)?;
+                }
+
+                Ok(())
+            },
+            | Pat::Bind {
+                name,
+                subpat: Some(subpat),
+            } => {
+                write!(f, "{} @ ", name)?;
+                self.pat_atom(*subpat, f)
+            },
+            | _ => self.pat_atom(id, f),
+        }
+    }
+
+    fn pat_atom(&self, id: PatId, f: &mut dyn Write) -> fmt::Result {
+        match &self.body[id] {
+            | Pat::Missing => write!(f, "{{missing}}"),
+            | Pat::Wildcard => write!(f, "_"),
+            | Pat::Path { path } => write!(f, "{}", path),
+            | Pat::Bind { name, subpat: None } => write!(f, "{}", name),
+            | Pat::Lit { lit } => literal(lit, f),
+            | Pat::Tuple { pats } => {
+                write!(f, "(")?;
+
+                for (i, &pat) in pats.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    self.pat(pat, f)?;
+                }
+
+                write!(f, ")")
+            },
+            | Pat::Record { fields, has_rest } => {
+                write!(f, "{{ ")?;
+                self.fields(fields, |id, f| self.pat(id, f), f)?;
+
+                if *has_rest {
+                    write!(f, "{}..", if fields.is_empty() { "" } else { ", " })?;
+                }
+
+                write!(f, " }}")
+            },
+            | _ => {
+                write!(f, "(")?;
+                self.pat(id, f)?;
+                write!(f, ")")
+            },
+        }
+    }
+
+    fn fields<T: Copy>(
+        &self,
+        fields: &[RecordField<T>],
+        val: impl Fn(T, &mut dyn Write) -> fmt::Result,
+        f: &mut dyn Write,
+    ) -> fmt::Result {
+        for (i, field) in fields.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}: ", field.name)?;
+            val(field.val, f)?;
+        }
+
+        Ok(())
+    }
+
+    fn ty(&self, id: LocalTypeRefId, f: &mut dyn Write) -> fmt::Result {
+        match &self.body[id] {
+            | TypeRef::Kinded(ty, kind) => {
+                self.ty_atom(*ty, f)?;
+                write!(f, " :: ")?;
+                self.ty(*kind, f)
+            },
+            | TypeRef::App(base, arg) => {
+                match self.body[*base] {
+                    | TypeRef::App(..) => self.ty(*base, f)?,
+                    | _ => self.ty_atom(*base, f)?,
+                }
+
+                write!(f, " ")?;
+                self.ty_atom(*arg, f)
+            },
+            | TypeRef::Func(arg, ret) => {
+                self.ty_atom(*arg, f)?;
+                write!(f, " -> ")?;
+                self.ty(*ret, f)
+            },
+            | TypeRef::Forall(vars, inner) => {
+                write!(f, "for")?;
+
+                for &var in vars.iter() {
+                    write!(f, " {}", self.body.type_map()[var].name)?;
+                }
+
+                write!(f, ". ")?;
+                self.ty(*inner, f)
+            },
+            | TypeRef::Constraint(ctnt, inner) => {
+                write!(f, "{}", ctnt.class)?;
+
+                for &ty in ctnt.types.iter() {
+                    write!(f, " ")?;
+                    self.ty_atom(ty, f)?;
+                }
+
+                write!(f, " => ")?;
+                self.ty(*inner, f)
+            },
+            | _ => self.ty_atom(id, f),
+        }
+    }
+
+    fn ty_atom(&self, id: LocalTypeRefId, f: &mut dyn Write) -> fmt::Result {
+        match &self.body[id] {
+            | TypeRef::Error => write!(f, "{{error}}"),
+            | TypeRef::Placeholder => write!(f, "_"),
+            | TypeRef::Figure(i) => write!(f, "{}", i),
+            | TypeRef::Symbol(s) => write!(f, "{:?}", s),
+            | TypeRef::Path(path) => write!(f, "{}", path),
+            | TypeRef::Tuple(tys) => {
+                write!(f, "(")?;
+
+                for (i, &ty) in tys.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    self.ty(ty, f)?;
+                }
+
+                write!(f, ")")
+            },
+            | TypeRef::Ptr(to, len) => {
+                match len {
+                    | PtrLen::Single => write!(f, "*")?,
+                    | PtrLen::Multiple(None) => write!(f, "[*]")?,
+                    | PtrLen::Multiple(Some(sentinel)) => write!(f, "[*:{}]", sentinel.0)?,
+                }
+
+                self.ty_atom(*to, f)
+            },
+            | TypeRef::Slice(of) => {
+                write!(f, "[]")?;
+                self.ty_atom(*of, f)
+            },
+            | TypeRef::Array(of, len) => {
+                write!(f, "[{}]", len)?;
+                self.ty_atom(*of, f)
+            },
+            | TypeRef::Record(fields, tail) => {
+                write!(f, "{{ ")?;
+                self.ty_fields(fields, *tail, f)?;
+                write!(f, " }}")
+            },
+            | TypeRef::Row(fields, tail) => {
+                write!(f, "(")?;
+                self.ty_fields(fields, *tail, f)?;
+                write!(f, ")")
+            },
+            | _ => {
+                write!(f, "(")?;
+                self.ty(id, f)?;
+                write!(f, ")")
+            },
+        }
+    }
+
+    fn ty_fields(
+        &self,
+        fields: &[hir_def::type_ref::Field],
+        tail: Option<LocalTypeRefId>,
+        f: &mut dyn Write,
+    ) -> fmt::Result {
+        for (i, field) in fields.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{} :: ", field.name)?;
+            self.ty(field.ty, f)?;
+        }
+
+        if let Some(tail) = tail {
+            write!(f, " | ")?;
+            self.ty(tail, f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn literal(lit: &Literal, f: &mut dyn Write) -> fmt::Result {
+    match lit {
+        | Literal::String(s) => write!(f, "{:?}", s),
+        | Literal::Char(c) => write!(f, "{:?}", c),
+        | Literal::Int(i) => write!(f, "{}", i),
+        | Literal::Float(bits) => write!(f, "{:?}", f64::from_bits(*bits)),
+    }
+}
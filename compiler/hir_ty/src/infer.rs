@@ -1,6 +1,8 @@
 mod ctnt;
 mod expr;
 mod kind;
+mod managed;
+mod moves;
 mod pat;
 mod skolem;
 mod subsume;
@@ -23,6 +25,7 @@ use hir_def::pat::PatId;
 use hir_def::resolver::{HasResolver, Resolver};
 use rustc_hash::FxHashMap;
 use std::sync::Arc;
+use unify::UnifyError;
 
 pub(crate) fn infer_query(db: &dyn HirDatabase, def: DefWithBodyId) -> Arc<InferenceResult> {
     let body = db.body(def);
@@ -68,7 +71,11 @@ pub(crate) fn infer_query(db: &dyn HirDatabase, def: DefWithBodyId) -> Arc<Infer
         icx.infer_body();
     }
 
-    Arc::new(icx.finish())
+    let mut result = icx.finish();
+
+    moves::check(db, def, &mut result);
+    managed::check(db, def, &mut result);
+    Arc::new(result)
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -94,6 +101,7 @@ pub(crate) struct InferenceContext<'a> {
     pub(crate) result: InferenceResult,
     subst: unify::Substitution,
     pub(crate) var_kinds: Vec<Ty>,
+    pub(crate) group_kinds: FxHashMap<hir_def::id::TypeCtorId, Ty>,
     class_env: ClassEnv,
     instance_records: usize,
     constraints: Vec<(Constraint, ExprOrPatId, Option<ClassEnvScope>)>,
@@ -150,6 +158,7 @@ impl<'a> InferenceContext<'a> {
             },
             subst: unify::Substitution::default(),
             var_kinds: Vec::default(),
+            group_kinds: FxHashMap::default(),
             class_env: ClassEnv::default(),
             instance_records: 0,
             constraints: Vec::default(),
@@ -253,8 +262,11 @@ impl<'a> InferenceContext<'a> {
         self.result.diagnostics.push(diag);
     }
 
-    pub(crate) fn report_mismatch(&mut self, expected: Ty, found: Ty, id: ExprOrPatId) {
-        self.report(InferenceDiagnostic::MismatchedType { id, expected, found });
+    pub(crate) fn report_mismatch(&mut self, error: UnifyError, expected: Ty, found: Ty, id: ExprOrPatId) {
+        match error {
+            | UnifyError::SkolemEscape(skolem) => self.report(InferenceDiagnostic::SkolemEscape { id, skolem }),
+            | UnifyError::Mismatch => self.report(InferenceDiagnostic::MismatchedType { id, expected, found }),
+        }
     }
 
     pub(crate) fn constrain(&mut self, id: ExprOrPatId, ctnt: Constraint) {
@@ -394,13 +406,20 @@ impl InferenceResult {
 
 pub(crate) mod diagnostics {
     use super::{ExprOrPatId, InferenceContext};
+    use crate::class::Instances;
     use crate::db::HirDatabase;
     use crate::diagnostics::*;
-    use crate::ty::{Constraint, Ty};
+    use crate::ty::{Constraint, Skolem, Ty};
+    use hir_def::body::BodySourceMap;
     use hir_def::diagnostic::DiagnosticSink;
     use hir_def::expr::ExprId;
-    use hir_def::id::{HasSource, TypeVarOwner};
+    use hir_def::id::{FuncId, HasSource, InstanceId, Lookup, TypeVarOwner, TypedDefId};
+    use hir_def::in_file::InFile;
+    use hir_def::name::Name;
+    use hir_def::pat::PatId;
     use hir_def::type_ref::LocalTypeRefId;
+    use syntax::ast::AstNode;
+    use syntax::SyntaxNodePtr;
 
     #[derive(Debug, PartialEq, Eq)]
     pub enum InferenceDiagnostic {
@@ -427,6 +446,10 @@ pub(crate) mod diagnostics {
             id: ExprOrPatId,
             ctnt: Constraint,
         },
+        SkolemEscape {
+            id: ExprOrPatId,
+            skolem: Skolem,
+        },
         BreakOutsideLoop {
             id: ExprId,
         },
@@ -439,6 +462,52 @@ pub(crate) mod diagnostics {
         CannotNextWithValue {
             id: ExprId,
         },
+        OverlappingInstance {
+            other: InstanceId,
+        },
+        OrphanInstance,
+        FunDepViolation {
+            other: Option<InstanceId>,
+        },
+        MissingMethod {
+            name: Name,
+        },
+        UnsolvedSuperclass {
+            ctnt: Constraint,
+        },
+        DefaultWithoutSignature {
+            func: FuncId,
+            name: Name,
+        },
+        CyclicTypeAlias {
+            id: LocalTypeRefId,
+            path: Vec<Name>,
+        },
+        CyclicType,
+        NoSuchField {
+            id: ExprId,
+            base: Ty,
+            field: Name,
+        },
+        UnsupportedExpr {
+            id: ExprId,
+        },
+        FloatPattern {
+            id: PatId,
+        },
+        MissingLangItem {
+            id: PatId,
+            name: &'static str,
+        },
+        UseOfMovedValue {
+            id: ExprId,
+            moved: ExprId,
+            name: Name,
+        },
+        ManagedTypeArgument {
+            id: ExprId,
+            ty: Ty,
+        },
     }
 
     impl InferenceDiagnostic {
@@ -458,12 +527,17 @@ pub(crate) mod diagnostics {
                     id,
                     ctnt: icx.subst_ctnt(&ctnt),
                 },
+                | InferenceDiagnostic::NoSuchField { id, base, field } => InferenceDiagnostic::NoSuchField {
+                    id,
+                    base: icx.subst_type(base),
+                    field,
+                },
                 | _ => self,
             }
         }
 
         pub fn add_to(&self, db: &dyn HirDatabase, owner: TypeVarOwner, sink: &mut DiagnosticSink) {
-            let file = owner.source(db.upcast()).file_id;
+            let file = owner.file_id(db.upcast());
 
             match self {
                 | InferenceDiagnostic::UnresolvedType { id } => {
@@ -479,10 +553,7 @@ pub(crate) mod diagnostics {
                         | _ => return,
                     };
 
-                    let src = match *id {
-                        | ExprOrPatId::ExprId(e) => soure_map.expr_syntax(e).unwrap().value.syntax_node_ptr(),
-                        | ExprOrPatId::PatId(e) => soure_map.pat_syntax(e).unwrap().value.syntax_node_ptr(),
-                    };
+                    let src = expr_or_pat_src(&soure_map, *id);
 
                     sink.push(UnresolvedValue { file, src });
                 },
@@ -513,10 +584,7 @@ pub(crate) mod diagnostics {
                         | _ => return,
                     };
 
-                    let src = match *id {
-                        | ExprOrPatId::ExprId(e) => soure_map.expr_syntax(e).unwrap().value.syntax_node_ptr(),
-                        | ExprOrPatId::PatId(e) => soure_map.pat_syntax(e).unwrap().value.syntax_node_ptr(),
-                    };
+                    let src = expr_or_pat_src(&soure_map, *id);
 
                     sink.push(MismatchedType {
                         file,
@@ -531,15 +599,27 @@ pub(crate) mod diagnostics {
                         | _ => return,
                     };
 
-                    let src = match *id {
-                        | ExprOrPatId::ExprId(e) => soure_map.expr_syntax(e).unwrap().value.syntax_node_ptr(),
-                        | ExprOrPatId::PatId(e) => soure_map.pat_syntax(e).unwrap().value.syntax_node_ptr(),
-                    };
+                    let src = expr_or_pat_src(&soure_map, *id);
 
                     sink.push(UnsolvedConstraint {
                         file,
                         src,
                         ctnt: ctnt.clone(),
+                        explanation: Instances::explain(db, ctnt),
+                    });
+                },
+                | InferenceDiagnostic::SkolemEscape { id, skolem } => {
+                    let soure_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&soure_map, *id);
+
+                    sink.push(SkolemEscape {
+                        file,
+                        src,
+                        skolem: *skolem,
                     });
                 },
                 | InferenceDiagnostic::BreakOutsideLoop { id } => {
@@ -582,7 +662,190 @@ pub(crate) mod diagnostics {
 
                     sink.push(CannotNextWithValue { file, src });
                 },
+                | InferenceDiagnostic::OverlappingInstance { other } => {
+                    let id = match owner {
+                        | TypeVarOwner::TypedDefId(TypedDefId::InstanceId(id)) => id,
+                        | _ => return,
+                    };
+
+                    sink.push(OverlappingInstance {
+                        file,
+                        src: instance_src(db, id).value,
+                        other: instance_src(db, *other),
+                    });
+                },
+                | InferenceDiagnostic::OrphanInstance => {
+                    let id = match owner {
+                        | TypeVarOwner::TypedDefId(TypedDefId::InstanceId(id)) => id,
+                        | _ => return,
+                    };
+
+                    sink.push(OrphanInstance {
+                        file,
+                        src: instance_src(db, id).value,
+                    });
+                },
+                | InferenceDiagnostic::FunDepViolation { other } => {
+                    let id = match owner {
+                        | TypeVarOwner::TypedDefId(TypedDefId::InstanceId(id)) => id,
+                        | _ => return,
+                    };
+
+                    sink.push(FunDepViolation {
+                        file,
+                        src: instance_src(db, id).value,
+                        other: other.map(|other| instance_src(db, other)),
+                    });
+                },
+                | InferenceDiagnostic::MissingMethod { name } => {
+                    let id = match owner {
+                        | TypeVarOwner::TypedDefId(TypedDefId::InstanceId(id)) => id,
+                        | _ => return,
+                    };
+
+                    sink.push(MissingMethod {
+                        file,
+                        src: instance_src(db, id).value,
+                        name: name.clone(),
+                    });
+                },
+                | InferenceDiagnostic::UnsolvedSuperclass { ctnt } => {
+                    let id = match owner {
+                        | TypeVarOwner::TypedDefId(TypedDefId::InstanceId(id)) => id,
+                        | _ => return,
+                    };
+
+                    sink.push(UnsolvedConstraint {
+                        file,
+                        src: instance_src(db, id).value,
+                        ctnt: ctnt.clone(),
+                        explanation: Instances::explain(db, ctnt),
+                    });
+                },
+                | InferenceDiagnostic::DefaultWithoutSignature { func, name } => {
+                    let src = func.lookup(db.upcast()).source(db.upcast());
+
+                    sink.push(DefaultWithoutSignature {
+                        file: src.file_id,
+                        src: SyntaxNodePtr::new(src.value.syntax()),
+                        name: name.clone(),
+                    });
+                },
+                | InferenceDiagnostic::CyclicTypeAlias { id, path } => {
+                    let src = owner.with_type_source_map(db.upcast(), |source_map| source_map.type_ref_syntax(*id));
+                    let src = match src {
+                        | Some(src) => src.syntax_node_ptr(),
+                        | None => SyntaxNodePtr::new(owner.source(db.upcast()).value.syntax()),
+                    };
+
+                    sink.push(CyclicTypeAlias {
+                        file,
+                        src,
+                        path: path.clone(),
+                    });
+                },
+                | InferenceDiagnostic::CyclicType => {
+                    let src = SyntaxNodePtr::new(owner.source(db.upcast()).value.syntax());
+
+                    sink.push(CyclicType { file, src });
+                },
+                | InferenceDiagnostic::NoSuchField { id, base, field } => {
+                    let source_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&source_map, (*id).into());
+
+                    sink.push(NoSuchField {
+                        file,
+                        src,
+                        base: *base,
+                        field: field.clone(),
+                    });
+                },
+                | InferenceDiagnostic::UnsupportedExpr { id } => {
+                    let source_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&source_map, (*id).into());
+
+                    sink.push(UnsupportedExpr { file, src });
+                },
+                | InferenceDiagnostic::FloatPattern { id } => {
+                    let source_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&source_map, (*id).into());
+
+                    sink.push(FloatPattern { file, src });
+                },
+                | InferenceDiagnostic::MissingLangItem { id, name } => {
+                    let source_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&source_map, (*id).into());
+
+                    sink.push(MissingLangItem { file, src, name });
+                },
+                | InferenceDiagnostic::UseOfMovedValue { id, moved, name } => {
+                    let source_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&source_map, (*id).into());
+                    let moved = expr_or_pat_src(&source_map, (*moved).into());
+
+                    sink.push(UseOfMovedValue {
+                        file,
+                        src,
+                        moved,
+                        name: name.clone(),
+                    });
+                },
+                | InferenceDiagnostic::ManagedTypeArgument { id, ty } => {
+                    let source_map = match owner {
+                        | TypeVarOwner::DefWithBodyId(id) => db.body_source_map(id).1,
+                        | _ => return,
+                    };
+
+                    let src = expr_or_pat_src(&source_map, (*id).into());
+
+                    sink.push(ManagedTypeArgument { file, src, ty: *ty });
+                },
             }
         }
     }
+
+    fn expr_or_pat_src(source_map: &BodySourceMap, id: ExprOrPatId) -> SyntaxNodePtr {
+        let src = match id {
+            | ExprOrPatId::ExprId(e) => source_map.expr_syntax(e).map(|s| s.value.syntax_node_ptr()),
+            | ExprOrPatId::PatId(e) => source_map.pat_syntax(e).map(|s| s.value.syntax_node_ptr()),
+        };
+
+        src.ok().or_else(|| source_map.synthetic_origin()).unwrap()
+    }
+
+    fn instance_src(db: &dyn HirDatabase, id: InstanceId) -> InFile<SyntaxNodePtr> {
+        let data = db.instance_data(id);
+
+        match &data.derive {
+            | Some(derive) => derive
+                .type_ctor
+                .lookup(db.upcast())
+                .source(db.upcast())
+                .map(|it| SyntaxNodePtr::new(it.syntax())),
+            | None => id
+                .lookup(db.upcast())
+                .source(db.upcast())
+                .map(|it| SyntaxNodePtr::new(it.syntax())),
+        }
+    }
 }
@@ -1,9 +1,10 @@
 use super::{BodyInferenceContext, Breakable, InferenceDiagnostic};
-use crate::display::HirDisplay;
 use crate::lower::LowerCtx;
 use crate::ty::*;
 use hir_def::expr::{Expr, ExprId, Literal, Stmt};
-use hir_def::id::TypeVarOwner;
+use hir_def::id::{CtorId, FixityId, TypeVarOwner, ValueTyDefId};
+use hir_def::name::Name;
+use hir_def::pat::PatId;
 use hir_def::resolver::{HasResolver, Resolver, ValueNs};
 use std::sync::Arc;
 
@@ -25,8 +26,15 @@ impl BodyInferenceContext<'_> {
             | Expr::Path { path } => match self.resolver.resolve_value_fully(self.db.upcast(), path) {
                 | Some(res) => 't: {
                     let id = match res {
-                        | ValueNs::Local(pat) => break 't self.result.type_of_pat[pat],
-                        | ValueNs::Fixity(id) => unimplemented!(),
+                        | ValueNs::Local(pat) => {
+                            let ty = self.result.type_of_pat[pat];
+
+                            break 't self.instantiate(ty, expr.into());
+                        },
+                        | ValueNs::Fixity(id) => match self.fixity_value(id) {
+                            | Some(id) => id,
+                            | None => break 't self.error(),
+                        },
                         | ValueNs::Func(id) => {
                             if self.owner == TypeVarOwner::DefWithBodyId(id.into()) {
                                 break 't self.subst_type(self.result.self_type);
@@ -76,12 +84,9 @@ impl BodyInferenceContext<'_> {
             },
             | Expr::Infix { op, lhs, rhs } => match self.resolver.resolve_value_fully(self.db.upcast(), op) {
                 | Some(ValueNs::Fixity(id)) => 't: {
-                    let data = self.db.fixity_data(id);
-                    let resolver = id.resolver(self.db.upcast());
-                    let id = match resolver.resolve_value_fully(self.db.upcast(), &data.func) {
-                        | Some(ValueNs::Func(id)) => id.into(),
-                        | Some(ValueNs::Ctor(id)) => id.into(),
-                        | _ => break 't self.error(),
+                    let id = match self.fixity_value(id) {
+                        | Some(id) => id,
+                        | None => break 't self.error(),
                     };
 
                     let ty = self.db.value_ty(id);
@@ -105,29 +110,58 @@ impl BodyInferenceContext<'_> {
                     let base_ty = self.infer_expr(*base);
                     let base_ty = self.subst_type(base_ty);
 
-                    if let TyKind::Tuple(tys) = base_ty.lookup(self.db) {
-                        tys[idx]
-                    } else {
-                        unimplemented!("{}", base_ty.display(self.db));
+                    match base_ty.lookup(self.db) {
+                        | TyKind::Tuple(tys) if idx < tys.len() => tys[idx],
+                        | TyKind::Error => self.error(),
+                        | _ => {
+                            self.report(InferenceDiagnostic::NoSuchField {
+                                id: expr,
+                                base: base_ty,
+                                field: field.clone(),
+                            });
+
+                            self.error()
+                        },
                     }
                 } else {
-                    let row_kind = self.lang_type("row-kind");
-                    let type_kind = self.lang_type("type-kind");
-                    let record_type = self.lang_type("record-type");
-                    let kind = TyKind::App(row_kind, type_kind).intern(self.db);
-                    let tail = self.fresh_type_with_kind(kind);
-                    let res = self.fresh_type();
-                    let fields = vec![Field {
-                        name: field.clone(),
-                        ty: res,
-                    }]
-                    .into();
+                    let base_ty = self.infer_expr(*base);
+                    let base_ty = self.subst_type(base_ty);
 
-                    let row = TyKind::Row(fields, Some(tail)).intern(self.db);
-                    let record = TyKind::App(record_type, row).intern(self.db);
+                    if let Some(fields) = self.ctor_fields(base_ty) {
+                        match fields.into_iter().find(|(name, _)| name == field) {
+                            | Some((_, ty)) => ty,
+                            | None => {
+                                self.report(InferenceDiagnostic::NoSuchField {
+                                    id: expr,
+                                    base: base_ty,
+                                    field: field.clone(),
+                                });
+
+                                self.error()
+                            },
+                        }
+                    } else {
+                        let row_kind = self.lang_type("row-kind");
+                        let type_kind = self.lang_type("type-kind");
+                        let record_type = self.lang_type("record-type");
+                        let kind = TyKind::App(row_kind, type_kind).intern(self.db);
+                        let tail = self.fresh_type_with_kind(kind);
+                        let res = self.fresh_type();
+                        let fields = vec![Field {
+                            name: field.clone(),
+                            ty: res,
+                        }]
+                        .into();
+
+                        let row = TyKind::Row(fields, Some(tail)).intern(self.db);
+                        let record = TyKind::App(record_type, row).intern(self.db);
+
+                        if let Err(error) = self.unify(base_ty, record) {
+                            self.report_mismatch(error, record, base_ty, (*base).into());
+                        }
 
-                    self.check_expr(*base, record);
-                    res
+                        res
+                    }
                 }
             },
             | Expr::Tuple { exprs } => {
@@ -149,6 +183,30 @@ impl BodyInferenceContext<'_> {
 
                 TyKind::App(record_type, row).intern(self.db)
             },
+            | Expr::Update { base, fields } => {
+                let base_ty = self.infer_expr(*base);
+                let base_ty = self.subst_type(base_ty);
+                let ctor_fields = self.ctor_fields(base_ty).unwrap_or_default();
+
+                for field in fields {
+                    match ctor_fields.iter().find(|(name, _)| *name == field.name) {
+                        | Some(&(_, ty)) => self.check_expr(field.val, ty),
+                        | None => {
+                            if base_ty.lookup(self.db) != TyKind::Error {
+                                self.report(InferenceDiagnostic::NoSuchField {
+                                    id: field.val,
+                                    base: base_ty,
+                                    field: field.name.clone(),
+                                });
+                            }
+
+                            self.infer_expr(field.val);
+                        },
+                    }
+                }
+
+                base_ty
+            },
             | Expr::Array { exprs } => {
                 let array_type = self.lang_type("array-type");
                 let len = TyKind::Figure(exprs.len() as i128).intern(self.db);
@@ -168,9 +226,10 @@ impl BodyInferenceContext<'_> {
                 let mut ty = ret;
                 let params = pats.iter().map(|&p| self.infer_pat(p)).collect::<Vec<_>>();
 
-                self.clos_ret_type = Some(ret);
+                let outer_ret = self.clos_ret_type.replace(ret);
+
                 self.check_block(stmts, ret, expr.into());
-                self.clos_ret_type = None;
+                self.clos_ret_type = outer_ret;
 
                 for param in params.into_iter().rev() {
                     ty = self.fn_type(param, ty);
@@ -192,8 +251,8 @@ impl BodyInferenceContext<'_> {
                     } else if else_ty == self.lang_type("never-type") {
                         then_ty
                     } else {
-                        if !self.unify_types(then_ty, else_ty) {
-                            self.report_mismatch(then_ty, else_ty, (*else_).into());
+                        if let Err(error) = self.unify(then_ty, else_ty) {
+                            self.report_mismatch(error, then_ty, else_ty, (*else_).into());
                         }
 
                         then_ty
@@ -285,8 +344,8 @@ impl BodyInferenceContext<'_> {
 
                         if ty == self.lang_type("never-type") {
                             *self.breakable.last_mut().unwrap() = Breakable::Loop(unit);
-                        } else if !self.unify_types(ty, unit) {
-                            self.report_mismatch(unit, ty, expr.into());
+                        } else if let Err(error) = self.unify(ty, unit) {
+                            self.report_mismatch(error, unit, ty, expr.into());
                         }
                     }
                 } else {
@@ -338,7 +397,12 @@ impl BodyInferenceContext<'_> {
 
                 self.lang_type("never-type")
             },
-            | e => unimplemented!("{:?}", e),
+            | Expr::Index { base, index } => {
+                self.infer_expr(*base);
+                self.infer_expr(*index);
+                self.report(InferenceDiagnostic::UnsupportedExpr { id: expr });
+                self.error()
+            },
         };
 
         // eprintln!("{:?} :: {}", body[expr], ty.display(self.db));
@@ -351,7 +415,7 @@ impl BodyInferenceContext<'_> {
         if let TypeVarOwner::DefWithBodyId(def) = self.owner {
             let new_resolver = Resolver::for_expr(self.db.upcast(), def, expr);
             let old_resolver = std::mem::replace(&mut self.resolver, new_resolver);
-            let last = stmts.len() - 1;
+            let last = stmts.len().saturating_sub(1);
             let mut diverges = false;
 
             for (i, stmt) in stmts.iter().enumerate() {
@@ -382,6 +446,10 @@ impl BodyInferenceContext<'_> {
                         self.resolver = Resolver::for_expr(self.db.upcast(), def, val);
                         self.check_expr(val, ty);
                     },
+                    | Stmt::Fun { pat, val } => {
+                        self.resolver = Resolver::for_expr(self.db.upcast(), def, val);
+                        self.infer_local_fun(pat, val);
+                    },
                 }
             }
 
@@ -422,8 +490,8 @@ impl BodyInferenceContext<'_> {
                 let infer = self.infer_expr(expr);
                 let infer = self.instantiate(infer, expr.into());
 
-                if !self.unify_types(infer, expected) {
-                    self.report_mismatch(expected, infer, expr.into());
+                if let Err(error) = self.unify(infer, expected) {
+                    self.report_mismatch(error, expected, infer, expr.into());
                 }
             },
             | (Expr::Typed { expr: inner, ty }, _) => self.owner.with_type_map(self.db.upcast(), |type_map| {
@@ -433,12 +501,8 @@ impl BodyInferenceContext<'_> {
 
                 self.check_kind(ty_, kind, *ty);
 
-                if !self.subsume_types(ty_, expected, expr.into()) {
-                    self.report(InferenceDiagnostic::MismatchedType {
-                        id: expr.into(),
-                        expected,
-                        found: ty_,
-                    });
+                if let Err(error) = self.subsume(ty_, expected, expr.into()) {
+                    self.report_mismatch(error, expected, ty_, expr.into());
                 }
 
                 self.check_expr(*inner, ty_);
@@ -447,7 +511,10 @@ impl BodyInferenceContext<'_> {
                 | Some(res) => {
                     let ty = match res {
                         | ValueNs::Local(pat) => self.result.type_of_pat[pat],
-                        | ValueNs::Fixity(_) => unimplemented!(),
+                        | ValueNs::Fixity(id) => match self.fixity_value(id) {
+                            | Some(id) => self.db.value_ty(id),
+                            | None => self.error(),
+                        },
                         | ValueNs::Func(id) => {
                             if self.owner == TypeVarOwner::DefWithBodyId(id.into()) {
                                 self.subst_type(self.result.self_type)
@@ -460,8 +527,8 @@ impl BodyInferenceContext<'_> {
                         | ValueNs::Ctor(id) => self.db.value_ty(id.into()),
                     };
 
-                    if !self.subsume_types(ty, expected, expr.into()) {
-                        self.report_mismatch(expected, ty, expr.into());
+                    if let Err(error) = self.subsume(ty, expected, expr.into()) {
+                        self.report_mismatch(error, expected, ty, expr.into());
                     }
                 },
                 | None => {
@@ -491,8 +558,8 @@ impl BodyInferenceContext<'_> {
                 let base_ty = self.infer_expr(*base);
                 let ret = self.check_app(base_ty, *arg, expr);
 
-                if !self.subsume_types(ret, expected, expr.into()) {
-                    self.report_mismatch(expected, ret, expr.into());
+                if let Err(error) = self.subsume(ret, expected, expr.into()) {
+                    self.report_mismatch(error, expected, ret, expr.into());
                 }
             },
             | (Expr::Tuple { exprs }, TyKind::Tuple(tys)) if exprs.len() == tys.len() => {
@@ -524,30 +591,22 @@ impl BodyInferenceContext<'_> {
                     self.block_ret_type = None;
                     self.block_break_type = None;
 
-                    if !self.unify_types(f_ty, ty) {
-                        self.report_mismatch(f_ty, ty, expr.into());
+                    if let Err(error) = self.unify(f_ty, ty) {
+                        self.report_mismatch(error, f_ty, ty, expr.into());
                     }
                 } else {
                     let infer = self.infer_expr(expr);
 
-                    if !self.subsume_types(infer, expected, expr.into()) {
-                        self.report(InferenceDiagnostic::MismatchedType {
-                            id: expr.into(),
-                            expected,
-                            found: infer,
-                        });
+                    if let Err(error) = self.subsume(infer, expected, expr.into()) {
+                        self.report_mismatch(error, expected, infer, expr.into());
                     }
                 }
             },
             | (_, _) => {
                 let infer = self.infer_expr(expr);
 
-                if !self.subsume_types(infer, expected, expr.into()) {
-                    self.report(InferenceDiagnostic::MismatchedType {
-                        id: expr.into(),
-                        expected,
-                        found: infer,
-                    });
+                if let Err(error) = self.subsume(infer, expected, expr.into()) {
+                    self.report_mismatch(error, expected, infer, expr.into());
                 }
             },
         }
@@ -561,8 +620,8 @@ impl BodyInferenceContext<'_> {
                 | TyKind::App(f, a) => {
                     let func_ty = self.lang_type("fn-type");
 
-                    if !self.unify_types(f, func_ty) {
-                        self.report_mismatch(func_ty, f, expr.into());
+                    if let Err(error) = self.unify(f, func_ty) {
+                        self.report_mismatch(error, func_ty, f, expr.into());
                     }
 
                     self.check_expr(arg, a);
@@ -573,8 +632,8 @@ impl BodyInferenceContext<'_> {
                     let ret = self.fresh_type();
                     let func_ty = self.fn_type(param, ret);
 
-                    if !self.unify_types(base_ty, func_ty) {
-                        self.report_mismatch(base_ty, func_ty, expr.into());
+                    if let Err(error) = self.unify(base_ty, func_ty) {
+                        self.report_mismatch(error, base_ty, func_ty, expr.into());
                     }
 
                     ret
@@ -595,8 +654,8 @@ impl BodyInferenceContext<'_> {
                 let ret = self.fresh_type();
                 let func_ty = self.fn_type(arg, ret);
 
-                if !self.unify_types(base_ty, func_ty) {
-                    self.report_mismatch(base_ty, func_ty, expr.into());
+                if let Err(error) = self.unify(base_ty, func_ty) {
+                    self.report_mismatch(error, base_ty, func_ty, expr.into());
                 }
 
                 ret
@@ -604,11 +663,80 @@ impl BodyInferenceContext<'_> {
         }
     }
 
+    /// Infers the type of a local function and generalizes it using `generalize_local`.
+    /// Recursive uses inside of its own body are monomorphic.
+    fn infer_local_fun(&mut self, pat: PatId, val: ExprId) {
+        let start = self.subst.next_unknown();
+        let ty = self.infer_pat(pat);
+
+        self.check_expr(val, ty);
+
+        let ty = self.generalize_local(ty, start);
+
+        self.result.type_of_pat.insert(pat, ty);
+    }
+
+    /// The named fields of a type with a single constructor that was declared with record syntax.
+    pub(crate) fn ctor_fields(&self, ty: Ty) -> Option<Vec<(Name, Ty)>> {
+        let mut base = ty;
+        let mut args = Vec::new();
+
+        while let TyKind::App(a, b) = base.lookup(self.db) {
+            args.push(b);
+            base = a;
+        }
+
+        args.reverse();
+
+        let id = match base.lookup(self.db) {
+            | TyKind::Ctor(id) => id,
+            | _ => return None,
+        };
+
+        let data = self.db.type_ctor_data(id);
+
+        if data.ctors.len() != 1 {
+            return None;
+        }
+
+        let (local_id, ctor) = data.ctors.iter().next()?;
+
+        if ctor.fields.is_empty() {
+            return None;
+        }
+
+        let lower = self.db.ctor_ty(CtorId { local_id, parent: id });
+        let fields = ctor
+            .fields
+            .iter()
+            .zip(ctor.types.iter())
+            .map(|(name, &t)| {
+                let ty = args.iter().fold(lower.types[t], |r, a| r.replace_var(self.db, *a));
+
+                (name.clone(), ty)
+            })
+            .collect();
+
+        Some(fields)
+    }
+
+    /// The function or constructor an operator refers to.
+    fn fixity_value(&self, id: FixityId) -> Option<ValueTyDefId> {
+        let data = self.db.fixity_data(id);
+        let resolver = id.resolver(self.db.upcast());
+
+        match resolver.resolve_value_fully(self.db.upcast(), &data.func)? {
+            | ValueNs::Func(id) => Some(id.into()),
+            | ValueNs::Ctor(id) => Some(id.into()),
+            | _ => None,
+        }
+    }
+
     pub fn check_block(&mut self, stmts: &[Stmt], expected: Ty, expr: ExprId) {
         if let TypeVarOwner::DefWithBodyId(def) = self.owner {
             let new_resolver = Resolver::for_expr(self.db.upcast(), def, expr);
             let old_resolver = std::mem::replace(&mut self.resolver, new_resolver);
-            let last = stmts.len() - 1;
+            let last = stmts.len().saturating_sub(1);
 
             for (i, stmt) in stmts.iter().enumerate() {
                 match *stmt {
@@ -635,13 +763,17 @@ impl BodyInferenceContext<'_> {
                         self.resolver = Resolver::for_expr(self.db.upcast(), def, val);
                         self.check_expr(val, ty);
                     },
+                    | Stmt::Fun { pat, val } => {
+                        self.resolver = Resolver::for_expr(self.db.upcast(), def, val);
+                        self.infer_local_fun(pat, val);
+                    },
                 }
             }
 
             let unit = self.unit();
 
-            if !self.unify_types(expected, unit) {
-                self.report_mismatch(expected, unit, expr.into());
+            if let Err(error) = self.unify(expected, unit) {
+                self.report_mismatch(error, expected, unit, expr.into());
             }
 
             self.resolver = old_resolver;
@@ -1,5 +1,4 @@
 use super::{InferenceContext, InferenceDiagnostic};
-use crate::display::HirDisplay;
 use crate::ty::*;
 use hir_def::id::TypeVarOwner;
 use hir_def::type_ref::LocalTypeRefId;
@@ -38,7 +37,9 @@ impl InferenceContext<'_> {
                 kind
             },
             | TyKind::Ctor(id) => {
-                if TypeVarOwner::TypedDefId(id.into()) == self.owner {
+                if let Some(&kind) = self.group_kinds.get(&id) {
+                    kind
+                } else if TypeVarOwner::TypedDefId(id.into()) == self.owner {
                     self.result.self_type
                 } else {
                     self.db.kind_for_ctor(id)
@@ -63,7 +64,6 @@ impl InferenceContext<'_> {
                 self.pop_var_kind();
                 self.fn_type(kind, inner_kind)
             },
-            | _ => unimplemented!("{}", ty.display(self.db)),
         }
     }
 
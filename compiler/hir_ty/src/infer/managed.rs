@@ -0,0 +1,145 @@
+//! Checks that the type variables of polymorphic functions are not instantiated with managed types.
+//!
+//! Values of a type variable are neither reference counted nor dropped, so a polymorphic function
+//! that copies or discards such a value would free a reference counted box twice or never run the
+//! drop method of a value. A type argument that contains a reference counted box or has a drop
+//! instance is reported instead. Intrinsics and methods that resolve to an instance are lowered
+//! for the types they are used at, so their type arguments are not checked.
+
+use super::{InferenceDiagnostic, InferenceResult};
+use crate::db::HirDatabase;
+use crate::ty::{Constraint, Ty, TyKind};
+use hir_def::body::Body;
+use hir_def::expr::{Expr, ExprId};
+use hir_def::id::{ClassId, CtorId, DefWithBodyId, HasModule, TypeCtorId};
+use hir_def::resolver::{HasResolver, Resolver, ValueNs};
+use rustc_hash::FxHashMap;
+
+pub(super) fn check(db: &dyn HirDatabase, def: DefWithBodyId, result: &mut InferenceResult) {
+    let lib = def.module(db.upcast()).lib;
+    let body = db.body(def);
+    let mut checker = ManagedChecker {
+        db,
+        drop_class: db.lang_item(lib, "drop-class".into()).and_then(|it| it.as_class()),
+        rc_type: db.lang_item(lib, "rc-type".into()).and_then(|it| it.as_type_ctor()),
+        managed: FxHashMap::default(),
+    };
+
+    let mut exprs = result.instances.keys().copied().collect::<Vec<_>>();
+
+    exprs.sort_by_key(|e| e.into_raw());
+
+    for expr in exprs {
+        if !checker.is_checked(def, &body, result, expr) {
+            continue;
+        }
+
+        let managed = result.instances[&expr].iter().copied().find(|&ty| checker.is_managed(ty));
+
+        if let Some(ty) = managed {
+            result.diagnostics.push(InferenceDiagnostic::ManagedTypeArgument { id: expr, ty });
+        }
+    }
+}
+
+struct ManagedChecker<'a> {
+    db: &'a dyn HirDatabase,
+    drop_class: Option<ClassId>,
+    rc_type: Option<TypeCtorId>,
+    managed: FxHashMap<Ty, bool>,
+}
+
+impl ManagedChecker<'_> {
+    /// Whether the type arguments of `expr` are passed to a polymorphic body.
+    fn is_checked(&self, def: DefWithBodyId, body: &Body, result: &InferenceResult, expr: ExprId) -> bool {
+        let path = match &body[expr] {
+            | Expr::Path { path } => path,
+            | Expr::Infix { op, .. } => op,
+            | _ => return false,
+        };
+
+        if result.methods.contains_key(&expr) {
+            return false;
+        }
+
+        let resolver = Resolver::for_expr(self.db.upcast(), def, expr);
+        let func = match resolver.resolve_value_fully(self.db.upcast(), path) {
+            | Some(ValueNs::Local(_)) => return true,
+            | Some(ValueNs::Func(id)) => id,
+            | Some(ValueNs::Fixity(id)) => {
+                let data = self.db.fixity_data(id);
+                let resolver = id.resolver(self.db.upcast());
+
+                match resolver.resolve_value_fully(self.db.upcast(), &data.func) {
+                    | Some(ValueNs::Func(id)) => id,
+                    | _ => return false,
+                }
+            },
+            | _ => return false,
+        };
+
+        !self.db.attrs(func.into()).by_key("intrinsic").exists()
+    }
+
+    fn is_managed(&mut self, ty: Ty) -> bool {
+        if let Some(&res) = self.managed.get(&ty) {
+            return res;
+        }
+
+        // recursive types are only managed if some other part of them is
+        self.managed.insert(ty, false);
+
+        let res = self.is_managed_impl(ty);
+
+        self.managed.insert(ty, res);
+        res
+    }
+
+    fn is_managed_impl(&mut self, ty: Ty) -> bool {
+        let mut base = ty;
+        let mut args = Vec::new();
+
+        while let TyKind::App(a, b) = base.lookup(self.db) {
+            args.push(b);
+            base = a;
+        }
+
+        args.reverse();
+
+        match base.lookup(self.db) {
+            | TyKind::Tuple(ts) => ts.iter().any(|&t| self.is_managed(t)),
+            | TyKind::Ctor(id) => {
+                if Some(id) == self.rc_type {
+                    return true;
+                }
+
+                if let Some(class) = self.drop_class {
+                    if self.db.solve_constraint(Constraint::new(class, [ty])).is_some() {
+                        return true;
+                    }
+                }
+
+                if self.db.attrs(id.into()).by_key("repr").exists() {
+                    return false;
+                }
+
+                let data = self.db.type_ctor_data(id);
+
+                for (local_id, ctor) in data.ctors.iter() {
+                    let lower = self.db.ctor_ty(CtorId { local_id, parent: id });
+
+                    for &t in ctor.types.iter() {
+                        let t = args.iter().fold(lower.types[t], |r, a| r.replace_var(self.db, *a));
+
+                        if self.is_managed(t) {
+                            return true;
+                        }
+                    }
+                }
+
+                false
+            },
+            | _ => false,
+        }
+    }
+}
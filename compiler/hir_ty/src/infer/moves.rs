@@ -0,0 +1,384 @@
+//! Checks that values with a drop instance are not used after they have been moved.
+//!
+//! A local is moved out of when it is used by value, either as a whole or by projecting
+//! out a field whose type needs to be dropped. Any later use of the same local, or of
+//! an overlapping field, is reported.
+
+use super::{InferenceDiagnostic, InferenceResult};
+use crate::db::HirDatabase;
+use crate::ty::{Constraint, Ty, TyKind};
+use hir_def::body::Body;
+use hir_def::expr::{Expr, ExprId, Stmt};
+use hir_def::id::{ClassId, CtorId, DefWithBodyId, HasModule};
+use hir_def::name::Name;
+use hir_def::pat::{Pat, PatId};
+use hir_def::resolver::{Resolver, ValueNs};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::Arc;
+
+pub(super) fn check(db: &dyn HirDatabase, def: DefWithBodyId, result: &mut InferenceResult) {
+    let lib = def.module(db.upcast()).lib;
+    let drop_class = match db.lang_item(lib, "drop-class".into()).and_then(|it| it.as_class()) {
+        | Some(class) => class,
+        | None => return,
+    };
+
+    let body = db.body(def);
+    let mut checker = MoveChecker {
+        db,
+        def,
+        body: body.clone(),
+        result,
+        drop_class,
+        has_drop: FxHashMap::default(),
+        moved: Moves::default(),
+        breaks: Vec::new(),
+        nexts: Vec::new(),
+        diverges: false,
+        reported: FxHashSet::default(),
+        diagnostics: Vec::new(),
+    };
+
+    checker.walk_expr(body.body_expr());
+
+    let diagnostics = checker.diagnostics;
+
+    result.diagnostics.extend(diagnostics);
+}
+
+/// A local and the fields projected out of it.
+type Place = (PatId, Vec<Name>);
+
+/// The places that have been moved out of, and where they were moved.
+type Moves = FxHashMap<Place, ExprId>;
+
+struct MoveChecker<'a> {
+    db: &'a dyn HirDatabase,
+    def: DefWithBodyId,
+    body: Arc<Body>,
+    result: &'a InferenceResult,
+    drop_class: ClassId,
+    has_drop: FxHashMap<Ty, bool>,
+    moved: Moves,
+    /// The moves at every `break` of the innermost loop.
+    breaks: Vec<Moves>,
+    /// The moves at every `next` of the innermost loop.
+    nexts: Vec<Moves>,
+    /// Whether the current expression can not be reached.
+    diverges: bool,
+    reported: FxHashSet<ExprId>,
+    diagnostics: Vec<InferenceDiagnostic>,
+}
+
+impl MoveChecker<'_> {
+    fn walk_expr(&mut self, expr: ExprId) {
+        let body = self.body.clone();
+
+        match &body[expr] {
+            | Expr::Path { .. } | Expr::Field { .. } if self.place(expr).is_some() => {
+                let place = self.place(expr).unwrap();
+
+                self.check_use(expr, &place);
+
+                if self.has_drop(self.result.type_of_expr[expr]) {
+                    self.moved.entry(place).or_insert(expr);
+                }
+            },
+            | Expr::Index { base, index } => {
+                match self.place(*base) {
+                    | Some(place) => {
+                        self.check_use(*base, &place);
+
+                        // elements can not be moved out one by one, so the whole array is moved instead
+                        if self.has_drop(self.result.type_of_expr[expr]) {
+                            self.moved.entry(place).or_insert(expr);
+                        }
+                    },
+                    | None => self.walk_expr(*base),
+                }
+
+                self.walk_expr(*index);
+            },
+            | Expr::Do { stmts } => self.walk_stmts(stmts),
+            | Expr::Clos { pats, stmts } => {
+                // a closure body does not run now, so control flow inside of it does not leak out
+                let diverges = self.diverges;
+                let breaks = std::mem::take(&mut self.breaks);
+                let nexts = std::mem::take(&mut self.nexts);
+
+                pats.iter().for_each(|&pat| self.reinit(pat));
+                self.walk_stmts(stmts);
+                self.diverges = diverges;
+                self.breaks = breaks;
+                self.nexts = nexts;
+            },
+            | Expr::If { cond, then, else_, .. } => {
+                self.walk_expr(*cond);
+
+                let before = self.moved.clone();
+                let then = self.branch(*then);
+
+                self.moved = before;
+
+                let else_ = match else_ {
+                    | Some(else_) => self.branch(*else_),
+                    | None => Some(self.moved.clone()),
+                };
+
+                self.merge(vec![then, else_]);
+            },
+            | Expr::Case { pred, arms } => {
+                self.walk_expr(*pred);
+
+                let before = self.moved.clone();
+                let mut states = Vec::with_capacity(arms.len());
+
+                for arm in arms {
+                    self.moved = before.clone();
+                    self.reinit(arm.pat);
+
+                    if let Some(guard) = arm.guard {
+                        self.walk_expr(guard);
+                    }
+
+                    states.push(self.branch(arm.expr));
+                }
+
+                self.merge(states);
+            },
+            | Expr::While { cond, body, .. } => {
+                let breaks = std::mem::take(&mut self.breaks);
+                let nexts = std::mem::take(&mut self.nexts);
+                let mut exits = Vec::new();
+
+                // the second iteration sees the moves made by the first one
+                for _ in 0..2 {
+                    self.walk_expr(*cond);
+                    exits.push(Some(self.moved.clone()));
+                    self.walk_expr(*body);
+                    self.continue_loop();
+                }
+
+                exits.extend(std::mem::replace(&mut self.breaks, breaks).into_iter().map(Some));
+                self.nexts = nexts;
+                self.merge(exits);
+            },
+            | Expr::Loop { body } => {
+                let breaks = std::mem::take(&mut self.breaks);
+                let nexts = std::mem::take(&mut self.nexts);
+
+                for _ in 0..2 {
+                    self.walk_expr(*body);
+                    self.continue_loop();
+                }
+
+                let exits = std::mem::replace(&mut self.breaks, breaks);
+
+                self.nexts = nexts;
+                self.merge(exits.into_iter().map(Some).collect());
+            },
+            | Expr::Break { expr } => {
+                if let Some(expr) = expr {
+                    self.walk_expr(*expr);
+                }
+
+                self.breaks.push(self.moved.clone());
+                self.diverges = true;
+            },
+            | Expr::Next { expr } => {
+                if let Some(expr) = expr {
+                    self.walk_expr(*expr);
+                }
+
+                self.nexts.push(self.moved.clone());
+                self.diverges = true;
+            },
+            | Expr::Return { expr } => {
+                if let Some(expr) = expr {
+                    self.walk_expr(*expr);
+                }
+
+                self.diverges = true;
+            },
+            | e => {
+                let mut children = Vec::new();
+
+                e.walk(|e| children.push(e));
+                children.into_iter().for_each(|e| self.walk_expr(e));
+            },
+        }
+    }
+
+    fn walk_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            match *stmt {
+                | Stmt::Let { pat, val } | Stmt::Bind { pat, val } => {
+                    self.walk_expr(val);
+                    self.reinit(pat);
+                },
+                | Stmt::Fun { pat, val } => {
+                    self.reinit(pat);
+                    self.walk_expr(val);
+                },
+                | Stmt::Expr { expr } => self.walk_expr(expr),
+            }
+        }
+    }
+
+    /// Walks one of several branches, returning its moves if the end of it can be reached.
+    fn branch(&mut self, expr: ExprId) -> Option<Moves> {
+        self.diverges = false;
+        self.walk_expr(expr);
+
+        if self.diverges {
+            None
+        } else {
+            Some(std::mem::take(&mut self.moved))
+        }
+    }
+
+    fn merge(&mut self, states: Vec<Option<Moves>>) {
+        let mut moved = Moves::default();
+        let mut diverges = true;
+
+        for state in states.into_iter().flatten() {
+            diverges = false;
+
+            for (place, at) in state {
+                moved.entry(place).or_insert(at);
+            }
+        }
+
+        self.moved = moved;
+        self.diverges = diverges;
+    }
+
+    /// Starts the next iteration of a loop from the end of the body and every `next`.
+    fn continue_loop(&mut self) {
+        let nexts = std::mem::take(&mut self.nexts);
+        let end = if self.diverges {
+            None
+        } else {
+            Some(std::mem::take(&mut self.moved))
+        };
+
+        self.merge(nexts.into_iter().map(Some).chain(std::iter::once(end)).collect());
+        self.diverges = false;
+    }
+
+    /// The locals bound by `pat` hold new values, so they are no longer moved.
+    fn reinit(&mut self, pat: PatId) {
+        let mut binders = FxHashSet::default();
+        let mut stack = vec![pat];
+
+        while let Some(pat) = stack.pop() {
+            if let Pat::Bind { .. } = self.body[pat] {
+                binders.insert(pat);
+            }
+
+            self.body[pat].walk(|p| stack.push(p));
+        }
+
+        self.moved.retain(|(local, _), _| !binders.contains(local));
+    }
+
+    fn place(&self, expr: ExprId) -> Option<Place> {
+        match &self.body[expr] {
+            | Expr::Path { path } => {
+                let resolver = Resolver::for_expr(self.db.upcast(), self.def, expr);
+
+                match resolver.resolve_value_fully(self.db.upcast(), path)? {
+                    | ValueNs::Local(pat) => Some((pat, Vec::new())),
+                    | _ => None,
+                }
+            },
+            | Expr::Field { base, field } => {
+                let (local, mut fields) = self.place(*base)?;
+
+                fields.push(field.clone());
+                Some((local, fields))
+            },
+            | _ => None,
+        }
+    }
+
+    fn check_use(&mut self, expr: ExprId, (local, fields): &Place) {
+        let moved = self
+            .moved
+            .iter()
+            .find(|((l, f), _)| l == local && (f.starts_with(fields) || fields.starts_with(f)))
+            .map(|(_, &at)| at);
+
+        if let Some(moved) = moved {
+            if self.reported.insert(expr) {
+                let name = match &self.body[*local] {
+                    | Pat::Bind { name, .. } => name.clone(),
+                    | _ => return,
+                };
+
+                self.diagnostics
+                    .push(InferenceDiagnostic::UseOfMovedValue { id: expr, moved, name });
+            }
+        }
+    }
+
+    fn has_drop(&mut self, ty: Ty) -> bool {
+        if let Some(&res) = self.has_drop.get(&ty) {
+            return res;
+        }
+
+        // recursive types only need a drop if some other part of them does
+        self.has_drop.insert(ty, false);
+
+        let res = self.has_drop_impl(ty);
+
+        self.has_drop.insert(ty, res);
+        res
+    }
+
+    fn has_drop_impl(&mut self, ty: Ty) -> bool {
+        let mut base = ty;
+        let mut args = Vec::new();
+
+        while let TyKind::App(a, b) = base.lookup(self.db) {
+            args.push(b);
+            base = a;
+        }
+
+        args.reverse();
+
+        match base.lookup(self.db) {
+            | TyKind::Tuple(ts) => ts.iter().any(|&t| self.has_drop(t)),
+            | TyKind::Ctor(id) => {
+                if self
+                    .db
+                    .solve_constraint(Constraint::new(self.drop_class, [ty]))
+                    .is_some()
+                {
+                    return true;
+                }
+
+                if self.db.attrs(id.into()).by_key("repr").exists() {
+                    return false;
+                }
+
+                let data = self.db.type_ctor_data(id);
+
+                for (local_id, ctor) in data.ctors.iter() {
+                    let lower = self.db.ctor_ty(CtorId { local_id, parent: id });
+
+                    for &t in ctor.types.iter() {
+                        let t = args.iter().fold(lower.types[t], |r, a| r.replace_var(self.db, *a));
+
+                        if self.has_drop(t) {
+                            return true;
+                        }
+                    }
+                }
+
+                false
+            },
+            | _ => false,
+        }
+    }
+}
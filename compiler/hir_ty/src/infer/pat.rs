@@ -95,18 +95,19 @@ impl BodyInferenceContext<'_> {
                     ty
                 },
                 | Literal::Float(_) => {
-                    let decimal = self.lang_class("decimal-class");
-                    let ty = self.fresh_type();
+                    self.report(InferenceDiagnostic::FloatPattern { id: pat });
+                    self.error()
+                },
+                | Literal::Char(_) => self.lang_type("char-type"),
+                | Literal::String(_) => {
+                    let lib = self.owner.module(self.db.upcast()).lib;
 
-                    self.constrain(pat.into(), Constraint {
-                        class: decimal,
-                        types: vec![ty].into(),
-                    });
+                    if self.db.lang_item(lib, "str-eq".into()).is_none() {
+                        self.report(InferenceDiagnostic::MissingLangItem { id: pat, name: "str-eq" });
+                    }
 
-                    ty
+                    self.lang_type("str-type")
                 },
-                | Literal::Char(_) => self.lang_type("char-type"),
-                | Literal::String(_) => self.lang_type("str-type"),
             },
         };
 
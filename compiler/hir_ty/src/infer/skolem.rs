@@ -3,21 +3,29 @@ use crate::ty::*;
 
 impl InferenceContext<'_> {
     pub fn skolemize(&mut self, kind: Ty, inner: Ty) -> Ty {
-        self.skolemize_impl(kind, inner, DebruijnIndex::INNER)
+        let scope = self.fresh_skolem_scope();
+
+        self.skolemize_in(kind, inner, scope)
+    }
+
+    pub fn skolemize_in(&mut self, kind: Ty, inner: Ty, scope: SkolemScope) -> Ty {
+        self.skolemize_impl(kind, inner, DebruijnIndex::INNER, scope)
     }
 
     pub fn unskolemize(&mut self, ty: Ty) -> Ty {
         ty.everywhere(self.db, &mut |ty| match ty.lookup(self.db) {
-            | TyKind::Skolem(sk, _) => sk.to_ty(self.db),
+            | TyKind::Skolem(sk, _) => sk.var.to_ty(self.db),
             | _ => ty,
         })
     }
 
-    fn skolemize_impl(&mut self, kind: Ty, inner: Ty, debruijn: DebruijnIndex) -> Ty {
+    fn skolemize_impl(&mut self, kind: Ty, inner: Ty, debruijn: DebruijnIndex, scope: SkolemScope) -> Ty {
         match inner.lookup(self.db) {
-            | TyKind::TypeVar(var) if var.debruijn() == debruijn => TyKind::Skolem(var, kind).intern(self.db),
+            | TyKind::TypeVar(var) if var.debruijn() == debruijn => {
+                TyKind::Skolem(Skolem { var, scope }, kind).intern(self.db)
+            },
             | TyKind::Skolem(sk, k) => {
-                let k = self.skolemize_impl(kind, k, debruijn);
+                let k = self.skolemize_impl(kind, k, debruijn, scope);
 
                 TyKind::Skolem(sk, k).intern(self.db)
             },
@@ -26,22 +34,25 @@ impl InferenceContext<'_> {
                     .iter()
                     .map(|f| Field {
                         name: f.name.clone(),
-                        ty: self.skolemize_impl(kind, f.ty, debruijn),
+                        ty: self.skolemize_impl(kind, f.ty, debruijn, scope),
                     })
                     .collect();
 
-                let tail = tail.map(|t| self.skolemize_impl(kind, t, debruijn));
+                let tail = tail.map(|t| self.skolemize_impl(kind, t, debruijn, scope));
 
                 TyKind::Row(fields, tail).intern(self.db)
             },
             | TyKind::Tuple(tys) => {
-                let tys = tys.iter().map(|&t| self.skolemize_impl(kind, t, debruijn)).collect();
+                let tys = tys
+                    .iter()
+                    .map(|&t| self.skolemize_impl(kind, t, debruijn, scope))
+                    .collect();
 
                 TyKind::Tuple(tys).intern(self.db)
             },
             | TyKind::App(a, b) => {
-                let a = self.skolemize_impl(kind, a, debruijn);
-                let b = self.skolemize_impl(kind, b, debruijn);
+                let a = self.skolemize_impl(kind, a, debruijn, scope);
+                let b = self.skolemize_impl(kind, b, debruijn, scope);
 
                 TyKind::App(a, b).intern(self.db)
             },
@@ -51,17 +62,17 @@ impl InferenceContext<'_> {
                     types: ctnt
                         .types
                         .iter()
-                        .map(|&t| self.skolemize_impl(kind, t, debruijn))
+                        .map(|&t| self.skolemize_impl(kind, t, debruijn, scope))
                         .collect(),
                 };
 
-                let ty = self.skolemize_impl(kind, ty, debruijn);
+                let ty = self.skolemize_impl(kind, ty, debruijn, scope);
 
                 TyKind::Ctnt(ctnt, ty).intern(self.db)
             },
             | TyKind::ForAll(k, inner) => {
-                let k = self.skolemize_impl(kind, k, debruijn);
-                let inner = self.skolemize_impl(kind, inner, debruijn.shifted_in());
+                let k = self.skolemize_impl(kind, k, debruijn, scope);
+                let inner = self.skolemize_impl(kind, inner, debruijn.shifted_in(), scope);
 
                 TyKind::ForAll(k, inner).intern(self.db)
             },
@@ -1,3 +1,4 @@
+use super::unify::UnifyError;
 use super::{ExprOrPatId, InferenceContext};
 use crate::display::HirDisplay;
 use crate::ty::*;
@@ -9,31 +10,32 @@ enum SubsumeMode {
 }
 
 impl InferenceContext<'_> {
-    pub fn subsume_types(&mut self, t1: Ty, t2: Ty, origin: ExprOrPatId) -> bool {
+    pub fn subsume(&mut self, t1: Ty, t2: Ty, origin: ExprOrPatId) -> Result<(), UnifyError> {
         let t1 = self.subst_type(t1);
         let t2 = self.subst_type(t2);
         let never = self.lang_type("never-type");
 
         if t1 == never {
-            return true;
+            return Ok(());
         }
 
         self.subsume_types_impl(t1, t2, origin, SubsumeMode::Ctnt)
     }
 
-    fn subsume_types_impl(&mut self, t1: Ty, t2: Ty, origin: ExprOrPatId, mode: SubsumeMode) -> bool {
+    fn subsume_types_impl(&mut self, t1: Ty, t2: Ty, origin: ExprOrPatId, mode: SubsumeMode) -> Result<(), UnifyError> {
         match (t1.lookup(self.db), t2.lookup(self.db)) {
+            // skolemize before instantiating, so the unknowns used for instantiation may refer to the skolems
+            | (_, TyKind::ForAll(kind, inner)) => {
+                let sk = self.skolemize(kind, inner);
+
+                self.subsume_types_impl(t1, sk, origin, mode)
+            },
             | (TyKind::ForAll(kind, inner), _) => {
                 let var = self.fresh_type_with_kind(kind);
                 let repl = inner.replace_var(self.db, var);
 
                 self.subsume_types_impl(repl, t2, origin, mode)
             },
-            | (_, TyKind::ForAll(kind, inner)) => {
-                let sk = self.skolemize(kind, inner);
-
-                self.subsume_types_impl(t1, sk, origin, mode)
-            },
             | (TyKind::Ctnt(ctnt, inner), _) if mode == SubsumeMode::Ctnt => {
                 self.constrain(origin, ctnt);
                 self.subsume_types_impl(inner, t2, origin, mode)
@@ -48,8 +50,9 @@ impl InferenceContext<'_> {
 
                 match (t1.match_ctor(self.db, func_id), t2.match_ctor(self.db, func_id)) {
                     | (Some([a1, r1]), Some([a2, r2])) => {
-                        return self.subsume_types_impl(a2, a1, origin, SubsumeMode::NoCtnt)
-                            && self.subsume_types_impl(r1, r2, origin, SubsumeMode::NoCtnt);
+                        self.subsume_types_impl(a2, a1, origin, SubsumeMode::NoCtnt)?;
+
+                        return self.subsume_types_impl(r1, r2, origin, SubsumeMode::NoCtnt);
                     },
                     | (_, _) => {},
                 }
@@ -66,7 +69,7 @@ impl InferenceContext<'_> {
                         if let None = r1 {
                             for (f1, f2) in ts2.iter().zip(ts1.iter()) {
                                 if f1.name != f2.name {
-                                    return false;
+                                    return Err(UnifyError::Mismatch);
                                 }
                             }
                         }
@@ -74,24 +77,22 @@ impl InferenceContext<'_> {
                         if let None = r2 {
                             for (f1, f2) in ts1.iter().zip(ts2.iter()) {
                                 if f1.name != f2.name {
-                                    return false;
+                                    return Err(UnifyError::Mismatch);
                                 }
                             }
                         }
 
-                        if !common.into_iter().all(std::convert::identity) {
-                            return false;
-                        }
+                        common.into_iter().collect::<Result<(), _>>()?;
 
                         let r1 = TyKind::Row(ts1, r1).intern(self.db);
                         let r2 = TyKind::Row(ts2, r2).intern(self.db);
 
-                        return self.unify_types(r1, r2);
+                        return self.unify(r1, r2);
                     },
                     | (_, _) => {},
                 }
 
-                self.unify_types(t1, t2)
+                self.unify(t1, t2)
             },
         }
     }
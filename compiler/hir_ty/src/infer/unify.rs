@@ -1,7 +1,7 @@
 use super::{ExprOrPatId, InferenceContext};
 use crate::display::HirDisplay;
 use crate::ty::*;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct UnkLevel(Vec<Unknown>);
@@ -11,12 +11,27 @@ pub(super) struct Substitution {
     next_unknown: u32,
     tys: FxHashMap<Unknown, Ty>,
     unsolved: FxHashMap<Unknown, (UnkLevel, Ty)>,
+    /// For every skolem scope, the first unknown that was created inside of it.
+    skolem_scopes: Vec<u32>,
+}
+
+/// Why two types could not be unified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnifyError {
+    Mismatch,
+    /// A skolem would escape the scope of its quantifier.
+    SkolemEscape(Skolem),
 }
 
 impl Substitution {
     pub fn unsolved(&self, u: Unknown) -> &(UnkLevel, Ty) {
         &self.unsolved[&u]
     }
+
+    /// The unknown that will be created next, unknowns below it already exist.
+    pub fn next_unknown(&self) -> u32 {
+        self.next_unknown
+    }
 }
 
 impl InferenceContext<'_> {
@@ -53,6 +68,36 @@ impl InferenceContext<'_> {
         self.subst.tys.insert(u, ty);
     }
 
+    pub fn fresh_skolem_scope(&mut self) -> SkolemScope {
+        let scope = SkolemScope::from_raw(self.subst.skolem_scopes.len() as u32);
+
+        self.subst.skolem_scopes.push(self.subst.next_unknown);
+        scope
+    }
+
+    /// Solves `u` to `ty`, unless `ty` contains a skolem that was introduced after `u` was created.
+    /// Such a skolem would escape the scope of its quantifier.
+    fn solve_type_checked(&mut self, u: Unknown, ty: Ty) -> Result<(), UnifyError> {
+        let scopes = &self.subst.skolem_scopes;
+        let mut escaped = None;
+
+        ty.everything(self.db, &mut |t| {
+            if let TyKind::Skolem(sk, _) = t.lookup(self.db) {
+                match scopes.get(sk.scope.raw() as usize) {
+                    | Some(&first) if u.raw() < first => escaped = Some(sk),
+                    | _ => {},
+                }
+            }
+        });
+
+        if let Some(sk) = escaped {
+            Err(UnifyError::SkolemEscape(sk))
+        } else {
+            self.solve_type(u, ty);
+            Ok(())
+        }
+    }
+
     pub fn subst_type(&self, ty: Ty) -> Ty {
         ty.everywhere(self.db, &mut |ty| match ty.lookup(self.db) {
             | TyKind::Unknown(u) => match self.subst.tys.get(&u) {
@@ -146,71 +191,137 @@ impl InferenceContext<'_> {
         self.subst_type(ty)
     }
 
+    /// Generalizes the type of a local function whose unknowns were all created after `start`.
+    ///
+    /// Only unknowns that do not occur in the environment are quantified over. The environment
+    /// consists of every unknown created before `start` and every unknown in a pending constraint,
+    /// so constrained type variables of local functions stay monomorphic and are solved by the
+    /// enclosing function instead.
+    pub fn generalize_local(&mut self, ty: Ty, start: u32) -> Ty {
+        let ty = self.subst_type(ty);
+        let mut env = FxHashSet::default();
+        let mut find_env = |ty: Ty| {
+            if let TyKind::Unknown(u) = ty.lookup(self.db) {
+                env.insert(u);
+            }
+        };
+
+        for raw in 0..start {
+            self.subst_type(Unknown::from_raw(raw).to_ty(self.db))
+                .everything(self.db, &mut find_env);
+        }
+
+        for (ctnt, _, _) in &self.constraints {
+            for &ty in ctnt.types.iter() {
+                self.subst_type(ty).everything(self.db, &mut find_env);
+            }
+        }
+
+        let mut unknowns = Vec::new();
+
+        ty.everything(self.db, &mut |ty| match ty.lookup(self.db) {
+            | TyKind::Unknown(u) if u.raw() >= start && !env.contains(&u) && !unknowns.contains(&u) => {
+                unknowns.push(u);
+            },
+            | _ => {},
+        });
+
+        for (i, &u) in unknowns.iter().enumerate() {
+            self.solve_type(u, TypeVar::new(DebruijnIndex::new(i as u32)).to_ty(self.db));
+        }
+
+        let mut ty = self.subst_type(ty);
+
+        for u in unknowns {
+            ty = TyKind::ForAll(self.subst.unsolved(u).1, ty).intern(self.db);
+        }
+
+        ty
+    }
+
     pub fn unify_types(&mut self, t1: Ty, t2: Ty) -> bool {
+        self.unify(t1, t2).is_ok()
+    }
+
+    pub fn unify(&mut self, t1: Ty, t2: Ty) -> Result<(), UnifyError> {
         let t1 = self.subst_type(t1);
         let t2 = self.subst_type(t2);
+        let ok = |b: bool| if b { Ok(()) } else { Err(UnifyError::Mismatch) };
 
         match (t1.lookup(self.db), t2.lookup(self.db)) {
-            | (TyKind::Error, _) | (_, TyKind::Error) => true,
-            | (TyKind::Unknown(u1), TyKind::Unknown(u2)) if u1 == u2 => true,
-            | (TyKind::Unknown(u), _) => {
-                self.solve_type(u, t2);
-                true
-            },
-            | (_, TyKind::Unknown(u)) => {
-                self.solve_type(u, t1);
-                true
-            },
-            | (TyKind::Skolem(c1, _), TyKind::Skolem(c2, _)) => c1 == c2,
-            | (TyKind::TypeVar(c1), TyKind::TypeVar(c2)) => c1 == c2,
-            | (TyKind::Figure(c1), TyKind::Figure(c2)) => c1 == c2,
-            | (TyKind::Symbol(c1), TyKind::Symbol(c2)) => c1 == c2,
+            | (TyKind::Error, _) | (_, TyKind::Error) => Ok(()),
+            | (TyKind::Unknown(u1), TyKind::Unknown(u2)) if u1 == u2 => Ok(()),
+            | (TyKind::Unknown(u), _) => self.solve_type_checked(u, t2),
+            | (_, TyKind::Unknown(u)) => self.solve_type_checked(u, t1),
+            | (TyKind::Skolem(c1, _), TyKind::Skolem(c2, _)) => ok(c1 == c2),
+            | (TyKind::TypeVar(c1), TyKind::TypeVar(c2)) => ok(c1 == c2),
+            | (TyKind::Figure(c1), TyKind::Figure(c2)) => ok(c1 == c2),
+            | (TyKind::Symbol(c1), TyKind::Symbol(c2)) => ok(c1 == c2),
             | (TyKind::Row(..), _) => self.unify_rows(t1, t2),
             | (_, TyKind::Row(..)) => self.unify_rows(t1, t2),
-            | (TyKind::Ctor(c1), TyKind::Ctor(c2)) => c1 == c2,
+            | (TyKind::Ctor(c1), TyKind::Ctor(c2)) => ok(c1 == c2),
             | (TyKind::Tuple(t1), TyKind::Tuple(t2)) if t1.len() == t2.len() => {
-                t1.iter().zip(t2.iter()).all(|(t1, t2)| self.unify_types(*t1, *t2))
+                t1.iter().zip(t2.iter()).try_for_each(|(t1, t2)| self.unify(*t1, *t2))
+            },
+            | (TyKind::App(a1, a2), TyKind::App(b1, b2)) => {
+                self.unify(a1, b1)?;
+                self.unify(a2, b2)
             },
-            | (TyKind::App(a1, a2), TyKind::App(b1, b2)) => self.unify_types(a1, b1) && self.unify_types(a2, b2),
             | (TyKind::ForAll(k1, t1), TyKind::ForAll(k2, t2)) => {
-                let sk1 = self.skolemize(k1, t1);
-                let sk2 = self.skolemize(k2, t2);
+                let scope = self.fresh_skolem_scope();
+                let sk1 = self.skolemize_in(k1, t1, scope);
+                let sk2 = self.skolemize_in(k2, t2, scope);
 
-                self.unify_types(sk1, sk2)
+                self.unify(sk1, sk2)
             },
             | (TyKind::ForAll(kind, ty), _) => {
                 let sk = self.skolemize(kind, ty);
 
-                self.unify_types(sk, t2)
+                self.unify(sk, t2)
             },
-            | (_, TyKind::ForAll(_, _)) => self.unify_types(t2, t1),
-            | (_, _) => false,
+            | (_, TyKind::ForAll(_, _)) => self.unify(t2, t1),
+            | (_, _) => Err(UnifyError::Mismatch),
         }
     }
 
-    fn unify_rows(&mut self, t1: Ty, t2: Ty) -> bool {
-        let (matches, (lhs, rhs)) = Ty::align_rows_with(self.db, |t1, t2| self.unify_types(t1, t2), t1, t2);
+    fn unify_rows(&mut self, t1: Ty, t2: Ty) -> Result<(), UnifyError> {
+        let (matches, (lhs, rhs)) = Ty::align_rows_with(self.db, |t1, t2| self.unify(t1, t2), t1, t2);
 
-        matches.into_iter().all(std::convert::identity) && self.unify_tails(lhs, rhs)
+        matches.into_iter().collect::<Result<(), _>>()?;
+        self.unify_tails(lhs, rhs)
     }
 
-    fn unify_tails(&mut self, (f1, t1): (List<Field>, Option<Ty>), (f2, t2): (List<Field>, Option<Ty>)) -> bool {
+    fn unify_tails(
+        &mut self,
+        (f1, t1): (List<Field>, Option<Ty>),
+        (f2, t2): (List<Field>, Option<Ty>),
+    ) -> Result<(), UnifyError> {
+        let ok = |b: bool| if b { Ok(()) } else { Err(UnifyError::Mismatch) };
+
         match (t1.map(|t| t.lookup(self.db)), t2.map(|t| t.lookup(self.db))) {
+            | (Some(TyKind::Unknown(u1)), Some(TyKind::Unknown(u2))) if u1 == u2 => ok(f1.is_empty() && f2.is_empty()),
             | (Some(TyKind::Unknown(u)), _) if f1.is_empty() => {
-                self.solve_type(u, TyKind::Row(f2, t2).intern(self.db));
-                true
+                self.solve_type_checked(u, TyKind::Row(f2, t2).intern(self.db))
             },
             | (_, Some(TyKind::Unknown(u))) if f2.is_empty() => {
-                self.solve_type(u, TyKind::Row(f1, t1).intern(self.db));
-                true
+                self.solve_type_checked(u, TyKind::Row(f1, t1).intern(self.db))
+            },
+            | (None, None) => ok(f1.is_empty() && f2.is_empty()),
+            | (Some(TyKind::TypeVar(v1)), Some(TyKind::TypeVar(v2))) => ok(v1 == v2 && f1.is_empty() && f2.is_empty()),
+            | (Some(TyKind::Skolem(s1, _)), Some(TyKind::Skolem(s2, _))) => {
+                ok(s1 == s2 && f1.is_empty() && f2.is_empty())
             },
-            | (None, None) => f1.is_empty() && f2.is_empty(),
-            | (Some(TyKind::TypeVar(v1)), Some(TyKind::TypeVar(v2))) => v1 == v2 && f1.is_empty() && f2.is_empty(),
-            | (Some(TyKind::Skolem(_, s1)), Some(TyKind::Skolem(_, s2))) => s1 == s2 && f1.is_empty() && f2.is_empty(),
-            | (Some(TyKind::Unknown(u1)), Some(TyKind::Unknown(u2))) if u1 != u2 => {
-                unimplemented!();
+            | (Some(TyKind::Unknown(u1)), Some(TyKind::Unknown(u2))) => {
+                // both rows are open, each tail is solved to the fields of the other row and a shared tail
+                let kind = self.subst.unsolved(u1).1;
+                let tail = self.fresh_type_with_kind(kind);
+                let r1 = TyKind::Row(f2, Some(tail)).intern(self.db);
+                let r2 = TyKind::Row(f1, Some(tail)).intern(self.db);
+
+                self.solve_type_checked(u1, r1)?;
+                self.solve_type_checked(u2, r2)
             },
-            | (_, _) => false,
+            | (_, _) => Err(UnifyError::Mismatch),
         }
     }
 }
@@ -6,6 +6,7 @@ use crate::infer::InferenceContext;
 use crate::ty::*;
 use base_db::input::FileId;
 use hir_def::arena::ArenaMap;
+use hir_def::data::TypeAliasData;
 use hir_def::diagnostic::DiagnosticSink;
 use hir_def::id::*;
 use hir_def::item_tree::{Assoc, Prec};
@@ -14,13 +15,25 @@ use hir_def::path::Path;
 use hir_def::resolver::HasResolver;
 use hir_def::resolver::{Resolver, TypeNs, ValueNs};
 use hir_def::type_ref::{LocalTypeRefId, PtrLen, TypeMap, TypeRef, TypeSourceMap};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::sync::Arc;
 
 pub(crate) struct LowerCtx<'a, 'b> {
     type_map: &'a TypeMap,
     icx: &'a mut InferenceContext<'b>,
     types: ArenaMap<LocalTypeRefId, Ty>,
+    group: Arc<TypeGroup>,
+    alias_stack: Vec<TypeAliasId>,
+    alias_cycle: Option<Vec<Name>>,
+    depth: usize,
+}
+
+/// A strongly connected component of type constructors and type aliases.
+/// The members of a group refer to each other and are therefore lowered together.
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct TypeGroup {
+    pub ctors: Vec<TypeCtorId>,
+    pub aliases: Vec<TypeAliasId>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -48,6 +61,10 @@ impl<'a, 'b> LowerCtx<'a, 'b> {
             type_map,
             icx,
             types: ArenaMap::default(),
+            group: Arc::default(),
+            alias_stack: Vec::new(),
+            alias_cycle: None,
+            depth: 0,
         }
     }
 
@@ -98,6 +115,32 @@ impl<'a, 'b> LowerCtx<'a, 'b> {
         res
     }
 
+    /// Like `for_assoc_item`, but for another member of the type group that is being lowered.
+    /// Diagnostics and types of the other member refer to its own type map, so they are discarded here.
+    /// They are reported when that member is lowered on its own.
+    fn for_group_member<T>(
+        &mut self,
+        owner: TypeVarOwner,
+        type_map: &TypeMap,
+        f: impl FnOnce(&mut LowerCtx) -> T,
+    ) -> T {
+        if owner == self.owner {
+            return f(self);
+        }
+
+        let diag_count = self.result.diagnostics.len();
+        let types = std::mem::take(&mut self.types);
+
+        self.depth += 1;
+
+        let res = self.for_assoc_item(owner, type_map, f);
+
+        self.depth -= 1;
+        self.types = types;
+        self.result.diagnostics.truncate(diag_count);
+        res
+    }
+
     pub fn lower_ty(&mut self, ty: LocalTypeRefId) -> Ty {
         self.lower_ty_ext(ty).0
     }
@@ -241,7 +284,19 @@ impl<'a, 'b> LowerCtx<'a, 'b> {
                     self.error()
                 }
             },
-            | ty => unimplemented!("{:?}", ty),
+            | TypeRef::Kinded(inner, kind) => {
+                let inner_ = self.lower_ty(*inner);
+                let kind = self.lower_ty(*kind);
+
+                self.check_kind(inner_, kind, *inner);
+                inner_
+            },
+            | TypeRef::Array(of, len) => {
+                let of_ = self.lower_ty(*of);
+
+                self.check_kind_type(of_, *of);
+                self.array_type(of_, *len as i128)
+            },
         };
 
         self.types.insert(ty, lowered);
@@ -290,7 +345,7 @@ impl<'a, 'b> LowerCtx<'a, 'b> {
     }
 
     pub(crate) fn lower_partly_resolved_path(
-        &self,
+        &mut self,
         resolution: TypeNs,
         remaining: usize,
         type_ref: LocalTypeRefId,
@@ -307,9 +362,9 @@ impl<'a, 'b> LowerCtx<'a, 'b> {
 
                 type_var.to_ty(self.db)
             },
-            | TypeNs::TypeAlias(id) => self.db.type_for_alias(id).ty,
+            | TypeNs::TypeAlias(id) => self.lower_alias_ref(id, type_ref),
             | TypeNs::TypeCtor(id) => {
-                if TypeVarOwner::TypedDefId(id.into()) == self.owner {
+                if TypeVarOwner::TypedDefId(id.into()) == self.owner || self.group_kinds.contains_key(&id) {
                     TyKind::Ctor(id).intern(self.db)
                 } else {
                     self.db.type_for_ctor(id).ty
@@ -324,6 +379,80 @@ impl<'a, 'b> LowerCtx<'a, 'b> {
         }
     }
 
+    /// Aliases in the current group are expanded in place, all other aliases are lowered by their own query.
+    fn lower_alias_ref(&mut self, id: TypeAliasId, type_ref: LocalTypeRefId) -> Ty {
+        if let Some(pos) = self.alias_stack.iter().position(|&a| a == id) {
+            let path = self.alias_stack[pos..]
+                .iter()
+                .chain(Some(&id))
+                .map(|&a| self.db.type_alias_data(a).name.clone())
+                .collect();
+
+            if self.depth == 0 {
+                self.report(InferenceDiagnostic::CyclicTypeAlias { id: type_ref, path });
+            } else {
+                self.alias_cycle.get_or_insert(path);
+            }
+
+            return self.error();
+        }
+
+        if !self.group.aliases.contains(&id) {
+            return self.db.type_for_alias(id).ty;
+        }
+
+        let data = self.db.type_alias_data(id);
+        let ty = self.for_group_member(TypeVarOwner::TypedDefId(id.into()), data.type_map(), |ctx| {
+            ctx.lower_alias(id, &data)
+        });
+
+        if self.depth == 0 {
+            if let Some(path) = self.alias_cycle.take() {
+                self.report(InferenceDiagnostic::CyclicTypeAlias { id: type_ref, path });
+            }
+        }
+
+        ty
+    }
+
+    fn lower_alias(&mut self, id: TypeAliasId, data: &TypeAliasData) -> Ty {
+        self.alias_stack.push(id);
+
+        let var_kinds = data
+            .vars
+            .iter()
+            .rev()
+            .map(|&var| {
+                let data = &data.type_map()[var];
+                let kind = data.kind.map(|k| self.lower_ty(k)).unwrap_or_else(|| self.fresh_kind());
+
+                self.push_var_kind(kind);
+                kind
+            })
+            .collect::<Vec<_>>();
+
+        let mut ty = self.lower_ty(data.alias);
+        let type_kind = std::lazy::OnceCell::new();
+
+        for kind in var_kinds {
+            let kind = self.subst_type(kind);
+
+            if let TyKind::Unknown(u) = kind.lookup(self.db) {
+                let kind = *type_kind.get_or_init(|| self.lang_type("type-kind"));
+
+                self.solve_type(u, kind);
+                ty = TyKind::ForAll(kind, ty).intern(self.db);
+            } else {
+                ty = TyKind::ForAll(kind, ty).intern(self.db);
+            }
+
+            self.pop_var_kind();
+        }
+
+        self.alias_stack.pop();
+        ty
+    }
+
     pub(crate) fn lower_constraint(&mut self, ctnt: &hir_def::type_ref::Constraint) -> Option<Constraint> {
         let class = self.lower_class_path(&ctnt.class)?;
         let types = ctnt.types.iter().map(|&t| self.lower_ty(t)).collect();
@@ -473,45 +602,21 @@ pub(crate) fn type_for_alias(db: &dyn HirDatabase, id: TypeAliasId) -> Arc<Lower
     let resolver = id.resolver(db.upcast());
     let mut icx = InferenceContext::new(db, resolver, TypeVarOwner::TypedDefId(id.into()));
     let mut ctx = LowerCtx::new(data.type_map(), &mut icx);
-    let var_kinds = data
-        .vars
-        .iter()
-        .enumerate()
-        .rev()
-        .map(|(i, &var)| {
-            let data = &data.type_map()[var];
-            let kind = data.kind.map(|k| ctx.lower_ty(k)).unwrap_or_else(|| ctx.fresh_kind());
 
-            ctx.push_var_kind(kind);
-            kind
-        })
-        .collect::<Vec<_>>();
-
-    let mut ty = ctx.lower_ty(data.alias);
-    let type_kind = std::lazy::OnceCell::new();
-
-    for kind in var_kinds {
-        let kind = ctx.subst_type(kind);
-
-        if let TyKind::Unknown(u) = kind.lookup(db) {
-            let kind = *type_kind.get_or_init(|| ctx.lang_type("type-kind"));
-
-            ctx.solve_type(u, kind);
-            ty = TyKind::ForAll(kind, ty).intern(db);
-        } else {
-            ty = TyKind::ForAll(kind, ty).intern(db);
-        }
-
-        ctx.pop_var_kind();
-    }
+    ctx.group = db.type_group(id.into());
 
+    let ty = ctx.lower_alias(id, &data);
     let ty = ctx.subst_type(ty);
 
     ctx.finish(ty)
 }
 
-pub(crate) fn type_for_alias_recover(db: &dyn HirDatabase, _cycle: &[String], id: &TypeAliasId) -> Arc<LowerResult> {
-    unimplemented!();
+pub(crate) fn type_for_alias_recover(db: &dyn HirDatabase, _cycle: &[String], _id: &TypeAliasId) -> Arc<LowerResult> {
+    Arc::new(LowerResult {
+        ty: TyKind::Error.intern(db),
+        types: ArenaMap::default(),
+        diagnostics: vec![InferenceDiagnostic::CyclicType],
+    })
 }
 
 pub(crate) fn type_for_ctor(db: &dyn HirDatabase, id: TypeCtorId) -> Arc<LowerResult> {
@@ -519,40 +624,98 @@ pub(crate) fn type_for_ctor(db: &dyn HirDatabase, id: TypeCtorId) -> Arc<LowerRe
     let resolver = id.resolver(db.upcast());
     let mut icx = InferenceContext::new(db, resolver, TypeVarOwner::TypedDefId(id.into()));
     let mut ctx = LowerCtx::new(data.type_map(), &mut icx);
-    let mut ty = TyKind::Ctor(id).intern(db);
-    let var_kinds = data
-        .vars
+    let group = db.type_group(id.into());
+
+    ctx.group = group.clone();
+
+    // every member of the group gets its kind up front so that the kinds
+    // of mutually recursive types are inferred together.
+    let group_vars = group
+        .ctors
         .iter()
-        .enumerate()
-        .rev()
-        .map(|(i, &var)| {
-            let data = &data.type_map()[var];
-            let kind = data.kind.map(|k| ctx.lower_ty(k)).unwrap_or_else(|| ctx.fresh_kind());
-            let var = TypeVar::new(DebruijnIndex::new(i as u32));
-            let arg = var.to_ty(db);
+        .map(|&member| {
+            let member_data = db.type_ctor_data(member);
+            let owner = TypeVarOwner::TypedDefId(member.into());
 
-            ty = TyKind::App(ty, arg).intern(db);
-            ctx.push_var_kind(kind);
-            kind
+            ctx.for_group_member(owner, member_data.type_map(), |ctx| {
+                let var_kinds = member_data
+                    .vars
+                    .iter()
+                    .rev()
+                    .map(|&var| {
+                        let data = &member_data.type_map()[var];
+
+                        data.kind.map(|k| ctx.lower_ty(k)).unwrap_or_else(|| ctx.fresh_kind())
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut kind = ctx.type_kind();
+
+                for &var_kind in &var_kinds {
+                    kind = ctx.fn_type(var_kind, kind);
+                }
+
+                if let Some(ann) = member_data.kind {
+                    let ann_ = ctx.lower_ty(ann);
+
+                    if !ctx.unify_types(kind, ann_) {
+                        ctx.report(InferenceDiagnostic::MismatchedKind {
+                            id: ann,
+                            expected: ann_,
+                            found: kind,
+                        });
+                    }
+                }
+
+                ctx.group_kinds.insert(member, kind);
+                var_kinds
+            })
         })
         .collect::<Vec<_>>();
 
-    let mut ty_kind = ctx.type_kind();
+    for (&member, var_kinds) in group.ctors.iter().zip(&group_vars) {
+        let member_data = db.type_ctor_data(member);
+        let owner = TypeVarOwner::TypedDefId(member.into());
+
+        ctx.for_group_member(owner, member_data.type_map(), |ctx| {
+            for &kind in var_kinds {
+                ctx.push_var_kind(kind);
+            }
+
+            for (_, ctor) in member_data.ctors.iter() {
+                for &ty in ctor.types.iter() {
+                    let ty_ = ctx.lower_ty(ty);
+
+                    ctx.check_kind_type(ty_, ty);
+                }
+            }
 
-    for &kind in &var_kinds {
-        ty_kind = ctx.fn_type(kind, ty_kind);
+            for _ in var_kinds {
+                ctx.pop_var_kind();
+            }
+        });
+
+        // alias cycles found in other members are reported by their own query.
+        ctx.alias_cycle = None;
     }
 
-    ctx.icx.result.self_type = ty_kind;
+    let var_kinds = group
+        .ctors
+        .iter()
+        .position(|&member| member == id)
+        .map(|i| group_vars[i].clone())
+        .unwrap_or_default();
 
-    for (_, ctor) in data.ctors.iter() {
-        for &ty in ctor.types.iter() {
-            let ty_ = ctx.lower_ty(ty);
+    let mut ty = TyKind::Ctor(id).intern(db);
 
-            ctx.check_kind_type(ty_, ty);
-        }
+    for i in (0..var_kinds.len()).rev() {
+        let var = TypeVar::new(DebruijnIndex::new(i as u32));
+
+        ty = TyKind::App(ty, var.to_ty(db)).intern(db);
     }
 
+    ctx.icx.result.self_type = ctx.group_kinds[&id];
+
     let type_kind = std::lazy::OnceCell::new();
 
     for kind in var_kinds {
@@ -566,8 +729,6 @@ pub(crate) fn type_for_ctor(db: &dyn HirDatabase, id: TypeCtorId) -> Arc<LowerRe
         } else {
             ty = TyKind::ForAll(kind, ty).intern(db);
         }
-
-        ctx.pop_var_kind();
     }
 
     let ty = ctx.subst_type(ty);
@@ -575,9 +736,12 @@ pub(crate) fn type_for_ctor(db: &dyn HirDatabase, id: TypeCtorId) -> Arc<LowerRe
     ctx.finish(ty)
 }
 
-pub(crate) fn type_for_ctor_recover(db: &dyn HirDatabase, _cycle: &[String], id: &TypeCtorId) -> Arc<LowerResult> {
-    dbg!(_cycle);
-    unimplemented!();
+pub(crate) fn type_for_ctor_recover(db: &dyn HirDatabase, _cycle: &[String], _id: &TypeCtorId) -> Arc<LowerResult> {
+    Arc::new(LowerResult {
+        ty: TyKind::Error.intern(db),
+        types: ArenaMap::default(),
+        diagnostics: vec![InferenceDiagnostic::CyclicType],
+    })
 }
 
 pub(crate) fn kind_for_ctor(db: &dyn HirDatabase, id: TypeCtorId) -> Ty {
@@ -603,9 +767,91 @@ pub(crate) fn kind_for_ctor(db: &dyn HirDatabase, id: TypeCtorId) -> Ty {
     }
 }
 
-pub(crate) fn kind_for_ctor_recover(db: &dyn HirDatabase, _cycle: &[String], id: &TypeCtorId) -> Ty {
-    let data = db.type_ctor_data(*id);
-    unimplemented!("{}", data.name);
+pub(crate) fn kind_for_ctor_recover(db: &dyn HirDatabase, _cycle: &[String], _id: &TypeCtorId) -> Ty {
+    TyKind::Error.intern(db)
+}
+
+pub(crate) fn type_group_query(db: &dyn HirDatabase, id: TypedDefId) -> Arc<TypeGroup> {
+    let mut deps = FxHashMap::default();
+    let mut order = Vec::new();
+    let mut stack = vec![id];
+
+    while let Some(def) = stack.pop() {
+        if deps.contains_key(&def) {
+            continue;
+        }
+
+        let def_deps = type_deps(db, def);
+
+        stack.extend(def_deps.iter().copied());
+        order.push(def);
+        deps.insert(def, def_deps);
+    }
+
+    // every definition in `deps` is reachable from `id`,
+    // so the group consists of those that can reach `id` again.
+    let mut group = FxHashSet::default();
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for &def in &order {
+            if !group.contains(&def) && deps[&def].iter().any(|d| *d == id || group.contains(d)) {
+                group.insert(def);
+                changed = true;
+            }
+        }
+    }
+
+    let mut res = TypeGroup::default();
+
+    if let TypedDefId::TypeCtorId(id) = id {
+        res.ctors.push(id);
+    }
+
+    for def in order {
+        if !group.contains(&def) {
+            continue;
+        }
+
+        match def {
+            | TypedDefId::TypeCtorId(ctor) if !res.ctors.contains(&ctor) => res.ctors.push(ctor),
+            | TypedDefId::TypeAliasId(alias) => res.aliases.push(alias),
+            | _ => {},
+        }
+    }
+
+    Arc::new(res)
+}
+
+/// The type constructors and type aliases that are referred to by the given definition.
+fn type_deps(db: &dyn HirDatabase, def: TypedDefId) -> Vec<TypedDefId> {
+    let resolver = def.resolver(db.upcast());
+    let mut deps = Vec::new();
+    let mut collect = |type_map: &TypeMap| {
+        for (_, type_ref) in type_map.iter() {
+            if let TypeRef::Path(path) = type_ref {
+                let dep = match resolver.resolve_type(db.upcast(), path) {
+                    | Some((TypeNs::TypeCtor(id), _)) => id.into(),
+                    | Some((TypeNs::TypeAlias(id), _)) => id.into(),
+                    | _ => continue,
+                };
+
+                if !deps.contains(&dep) {
+                    deps.push(dep);
+                }
+            }
+        }
+    };
+
+    match def {
+        | TypedDefId::TypeCtorId(id) => collect(db.type_ctor_data(id).type_map()),
+        | TypedDefId::TypeAliasId(id) => collect(db.type_alias_data(id).type_map()),
+        | _ => {},
+    }
+
+    deps
 }
 
 pub(crate) fn lower_class_query(db: &dyn HirDatabase, id: ClassId) -> Arc<ClassLowerResult> {
@@ -644,6 +890,12 @@ pub(crate) fn lower_class_query(db: &dyn HirDatabase, id: ClassId) -> Arc<ClassL
         })
         .collect();
 
+    let superclasses = data
+        .constraints
+        .iter()
+        .filter_map(|c| ctx.lower_constraint(c))
+        .collect();
+
     let diag_count = ctx.result.diagnostics.len();
 
     for &(_, id) in data.items.iter() {
@@ -679,7 +931,21 @@ pub(crate) fn lower_class_query(db: &dyn HirDatabase, id: ClassId) -> Arc<ClassL
 
     ctx.result.diagnostics.truncate(diag_count);
 
-    ctx.finish_class(Class { id, vars, fundeps })
+    for (name, func) in data.defaults.iter() {
+        if data.item(name).is_none() {
+            ctx.report(InferenceDiagnostic::DefaultWithoutSignature {
+                func: *func,
+                name: name.clone(),
+            });
+        }
+    }
+
+    ctx.finish_class(Class {
+        id,
+        vars,
+        fundeps,
+        superclasses,
+    })
 }
 
 pub(crate) fn lower_instance_query(db: &dyn HirDatabase, id: InstanceId) -> Arc<InstanceLowerResult> {
@@ -704,6 +970,15 @@ pub(crate) fn lower_instance_query(db: &dyn HirDatabase, id: InstanceId) -> Arc<
 
     let (class, types) = if let Some(class) = ctx.lower_class_path(&data.class) {
         let lower = db.lower_class(class);
+        let class_data = db.class_data(class);
+
+        for (name, item) in class_data.items.iter() {
+            if let AssocItemId::FuncId(_) = item {
+                if data.item(name).is_none() {
+                    ctx.report(InferenceDiagnostic::MissingMethod { name: name.clone() });
+                }
+            }
+        }
 
         (
             lower.class.id,
@@ -14,7 +14,7 @@ pub enum TyKind {
     Error,
 
     Unknown(Unknown),
-    Skolem(TypeVar, Ty),
+    Skolem(Skolem, Ty),
     TypeVar(TypeVar),
 
     Figure(i128),
@@ -50,6 +50,17 @@ pub struct TypeVar(DebruijnIndex);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DebruijnIndex(u32);
 
+/// A rigid type variable. The scope distinguishes skolems of the same type variable
+/// that were introduced by different quantifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Skolem {
+    pub var: TypeVar,
+    pub scope: SkolemScope,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SkolemScope(u32);
+
 impl Ty {
     pub fn lookup(self, db: &dyn HirDatabase) -> TyKind {
         db.lookup_intern_ty(self)
@@ -352,6 +363,16 @@ impl Unknown {
     }
 }
 
+impl SkolemScope {
+    pub const fn from_raw(id: u32) -> Self {
+        SkolemScope(id)
+    }
+
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+}
+
 impl TypeVar {
     pub const fn new(debruijn: DebruijnIndex) -> Self {
         Self(debruijn)
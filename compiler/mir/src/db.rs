@@ -2,6 +2,7 @@ use crate::eval::EvalResult;
 use crate::instance_record::InstanceRecord;
 use crate::ir::Bodies;
 use crate::layout::Layout;
+use crate::opt::OptLevel;
 use crate::ty::Type;
 use base_db::Upcast;
 use hir::db::HirDatabase;
@@ -13,6 +14,9 @@ pub trait MirDatabase: HirDatabase + Upcast<dyn HirDatabase> {
     #[salsa::input]
     fn target_triple(&self) -> Arc<target_lexicon::Triple>;
 
+    #[salsa::input]
+    fn opt_level(&self) -> OptLevel;
+
     #[salsa::invoke(crate::layout::layout_of_query)]
     fn layout_of(&self, ty: Arc<Type>) -> Arc<Layout>;
 
@@ -1,6 +1,8 @@
 use crate::db::MirDatabase;
+use crate::intrinsic::{BinOp, CmpOp, Intrinsic, ScalarType, UnOp};
 use crate::ir::*;
 use crate::ty::{Type, TypeKind};
+use hir::id::HasModule as _;
 use hir_def::arena::ArenaMap;
 use std::sync::Arc;
 
@@ -55,10 +57,12 @@ impl<'a> VM<'a> {
     }
 
     fn eval_block(&mut self, body: &Body, block: BlockId) -> EvalResult {
-        let Block { stmts, term } = &body.blocks[block];
+        let Block { stmts, term, .. } = &body.blocks[block];
 
         for stmt in stmts {
-            self.eval_stmt(body, stmt);
+            if self.eval_stmt(body, stmt).is_none() {
+                return EvalResult::Abort;
+            }
         }
 
         self.eval_term(body, term)
@@ -77,23 +81,82 @@ impl<'a> VM<'a> {
         }
     }
 
-    fn eval_stmt(&mut self, body: &Body, stmt: &Stmt) {
+    fn eval_stmt(&mut self, body: &Body, stmt: &Stmt) -> Option<()> {
         match stmt {
             | Stmt::Assign(place, rvalue) => self.eval_assign(body, place, rvalue),
             | Stmt::SetDiscr(place, discr) => unimplemented!(),
+            | Stmt::Call(_, Operand::Const(Const::Addr(id), _), _) if self.is_panic_handler(id.def) => None,
             | Stmt::Call(..) => unimplemented!(),
+            | Stmt::Drop(_) => Some(()),
         }
     }
 
-    fn eval_assign(&mut self, body: &Body, place: &Place, rvalue: &RValue) {
+    fn is_panic_handler(&self, def: hir::id::DefWithBodyId) -> bool {
+        let lib = def.module(self.db.upcast()).lib;
+        let handler = self.db.lang_item(lib, "panic-handler".into()).and_then(|it| it.as_func());
+
+        handler.map(hir::id::DefWithBodyId::from) == Some(def)
+    }
+
+    fn eval_assign(&mut self, body: &Body, place: &Place, rvalue: &RValue) -> Option<()> {
         match rvalue {
             | RValue::Use(op) => {
                 let val = self.eval_operand(body, op);
 
                 self.store(body, place, val);
             },
+            | RValue::Intrinsic(name, args) => {
+                let args = args.iter().map(|a| self.eval_operand(body, a)).collect::<Vec<_>>();
+                let val = self.eval_intrinsic(name, &args)?;
+
+                self.store(body, place, val);
+            },
             | _ => unimplemented!(),
         }
+
+        Some(())
+    }
+
+    /// Evaluates a scalar intrinsic. Returns `None` when the intrinsic would trap.
+    pub(crate) fn eval_intrinsic(&mut self, name: &str, args: &[Const]) -> Option<Const> {
+        let pointer_bits = self
+            .db
+            .target_triple()
+            .pointer_width()
+            .map(|w| w.bits() as u32)
+            .unwrap_or(32);
+        let bits = |ty: ScalarType| ty.bits(pointer_bits);
+        let scalar = |i: usize| match args.get(i) {
+            | Some(Const::Scalar(s)) => Some(*s),
+            | _ => None,
+        };
+
+        if name == "transmute" {
+            return args.get(0).cloned();
+        }
+
+        let val = match Intrinsic::parse(name)? {
+            | Intrinsic::Binary(op, ty) if ty.is_float() => float_binop(op, ty, scalar(0)?, scalar(1)?),
+            | Intrinsic::Binary(op, ty) => match int_binop(op, ty, scalar(0)?, scalar(1)?, bits(ty))? {
+                // only a division of the minimum value by -1 traps, other operations wrap around
+                | (_, true) if op == BinOp::Div => return None,
+                | (r, _) => r,
+            },
+            | Intrinsic::Checked(op, ty) => {
+                let (r, overflow) = int_binop(op, ty, scalar(0)?, scalar(1)?, bits(ty))?;
+
+                return Some(Const::Tuple(vec![Const::Scalar(r), Const::Scalar(overflow as u128)]));
+            },
+            | Intrinsic::Unary(UnOp::Neg, ty) if ty.is_float() => {
+                (-f64::from_bits(scalar(0)? as u64)).to_bits() as u128
+            },
+            | Intrinsic::Unary(UnOp::Neg, ty) => int_binop(BinOp::Sub, ty, 0, scalar(0)?, bits(ty))?.0,
+            | Intrinsic::Unary(UnOp::Not, ty) => truncate(!scalar(0)?, bits(ty)),
+            | Intrinsic::Cmp(op, ty) => cmp(op, ty, scalar(0)?, scalar(1)?, bits(ty)) as u128,
+            | Intrinsic::Convert(from, to) => convert(from, to, scalar(0)?, bits(from), bits(to)),
+        };
+
+        Some(Const::Scalar(val))
     }
 
     fn store(&mut self, body: &Body, place: &Place, value: Const) {
@@ -182,3 +245,136 @@ impl Const {
         }
     }
 }
+
+/// Floats are represented by the bits of an `f64`, regardless of their type.
+fn float_binop(op: BinOp, ty: ScalarType, lhs: u128, rhs: u128) -> u128 {
+    let lhs = f64::from_bits(lhs as u64);
+    let rhs = f64::from_bits(rhs as u64);
+    let res = match op {
+        | BinOp::Add => lhs + rhs,
+        | BinOp::Sub => lhs - rhs,
+        | BinOp::Mul => lhs * rhs,
+        | BinOp::Div => lhs / rhs,
+        | BinOp::Rem => lhs % rhs,
+        | _ => unreachable!(),
+    };
+
+    round_float(ty, res).to_bits() as u128
+}
+
+/// Returns the truncated result and whether it overflowed, or `None` when dividing by zero.
+fn int_binop(op: BinOp, ty: ScalarType, lhs: u128, rhs: u128, bits: u32) -> Option<(u128, bool)> {
+    let shift = (rhs % bits as u128) as u32;
+
+    if matches!(op, BinOp::Div | BinOp::Rem) && truncate(rhs, bits) == 0 {
+        return None;
+    }
+
+    if ty.is_signed() {
+        let lhs = sign_extend(lhs, bits);
+        let rhs = sign_extend(rhs, bits);
+        let (res, overflow) = match op {
+            | BinOp::Add => lhs.overflowing_add(rhs),
+            | BinOp::Sub => lhs.overflowing_sub(rhs),
+            | BinOp::Mul => lhs.overflowing_mul(rhs),
+            | BinOp::Div => lhs.overflowing_div(rhs),
+            | BinOp::Rem => lhs.overflowing_rem(rhs),
+            | BinOp::And => return Some((truncate((lhs & rhs) as u128, bits), false)),
+            | BinOp::Or => return Some((truncate((lhs | rhs) as u128, bits), false)),
+            | BinOp::Xor => return Some((truncate((lhs ^ rhs) as u128, bits), false)),
+            | BinOp::Shl => return Some((truncate((lhs << shift) as u128, bits), false)),
+            | BinOp::Shr => return Some((truncate((lhs >> shift) as u128, bits), false)),
+        };
+
+        let trunc = truncate(res as u128, bits);
+
+        Some((trunc, overflow || sign_extend(trunc, bits) != res))
+    } else {
+        let lhs = truncate(lhs, bits);
+        let rhs = truncate(rhs, bits);
+        let (res, overflow) = match op {
+            | BinOp::Add => lhs.overflowing_add(rhs),
+            | BinOp::Sub => lhs.overflowing_sub(rhs),
+            | BinOp::Mul => lhs.overflowing_mul(rhs),
+            | BinOp::Div => (lhs / rhs, false),
+            | BinOp::Rem => (lhs % rhs, false),
+            | BinOp::And => return Some((lhs & rhs, false)),
+            | BinOp::Or => return Some((lhs | rhs, false)),
+            | BinOp::Xor => return Some((lhs ^ rhs, false)),
+            | BinOp::Shl => return Some((truncate(lhs << shift, bits), false)),
+            | BinOp::Shr => return Some((lhs >> shift, false)),
+        };
+
+        let trunc = truncate(res, bits);
+
+        Some((trunc, overflow || trunc != res))
+    }
+}
+
+fn cmp(op: CmpOp, ty: ScalarType, lhs: u128, rhs: u128, bits: u32) -> bool {
+    let ord = if ty.is_float() {
+        let lhs = f64::from_bits(lhs as u64);
+        let rhs = f64::from_bits(rhs as u64);
+
+        match lhs.partial_cmp(&rhs) {
+            | Some(ord) => ord,
+            | None => return op == CmpOp::Ne,
+        }
+    } else if ty.is_signed() {
+        sign_extend(lhs, bits).cmp(&sign_extend(rhs, bits))
+    } else {
+        truncate(lhs, bits).cmp(&truncate(rhs, bits))
+    };
+
+    match op {
+        | CmpOp::Eq => ord.is_eq(),
+        | CmpOp::Ne => ord.is_ne(),
+        | CmpOp::Lt => ord.is_lt(),
+        | CmpOp::Le => ord.is_le(),
+        | CmpOp::Gt => ord.is_gt(),
+        | CmpOp::Ge => ord.is_ge(),
+    }
+}
+
+fn convert(from: ScalarType, to: ScalarType, val: u128, from_bits: u32, to_bits: u32) -> u128 {
+    match (from.is_float(), to.is_float()) {
+        | (false, false) if from.is_signed() => truncate(sign_extend(val, from_bits) as u128, to_bits),
+        | (false, false) => truncate(val, to_bits),
+        | (false, true) if from.is_signed() => round_float(to, sign_extend(val, from_bits) as f64).to_bits() as u128,
+        | (false, true) => round_float(to, truncate(val, from_bits) as f64).to_bits() as u128,
+        | (true, true) => round_float(to, f64::from_bits(val as u64)).to_bits() as u128,
+        | (true, false) => {
+            let val = f64::from_bits(val as u64);
+
+            if to.is_signed() {
+                let min = sign_extend(1 << (to_bits - 1), to_bits);
+                let max = !min;
+
+                truncate((val as i128).max(min).min(max) as u128, to_bits)
+            } else {
+                (val as u128).min(truncate(!0, to_bits))
+            }
+        },
+    }
+}
+
+fn round_float(ty: ScalarType, val: f64) -> f64 {
+    match ty {
+        | ScalarType::F32 => val as f32 as f64,
+        | _ => val,
+    }
+}
+
+fn truncate(val: u128, bits: u32) -> u128 {
+    if bits >= 128 {
+        val
+    } else {
+        val & ((1 << bits) - 1)
+    }
+}
+
+fn sign_extend(val: u128, bits: u32) -> i128 {
+    let shift = 128 - bits;
+
+    ((val << shift) as i128) >> shift
+}
@@ -0,0 +1,296 @@
+//! The arithmetic, bitwise, comparison and conversion intrinsics for every scalar type declared in `prim`.
+//!
+//! Intrinsic names are of the form `{op}_{type}` (e.g. `add_i32`, `lt_f64`), `checked_{op}_{type}` and
+//! `{from}_to_{to}` (e.g. `i64_to_f32`).
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Intrinsic {
+    /// Arithmetic on integers wraps around when it overflows.
+    Binary(BinOp, ScalarType),
+    /// Returns the wrapped result and whether it overflowed.
+    Checked(BinOp, ScalarType),
+    Unary(UnOp, ScalarType),
+    Cmp(CmpOp, ScalarType),
+    /// Integers are truncated or extended, floats are converted to integers with saturation.
+    Convert(ScalarType, ScalarType),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarType {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Int,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Uint,
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Intrinsic {
+    pub fn parse(name: &str) -> Option<Self> {
+        let intrinsic = if let Some((from, to)) = name.split_once("_to_") {
+            Intrinsic::Convert(ScalarType::parse(from)?, ScalarType::parse(to)?)
+        } else {
+            let (op, ty) = name.rsplit_once('_')?;
+            let ty = ScalarType::parse(ty)?;
+
+            if let Some(op) = op.strip_prefix("checked_") {
+                Intrinsic::Checked(BinOp::parse(op)?, ty)
+            } else if let Some(op) = BinOp::parse(op) {
+                Intrinsic::Binary(op, ty)
+            } else if let Some(op) = UnOp::parse(op) {
+                Intrinsic::Unary(op, ty)
+            } else {
+                Intrinsic::Cmp(CmpOp::parse(op)?, ty)
+            }
+        };
+
+        if intrinsic.is_valid() {
+            Some(intrinsic)
+        } else {
+            None
+        }
+    }
+
+    /// All valid scalar intrinsics.
+    pub fn all() -> impl Iterator<Item = Intrinsic> {
+        let mut all = Vec::new();
+
+        for &ty in ScalarType::ALL {
+            all.extend(BinOp::ALL.iter().map(|&op| Intrinsic::Binary(op, ty)));
+            all.extend(BinOp::ALL.iter().map(|&op| Intrinsic::Checked(op, ty)));
+            all.extend(UnOp::ALL.iter().map(|&op| Intrinsic::Unary(op, ty)));
+            all.extend(CmpOp::ALL.iter().map(|&op| Intrinsic::Cmp(op, ty)));
+            all.extend(ScalarType::ALL.iter().map(|&to| Intrinsic::Convert(ty, to)));
+        }
+
+        all.into_iter().filter(|i| i.is_valid())
+    }
+
+    pub fn is_valid(self) -> bool {
+        match self {
+            | Intrinsic::Binary(op, ty) => !ty.is_float() || op.is_arithmetic(),
+            | Intrinsic::Checked(op, ty) => !ty.is_float() && op.can_overflow(),
+            | Intrinsic::Unary(UnOp::Neg, ty) => ty.is_signed() || ty.is_float(),
+            | Intrinsic::Unary(UnOp::Not, ty) => !ty.is_float(),
+            | Intrinsic::Cmp(_, _) => true,
+            | Intrinsic::Convert(from, to) => from != to,
+        }
+    }
+}
+
+impl ScalarType {
+    pub const ALL: &'static [ScalarType] = &[
+        ScalarType::I8,
+        ScalarType::I16,
+        ScalarType::I32,
+        ScalarType::I64,
+        ScalarType::I128,
+        ScalarType::Int,
+        ScalarType::U8,
+        ScalarType::U16,
+        ScalarType::U32,
+        ScalarType::U64,
+        ScalarType::U128,
+        ScalarType::Uint,
+        ScalarType::F32,
+        ScalarType::F64,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        ScalarType::ALL.iter().copied().find(|ty| ty.name() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            | ScalarType::I8 => "i8",
+            | ScalarType::I16 => "i16",
+            | ScalarType::I32 => "i32",
+            | ScalarType::I64 => "i64",
+            | ScalarType::I128 => "i128",
+            | ScalarType::Int => "int",
+            | ScalarType::U8 => "u8",
+            | ScalarType::U16 => "u16",
+            | ScalarType::U32 => "u32",
+            | ScalarType::U64 => "u64",
+            | ScalarType::U128 => "u128",
+            | ScalarType::Uint => "uint",
+            | ScalarType::F32 => "f32",
+            | ScalarType::F64 => "f64",
+        }
+    }
+
+    /// The name of the type in `prim`.
+    pub fn prim_name(self) -> &'static str {
+        match self {
+            | ScalarType::I8 => "Int8",
+            | ScalarType::I16 => "Int16",
+            | ScalarType::I32 => "Int32",
+            | ScalarType::I64 => "Int64",
+            | ScalarType::I128 => "Int128",
+            | ScalarType::Int => "Int",
+            | ScalarType::U8 => "Uint8",
+            | ScalarType::U16 => "Uint16",
+            | ScalarType::U32 => "Uint32",
+            | ScalarType::U64 => "Uint64",
+            | ScalarType::U128 => "Uint128",
+            | ScalarType::Uint => "Uint",
+            | ScalarType::F32 => "Float32",
+            | ScalarType::F64 => "Float64",
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, ScalarType::F32 | ScalarType::F64)
+    }
+
+    pub fn is_signed(self) -> bool {
+        match self {
+            | ScalarType::I8
+            | ScalarType::I16
+            | ScalarType::I32
+            | ScalarType::I64
+            | ScalarType::I128
+            | ScalarType::Int => true,
+            | _ => false,
+        }
+    }
+
+    pub fn bits(self, pointer_bits: u32) -> u32 {
+        match self {
+            | ScalarType::I8 | ScalarType::U8 => 8,
+            | ScalarType::I16 | ScalarType::U16 => 16,
+            | ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 32,
+            | ScalarType::I64 | ScalarType::U64 | ScalarType::F64 => 64,
+            | ScalarType::I128 | ScalarType::U128 => 128,
+            | ScalarType::Int | ScalarType::Uint => pointer_bits,
+        }
+    }
+}
+
+impl BinOp {
+    pub const ALL: &'static [BinOp] = &[
+        BinOp::Add,
+        BinOp::Sub,
+        BinOp::Mul,
+        BinOp::Div,
+        BinOp::Rem,
+        BinOp::And,
+        BinOp::Or,
+        BinOp::Xor,
+        BinOp::Shl,
+        BinOp::Shr,
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        BinOp::ALL.iter().copied().find(|op| op.name() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            | BinOp::Add => "add",
+            | BinOp::Sub => "sub",
+            | BinOp::Mul => "mul",
+            | BinOp::Div => "div",
+            | BinOp::Rem => "rem",
+            | BinOp::And => "and",
+            | BinOp::Or => "or",
+            | BinOp::Xor => "xor",
+            | BinOp::Shl => "shl",
+            | BinOp::Shr => "shr",
+        }
+    }
+
+    pub fn is_arithmetic(self) -> bool {
+        matches!(self, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem)
+    }
+
+    pub fn can_overflow(self) -> bool {
+        matches!(self, BinOp::Add | BinOp::Sub | BinOp::Mul)
+    }
+}
+
+impl UnOp {
+    pub const ALL: &'static [UnOp] = &[UnOp::Neg, UnOp::Not];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        UnOp::ALL.iter().copied().find(|op| op.name() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            | UnOp::Neg => "neg",
+            | UnOp::Not => "not",
+        }
+    }
+}
+
+impl CmpOp {
+    pub const ALL: &'static [CmpOp] = &[CmpOp::Eq, CmpOp::Ne, CmpOp::Lt, CmpOp::Le, CmpOp::Gt, CmpOp::Ge];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        CmpOp::ALL.iter().copied().find(|op| op.name() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            | CmpOp::Eq => "eq",
+            | CmpOp::Ne => "ne",
+            | CmpOp::Lt => "lt",
+            | CmpOp::Le => "le",
+            | CmpOp::Gt => "gt",
+            | CmpOp::Ge => "ge",
+        }
+    }
+}
+
+impl fmt::Display for Intrinsic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            | Intrinsic::Binary(op, ty) => write!(f, "{}_{}", op.name(), ty.name()),
+            | Intrinsic::Checked(op, ty) => write!(f, "checked_{}_{}", op.name(), ty.name()),
+            | Intrinsic::Unary(op, ty) => write!(f, "{}_{}", op.name(), ty.name()),
+            | Intrinsic::Cmp(op, ty) => write!(f, "{}_{}", op.name(), ty.name()),
+            | Intrinsic::Convert(from, to) => write!(f, "{}_to_{}", from.name(), to.name()),
+        }
+    }
+}
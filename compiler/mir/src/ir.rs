@@ -12,6 +12,10 @@ use std::sync::Arc;
 pub struct Bodies {
     pub(crate) bodies: Arena<Body>,
     pub(crate) arities: FxHashMap<usize, LocalBodyId>,
+    /// Bodies of local functions and closures with the body they were lifted out of. They take the
+    /// type arguments of the type variables they generalize over after their own parameters,
+    /// followed by the type arguments and instance records of the body they were lifted out of.
+    pub(crate) lifted: FxHashMap<LocalBodyId, LocalBodyId>,
 }
 
 pub type LocalBodyId = Idx<Body>;
@@ -30,6 +34,7 @@ pub struct Body {
     pub records: Vec<Arc<InstanceRecord>>,
     pub entry: Option<BlockId>,
     pub ret: Option<LocalId>,
+    pub var_debug_info: Vec<VarDebugInfo>,
 }
 
 pub type LocalId = Idx<Local>;
@@ -48,6 +53,14 @@ pub enum LocalKind {
     Var,
 }
 
+/// A user variable, so that debuggers can show its value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VarDebugInfo {
+    pub name: Name,
+    pub pat: hir::PatId,
+    pub place: Place,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Location {
     pub block: BlockId,
@@ -57,6 +70,8 @@ pub struct Location {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Block {
     pub stmts: Vec<Stmt>,
+    /// The expression each statement was lowered from, parallel to `stmts`.
+    pub sources: Vec<Option<hir::ExprId>>,
     pub term: Term,
 }
 
@@ -65,6 +80,8 @@ pub enum Stmt {
     Assign(Place, RValue),
     SetDiscr(Place, u128),
     Call(Place, Operand, Vec<Operand>),
+    /// The end of the scope of a local. Owned values are released here by `rc`, otherwise it does nothing.
+    Drop(Place),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -379,6 +396,10 @@ impl display::HirDisplay for Stmt {
 
                 write!(f, ")")
             },
+            | Stmt::Drop(p) => {
+                write!(f, "drop ")?;
+                p.hir_fmt(f)
+            },
         }
     }
 }
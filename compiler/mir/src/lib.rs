@@ -5,9 +5,12 @@ pub extern crate target_lexicon;
 pub mod db;
 pub mod eval;
 pub mod instance_record;
+pub mod intrinsic;
 pub mod ir;
 pub mod layout;
 mod lower;
+pub mod opt;
 mod post;
+mod rc;
 pub mod ty;
 pub mod visit;
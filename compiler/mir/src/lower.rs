@@ -1,4 +1,5 @@
 pub mod builder;
+pub mod local;
 pub mod pattern;
 
 use crate::db::MirDatabase;
@@ -11,6 +12,7 @@ use hir::id::{DefWithBodyId, HasModule as _};
 use hir::ty::{Ty, TyKind, TypeVar};
 use hir::MethodSource;
 use hir_def::resolver::{HasResolver, Resolver, ValueNs};
+use local::LocalFuns;
 use rustc_hash::FxHashMap;
 use std::sync::Arc;
 
@@ -56,27 +58,10 @@ impl Bodies {
         }
 
         let mut lcx = LowerCtx::new(db, def);
-        let lib = def.module(db.upcast()).lib;
-        let type_kind = db.lang_item(lib, "type-kind".into()).unwrap();
-        let type_kind = type_kind.as_type_ctor().unwrap();
-        let figure_kind = db.lang_item(lib, "figure-kind".into()).unwrap();
-        let figure_kind = figure_kind.as_type_ctor().unwrap();
-        let symbol_kind = db.lang_item(lib, "symbol-kind".into()).unwrap();
-        let symbol_kind = symbol_kind.as_type_ctor().unwrap();
 
         if !matches!(body[body.body_expr()], hir::Expr::Missing) {
             for var in vars {
-                let kind = var.lookup(db.upcast());
-
-                if TyKind::Ctor(type_kind) == kind {
-                    lcx.add_type_var(Some(TypeVarKind::Type));
-                } else if TyKind::Ctor(figure_kind) == kind {
-                    lcx.add_type_var(Some(TypeVarKind::Figure));
-                } else if TyKind::Ctor(symbol_kind) == kind {
-                    lcx.add_type_var(Some(TypeVarKind::Symbol));
-                } else {
-                    lcx.add_type_var(None);
-                }
+                lcx.add_type_var(type_var_kind(db, def, var));
             }
 
             for ctnt in ctnts {
@@ -90,6 +75,28 @@ impl Bodies {
     }
 }
 
+/// The kind of a type variable of kind `kind` as it is passed at runtime,
+/// kinds other than `Type`, `Figure` and `Symbol` are not passed at all.
+fn type_var_kind(db: &dyn MirDatabase, def: DefWithBodyId, kind: Ty) -> Option<TypeVarKind> {
+    let lib = def.module(db.upcast()).lib;
+    let kind = kind.lookup(db.upcast());
+    let is_kind = |name: &str| {
+        let id = db.lang_item(lib, name.into()).unwrap();
+
+        TyKind::Ctor(id.as_type_ctor().unwrap()) == kind
+    };
+
+    if is_kind("type-kind") {
+        Some(TypeVarKind::Type)
+    } else if is_kind("figure-kind") {
+        Some(TypeVarKind::Figure)
+    } else if is_kind("symbol-kind") {
+        Some(TypeVarKind::Symbol)
+    } else {
+        None
+    }
+}
+
 struct LowerCtx<'a> {
     db: &'a dyn MirDatabase,
     def: hir::id::DefWithBodyId,
@@ -98,6 +105,7 @@ struct LowerCtx<'a> {
     type_vars: Vec<Option<TypeVarKind>>,
     instance_records: Vec<Arc<InstanceRecord>>,
     bodies: Bodies,
+    local_funs: LocalFuns,
 }
 
 struct BodyLowerCtx<'a> {
@@ -108,6 +116,7 @@ struct BodyLowerCtx<'a> {
     builder: Builder<'a>,
     ret: LocalId,
     binders: FxHashMap<hir::PatId, Place>,
+    local_funs: &'a mut LocalFuns,
 }
 
 impl<'a> LowerCtx<'a> {
@@ -120,11 +129,14 @@ impl<'a> LowerCtx<'a> {
             infer: db.infer(def),
             type_vars: Vec::new(),
             instance_records: Vec::new(),
+            local_funs: LocalFuns::default(),
         }
     }
 
     fn finish(mut self) -> Bodies {
-        crate::post::postprocess(self.db, &mut self.bodies);
+        crate::post::postprocess(self.db, self.def, &mut self.bodies);
+        crate::opt::optimize(self.db, &mut self.bodies);
+        crate::rc::insert(self.db, self.def, &mut self.bodies);
         self.bodies
     }
 
@@ -161,6 +173,7 @@ impl<'a> LowerCtx<'a> {
             builder,
             ret,
             binders: FxHashMap::default(),
+            local_funs: &mut self.local_funs,
         };
 
         if !bcx.def.has_body(bcx.db.upcast()) {
@@ -173,6 +186,19 @@ impl<'a> LowerCtx<'a> {
 
         bcx.lower();
 
+        // lowering a body can define more local functions and closures
+        loop {
+            if let Some(pat) = self.local_funs.next() {
+                self.lower_local_fun(pat);
+            } else if let Some((pat, clos, type_args)) = self.local_funs.next_clos() {
+                self.lower_local_fun_clos(pat, clos, type_args);
+            } else if let Some(clos) = self.local_funs.next_closure() {
+                self.lower_closure(clos);
+            } else {
+                break;
+            }
+        }
+
         // eprintln!("{}", self.bodies.display(self.db.upcast()));
     }
 
@@ -316,6 +342,8 @@ impl<'a> BodyLowerCtx<'a> {
 
         self.builder.set_block(entry);
 
+        let mut args = Vec::new();
+
         for param in self.hir.params().to_vec() {
             let ty = self.infer.type_of_pat[param];
             let ty = self.db.mir_type(ty);
@@ -323,16 +351,24 @@ impl<'a> BodyLowerCtx<'a> {
             let place = Place::new(arg);
             let pat = self.convert_pat(param, place, &mut FxHashMap::default());
 
-            assert!(pat.is_none());
-            // self.lower_pat(param, place);
+            self.lower_irrefutable(pat);
+            args.push(arg);
         }
 
         let ret = Place::new(self.ret);
 
         self.lower_expr(self.hir.body_expr(), Some(ret));
+        self.drop_scope(args);
         self.builder.ret();
     }
 
+    /// Ends the scope of the locals declared in it, in reverse order of declaration.
+    fn drop_scope(&mut self, locals: Vec<LocalId>) {
+        for local in locals.into_iter().rev() {
+            self.builder.drop(local);
+        }
+    }
+
     fn lower_pat(&mut self, id: hir::PatId, place: Place) {
         let body = Arc::clone(self.hir);
         let ty = self.infer.type_of_pat[id];
@@ -341,7 +377,8 @@ impl<'a> BodyLowerCtx<'a> {
             | hir::Pat::Missing => {},
             | hir::Pat::Wildcard => {},
             | hir::Pat::Typed { pat, .. } => self.lower_pat(pat, place),
-            | hir::Pat::Bind { subpat, .. } => {
+            | hir::Pat::Bind { ref name, subpat } => {
+                self.builder.debug_var(name.clone(), id, place.clone());
                 self.binders.insert(id, place.clone());
 
                 if let Some(subpat) = subpat {
@@ -383,15 +420,18 @@ impl<'a> BodyLowerCtx<'a> {
     }
 
     fn lower_expr(&mut self, id: hir::ExprId, mut ret: Option<Place>) -> Operand {
+        let prev = self.builder.set_source(Some(id));
         let op = self.lower_expr_impl(id, &mut ret);
-
-        if let Some(ret) = ret {
+        let op = if let Some(ret) = ret {
             self.builder.use_op(ret.clone(), op);
 
             Operand::Place(ret)
         } else {
             op
-        }
+        };
+
+        self.builder.set_source(prev);
+        op
     }
 
     fn lower_expr_impl(&mut self, id: hir::ExprId, ret: &mut Option<Place>) -> Operand {
@@ -415,15 +455,14 @@ impl<'a> BodyLowerCtx<'a> {
                 let resolver = Resolver::for_expr(self.db.upcast(), self.def, id);
 
                 if let Some(ValueNs::Fixity(f)) = resolver.resolve_value_fully(self.db.upcast(), op) {
-                    let fixity = self.db.fixity_data(f);
-                    let resolver = f.resolver(self.db.upcast());
+                    let (func, res) = self.resolve_fixity(f);
 
-                    match resolver.resolve_value_fully(self.db.upcast(), &fixity.func) {
+                    match res {
                         | Some(ValueNs::Func(mut f)) => {
                             resolve_method!(
                                 self,
                                 id,
-                                &fixity.func,
+                                &func,
                                 |inst| self.lower_func_app(inst, id, vec![lhs, rhs], hir_ty, ret.take()),
                                 |idx| {
                                     // @TODO: use record
@@ -452,6 +491,10 @@ impl<'a> BodyLowerCtx<'a> {
                     let base = self.lower_expr(base, ret.take());
                     let base = self.builder.placed(base);
 
+                    if let Some(idx) = self.ctor_field(base_ty, field) {
+                        return Operand::Place(base.field(idx));
+                    }
+
                     if let Some([row]) = base_ty.match_ctor(self.db.upcast(), record_id) {
                         if let TyKind::Row(fields, _) = row.lookup(self.db.upcast()) {
                             let idx = fields.iter().position(|f| &f.name == field).unwrap();
@@ -492,32 +535,21 @@ impl<'a> BodyLowerCtx<'a> {
 
                 Operand::Place(ret)
             },
-            | hir::Expr::Do { ref stmts } => {
-                let last = stmts.len() - 1;
-
-                for (i, &stmt) in stmts.iter().enumerate() {
-                    match stmt {
-                        | hir::Stmt::Expr { expr } => {
-                            if i == last {
-                                return self.lower_expr(expr, ret.take());
-                            } else {
-                                self.lower_expr(expr, None);
-                            }
-                        },
-                        | hir::Stmt::Bind { pat, val } | hir::Stmt::Let { pat, val } => {
-                            let hir_ty = self.infer.type_of_expr[val];
-                            let ty = self.db.mir_type(hir_ty);
-                            let place = self.builder.create_var(ty);
-                            let place = Place::new(place);
-
-                            self.lower_expr(val, Some(place.clone()));
-                            self.lower_pat(pat, place);
-                        },
-                    }
+            | hir::Expr::Update { base, ref fields } => {
+                let ret = ret.take().unwrap_or_else(|| Place::new(self.builder.create_var(ty)));
+
+                self.lower_expr(base, Some(ret.clone()));
+
+                for field in fields {
+                    let idx = self.ctor_field(hir_ty, &field.name).unwrap();
+
+                    self.lower_expr(field.val, Some(ret.clone().field(idx)));
                 }
 
-                Operand::Const(Const::Tuple(Vec::new()), ty)
+                Operand::Place(ret)
             },
+            | hir::Expr::Do { ref stmts } => self.lower_block(stmts, ty, ret.take()),
+            | hir::Expr::Clos { .. } => self.lower_closure_value(id, hir_ty, ret.take()),
             | hir::Expr::If {
                 cond,
                 then,
@@ -564,22 +596,71 @@ impl<'a> BodyLowerCtx<'a> {
                 Operand::Place(ret)
             },
             | hir::Expr::Case { pred, ref arms } => {
-                let pred = self.lower_expr(pred, None);
-                let pred = self.builder.placed(pred);
-                let case = self.convert_arms(vec![pred], &arms);
+                // the scrutinee lives until the end of the case, the arms bind parts of it
+                let pred_ty = self.db.mir_type(self.infer.type_of_expr[pred]);
+                let local = self.builder.create_var(pred_ty);
+
+                self.lower_expr(pred, Some(Place::new(local)));
 
-                self.lower_case(case, ty, ret.take())
+                let case = self.convert_arms(vec![Place::new(local)], &arms);
+                let res = self.lower_case(case, ty, ret.take());
+
+                self.builder.drop(local);
+                res
             },
             | ref e => unimplemented!("{:?}", e),
         }
     }
 
+    fn lower_block(&mut self, stmts: &[hir::Stmt], ty: Arc<Type>, mut ret: Option<Place>) -> Operand {
+        let last = stmts.len() - 1;
+        let mut locals = Vec::new();
+        let mut res = Operand::Const(Const::Tuple(Vec::new()), ty.clone());
+
+        for (i, &stmt) in stmts.iter().enumerate() {
+            match stmt {
+                | hir::Stmt::Expr { expr } => {
+                    if i == last && locals.is_empty() {
+                        return self.lower_expr(expr, ret);
+                    } else if i == last {
+                        // the result may refer to the locals, so it is moved out before they are dropped
+                        let place = ret
+                            .take()
+                            .unwrap_or_else(|| Place::new(self.builder.create_var(ty.clone())));
+
+                        res = self.lower_expr(expr, Some(place));
+                        break;
+                    } else {
+                        self.lower_expr(expr, None);
+                    }
+                },
+                | hir::Stmt::Bind { pat, val } | hir::Stmt::Let { pat, val } => {
+                    let hir_ty = self.infer.type_of_expr[val];
+                    let ty = self.db.mir_type(hir_ty);
+                    let local = self.builder.create_var(ty);
+                    let place = Place::new(local);
+
+                    self.lower_expr(val, Some(place.clone()));
+                    self.lower_pat(pat, place);
+                    locals.push(local);
+                },
+                | hir::Stmt::Fun { pat, val } => self.define_local_fun(pat, val),
+            }
+        }
+
+        self.drop_scope(locals);
+        res
+    }
+
     fn lower_path(&mut self, expr: hir::ExprId, path: &hir::Path, mut hir_ty: Ty, ret: &mut Option<Place>) -> Operand {
-        let resolver = Resolver::for_expr(self.db.upcast(), self.def, expr);
+        let (path, res) = match self.resolve_path(expr, path) {
+            | Some(ValueNs::Fixity(f)) => self.resolve_fixity(f),
+            | res => (path.clone(), res),
+        };
 
-        match resolver.resolve_value_fully(self.db.upcast(), path) {
+        match res {
             | Some(ValueNs::Func(mut id)) => {
-                resolve_method!(self, expr, path, |inst| id = inst, |rec| unimplemented!(), || {});
+                resolve_method!(self, expr, &path, |inst| id = inst, |rec| unimplemented!(), || {});
 
                 while let TyKind::ForAll(_, t) = hir_ty.lookup(self.db.upcast()) {
                     hir_ty = t;
@@ -624,7 +705,18 @@ impl<'a> BodyLowerCtx<'a> {
                     Operand::Place(ret)
                 }
             },
-            | Some(ValueNs::Local(pat)) => Operand::Place(self.binders[&pat].clone()),
+            | Some(ValueNs::Local(pat)) => match self.local_funs.get(pat) {
+                | Some(fun) if fun.captures.is_empty() && !self.has_type_args(pat) => {
+                    let id = BodyId {
+                        def: self.def,
+                        local_id: fun.body,
+                    };
+
+                    Operand::Const(Const::Addr(id), fun.ty.clone())
+                },
+                | Some(_) => self.lower_local_fun_value(pat, expr, hir_ty, ret.take()),
+                | None => Operand::Place(self.binders[&pat].clone()),
+            },
             | Some(ValueNs::Const(id)) => match self.db.eval(id.into()) {
                 | crate::eval::EvalResult::Finished(c) => Operand::Const(c, self.db.mir_type(hir_ty)),
                 | _ => Operand::Const(Const::Undefined, self.db.mir_type(hir_ty)),
@@ -652,18 +744,26 @@ impl<'a> BodyLowerCtx<'a> {
         }
 
         let func = if let hir::Expr::Path { path } = &body[base] {
-            match self.resolve_path(base, path) {
+            let (path, res) = match self.resolve_path(base, path) {
+                | Some(ValueNs::Fixity(f)) => self.resolve_fixity(f),
+                | res => (path.clone(), res),
+            };
+
+            match res {
                 | Some(ValueNs::Func(mut id)) => {
                     resolve_method!(
                         self,
                         base,
-                        path,
+                        &path,
                         |inst| return self.lower_func_app(inst, base, args, ret_ty, ret),
                         |idx| Operand::Record(idx, path.segments().last().unwrap().clone()),
                         || return self.lower_func_app(id, base, args, ret_ty, ret)
                     )
                 },
                 | Some(ValueNs::Ctor(id)) => return self.lower_ctor_app(id, args, ret_ty, ret),
+                | Some(ValueNs::Local(pat)) if self.is_saturated_local_fun(pat, args.len()) => {
+                    return self.lower_local_fun_app(pat, base, args, ret_ty, ret);
+                },
                 | _ => self.lower_expr(base, None),
             }
         } else {
@@ -688,6 +788,37 @@ impl<'a> BodyLowerCtx<'a> {
         resolver.resolve_value_fully(self.db.upcast(), path)
     }
 
+    /// The index of a named field of a type with a single constructor.
+    fn ctor_field(&self, mut ty: Ty, field: &hir::Name) -> Option<usize> {
+        while let TyKind::App(base, _) = ty.lookup(self.db.upcast()) {
+            ty = base;
+        }
+
+        match ty.lookup(self.db.upcast()) {
+            | TyKind::Ctor(id) => {
+                let data = self.db.type_ctor_data(id);
+
+                if data.ctors.len() != 1 {
+                    return None;
+                }
+
+                let (_, ctor) = data.ctors.iter().next()?;
+
+                ctor.field(field)
+            },
+            | _ => None,
+        }
+    }
+
+    /// Resolves the function an operator refers to, along with its path.
+    fn resolve_fixity(&self, id: hir::id::FixityId) -> (hir::Path, Option<ValueNs>) {
+        let fixity = self.db.fixity_data(id);
+        let resolver = id.resolver(self.db.upcast());
+        let res = resolver.resolve_value_fully(self.db.upcast(), &fixity.func);
+
+        (fixity.func.clone(), res)
+    }
+
     fn lower_func_app(
         &mut self,
         func: hir::id::FuncId,
@@ -707,6 +838,11 @@ impl<'a> BodyLowerCtx<'a> {
 
             match name.as_str() {
                 | "unsafe" => return self.lower_expr(args.remove(0), Some(ret)),
+                | "crash" => {
+                    let msg = self.lower_expr(args.remove(0), None);
+
+                    self.lower_crash(msg);
+                },
                 | "apply" => match self.lower_expr(args.remove(0), None) {
                     | Operand::Const(
                         Const::Addr(BodyId {
@@ -748,6 +884,13 @@ impl<'a> BodyLowerCtx<'a> {
 
                     self.builder.use_op(ret.clone(), Operand::Place(arg.deref()));
                 },
+                | "rc_get" => {
+                    let arg = self.lower_expr(args[0], None);
+                    let arg = self.builder.placed(arg);
+
+                    // skip the reference count
+                    self.builder.use_op(ret.clone(), Operand::Place(arg.deref().field(1)));
+                },
                 | "ptr_write" => {
                     let ptr = self.lower_expr(args[0], None);
                     let ptr = self.builder.placed(ptr);
@@ -814,6 +957,82 @@ impl<'a> BodyLowerCtx<'a> {
         Operand::Place(ret)
     }
 
+    fn is_intrinsic(&self, func: hir::id::FuncId, name: &str) -> bool {
+        self.db.attrs(func.into()).by_key("intrinsic").exists() && self.db.func_data(func).name.to_string() == name
+    }
+
+    /// Lowers `intrinsics/crash` with the message `msg`, lowering continues in an unreachable block.
+    fn lower_crash(&mut self, msg: Operand) {
+        let next = self.builder.create_block();
+
+        self.lower_panic_with(|_| msg);
+        self.builder.set_block(next);
+    }
+
+    /// Ends the current block with a call to the `panic-handler` lang item, passing it `msg` and the
+    /// file and line of the expression that is being lowered. Without a panic handler the block just aborts.
+    pub fn lower_panic(&mut self, msg: &str) {
+        self.lower_panic_with(|ty| Operand::Const(Const::String(msg.into()), ty));
+    }
+
+    /// Like `lower_panic`, the message is created by `msg` from the type of the message parameter.
+    fn lower_panic_with(&mut self, msg: impl FnOnce(Arc<Type>) -> Operand) {
+        if let Some((id, func_lyt)) = self.lang_func("panic-handler", 3) {
+            let (params, ret_ty) = match &func_lyt.kind {
+                | TypeKind::Func(sig) => (sig.params.clone(), sig.ret.clone()),
+                | _ => unreachable!(),
+            };
+
+            let (file, line) = self.source_location();
+            let ret = Place::new(self.builder.create_var(ret_ty));
+            let args = vec![
+                msg(params[0].clone()),
+                Operand::Const(Const::String(file), params[1].clone()),
+                Operand::Const(Const::Scalar(line as u128), params[2].clone()),
+            ];
+
+            self.builder.call(ret, Operand::Const(Const::Addr(id), func_lyt), args);
+        }
+
+        self.builder.abort();
+    }
+
+    /// The body and signature of the lang item function `name` applied to `arity` arguments.
+    pub fn lang_func(&mut self, name: &str, arity: usize) -> Option<(BodyId, Arc<Type>)> {
+        let lib = self.def.module(self.db.upcast()).lib;
+        let func = self.db.lang_item(lib, name.into())?.as_func()?;
+        let def: DefWithBodyId = func.into();
+
+        if def == self.def {
+            let (id, _) = self.builder.arity(def, arity);
+
+            Some((id, self.builder.signature(id.local_id)))
+        } else {
+            let bodies = self.db.body_mir(def);
+            let (id, _) = bodies.arity(def, arity);
+
+            Some((id, bodies.signature(id.local_id)))
+        }
+    }
+
+    /// The file and line (starting at 1) of the expression that is being lowered.
+    fn source_location(&self) -> (String, u32) {
+        let (_, source_map) = self.db.body_source_map(self.def);
+        let src = self.builder.source().and_then(|expr| source_map.expr_syntax(expr).ok());
+
+        match src {
+            | Some(src) => {
+                let source_root = self.db.source_root(self.db.file_source_root(src.file_id));
+                let file = source_root.relative_path(src.file_id).to_string();
+                let offset = src.value.syntax_node_ptr().range().start();
+                let line = self.db.line_index(src.file_id).line_col(offset).line;
+
+                (file, line + 1)
+            },
+            | None => ("<unknown>".into(), 0),
+        }
+    }
+
     fn lang_type(&self, name: &'static str) -> hir::id::TypeCtorId {
         let item = self
             .db
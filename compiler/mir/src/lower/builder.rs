@@ -10,6 +10,7 @@ pub struct Builder<'a> {
     body: LocalBodyId,
     block: Option<BlockId>,
     unit_tmp: Option<LocalId>,
+    source: Option<hir::ExprId>,
 }
 
 impl Bodies {
@@ -31,6 +32,7 @@ impl Bodies {
             body: id,
             block: None,
             unit_tmp: None,
+            source: None,
         }
     }
 }
@@ -50,6 +52,17 @@ impl<'a> std::ops::DerefMut for Builder<'a> {
 }
 
 impl<'a> Builder<'a> {
+    /// Adds a body that is lifted out of the current body, sharing its type variables and instance records.
+    /// The lifted body has the type variables in `type_vars` in front of the shared ones.
+    pub fn add_lifted(&mut self, type_vars: Vec<Option<TypeVarKind>>) -> LocalBodyId {
+        let type_vars = type_vars.into_iter().chain(self.body().type_vars.iter().cloned()).collect();
+        let records = self.body().records.clone();
+        let id = self.bodies.add(type_vars, records);
+
+        self.bodies.lifted.insert(id, self.body);
+        id
+    }
+
     pub fn body(&self) -> &Body {
         &self.bodies[self.body]
     }
@@ -61,6 +74,7 @@ impl<'a> Builder<'a> {
     pub fn create_block(&mut self) -> BlockId {
         self.body_mut().blocks.alloc(Block {
             stmts: Vec::new(),
+            sources: Vec::new(),
             term: Term::Abort,
         })
     }
@@ -122,6 +136,19 @@ impl<'a> Builder<'a> {
         self.body().ret.unwrap()
     }
 
+    /// Sets the expression new statements are lowered from, returning the previous one.
+    pub fn set_source(&mut self, source: Option<hir::ExprId>) -> Option<hir::ExprId> {
+        std::mem::replace(&mut self.source, source)
+    }
+
+    pub fn source(&self) -> Option<hir::ExprId> {
+        self.source
+    }
+
+    pub fn debug_var(&mut self, name: hir::Name, pat: hir::PatId, place: Place) {
+        self.body_mut().var_debug_info.push(VarDebugInfo { name, pat, place });
+    }
+
     pub fn location(&self) -> Location {
         let block = self.block.unwrap();
 
@@ -199,8 +226,16 @@ impl<'a> Builder<'a> {
         self.stmt(Stmt::Call(ret, func, args));
     }
 
-    fn stmt(&mut self, stmt: Stmt) {
-        self.block().stmts.push(stmt);
+    pub fn drop(&mut self, local: LocalId) {
+        self.stmt(Stmt::Drop(Place::new(local)));
+    }
+
+    pub(crate) fn stmt(&mut self, stmt: Stmt) {
+        let source = self.source;
+        let block = self.block();
+
+        block.stmts.push(stmt);
+        block.sources.push(source);
     }
 
     fn block(&mut self) -> &mut Block {
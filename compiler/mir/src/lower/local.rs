@@ -0,0 +1,491 @@
+use super::*;
+use rustc_hash::FxHashSet;
+
+/// Local functions are lambda lifted: every local function gets its own body which takes the
+/// locals it captures as extra parameters in front of its own parameters. The type variables a local
+/// function is generalized over are passed as type arguments after its parameters. When a local function
+/// that captures locals or takes type arguments is used as a value, a closure is built whose environment
+/// holds the captures.
+/// Anonymous closures are lifted into a body which takes their environment in front of their parameters.
+#[derive(Default)]
+pub struct LocalFuns {
+    funs: FxHashMap<hir::PatId, LocalFun>,
+    queue: Vec<hir::PatId>,
+    clos_queue: Vec<(hir::PatId, LocalBodyId, Vec<Operand>)>,
+    closures: Vec<Closure>,
+}
+
+pub struct LocalFun {
+    pub body: LocalBodyId,
+    pub val: hir::ExprId,
+    pub captures: Vec<hir::PatId>,
+    pub ty: Arc<Type>,
+    /// The kinds of the type variables the local function is generalized over.
+    pub type_vars: Vec<Option<TypeVarKind>>,
+    /// The code of the closure, which unpacks the environment and calls the lifted body.
+    pub clos: Option<LocalBodyId>,
+}
+
+pub struct Closure {
+    pub body: LocalBodyId,
+    pub val: hir::ExprId,
+    pub captures: Vec<hir::PatId>,
+    /// The type of the code of the closure, which takes the environment in front of the parameters.
+    pub ty: Arc<Type>,
+}
+
+impl LocalFuns {
+    pub fn get(&self, pat: hir::PatId) -> Option<&LocalFun> {
+        self.funs.get(&pat)
+    }
+
+    pub fn next(&mut self) -> Option<hir::PatId> {
+        self.queue.pop()
+    }
+
+    pub fn next_clos(&mut self) -> Option<(hir::PatId, LocalBodyId, Vec<Operand>)> {
+        self.clos_queue.pop()
+    }
+
+    pub fn next_closure(&mut self) -> Option<Closure> {
+        self.closures.pop()
+    }
+}
+
+impl LowerCtx<'_> {
+    pub(super) fn lower_local_fun(&mut self, pat: hir::PatId) {
+        let fun = self.local_funs.get(pat).unwrap();
+        let (local_id, captures) = (fun.body, fun.captures.clone());
+        let (params, expr) = match &self.hir[fun.val] {
+            | hir::Expr::Clos { pats, stmts } => match stmts[..] {
+                | [hir::Stmt::Expr { expr }] => (pats.clone(), expr),
+                | _ => unreachable!(),
+            },
+            | _ => unreachable!(),
+        };
+
+        let mut builder = self.bodies.builder(local_id);
+        let ret = builder.create_ret(self.db.mir_type(self.infer.type_of_expr[expr]));
+        let mut bcx = BodyLowerCtx {
+            db: self.db,
+            def: self.def,
+            hir: &self.hir,
+            infer: &self.infer,
+            builder,
+            ret,
+            binders: FxHashMap::default(),
+            local_funs: &mut self.local_funs,
+        };
+
+        let entry = bcx.builder.create_block();
+
+        bcx.builder.set_block(entry);
+
+        let mut args = Vec::new();
+
+        for capture in captures {
+            let ty = bcx.db.mir_type(bcx.infer.type_of_pat[capture]);
+            let arg = bcx.builder.create_arg(ty);
+
+            bcx.binders.insert(capture, Place::new(arg));
+            args.push(arg);
+        }
+
+        for param in params {
+            let ty = bcx.db.mir_type(bcx.infer.type_of_pat[param]);
+            let arg = bcx.builder.create_arg(ty);
+            let pat = bcx.convert_pat(param, Place::new(arg), &mut FxHashMap::default());
+
+            bcx.lower_irrefutable(pat);
+            args.push(arg);
+        }
+
+        bcx.lower_expr(expr, Some(Place::new(ret)));
+        bcx.drop_scope(args);
+        bcx.builder.ret();
+    }
+
+    /// Lowers the code of the closure of a local function, which takes a pointer to the captured
+    /// locals in front of the parameters of the local function and passes along `type_args`.
+    pub(super) fn lower_local_fun_clos(&mut self, pat: hir::PatId, clos: LocalBodyId, type_args: Vec<Operand>) {
+        let fun = self.local_funs.get(pat).unwrap();
+        let func = Operand::Const(
+            Const::Addr(BodyId {
+                def: self.def,
+                local_id: fun.body,
+            }),
+            fun.ty.clone(),
+        );
+
+        let captures = fun.captures.len();
+        let (params, ret_ty) = match &fun.ty.kind {
+            | TypeKind::Func(sig) => (sig.params.clone(), sig.ret.clone()),
+            | _ => unreachable!(),
+        };
+
+        let mut builder = self.bodies.builder(clos);
+        let ret = builder.create_ret(ret_ty);
+        let env_ty = Type::and(params[..captures].iter().cloned());
+        let env = Place::new(builder.create_arg(Type::ref_(env_ty)));
+        let args = params[captures..]
+            .iter()
+            .map(|ty| Place::new(builder.create_arg(ty.clone())))
+            .collect::<Vec<_>>();
+
+        let entry = builder.create_block();
+
+        builder.set_block(entry);
+
+        let ops = (0..captures)
+            .map(|i| env.clone().deref().field(i))
+            .chain(args)
+            .map(Operand::Place)
+            .chain(type_args)
+            .collect();
+
+        builder.call(Place::new(ret), func, ops);
+        builder.ret();
+    }
+
+    /// Lowers the body of an anonymous closure, the captured locals are read from its environment.
+    pub(super) fn lower_closure(&mut self, clos: Closure) {
+        let hir = Arc::clone(&self.hir);
+        let (params, stmts) = match &hir[clos.val] {
+            | hir::Expr::Clos { pats, stmts } => (pats, stmts),
+            | _ => unreachable!(),
+        };
+
+        let (env_ty, ret_ty) = match &clos.ty.kind {
+            | TypeKind::Func(sig) => (sig.params[0].clone(), sig.ret.clone()),
+            | _ => unreachable!(),
+        };
+
+        let mut builder = self.bodies.builder(clos.body);
+        let ret = builder.create_ret(ret_ty.clone());
+        let env = Place::new(builder.create_arg(env_ty));
+        let mut bcx = BodyLowerCtx {
+            db: self.db,
+            def: self.def,
+            hir: &self.hir,
+            infer: &self.infer,
+            builder,
+            ret,
+            binders: FxHashMap::default(),
+            local_funs: &mut self.local_funs,
+        };
+
+        let entry = bcx.builder.create_block();
+
+        bcx.builder.set_block(entry);
+
+        for (i, &capture) in clos.captures.iter().enumerate() {
+            bcx.binders.insert(capture, env.clone().deref().field(i));
+        }
+
+        let mut args = Vec::new();
+
+        for &param in params {
+            let ty = bcx.db.mir_type(bcx.infer.type_of_pat[param]);
+            let arg = bcx.builder.create_arg(ty);
+            let pat = bcx.convert_pat(param, Place::new(arg), &mut FxHashMap::default());
+
+            bcx.lower_irrefutable(pat);
+            args.push(arg);
+        }
+
+        bcx.lower_block(stmts, ret_ty, Some(Place::new(ret)));
+        bcx.drop_scope(args);
+        bcx.builder.ret();
+    }
+}
+
+impl BodyLowerCtx<'_> {
+    /// Allocates the body of a local function, it is lowered after the current body is finished.
+    pub(super) fn define_local_fun(&mut self, pat: hir::PatId, val: hir::ExprId) {
+        let (params, expr) = match &self.hir[val] {
+            | hir::Expr::Clos { pats, stmts } => match stmts[..] {
+                | [hir::Stmt::Expr { expr }] => (pats.clone(), expr),
+                | _ => unreachable!(),
+            },
+            | _ => unreachable!(),
+        };
+
+        let captures = self.local_fun_captures(pat, val);
+        let params = captures
+            .iter()
+            .chain(params.iter())
+            .map(|&p| self.db.mir_type(self.infer.type_of_pat[p]))
+            .collect();
+
+        let ret = self.db.mir_type(self.infer.type_of_expr[expr]);
+        let ty = Type::func(params, ret);
+        let mut fun_ty = self.infer.type_of_pat[pat];
+        let mut type_vars = Vec::new();
+
+        while let TyKind::ForAll(kind, inner) = fun_ty.lookup(self.db.upcast()) {
+            type_vars.push(super::type_var_kind(self.db, self.def, kind));
+            fun_ty = inner;
+        }
+
+        let body = self.builder.add_lifted(type_vars.clone());
+
+        self.local_funs.funs.insert(pat, LocalFun {
+            body,
+            val,
+            captures,
+            ty,
+            type_vars,
+            clos: None,
+        });
+
+        self.local_funs.queue.push(pat);
+    }
+
+    pub(super) fn is_saturated_local_fun(&self, pat: hir::PatId, args: usize) -> bool {
+        match self.local_funs.get(pat) {
+            | Some(fun) => match &self.hir[fun.val] {
+                | hir::Expr::Clos { pats, .. } => pats.len() == args,
+                | _ => false,
+            },
+            | None => false,
+        }
+    }
+
+    /// Whether a local function is generalized over type variables that are passed at runtime.
+    pub(super) fn has_type_args(&self, pat: hir::PatId) -> bool {
+        self.local_funs.get(pat).map_or(false, |fun| fun.type_vars.iter().any(Option::is_some))
+    }
+
+    /// Calls a local function directly, passing along the locals it captures and its type arguments.
+    pub(super) fn lower_local_fun_app(
+        &mut self,
+        pat: hir::PatId,
+        expr: hir::ExprId,
+        args: Vec<hir::ExprId>,
+        ret_ty: Ty,
+        ret: Option<Place>,
+    ) -> Operand {
+        let fun = self.local_funs.get(pat).unwrap();
+        let func = Const::Addr(BodyId {
+            def: self.def,
+            local_id: fun.body,
+        });
+
+        let func = Operand::Const(func, fun.ty.clone());
+        let mut ops = fun
+            .captures
+            .iter()
+            .map(|c| Operand::Place(self.binders[c].clone()))
+            .collect::<Vec<_>>();
+
+        ops.extend(args.into_iter().map(|a| self.lower_expr(a, None)));
+        ops.extend(self.local_type_args(pat, expr));
+
+        let ret = ret.unwrap_or_else(|| {
+            let ty = self.db.mir_type(ret_ty);
+
+            Place::new(self.builder.create_var(ty))
+        });
+
+        self.builder.call(ret.clone(), func, ops);
+
+        Operand::Place(ret)
+    }
+
+    /// Builds a closure for a local function that is used as a value, its environment is a pointer to
+    /// a copy of the locals it captures. Local functions with type arguments get a closure for every use.
+    pub(super) fn lower_local_fun_value(
+        &mut self,
+        pat: hir::PatId,
+        expr: hir::ExprId,
+        ty: Ty,
+        ret: Option<Place>,
+    ) -> Operand {
+        let type_args = self.local_type_args(pat, expr);
+        let clos = match self.local_funs.get(pat).unwrap().clos {
+            | Some(clos) => clos,
+            | None => {
+                let clos = self.builder.add_lifted(Vec::new());
+
+                if !self.has_type_args(pat) {
+                    self.local_funs.funs.get_mut(&pat).unwrap().clos = Some(clos);
+                }
+
+                self.local_funs.clos_queue.push((pat, clos, type_args));
+                clos
+            },
+        };
+
+        let fun = self.local_funs.get(pat).unwrap();
+        let captures = fun.captures.clone();
+        let (params, ret_ty) = match &fun.ty.kind {
+            | TypeKind::Func(sig) => (sig.params.clone(), sig.ret.clone()),
+            | _ => unreachable!(),
+        };
+
+        let env_ty = Type::and(params[..captures.len()].iter().cloned());
+        let clos_params = std::iter::once(Type::ref_(env_ty.clone()))
+            .chain(params[captures.len()..].iter().cloned())
+            .collect();
+
+        let code = Operand::Const(
+            Const::Addr(BodyId {
+                def: self.def,
+                local_id: clos,
+            }),
+            Type::func(clos_params, ret_ty),
+        );
+
+        self.build_closure(&captures, env_ty, code, self.db.mir_type(ty), ret)
+    }
+
+    /// Lifts an anonymous closure into its own body and builds the closure value, its environment is
+    /// a pointer to a copy of the locals it captures.
+    pub(super) fn lower_closure_value(&mut self, val: hir::ExprId, ty: Ty, ret: Option<Place>) -> Operand {
+        let params = match &self.hir[val] {
+            | hir::Expr::Clos { pats, .. } => pats.clone(),
+            | _ => unreachable!(),
+        };
+
+        let captures = self.closure_captures(val);
+        let env_ty = Type::and(captures.iter().map(|&c| self.db.mir_type(self.infer.type_of_pat[c])));
+
+        let clos_params = std::iter::once(Type::ref_(env_ty.clone()))
+            .chain(params.iter().map(|&p| self.db.mir_type(self.infer.type_of_pat[p])))
+            .collect();
+
+        let ret_ty = self.db.mir_type(self.closure_ret_type(ty, params.len()));
+        let clos_ty = Type::func(clos_params, ret_ty);
+        let body = self.builder.add_lifted(Vec::new());
+        let code = Operand::Const(
+            Const::Addr(BodyId {
+                def: self.def,
+                local_id: body,
+            }),
+            clos_ty.clone(),
+        );
+
+        self.local_funs.closures.push(Closure {
+            body,
+            val,
+            captures: captures.clone(),
+            ty: clos_ty,
+        });
+
+        self.build_closure(&captures, env_ty, code, self.db.mir_type(ty), ret)
+    }
+
+    /// Copies the captured locals into a new environment and pairs it with the code of the closure.
+    fn build_closure(
+        &mut self,
+        captures: &[hir::PatId],
+        env_ty: Arc<Type>,
+        code: Operand,
+        ty: Arc<Type>,
+        ret: Option<Place>,
+    ) -> Operand {
+        let ret = ret.unwrap_or_else(|| Place::new(self.builder.create_var(ty)));
+        let env = Place::new(self.builder.create_var(Type::ref_(env_ty.clone())));
+
+        self.builder.alloc(self.db, env.clone(), env_ty);
+
+        for (i, capture) in captures.iter().enumerate() {
+            let capture = Operand::Place(self.binders[capture].clone());
+
+            self.builder.use_op(env.clone().deref().field(i), capture);
+        }
+
+        self.builder.use_op(ret.clone().field(0), Operand::Place(env));
+        self.builder.use_op(ret.clone().field(1), code);
+
+        Operand::Place(ret)
+    }
+
+    /// The type arguments of the local function `pat` at its use `expr`. Uses inside of the local function
+    /// itself are not instantiated, they pass along the type arguments of the local function instead.
+    fn local_type_args(&self, pat: hir::PatId, expr: hir::ExprId) -> Vec<Operand> {
+        let fun = self.local_funs.get(pat).unwrap();
+        let types = match self.infer.instances.get(&expr) {
+            | Some(types) => types,
+            | None => return Vec::new(),
+        };
+
+        fun.type_vars
+            .iter()
+            .zip(types.iter())
+            .filter_map(|(kind, &ty)| match (kind.as_ref()?, ty.lookup(self.db.upcast())) {
+                | (TypeVarKind::Type, _) => {
+                    let lyt = self.db.layout_of(self.db.mir_type(ty));
+
+                    Some(Operand::Const(Const::type_info(&lyt), Type::type_info(self.db)))
+                },
+                | (TypeVarKind::Figure, TyKind::Figure(n)) => {
+                    Some(Operand::Const(Const::Scalar(n as u128), Type::ptr_sized_int(self.db, true)))
+                },
+                | (TypeVarKind::Symbol, TyKind::Symbol(s)) => {
+                    Some(Operand::Const(Const::String(s), Type::str_slice(self.db)))
+                },
+                | (TypeVarKind::Figure, _) => {
+                    Some(Operand::Const(Const::Undefined, Type::ptr_sized_int(self.db, true)))
+                },
+                | (TypeVarKind::Symbol, _) => Some(Operand::Const(Const::Undefined, Type::str_slice(self.db))),
+            })
+            .collect()
+    }
+
+    /// The return type of a closure of type `ty` with `arity` parameters.
+    fn closure_ret_type(&self, mut ty: Ty, arity: usize) -> Ty {
+        let func_id = self.lang_type("fn-type");
+
+        for _ in 0..arity {
+            ty = match ty.match_ctor(self.db.upcast(), func_id) {
+                | Some([_, ret]) => ret,
+                | _ => unreachable!(),
+            };
+        }
+
+        ty
+    }
+
+    /// The locals from outside of `val` used by the local function `pat`, including the locals
+    /// captured by the local functions it calls.
+    fn local_fun_captures(&self, pat: hir::PatId, val: hir::ExprId) -> Vec<hir::PatId> {
+        let mut captures = self.closure_captures(val);
+
+        captures.retain(|&c| c != pat);
+        captures
+    }
+
+    /// The locals from outside of the closure `val` which it uses.
+    fn closure_captures(&self, val: hir::ExprId) -> Vec<hir::PatId> {
+        let scopes = self.db.expr_scopes(self.def);
+        let visible = scopes
+            .scope_chain(scopes.scope_for(val))
+            .flat_map(|scope| scopes.entries(scope).iter().map(|entry| entry.pat()))
+            .collect::<FxHashSet<_>>();
+
+        let mut captures = Vec::new();
+
+        self.collect_captures(val, &visible, &mut captures);
+        captures
+    }
+
+    fn collect_captures(&self, expr: hir::ExprId, visible: &FxHashSet<hir::PatId>, captures: &mut Vec<hir::PatId>) {
+        if let hir::Expr::Path { path } = &self.hir[expr] {
+            if let Some(ValueNs::Local(pat)) = self.resolve_path(expr, path) {
+                let pats = match self.local_funs.get(pat) {
+                    | Some(fun) => fun.captures.clone(),
+                    | None => vec![pat],
+                };
+
+                for pat in pats {
+                    if visible.contains(&pat) && !captures.contains(&pat) {
+                        captures.push(pat);
+                    }
+                }
+            }
+        }
+
+        self.hir[expr].walk(|e| self.collect_captures(e, visible, captures));
+    }
+}
@@ -72,7 +72,18 @@ impl BodyLowerCtx<'_> {
             | hir::Pat::Typed { pat, .. } => self.convert_pat(*pat, pred, discrs),
             | hir::Pat::Missing => None,
             | hir::Pat::Wildcard => None,
-            | hir::Pat::Bind { subpat, .. } => {
+            | hir::Pat::Lit { lit } => {
+                let val = match *lit {
+                    | hir::Literal::Int(i) => CheckVal::Scalar(i as u128),
+                    | hir::Literal::Float(_) => unreachable!("float patterns are rejected by type inference"),
+                    | hir::Literal::Char(c) => CheckVal::Scalar(c as u128),
+                    | hir::Literal::String(ref s) => CheckVal::String(s.clone()),
+                };
+
+                Some(Pattern::Check(Operand::Place(pred), val))
+            },
+            | hir::Pat::Bind { name, subpat } => {
+                self.builder.debug_var(name.clone(), pat, pred.clone());
                 self.binders.insert(pat, pred.clone());
                 subpat.and_then(|s| self.convert_pat(s, pred, discrs))
             },
@@ -137,14 +148,13 @@ impl BodyLowerCtx<'_> {
             let pred = pred.downcast(idx);
             let preds = (0..args.len()).map(|i| pred.clone().field(i)).collect::<Vec<_>>();
 
-            if let Some(mut pats) = self.convert_pats(&preds, args, discrs) {
-                if let Pattern::And(pats) = &mut pats {
+            match self.convert_pats(&preds, args, discrs) {
+                | Some(Pattern::And(mut pats)) => {
                     pats.insert(0, discr);
-                }
-
-                Some(pats)
-            } else {
-                Some(discr)
+                    Some(Pattern::And(pats))
+                },
+                | Some(pat) => Some(Pattern::And(vec![discr, pat])),
+                | None => Some(discr),
             }
         }
     }
@@ -152,42 +162,121 @@ impl BodyLowerCtx<'_> {
     pub fn lower_case(&mut self, case: Case, ty: Arc<Type>, ret: Option<Place>) -> Operand {
         let ret = ret.unwrap_or_else(|| Place::new(self.builder.create_var(ty)));
         let exit_block = self.builder.create_block();
-        let last = case.arms.len() - 1;
 
-        for (i, arm) in case.arms.into_iter().enumerate() {
-            if let Some(pat) = arm.pat {
-                let succ = self.builder.create_block();
-                let fail = if i == last {
-                    exit_block
-                } else {
-                    self.builder.create_block()
-                };
-
-                self.lower_pattern(pat, arm.guard, succ, fail);
-                self.builder.set_block(succ);
-                self.lower_expr(arm.expr, Some(ret.clone()));
-                self.builder.jump(exit_block);
-                self.builder.set_block(fail);
-            } else {
+        for arm in case.arms {
+            if arm.pat.is_none() && arm.guard.is_none() {
                 self.lower_expr(arm.expr, Some(ret.clone()));
                 self.builder.jump(exit_block);
                 self.builder.set_block(exit_block);
-                break;
+
+                return Operand::Place(ret);
             }
+
+            let succ = self.builder.create_block();
+            let fail = self.builder.create_block();
+
+            self.lower_pattern(arm.pat, arm.guard, succ, fail);
+            self.builder.set_block(succ);
+            self.lower_expr(arm.expr, Some(ret.clone()));
+            self.builder.jump(exit_block);
+            self.builder.set_block(fail);
         }
 
+        self.lower_panic("no case arm matched");
+        self.builder.set_block(exit_block);
+
         Operand::Place(ret)
     }
 
-    fn lower_pattern(&mut self, pat: Pattern, guard: Option<hir::ExprId>, succ: BlockId, fail: BlockId) {
+    /// Checks a pattern that has to match, like a parameter of a function, and panics when it does not.
+    pub fn lower_irrefutable(&mut self, pat: Option<Pattern>) {
+        if let Some(pat) = pat {
+            let succ = self.builder.create_block();
+            let fail = self.builder.create_block();
+
+            self.lower_check(pat, succ, fail);
+            self.builder.set_block(fail);
+            self.lower_panic("refutable pattern did not match");
+            self.builder.set_block(succ);
+        }
+    }
+
+    fn lower_pattern(&mut self, pat: Option<Pattern>, guard: Option<hir::ExprId>, succ: BlockId, fail: BlockId) {
+        let matched = if guard.is_some() {
+            self.builder.create_block()
+        } else {
+            succ
+        };
+
+        match pat {
+            | Some(pat) => self.lower_check(pat, matched, fail),
+            | None => self.builder.jump(matched),
+        }
+
+        if let Some(guard) = guard {
+            self.builder.set_block(matched);
+
+            let cond = self.lower_expr(guard, None);
+
+            self.builder.switch(cond, vec![0], vec![fail, succ]);
+        }
+    }
+
+    fn lower_check(&mut self, pat: Pattern, succ: BlockId, fail: BlockId) {
         match pat {
             | Pattern::Check(op, val) => match val {
                 | CheckVal::Scalar(s) => {
                     self.builder.switch(op, vec![s], vec![succ, fail]);
                 },
-                | CheckVal::String(_) => unimplemented!(),
+                | CheckVal::String(s) => {
+                    // a missing `str-eq` is reported by type inference
+                    let (id, func_lyt) = match self.lang_func("str-eq", 2) {
+                        | Some(func) => func,
+                        | None => return self.builder.jump(fail),
+                    };
+
+                    let (params, ret_ty) = match &func_lyt.kind {
+                        | TypeKind::Func(sig) => (sig.params.clone(), sig.ret.clone()),
+                        | _ => unreachable!(),
+                    };
+
+                    let eq = Place::new(self.builder.create_var(ret_ty));
+                    let args = vec![op, Operand::Const(Const::String(s), params[1].clone())];
+
+                    self.builder.call(eq.clone(), Operand::Const(Const::Addr(id), func_lyt), args);
+                    self.builder.switch(Operand::Place(eq), vec![0], vec![fail, succ]);
+                },
+            },
+            | Pattern::And(pats) if pats.is_empty() => self.builder.jump(succ),
+            | Pattern::And(pats) => {
+                let last = pats.len() - 1;
+
+                for (i, pat) in pats.into_iter().enumerate() {
+                    if i == last {
+                        self.lower_check(pat, succ, fail);
+                    } else {
+                        let next = self.builder.create_block();
+
+                        self.lower_check(pat, next, fail);
+                        self.builder.set_block(next);
+                    }
+                }
+            },
+            | Pattern::Or(pats) if pats.is_empty() => self.builder.jump(fail),
+            | Pattern::Or(pats) => {
+                let last = pats.len() - 1;
+
+                for (i, pat) in pats.into_iter().enumerate() {
+                    if i == last {
+                        self.lower_check(pat, succ, fail);
+                    } else {
+                        let next = self.builder.create_block();
+
+                        self.lower_check(pat, succ, next);
+                        self.builder.set_block(next);
+                    }
+                }
             },
-            | _ => unimplemented!(),
         }
     }
 }
@@ -0,0 +1,502 @@
+//! Optimizations on MIR that only run in optimized profiles.
+//!
+//! Only locals of kind `Var` are rewritten, arguments and the return local are left as is.
+
+use crate::db::MirDatabase;
+use crate::eval::VM;
+use crate::intrinsic::Intrinsic;
+use crate::ir::*;
+use crate::ty::Type;
+use crate::visit::{Visitor, VisitorMut};
+use hir::arena::Arena;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    None,
+    Speed,
+    SpeedAndSize,
+}
+
+impl OptLevel {
+    /// The name of the level in the Cranelift settings.
+    pub fn name(self) -> &'static str {
+        match self {
+            | OptLevel::None => "none",
+            | OptLevel::Speed => "speed",
+            | OptLevel::SpeedAndSize => "speed_and_size",
+        }
+    }
+}
+
+pub(crate) fn optimize(db: &dyn MirDatabase, bodies: &mut Bodies) {
+    if db.opt_level() == OptLevel::None {
+        return;
+    }
+
+    for (_, body) in bodies.bodies.iter_mut() {
+        propagate_consts(db, body);
+        eliminate_copies(body);
+        remove_dead_stores(body);
+        remove_dead_blocks(body);
+    }
+}
+
+#[derive(Default)]
+struct LocalUse {
+    reads: usize,
+    writes: usize,
+    borrowed: bool,
+    /// Whether the scope of the local ends with a `Stmt::Drop`.
+    dropped: bool,
+    /// The location of the last write.
+    def: Option<Location>,
+}
+
+fn collect_uses(body: &Body) -> FxHashMap<LocalId, LocalUse> {
+    struct Collector {
+        uses: FxHashMap<LocalId, LocalUse>,
+        location: Option<Location>,
+    }
+
+    impl Collector {
+        fn read_place(&mut self, place: &Place) {
+            self.uses.entry(place.local).or_default().reads += 1;
+            self.read_elems(place);
+        }
+
+        fn read_elems(&mut self, place: &Place) {
+            for elem in &place.elems {
+                if let PlaceElem::Index(op) | PlaceElem::Offset(op) = elem {
+                    self.read_op(op);
+                }
+            }
+        }
+
+        fn write_place(&mut self, place: &Place) {
+            // writing through a pointer reads the pointer
+            if place.elems.contains(&PlaceElem::Deref) {
+                return self.read_place(place);
+            }
+
+            let uses = self.uses.entry(place.local).or_default();
+
+            uses.writes += 1;
+            uses.def = self.location;
+            self.read_elems(place);
+        }
+
+        fn read_op(&mut self, op: &Operand) {
+            if let Operand::Place(place) = op {
+                self.read_place(place);
+            }
+        }
+    }
+
+    let mut c = Collector {
+        uses: FxHashMap::default(),
+        location: None,
+    };
+
+    for (block, data) in body.blocks.iter() {
+        for (stmt, s) in data.stmts.iter().enumerate() {
+            c.location = Some(Location { block, stmt });
+
+            match s {
+                | Stmt::Assign(place, rvalue) => {
+                    match rvalue {
+                        | RValue::Use(op) => c.read_op(op),
+                        | RValue::AddrOf(place) => {
+                            if !place.elems.contains(&PlaceElem::Deref) {
+                                c.uses.entry(place.local).or_default().borrowed = true;
+                            }
+
+                            c.read_place(place);
+                        },
+                        | RValue::GetDiscr(place) => c.read_place(place),
+                        | RValue::Intrinsic(_, args) => args.iter().for_each(|a| c.read_op(a)),
+                    }
+
+                    c.write_place(place);
+                },
+                | Stmt::SetDiscr(place, _) => c.write_place(place),
+                | Stmt::Call(ret, func, args) => {
+                    c.read_op(func);
+                    args.iter().for_each(|a| c.read_op(a));
+                    c.write_place(ret);
+                },
+                // the value is still released at the end of its scope
+                | Stmt::Drop(place) => {
+                    c.uses.entry(place.local).or_default().dropped = true;
+                    c.read_place(place);
+                },
+            }
+        }
+
+        if let Term::Switch(op, _, _) = &data.term {
+            c.read_op(op);
+        }
+    }
+
+    // keep the values of user variables around for debuggers
+    for var in &body.var_debug_info {
+        c.read_place(&var.place);
+    }
+
+    c.uses
+}
+
+fn is_var(body: &Body, local: LocalId) -> bool {
+    body.locals[local].kind == LocalKind::Var
+}
+
+/// Replaces reads of locals that are only ever assigned a constant with the constant,
+/// evaluates intrinsics with constant arguments and turns switches on constants into jumps.
+fn propagate_consts(db: &dyn MirDatabase, body: &mut Body) {
+    struct Replacer<'a> {
+        consts: &'a FxHashMap<LocalId, Operand>,
+        changed: bool,
+    }
+
+    impl VisitorMut for Replacer<'_> {
+        fn visit_operand(&mut self, operand: &mut Operand) {
+            if let Operand::Place(place) = operand {
+                if place.elems.is_empty() {
+                    if let Some(op) = self.consts.get(&place.local) {
+                        *operand = op.clone();
+                        self.changed = true;
+                        return;
+                    }
+                }
+            }
+
+            self.super_operand(operand);
+        }
+    }
+
+    let mut vm = VM::new(db);
+
+    loop {
+        let uses = collect_uses(body);
+        let mut consts = FxHashMap::default();
+
+        for (_, block) in body.blocks.iter() {
+            for stmt in &block.stmts {
+                if let Stmt::Assign(place, RValue::Use(op @ Operand::Const(_, ty))) = stmt {
+                    let local = place.local;
+                    let use_ = &uses[&local];
+
+                    if place.elems.is_empty()
+                        && is_var(body, local)
+                        && use_.writes == 1
+                        && !use_.borrowed
+                        && *ty == body.locals[local].ty
+                    {
+                        consts.insert(local, op.clone());
+                    }
+                }
+            }
+        }
+
+        let mut replacer = Replacer {
+            consts: &consts,
+            changed: false,
+        };
+
+        for (_, block) in body.blocks.iter_mut() {
+            replacer.visit_block(block);
+        }
+
+        let mut changed = replacer.changed;
+
+        for (_, block) in body.blocks.iter_mut() {
+            for stmt in &mut block.stmts {
+                if let Stmt::Assign(place, rvalue @ RValue::Intrinsic(..)) = stmt {
+                    if let Some(op) = fold_intrinsic(&mut vm, rvalue, &body.locals[place.local].ty) {
+                        *rvalue = RValue::Use(op);
+                        changed = true;
+                    }
+                }
+            }
+
+            if let Term::Switch(Operand::Const(Const::Scalar(val), _), vals, targets) = &block.term {
+                let target = match vals.iter().position(|v| v == val) {
+                    | Some(i) => targets[i],
+                    | None => *targets.last().unwrap(),
+                };
+
+                block.term = Term::Jump(target);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn fold_intrinsic(vm: &mut VM, rvalue: &RValue, ty: &Arc<Type>) -> Option<Operand> {
+    let (name, args) = match rvalue {
+        | RValue::Intrinsic(name, args) => (name, args),
+        | _ => return None,
+    };
+
+    // only the scalar intrinsics are pure
+    Intrinsic::parse(name)?;
+
+    let args = args
+        .iter()
+        .map(|a| match a {
+            | Operand::Const(c, _) => Some(c.clone()),
+            | _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    match vm.eval_intrinsic(name, &args)? {
+        | c @ Const::Scalar(_) => Some(Operand::Const(c, ty.clone())),
+        | _ => None,
+    }
+}
+
+/// Removes copies `a = b` where `a` is assigned only once and `b` does not change while `a` is alive,
+/// replacing every use of `a` with `b`.
+fn eliminate_copies(body: &mut Body) {
+    struct Renamer<'a> {
+        copies: &'a FxHashMap<LocalId, LocalId>,
+    }
+
+    impl VisitorMut for Renamer<'_> {
+        fn visit_place(&mut self, place: &mut Place) {
+            if let Some(&local) = self.copies.get(&place.local) {
+                place.local = local;
+            }
+
+            self.super_place(place);
+        }
+    }
+
+    let uses = collect_uses(body);
+    let mut copies = FxHashMap::default();
+    let mut removed = FxHashSet::default();
+
+    for (block, data) in body.blocks.iter() {
+        for (i, stmt) in data.stmts.iter().enumerate() {
+            if let Stmt::Assign(a, RValue::Use(Operand::Place(b))) = stmt {
+                if !a.elems.is_empty() || !b.elems.is_empty() || a.local == b.local || !is_var(body, a.local) {
+                    continue;
+                }
+
+                let (a_use, b_use) = (&uses[&a.local], &uses[&b.local]);
+
+                if a_use.writes != 1 || a_use.borrowed || b_use.borrowed {
+                    continue;
+                }
+
+                // both locals own their value until the end of their own scope, merging them would
+                // release the value at the end of the inner scope while the outer local still uses it
+                if a_use.dropped || b_use.dropped {
+                    continue;
+                }
+
+                if body.locals[a.local].ty != body.locals[b.local].ty {
+                    continue;
+                }
+
+                // `b` must be assigned before the copy in the same block, and `a` may not be read
+                // in that block before the copy, so that reads of `a` always see the current value of `b`.
+                let b_is_stable = match body.locals[b.local].kind {
+                    | LocalKind::Arg => b_use.writes == 0,
+                    | LocalKind::Var => {
+                        b_use.writes == 1
+                            && matches!(b_use.def, Some(def) if def.block == block && def.stmt < i)
+                            && !data.stmts[..i].iter().any(|s| mentions_local(s, a.local))
+                    },
+                    | LocalKind::Ret => false,
+                };
+
+                if b_is_stable {
+                    copies.insert(a.local, b.local);
+                    removed.insert(Location { block, stmt: i });
+                }
+            }
+        }
+    }
+
+    if copies.is_empty() {
+        return;
+    }
+
+    // resolve chains of copies so that every local is renamed to the original
+    let roots = copies
+        .keys()
+        .map(|&a| {
+            let mut root = a;
+
+            for _ in 0..copies.len() {
+                match copies.get(&root) {
+                    | Some(&b) => root = b,
+                    | None => break,
+                }
+            }
+
+            (a, root)
+        })
+        .collect::<FxHashMap<_, _>>();
+
+    for (block, data) in body.blocks.iter_mut() {
+        let mut i = 0;
+
+        retain_stmts(data, |_| {
+            i += 1;
+            !removed.contains(&Location { block, stmt: i - 1 })
+        });
+    }
+
+    let mut renamer = Renamer { copies: &roots };
+
+    for (_, block) in body.blocks.iter_mut() {
+        renamer.visit_block(block);
+    }
+
+    for var in &mut body.var_debug_info {
+        renamer.visit_place(&mut var.place);
+    }
+}
+
+fn mentions_local(stmt: &Stmt, local: LocalId) -> bool {
+    struct Finder {
+        local: LocalId,
+        found: bool,
+    }
+
+    impl Visitor for Finder {
+        fn visit_place(&mut self, place: &Place) {
+            self.found |= place.local == self.local;
+            self.super_place(place);
+        }
+    }
+
+    let mut finder = Finder { local, found: false };
+
+    finder.visit_stmt(stmt);
+    finder.found
+}
+
+/// Removes side effect free assignments to locals that are never read.
+fn remove_dead_stores(body: &mut Body) {
+    loop {
+        let uses = collect_uses(body);
+        let locals = &body.locals;
+        let is_dead = |place: &Place| {
+            let use_ = &uses[&place.local];
+
+            locals[place.local].kind == LocalKind::Var
+                && use_.reads == 0
+                && !use_.borrowed
+                && !place.elems.contains(&PlaceElem::Deref)
+        };
+
+        let mut changed = false;
+
+        for (_, block) in body.blocks.iter_mut() {
+            let len = block.stmts.len();
+
+            retain_stmts(block, |stmt| match stmt {
+                | Stmt::Assign(place, RValue::Use(_) | RValue::AddrOf(_) | RValue::GetDiscr(_)) => !is_dead(place),
+                | Stmt::SetDiscr(place, _) => !is_dead(place),
+                | _ => true,
+            });
+
+            changed |= block.stmts.len() != len;
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Removes blocks that cannot be reached from the entry block, after threading jumps through empty blocks.
+fn remove_dead_blocks(body: &mut Body) {
+    let entry = match body.entry {
+        | Some(entry) => entry,
+        | None => return,
+    };
+
+    let blocks = &body.blocks;
+    let thread = |mut to: BlockId| {
+        for _ in 0..blocks.len() {
+            match &blocks[to] {
+                | Block {
+                    stmts,
+                    term: Term::Jump(next),
+                    ..
+                } if stmts.is_empty() => to = *next,
+                | _ => break,
+            }
+        }
+
+        to
+    };
+
+    let entry = thread(entry);
+    let terms = blocks
+        .iter()
+        .map(|(_, block)| match &block.term {
+            | Term::Jump(to) => Term::Jump(thread(*to)),
+            | Term::Switch(op, vals, targets) => {
+                Term::Switch(op.clone(), vals.clone(), targets.iter().map(|&t| thread(t)).collect())
+            },
+            | term => term.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    for ((_, block), term) in body.blocks.iter_mut().zip(terms) {
+        block.term = term;
+    }
+
+    let mut reachable = FxHashSet::default();
+    let mut stack = vec![entry];
+
+    while let Some(block) = stack.pop() {
+        if reachable.insert(block) {
+            match &body.blocks[block].term {
+                | Term::Jump(to) => stack.push(*to),
+                | Term::Switch(_, _, targets) => stack.extend(targets.iter().copied()),
+                | Term::Abort | Term::Return => {},
+            }
+        }
+    }
+
+    let mut blocks = Arena::default();
+    let mut map = FxHashMap::default();
+
+    for (id, block) in body.blocks.iter() {
+        if reachable.contains(&id) {
+            map.insert(id, blocks.alloc(block.clone()));
+        }
+    }
+
+    for (_, block) in blocks.iter_mut() {
+        match &mut block.term {
+            | Term::Jump(to) => *to = map[to],
+            | Term::Switch(_, _, targets) => targets.iter_mut().for_each(|t| *t = map[t]),
+            | Term::Abort | Term::Return => {},
+        }
+    }
+
+    body.entry = Some(map[&entry]);
+    body.blocks = blocks;
+}
+
+/// Removes statements from a block, keeping the sources in sync.
+fn retain_stmts(block: &mut Block, mut f: impl FnMut(&Stmt) -> bool) {
+    let keep = block.stmts.iter().map(|s| f(s)).collect::<Vec<_>>();
+    let mut keep_stmts = keep.iter();
+    let mut keep_sources = keep.iter();
+
+    block.stmts.retain(|_| *keep_stmts.next().unwrap());
+    block.sources.retain(|_| *keep_sources.next().unwrap());
+}
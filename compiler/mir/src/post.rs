@@ -4,14 +4,16 @@ use crate::lower::builder::Builder;
 use crate::ty::{Type, TypeVarKind};
 use crate::visit::VisitorMut;
 use hir::display::HirDisplay;
+use hir::id::DefWithBodyId;
 use hir::ty::TypeVar;
 use rustc_hash::FxHashMap;
 
-pub fn postprocess(db: &dyn MirDatabase, bodies: &mut Bodies) {
+pub fn postprocess(db: &dyn MirDatabase, def: DefWithBodyId, bodies: &mut Bodies) {
     let ids = bodies.bodies.iter().map(|(id, _)| id).collect::<Vec<_>>();
+    let lifted = bodies.lifted.clone();
 
     for id in ids {
-        process_body(db, bodies.builder(id));
+        process_body(db, def, &lifted, id, bodies.builder(id));
     }
 
     // eprintln!("{}", bodies.display(db.upcast()));
@@ -19,14 +21,26 @@ pub fn postprocess(db: &dyn MirDatabase, bodies: &mut Bodies) {
 
 struct PostCtx<'a> {
     db: &'a dyn MirDatabase,
+    def: DefWithBodyId,
+    lifted: &'a FxHashMap<LocalBodyId, LocalBodyId>,
+    id: LocalBodyId,
     builder: Builder<'a>,
     type_vars: Vec<Option<Place>>,
     records: Vec<Place>,
 }
 
-fn process_body(db: &dyn MirDatabase, builder: Builder) {
+fn process_body<'a>(
+    db: &'a dyn MirDatabase,
+    def: DefWithBodyId,
+    lifted: &'a FxHashMap<LocalBodyId, LocalBodyId>,
+    id: LocalBodyId,
+    builder: Builder<'a>,
+) {
     PostCtx {
         db,
+        def,
+        lifted,
+        id,
         builder,
         type_vars: Vec::new(),
         records: Vec::new(),
@@ -70,6 +84,29 @@ impl<'a> PostCtx<'a> {
 }
 
 impl VisitorMut for PostCtx<'_> {
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        // lifted bodies take the type arguments and instance records of their parent, which are the last
+        // type arguments of the caller. Uses of a local function inside of itself do not instantiate its
+        // type variables, so the caller passes along its own type arguments for them as well.
+        if let Stmt::Call(_, Operand::Const(Const::Addr(id), _), args) = stmt {
+            if let Some(&parent) = self.lifted.get(&id.local_id).filter(|_| id.def == self.def) {
+                let mut caller = std::iter::successors(Some(self.id), |b| self.lifted.get(b).copied());
+                let shared = if caller.any(|b| b == id.local_id) {
+                    self.builder[id.local_id].type_vars.len()
+                } else {
+                    self.builder[parent].type_vars.len()
+                };
+
+                let type_vars = &self.type_vars[self.type_vars.len() - shared..];
+                let extra = type_vars.iter().flatten().chain(&self.records).cloned();
+
+                args.extend(extra.map(Operand::Place));
+            }
+        }
+
+        self.super_stmt(stmt);
+    }
+
     fn visit_operand(&mut self, operand: &mut Operand) {
         if let Operand::Record(idx, ref field) = *operand {
             let record = &self.builder.body().records[idx];
@@ -0,0 +1,742 @@
+//! Inserts reference counting and drop calls into MIR.
+//!
+//! Every local whose type contains a reference counted box (see `ReprOptions::rc`) or has an instance
+//! of the drop class (see `ReprOptions::drop`) owns its value.
+//! The value is moved out on its last use, and retained when it is copied anywhere else.
+//! Values with a drop instance cannot be retained, copying a part of them moves that part out instead.
+//! Locals whose scope ends with a `Stmt::Drop`, like `let` bindings, parameters and case scrutinees, are
+//! released there unless they were moved out, calling the drop instances of their parts. Temporaries are
+//! released as soon as they are no longer live.
+//! Because a local may or may not hold a value depending on the path taken to get somewhere,
+//! every owned local gets a drop flag which is checked before it is released.
+//!
+//! Releasing a box decrements its count, when the count reaches zero the value in the box is released
+//! and the box is freed. This is done by release glue: a body generated for every box type.
+//!
+//! Values of a type variable are not managed, and neither are the values behind a raw pointer.
+//! Type inference rejects instantiating a type variable with a managed type, so a polymorphic body
+//! never copies or discards a managed value.
+//! Copying a value with a drop instance out of a box or pointer runs its drop method for every copy.
+//! Taking the address of a temporary does not keep it alive, the pointer must not outlive its last use.
+
+use crate::db::MirDatabase;
+use crate::ir::*;
+use crate::lower::builder::Builder;
+use crate::ty::{Type, TypeKind};
+use hir::id::DefWithBodyId;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::Arc;
+
+pub(crate) fn insert(db: &dyn MirDatabase, def: DefWithBodyId, bodies: &mut Bodies) {
+    let mut ctx = RcCtx {
+        db,
+        def,
+        ownership: FxHashMap::default(),
+        glue: FxHashMap::default(),
+        pending: Vec::new(),
+    };
+
+    let ids = bodies.bodies.iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+    for id in ids {
+        if bodies[id].entry.is_some() {
+            ctx.process_body(bodies, id);
+        }
+    }
+
+    while let Some((id, ty)) = ctx.pending.pop() {
+        ctx.generate_release_glue(bodies, id, ty);
+    }
+}
+
+/// How the values of a type are owned, ordered so that a value is owned like its strictest part.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Ownership {
+    Trivial,
+    /// The value can be retained.
+    Shared,
+    /// The value can only be moved.
+    Unique,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Glue {
+    Retain,
+    Release,
+}
+
+struct RcCtx<'a> {
+    db: &'a dyn MirDatabase,
+    def: DefWithBodyId,
+    ownership: FxHashMap<Arc<Type>, Ownership>,
+    glue: FxHashMap<Arc<Type>, LocalBodyId>,
+    pending: Vec<(LocalBodyId, Arc<Type>)>,
+}
+
+type LiveSet = FxHashSet<LocalId>;
+
+impl RcCtx<'_> {
+    fn ownership(&mut self, ty: &Arc<Type>) -> Ownership {
+        if let Some(&res) = self.ownership.get(ty) {
+            return res;
+        }
+
+        let res = match &ty.kind {
+            | _ if ty.repr.drop.is_some() => Ownership::Unique,
+            | TypeKind::Ptr(_) if ty.repr.rc => Ownership::Shared,
+            | TypeKind::Recurse(t) => {
+                let ty = Type::mir_type_query(self.db, *t);
+
+                self.ownership(&ty)
+            },
+            | TypeKind::Array(elem, _) => self.ownership(elem),
+            | TypeKind::And(fields) | TypeKind::Or(fields, true) => fields
+                .iter()
+                .map(|f| self.ownership(f))
+                .max()
+                .unwrap_or(Ownership::Trivial),
+            | _ => Ownership::Trivial,
+        };
+
+        self.ownership.insert(ty.clone(), res);
+        res
+    }
+
+    /// Whether values of this type own a reference counted box or have a drop instance.
+    fn needs_drop(&mut self, ty: &Arc<Type>) -> bool {
+        self.ownership(ty) != Ownership::Trivial
+    }
+
+    fn process_body(&mut self, bodies: &mut Bodies, id: LocalBodyId) {
+        let body = &bodies[id];
+        let managed = body
+            .locals
+            .iter()
+            .filter(|(_, l)| l.kind != LocalKind::Ret && self.needs_drop(&l.ty))
+            .map(|(local, _)| local)
+            .collect::<LiveSet>();
+
+        if managed.is_empty() {
+            return;
+        }
+
+        let live_in = liveness(body, &managed);
+        let old_entry = body.entry.unwrap();
+        let blocks = body.blocks.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        let mut preds = FxHashMap::<BlockId, Vec<BlockId>>::default();
+
+        for (block, data) in body.blocks.iter() {
+            for succ in successors(&data.term) {
+                preds.entry(succ).or_default().push(block);
+            }
+        }
+
+        let live_out = blocks
+            .iter()
+            .map(|&b| (b, live_out(&bodies[id], &live_in, b)))
+            .collect::<FxHashMap<_, _>>();
+
+        let scoped = body
+            .blocks
+            .iter()
+            .flat_map(|(_, data)| data.stmts.iter())
+            .filter_map(|stmt| match stmt {
+                | Stmt::Drop(place) if managed.contains(&place.local) => Some(place.local),
+                | _ => None,
+            })
+            .collect::<LiveSet>();
+
+        let mut builder = bodies.builder(id);
+        let mut managed = managed.into_iter().collect::<Vec<_>>();
+
+        managed.sort_by_key(|l| l.into_raw());
+
+        let flags = managed
+            .iter()
+            .map(|&l| (l, Place::new(builder.create_var(Type::byte()))))
+            .collect::<FxHashMap<_, _>>();
+
+        // parts of a value that can only be moved get a flag that is set once they are moved out
+        let mut moved = FxHashMap::<LocalId, Vec<(Place, Place)>>::default();
+
+        let parts = builder
+            .body()
+            .blocks
+            .iter()
+            .flat_map(|(_, data)| data.stmts.iter())
+            .flat_map(owning_operands)
+            .filter_map(|op| match op {
+                | Operand::Place(place)
+                    if !place.elems.is_empty()
+                        && !place.elems.contains(&PlaceElem::Deref)
+                        && flags.contains_key(&place.local) =>
+                {
+                    Some(place.clone())
+                },
+                | _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for place in parts {
+            let ty = builder.place_type(&place);
+
+            if self.ownership(&ty) == Ownership::Unique {
+                let paths = moved.entry(place.local).or_default();
+
+                if !paths.iter().any(|(p, _)| *p == place) {
+                    let flag = Place::new(builder.create_var(Type::byte()));
+
+                    paths.push((place, flag));
+                }
+            }
+        }
+
+        let mut body_ctx = BodyCtx {
+            ctx: self,
+            builder,
+            flags,
+            moved,
+            scoped,
+        };
+
+        // arguments are owned by the callee, everything else starts out uninitialized
+        let entry = body_ctx.builder.create_block();
+
+        body_ctx.builder.set_block(entry);
+        body_ctx.builder.body_mut().entry = Some(entry);
+
+        for &local in &managed {
+            let is_arg = body_ctx.builder.body().locals[local].kind == LocalKind::Arg;
+
+            body_ctx.set_flag(local, is_arg);
+            body_ctx.reset_moved(&Place::new(local));
+
+            if is_arg && !live_in[&old_entry].contains(&local) && !body_ctx.scoped.contains(&local) {
+                body_ctx.release(local);
+            }
+        }
+
+        body_ctx.builder.jump(old_entry);
+
+        for block in blocks {
+            let mut dead = preds
+                .get(&block)
+                .into_iter()
+                .flatten()
+                .flat_map(|p| live_out[p].iter())
+                .filter(|l| !live_in[&block].contains(l))
+                .copied()
+                .collect::<Vec<_>>();
+
+            dead.sort_by_key(|l| l.into_raw());
+            dead.dedup();
+            body_ctx.process_block(block, dead, &live_out[&block]);
+        }
+    }
+
+    fn release_glue(&mut self, bodies: &mut Bodies, ty: Arc<Type>) -> LocalBodyId {
+        if let Some(&id) = self.glue.get(&ty) {
+            return id;
+        }
+
+        let id = bodies.add(Vec::new(), Vec::new());
+
+        self.glue.insert(ty.clone(), id);
+        self.pending.push((id, ty));
+        id
+    }
+
+    fn generate_release_glue(&mut self, bodies: &mut Bodies, id: LocalBodyId, ty: Arc<Type>) {
+        let elem = match &ty.kind {
+            | TypeKind::Ptr(elem) => match &elem.kind {
+                | TypeKind::And(fields) => fields[1].clone(),
+                | _ => unreachable!(),
+            },
+            | _ => unreachable!(),
+        };
+
+        let mut builder = bodies.builder(id);
+
+        builder.create_ret(Arc::new(Type::UNIT));
+
+        let arg = Place::new(builder.create_arg(ty));
+        let entry = builder.create_block();
+        let free = builder.create_block();
+        let exit = builder.create_block();
+        let last = Place::new(builder.create_var(Type::byte()));
+        let unit = Place::new(builder.create_var(Arc::new(Type::UNIT)));
+
+        builder.set_block(entry);
+        builder.intrinsic(last.clone(), "rc_release", vec![Operand::Place(arg.clone())]);
+        builder.switch(Operand::Place(last), vec![0], vec![exit, free]);
+        builder.set_block(free);
+        self.emit_glue(&mut builder, arg.clone().deref().field(1), elem, Glue::Release, &[]);
+        builder.intrinsic(unit, "dealloc", vec![Operand::Place(arg)]);
+        builder.jump(exit);
+        builder.set_block(exit);
+        builder.ret();
+    }
+
+    /// Retains or releases the value at `place`, skipping the parts in `moved` whose flag is set.
+    fn emit_glue(&mut self, builder: &mut Builder, place: Place, ty: Arc<Type>, glue: Glue, moved: &[(Place, Place)]) {
+        if !self.needs_drop(&ty) {
+            return;
+        }
+
+        if let Some((_, flag)) = moved.iter().find(|(p, _)| *p == place) {
+            let owned = builder.create_block();
+            let next = builder.create_block();
+
+            builder.switch(Operand::Place(flag.clone()), vec![0], vec![owned, next]);
+            builder.set_block(owned);
+            self.emit_glue(builder, place, ty, glue, &[]);
+            builder.jump(next);
+            builder.set_block(next);
+            return;
+        }
+
+        if let (Glue::Release, Some(func)) = (glue, ty.repr.drop) {
+            let def = DefWithBodyId::FuncId(func);
+            let (func, _) = if def == self.def {
+                builder.arity(def, 1)
+            } else {
+                self.db.body_mir(def).arity(def, 1)
+            };
+
+            let ptr_ty = Type::ref_(ty.clone());
+            let ptr = Place::new(builder.create_var(ptr_ty.clone()));
+            let unit = Place::new(builder.create_var(Arc::new(Type::UNIT)));
+            let func_ty = Type::func(Arc::new([ptr_ty]), Arc::new(Type::UNIT));
+
+            builder.addr_of(ptr.clone(), place.clone());
+            builder.call(unit, Operand::Const(Const::Addr(func), func_ty), vec![Operand::Place(ptr)]);
+        }
+
+        match &ty.kind {
+            | TypeKind::Ptr(_) if ty.repr.rc => {
+                let unit = Place::new(builder.create_var(Arc::new(Type::UNIT)));
+
+                match glue {
+                    | Glue::Retain => builder.intrinsic(unit, "rc_retain", vec![Operand::Place(place)]),
+                    | Glue::Release => {
+                        let local_id = self.release_glue(builder, ty.clone());
+                        let func = Const::Addr(BodyId { def: self.def, local_id });
+                        let func_ty = Type::func(Arc::new([ty.clone()]), Arc::new(Type::UNIT));
+
+                        builder.call(unit, Operand::Const(func, func_ty), vec![Operand::Place(place)]);
+                    },
+                }
+            },
+            | TypeKind::Recurse(t) => {
+                let ty = Type::mir_type_query(self.db, *t);
+
+                self.emit_glue(builder, place, ty, glue, moved);
+            },
+            | TypeKind::And(fields) => {
+                for (i, field) in fields.iter().enumerate() {
+                    self.emit_glue(builder, place.clone().field(i), field.clone(), glue, moved);
+                }
+            },
+            | TypeKind::Or(variants, _) => {
+                let discr_ty = Type::discriminant(self.db, ty.clone());
+                let discr = Place::new(builder.create_var(discr_ty));
+                let exit = builder.create_block();
+                let mut vals = Vec::new();
+                let mut blocks = Vec::new();
+
+                for (i, variant) in variants.iter().enumerate() {
+                    if self.needs_drop(variant) {
+                        vals.push(i as u128);
+                        blocks.push(builder.create_block());
+                    }
+                }
+
+                blocks.push(exit);
+                builder.get_discr(discr.clone(), place.clone());
+                builder.switch(Operand::Place(discr), vals.clone(), blocks.clone());
+
+                for (&i, &block) in vals.iter().zip(blocks.iter()) {
+                    builder.set_block(block);
+                    self.emit_glue(
+                        builder,
+                        place.clone().downcast(i as usize),
+                        variants[i as usize].clone(),
+                        glue,
+                        moved,
+                    );
+                    builder.jump(exit);
+                }
+
+                builder.set_block(exit);
+            },
+            | TypeKind::Array(elem, len) => {
+                let uint = Type::ptr_sized_int(self.db, false);
+                let idx = Place::new(builder.create_var(uint.clone()));
+                let cond = Place::new(builder.create_var(Type::byte()));
+                let head = builder.create_block();
+                let next = builder.create_block();
+                let exit = builder.create_block();
+
+                builder.use_op(idx.clone(), Operand::Const(Const::Scalar(0), uint.clone()));
+                builder.jump(head);
+                builder.set_block(head);
+                builder.intrinsic(cond.clone(), "lt_uint", vec![
+                    Operand::Place(idx.clone()),
+                    Operand::Const(Const::Scalar(*len as u128), uint.clone()),
+                ]);
+
+                builder.switch(Operand::Place(cond), vec![0], vec![exit, next]);
+                builder.set_block(next);
+                self.emit_glue(builder, place.index(Operand::Place(idx.clone())), elem.clone(), glue, moved);
+                builder.intrinsic(idx.clone(), "add_uint", vec![
+                    Operand::Place(idx),
+                    Operand::Const(Const::Scalar(1), uint),
+                ]);
+
+                builder.jump(head);
+                builder.set_block(exit);
+            },
+            | _ => {},
+        }
+    }
+}
+
+struct BodyCtx<'a, 'b> {
+    ctx: &'a mut RcCtx<'b>,
+    builder: Builder<'a>,
+    flags: FxHashMap<LocalId, Place>,
+    moved: FxHashMap<LocalId, Vec<(Place, Place)>>,
+    /// The locals that are released at the end of their scope instead of when they are no longer live.
+    scoped: LiveSet,
+}
+
+impl BodyCtx<'_, '_> {
+    fn process_block(&mut self, block: BlockId, dead: Vec<LocalId>, live_out: &LiveSet) {
+        let data = &mut self.builder.body_mut().blocks[block];
+        let stmts = std::mem::take(&mut data.stmts);
+        let sources = std::mem::take(&mut data.sources);
+        let term = data.term.clone();
+        let mut live = live_out.clone();
+        let mut live_after = Vec::with_capacity(stmts.len());
+
+        for stmt in stmts.iter().rev() {
+            live_after.push(live.clone());
+            transfer(stmt, |l| self.flags.contains_key(l), &mut live);
+        }
+
+        live_after.reverse();
+        self.builder.set_block(block);
+
+        // temporaries that are live at the end of a predecessor but not here
+        for local in dead {
+            if !self.scoped.contains(&local) {
+                self.release(local);
+            }
+        }
+
+        for ((stmt, source), live) in stmts.into_iter().zip(sources).zip(live_after) {
+            let prev = self.builder.set_source(source);
+
+            self.process_stmt(stmt, &live);
+            self.builder.set_source(prev);
+        }
+
+        match term {
+            | Term::Abort => self.builder.abort(),
+            | Term::Return => self.builder.ret(),
+            | Term::Jump(to) => self.builder.jump(to),
+            | Term::Switch(op, vals, blocks) => self.builder.switch(op, vals, blocks),
+        }
+    }
+
+    fn process_stmt(&mut self, mut stmt: Stmt, live: &LiveSet) {
+        if let Stmt::Drop(place) = &stmt {
+            if self.flags.contains_key(&place.local) {
+                self.release(place.local);
+            }
+
+            return;
+        }
+
+        let mut reads = Vec::new();
+
+        stmt_reads(&stmt, &mut reads);
+        reads.retain(|l| self.flags.contains_key(l));
+
+        let killed = stmt_kills(&stmt).filter(|l| self.flags.contains_key(l));
+        let continues = |l: &LocalId| live.contains(l) && killed != Some(*l);
+        let mut moved = FxHashSet::default();
+        let mut moved_parts = Vec::new();
+        let owning = owning_operands(&stmt).to_vec();
+
+        for op in owning {
+            if let Operand::Place(place) = op {
+                let is_last_use = place.elems.is_empty()
+                    && !continues(&place.local)
+                    && reads.iter().filter(|&&l| l == place.local).count() == 1;
+
+                if is_last_use && self.flags.contains_key(&place.local) {
+                    moved.insert(place.local);
+                } else if let Some(flag) = self.moved_flag(&place) {
+                    moved_parts.push(flag);
+                } else {
+                    let ty = self.builder.place_type(&place);
+
+                    self.ctx.emit_glue(&mut self.builder, place, ty, Glue::Retain, &[]);
+                }
+            }
+        }
+
+        let init = match &stmt {
+            | Stmt::Assign(place, RValue::Use(Operand::Const(..))) if place.elems.is_empty() => None,
+            | Stmt::Assign(place, _) | Stmt::Call(place, _, _) | Stmt::SetDiscr(place, _)
+                if !place.elems.contains(&PlaceElem::Deref) =>
+            {
+                Some(place.clone())
+            },
+            | _ => None,
+        };
+
+        // the old value is still read by this statement, so write the new one to a temporary first
+        let replaced = match killed {
+            | Some(local) if reads.contains(&local) && !moved.contains(&local) => {
+                let ty = self.builder.body().locals[local].ty.clone();
+                let tmp = Place::new(self.builder.create_var(ty));
+
+                match &mut stmt {
+                    | Stmt::Assign(place, _) | Stmt::Call(place, _, _) => *place = tmp.clone(),
+                    | Stmt::SetDiscr(_, _) => unreachable!(),
+                }
+
+                Some((local, tmp))
+            },
+            | _ => None,
+        };
+
+        let retain_result = match &stmt {
+            | Stmt::Assign(place, RValue::Intrinsic(name, _)) if name != "rc_new" => Some(place.clone()),
+            | _ => None,
+        };
+
+        self.builder.stmt(stmt);
+
+        if let Some(place) = retain_result {
+            let ty = self.builder.place_type(&place);
+
+            self.ctx.emit_glue(&mut self.builder, place, ty, Glue::Retain, &[]);
+        }
+
+        for &local in &moved {
+            if killed != Some(local) {
+                self.set_flag(local, false);
+            }
+        }
+
+        for flag in moved_parts {
+            self.builder.use_op(flag, Operand::Const(Const::Scalar(1), Type::byte()));
+        }
+
+        let mut dying = reads
+            .iter()
+            .copied()
+            .filter(|l| !moved.contains(l) && !continues(l))
+            .filter(|l| !self.scoped.contains(l) || killed == Some(*l))
+            .filter(|l| killed != Some(*l) || replaced.is_some())
+            .collect::<Vec<_>>();
+
+        dying.sort_by_key(|l| l.into_raw());
+        dying.dedup();
+
+        for local in dying {
+            self.release(local);
+        }
+
+        if let Some((local, tmp)) = replaced {
+            self.builder.use_op(Place::new(local), Operand::Place(tmp));
+        }
+
+        if let Some(place) = init {
+            if self.flags.contains_key(&place.local) {
+                self.set_flag(place.local, true);
+                self.reset_moved(&place);
+            }
+        } else if let Some(local) = killed {
+            self.set_flag(local, false);
+        }
+    }
+
+    fn set_flag(&mut self, local: LocalId, value: bool) {
+        let flag = self.flags[&local].clone();
+
+        self.builder
+            .use_op(flag, Operand::Const(Const::Scalar(value as u128), Type::byte()));
+    }
+
+    /// The flag tracking whether this part of a unique value has been moved out.
+    fn moved_flag(&self, place: &Place) -> Option<Place> {
+        self.moved
+            .get(&place.local)?
+            .iter()
+            .find(|(p, _)| p == place)
+            .map(|(_, flag)| flag.clone())
+    }
+
+    /// Marks all moved parts overlapping with `dest` as present again after it was written to.
+    fn reset_moved(&mut self, dest: &Place) {
+        let flags = match self.moved.get(&dest.local) {
+            | Some(paths) => paths
+                .iter()
+                .filter(|(p, _)| p.elems.starts_with(&dest.elems) || dest.elems.starts_with(&p.elems))
+                .map(|(_, flag)| flag.clone())
+                .collect::<Vec<_>>(),
+            | None => return,
+        };
+
+        for flag in flags {
+            self.builder.use_op(flag, Operand::Const(Const::Scalar(0), Type::byte()));
+        }
+    }
+
+    /// Releases the value of a local if its drop flag is set.
+    fn release(&mut self, local: LocalId) {
+        let flag = self.flags[&local].clone();
+        let ty = self.builder.body().locals[local].ty.clone();
+        let release = self.builder.create_block();
+        let next = self.builder.create_block();
+
+        self.builder.switch(Operand::Place(flag), vec![0], vec![next, release]);
+        self.builder.set_block(release);
+        self.set_flag(local, false);
+        let moved = self.moved.get(&local).cloned().unwrap_or_default();
+
+        self.ctx
+            .emit_glue(&mut self.builder, Place::new(local), ty, Glue::Release, &moved);
+        self.builder.jump(next);
+        self.builder.set_block(next);
+    }
+}
+
+/// The operands of a statement whose value is moved or copied into the destination.
+fn owning_operands(stmt: &Stmt) -> &[Operand] {
+    match stmt {
+        | Stmt::Assign(_, RValue::Use(op)) => std::slice::from_ref(op),
+        | Stmt::Assign(_, RValue::Intrinsic(name, args)) if name == "rc_new" => args,
+        | Stmt::Call(_, _, args) => args,
+        | _ => &[],
+    }
+}
+
+fn successors(term: &Term) -> Vec<BlockId> {
+    match term {
+        | Term::Jump(to) => vec![*to],
+        | Term::Switch(_, _, blocks) => blocks.clone(),
+        | Term::Abort | Term::Return => Vec::new(),
+    }
+}
+
+fn live_out(body: &Body, live_in: &FxHashMap<BlockId, LiveSet>, block: BlockId) -> LiveSet {
+    successors(&body.blocks[block].term)
+        .into_iter()
+        .flat_map(|s| live_in[&s].iter().copied())
+        .collect()
+}
+
+/// Computes the managed locals that are live at the start of every block.
+fn liveness(body: &Body, managed: &LiveSet) -> FxHashMap<BlockId, LiveSet> {
+    let mut live_in = body
+        .blocks
+        .iter()
+        .map(|(id, _)| (id, LiveSet::default()))
+        .collect::<FxHashMap<_, _>>();
+
+    loop {
+        let mut changed = false;
+
+        for (block, data) in body.blocks.iter() {
+            let mut live = live_out(body, &live_in, block);
+
+            for stmt in data.stmts.iter().rev() {
+                transfer(stmt, |l| managed.contains(l), &mut live);
+            }
+
+            if live != live_in[&block] {
+                live_in.insert(block, live);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return live_in;
+        }
+    }
+}
+
+/// Turns the managed locals live after a statement into the ones live before it.
+fn transfer(stmt: &Stmt, is_managed: impl Fn(&LocalId) -> bool, live: &mut LiveSet) {
+    let mut reads = Vec::new();
+
+    if let Some(local) = stmt_kills(stmt) {
+        live.remove(&local);
+    }
+
+    stmt_reads(stmt, &mut reads);
+    live.extend(reads.into_iter().filter(|l| is_managed(l)));
+}
+
+/// The local whose entire value is overwritten by a statement.
+fn stmt_kills(stmt: &Stmt) -> Option<LocalId> {
+    match stmt {
+        | Stmt::Assign(place, _) | Stmt::Call(place, _, _) if place.elems.is_empty() => Some(place.local),
+        | _ => None,
+    }
+}
+
+/// Collects every local read by a statement, once for every time it is mentioned.
+/// Writing part of a local counts as a read, so that the rest of the value stays alive.
+fn stmt_reads(stmt: &Stmt, reads: &mut Vec<LocalId>) {
+    fn place(place: &Place, reads: &mut Vec<LocalId>) {
+        reads.push(place.local);
+        elems(place, reads);
+    }
+
+    fn elems(place: &Place, reads: &mut Vec<LocalId>) {
+        for elem in &place.elems {
+            if let PlaceElem::Index(op) | PlaceElem::Offset(op) = elem {
+                operand(op, reads);
+            }
+        }
+    }
+
+    fn operand(op: &Operand, reads: &mut Vec<LocalId>) {
+        if let Operand::Place(p) = op {
+            place(p, reads);
+        }
+    }
+
+    fn dest(p: &Place, reads: &mut Vec<LocalId>) {
+        if p.elems.is_empty() {
+            elems(p, reads);
+        } else {
+            place(p, reads);
+        }
+    }
+
+    match stmt {
+        | Stmt::Assign(ret, rvalue) => {
+            match rvalue {
+                | RValue::Use(op) => operand(op, reads),
+                | RValue::AddrOf(p) | RValue::GetDiscr(p) => place(p, reads),
+                | RValue::Intrinsic(_, args) => args.iter().for_each(|a| operand(a, reads)),
+            }
+
+            dest(ret, reads);
+        },
+        | Stmt::SetDiscr(ret, _) => place(ret, reads),
+        | Stmt::Drop(_) => {},
+        | Stmt::Call(ret, func, args) => {
+            operand(func, reads);
+            args.iter().for_each(|a| operand(a, reads));
+            dest(ret, reads);
+        },
+    }
+}
@@ -2,7 +2,9 @@ use crate::db::MirDatabase;
 use crate::layout::{Integer, Primitive};
 use hir::attrs::{AttrInput, AttrInputGroup};
 use hir::display::HirDisplay;
-use hir::ty::{Ty, TyKind, TypeVar};
+use hir::id::Lookup as _;
+use hir::ty::{Constraint, Ty, TyKind, TypeVar};
+use hir::AsName as _;
 use std::fmt;
 use std::sync::Arc;
 
@@ -18,6 +20,10 @@ pub struct ReprOptions {
     pub scalar: Option<Primitive>,
     pub valid_range_start: Option<u128>,
     pub valid_range_end: Option<u128>,
+    /// A pointer to a reference counted box, which is released when its owner is dropped.
+    pub rc: bool,
+    /// The `drop` method of the drop class instance for this type, called before the fields are dropped.
+    pub drop: Option<hir::id::FuncId>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -53,11 +59,14 @@ impl Type {
             scalar: None,
             valid_range_start: None,
             valid_range_end: None,
+            rc: false,
+            drop: None,
         },
         kind: TypeKind::Unit,
     };
 
     pub fn mir_type_query(db: &dyn MirDatabase, mut ty: Ty) -> Arc<Type> {
+        let full_ty = ty;
         let mut args = Vec::new();
 
         while let TyKind::App(a, b) = ty.lookup(db.upcast()) {
@@ -92,22 +101,32 @@ impl Type {
                     return Self::from_repr(db, attr, &args);
                 } else {
                     let data = db.type_ctor_data(id);
-
-                    if data.ctors.is_empty() {
-                        TypeKind::Unit
-                    } else if data.ctors.len() == 1 {
+                    let mut res = if data.ctors.len() == 1 {
                         let (local_id, ctor) = data.ctors.iter().next().unwrap();
 
-                        return Self::variant_type(db, local_id, ctor, id, &args);
+                        Self::variant_type(db, local_id, ctor, id, &args)
                     } else {
                         let variants = data
                             .ctors
                             .iter()
                             .map(|(local_id, ctor)| Self::variant_type(db, local_id, ctor, id, &args))
-                            .collect();
+                            .collect::<Arc<[_]>>();
+
+                        Arc::new(Type {
+                            repr: ReprOptions::default(),
+                            kind: if variants.is_empty() {
+                                TypeKind::Unit
+                            } else {
+                                TypeKind::Or(variants, true)
+                            },
+                        })
+                    };
 
-                        TypeKind::Or(variants, true)
+                    if let Some(drop) = Self::drop_method(db, id, full_ty) {
+                        Arc::make_mut(&mut res).repr.drop = Some(drop);
                     }
+
+                    return res;
                 }
             },
         };
@@ -118,6 +137,16 @@ impl Type {
         })
     }
 
+    /// The `drop` method of the drop class instance for `ty`, if it has one.
+    fn drop_method(db: &dyn MirDatabase, id: hir::id::TypeCtorId, ty: Ty) -> Option<hir::id::FuncId> {
+        let lib = id.lookup(db.upcast()).module.lib;
+        let class = db.lang_item(lib, "drop-class".into())?.as_class()?;
+        let solved = db.solve_constraint(Constraint::new(class, [ty]))?;
+        let item = db.instance_data(solved.instance).item(&"drop".as_name())?;
+
+        item.as_func_id()
+    }
+
     fn variant_type(
         db: &dyn MirDatabase,
         local_id: hir::id::LocalCtorId,
@@ -175,6 +204,23 @@ impl Type {
             }
         }
 
+        if let Some(rc) = group.field("rc").and_then(AttrInput::group) {
+            if let Some(elem) = rc.field("elem") {
+                let elem = if let Some(idx) = elem.int() {
+                    db.mir_type(args[idx as usize])
+                } else {
+                    Self::from_repr(db, elem.group().unwrap(), args)
+                };
+
+                // the box starts with the reference count
+                let count = Type::ptr_sized_int(db, false);
+
+                repr.rc = true;
+                repr.valid_range_start = Some(1);
+                kind = TypeKind::Ptr(Type::and([count, elem]));
+            }
+        }
+
         if let Some(array) = group.field("array").and_then(AttrInput::group) {
             if let Some(elem) = array.field("elem") {
                 if let Some(len) = array.field("len") {
@@ -377,16 +423,18 @@ impl Type {
         Type::ref_(struc)
     }
 
-    pub fn str_slice(db: &dyn MirDatabase) -> Arc<Type> {
-        let byte = Arc::new(Type {
+    pub fn byte() -> Arc<Type> {
+        Arc::new(Type {
             kind: TypeKind::Unit,
             repr: ReprOptions {
                 scalar: Some(Primitive::Int(Integer::I8, false)),
                 ..ReprOptions::default()
             },
-        });
+        })
+    }
 
-        let ptr = Type::ref_(byte);
+    pub fn str_slice(db: &dyn MirDatabase) -> Arc<Type> {
+        let ptr = Type::ref_(Type::byte());
         let uint = Type::ptr_sized_int(db, false);
 
         Type::and([ptr, uint])
@@ -416,6 +464,7 @@ impl fmt::Display for Type {
                 | None => write!(f, "()"),
             },
             | TypeKind::Var(var) => var.fmt(f),
+            | TypeKind::Ptr(to) if self.repr.rc => write!(f, "rc {}", to),
             | TypeKind::Ptr(to) => write!(f, "*{}", to),
             | TypeKind::Array(of, len) => write!(f, "[{}]{}", len, of),
             | TypeKind::And(tys) => {
@@ -71,7 +71,7 @@ macro_rules! make_visitor {
                         self.visit_place(place);
                         self.visit_rvalue(rvalue);
                     },
-                    | Stmt::SetDiscr(place, _) => {
+                    | Stmt::SetDiscr(place, _) | Stmt::Drop(place) => {
                         self.visit_place(place);
                     },
                     | Stmt::Call(ret, func, args) => {
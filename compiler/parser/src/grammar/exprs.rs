@@ -1,5 +1,5 @@
 use super::*;
-use crate::parser::{CompletedMarker, Parser};
+use crate::parser::{CompletedMarker, Marker, Parser};
 use crate::syntax_kind::*;
 
 crate fn expr(p: &mut Parser) {
@@ -42,10 +42,10 @@ crate fn infix(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
         infix(p, allow_do);
 
         m = expr.complete(p, EXPR_INFIX);
-    } else if p.at(OPERATOR) || p.at(STAR) {
+    } else if at_operator(p) {
         let expr = m.precede(p);
 
-        while p.at(OPERATOR) || p.at(STAR) {
+        while at_operator(p) {
             p.bump_any();
             app(p, allow_do);
         }
@@ -56,6 +56,11 @@ crate fn infix(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
     Some(m)
 }
 
+/// Whether the parser is at an infix operator, but not at the operator of a left section like `(1 +)`.
+fn at_operator(p: &Parser) -> bool {
+    (p.at(OPERATOR) || p.at(STAR)) && !p.nth_at(1, R_PAREN)
+}
+
 crate fn app(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
     let mut m = postfix(p, allow_do)?;
 
@@ -116,6 +121,10 @@ crate fn atom(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
             literal(p);
             Some(m.complete(p, EXPR_LITERAL))
         },
+        | STRING_START => {
+            interp(p);
+            Some(m.complete(p, EXPR_INTERP))
+        },
         | DO_KW if allow_do => {
             p.bump(DO_KW);
 
@@ -253,10 +262,26 @@ crate fn atom(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
 
             if p.eat(R_PAREN) {
                 Some(m.complete(p, EXPR_TUPLE))
+            } else if p.at(OPERATOR) || p.at(STAR) {
+                p.bump_any();
+
+                if !p.at(R_PAREN) {
+                    expr(p);
+                }
+
+                p.expect(R_PAREN);
+                Some(m.complete(p, EXPR_SECTION))
             } else {
                 let mut is_tuple = false;
                 let _ = expr(p);
 
+                if p.at(OPERATOR) || p.at(STAR) {
+                    p.bump_any();
+                    p.expect(R_PAREN);
+
+                    return Some(m.complete(p, EXPR_SECTION));
+                }
+
                 while p.eat(COMMA) {
                     is_tuple = true;
 
@@ -292,6 +317,13 @@ crate fn atom(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
         },
         | L_BRACE => {
             p.bump(L_BRACE);
+
+            // a record update: `{ base | field: val }`
+            if !p.at(R_BRACE) && !(p.at(IDENT) && (p.nth_at(1, COLON) || p.nth_at(1, COMMA) || p.nth_at(1, R_BRACE))) {
+                expr(p);
+                p.expect(PIPE);
+            }
+
             patterns::record_fields(p, expr, false);
             p.expect(R_BRACE);
             Some(m.complete(p, EXPR_RECORD))
@@ -308,8 +340,8 @@ crate fn atom(p: &mut Parser, allow_do: bool) -> Option<CompletedMarker> {
 fn peek(p: &Parser, allow_do: bool) -> bool {
     match p.current() {
         | DO_KW => allow_do,
-        | IDENT | INT | FLOAT | CHAR | STRING | L_PAREN | L_BRACE | L_BRACKET | IF_KW | UNLESS_KW | WHILE_KW
-        | LOOP_KW | UNTIL_KW | NEXT_KW | BREAK_KW | YIELD_KW | RETURN_KW | CASE_KW | UNDERSCORE => true,
+        | IDENT | INT | FLOAT | CHAR | STRING | STRING_START | L_PAREN | L_BRACE | L_BRACKET | IF_KW | UNLESS_KW
+        | WHILE_KW | LOOP_KW | UNTIL_KW | NEXT_KW | BREAK_KW | YIELD_KW | RETURN_KW | CASE_KW | UNDERSCORE => true,
         | _ => false,
     }
 }
@@ -331,6 +363,21 @@ crate fn literal(p: &mut Parser) {
     }
 }
 
+/// An interpolated string: `"x = {x}, y = {y}"`.
+fn interp(p: &mut Parser) {
+    p.bump(STRING_START);
+
+    loop {
+        expr(p);
+
+        if !p.eat(STRING_MID) {
+            break;
+        }
+    }
+
+    p.expect(STRING_END);
+}
+
 crate fn block(p: &mut Parser) {
     let m = p.start();
 
@@ -353,10 +400,7 @@ crate fn stmt(p: &mut Parser) {
     let m = p.start();
 
     if p.eat(LET_KW) {
-        patterns::app(p);
-        p.expect(EQUALS);
-        expr(p);
-        m.complete(p, STMT_LET);
+        binding(p, m);
     } else {
         for i in 0..100 {
             if p.nth_at(i, LEFT_ARROW) {
@@ -375,6 +419,34 @@ crate fn stmt(p: &mut Parser) {
     }
 }
 
+/// A binding of a pattern or a local function, as in `let` statements and `where` clauses.
+crate fn binding(p: &mut Parser, m: Marker) {
+    patterns::app(p);
+    p.expect(EQUALS);
+    expr(p);
+    m.complete(p, STMT_LET);
+}
+
+crate fn where_clause(p: &mut Parser) {
+    let m = p.start();
+
+    p.expect(WHERE_KW);
+    p.expect(LYT_START);
+
+    while !p.at(EOF) && !p.at(LYT_END) {
+        let binding_m = p.start();
+
+        binding(p, binding_m);
+
+        if !p.at(LYT_END) {
+            p.expect(LYT_SEP);
+        }
+    }
+
+    p.expect(LYT_END);
+    m.complete(p, WHERE_CLAUSE);
+}
+
 crate fn case_arm(p: &mut Parser) {
     let m = p.start();
 
@@ -114,16 +114,62 @@ crate fn fun(p: &mut Parser, m: Marker) {
             patterns::atom(p);
         }
 
-        if p.eat(PIPE) {
+        if p.at(PIPE) && at_generics(p) {
+            p.bump(PIPE);
             types::generics(p, EQUALS);
+        } else if p.eat(PIPE) {
+            let guard = p.start();
+
+            exprs::expr(p);
+            guard.complete(p, CASE_GUARD);
         }
 
         p.expect(EQUALS);
         exprs::expr(p);
+
+        if p.at(WHERE_KW) {
+            exprs::where_clause(p);
+        }
+
         m.complete(p, ITEM_FUN);
     }
 }
 
+/// Whether the `|` after the parameters of a function introduces generics (`| a b : Show a`)
+/// rather than a guard (`| x > 0`). Generics are a list of type variables, which may be kinded,
+/// followed by constraints or by the `=` of the body.
+fn at_generics(p: &Parser) -> bool {
+    let mut n = 1;
+
+    loop {
+        if p.nth_at(n, IDENT) {
+            n += 1;
+        } else if p.nth_at(n, L_PAREN) && p.nth_at(n + 1, IDENT) && p.nth_at(n + 2, DBL_COLON) {
+            let mut depth = 0;
+
+            // skip the kind, which can contain parentheses itself
+            loop {
+                match p.nth(n) {
+                    | L_PAREN => depth += 1,
+                    | R_PAREN => depth -= 1,
+                    | EOF => return false,
+                    | _ => {},
+                }
+
+                n += 1;
+
+                if depth == 0 {
+                    break;
+                }
+            }
+        } else {
+            break;
+        }
+    }
+
+    n > 1 && (p.nth_at(n, COLON) || p.nth_at(n, EQUALS))
+}
+
 crate fn static_(p: &mut Parser, m: Marker) {
     p.expect(STATIC_KW);
     paths::name(p);
@@ -183,16 +229,57 @@ crate fn type_(p: &mut Parser, m: Marker, foreign: bool) {
     m.complete(p, ITEM_TYPE);
 }
 
+/// A data constructor with either positional fields (`| Point Int32 Int32`) or named fields
+/// (`| Point { x :: Int32, y :: Int32 }`). Braces with a row tail (`| C { x :: Int32 | r }`) are a
+/// positional record type, a closed record type as the first field has to be parenthesized (`| C ({ x :: Int32 })`).
 crate fn ctor(p: &mut Parser, m: Marker) {
     paths::name(p);
 
-    while types::peek(p) {
-        types::atom(p);
+    if p.at(L_BRACE) && !at_open_record(p) {
+        p.bump(L_BRACE);
+
+        while !p.at(EOF) && !p.at(R_BRACE) {
+            let field = p.start();
+
+            paths::name(p);
+            p.expect(DBL_COLON);
+            types::ty(p);
+            field.complete(p, CTOR_FIELD);
+
+            if !p.at(R_BRACE) {
+                p.expect(COMMA);
+            }
+        }
+
+        p.expect(R_BRACE);
+    } else {
+        while types::peek(p) {
+            types::atom(p);
+        }
     }
 
     m.complete(p, ITEM_CTOR);
 }
 
+/// Whether the braces at the current token close with a row tail, making them an open record type.
+fn at_open_record(p: &Parser) -> bool {
+    let mut n = 1;
+    let mut depth = 0;
+
+    loop {
+        match p.nth(n) {
+            | L_BRACE | L_PAREN | L_BRACKET => depth += 1,
+            | R_BRACE if depth == 0 => return false,
+            | R_BRACE | R_PAREN | R_BRACKET => depth -= 1,
+            | PIPE if depth == 0 => return true,
+            | EOF => return false,
+            | _ => {},
+        }
+
+        n += 1;
+    }
+}
+
 crate fn class(p: &mut Parser, m: Marker) {
     p.expect(CLASS_KW);
     paths::name(p);
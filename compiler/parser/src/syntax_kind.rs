@@ -38,6 +38,9 @@ pub enum SyntaxKind {
     INT,
     FLOAT,
     STRING,
+    STRING_START,
+    STRING_MID,
+    STRING_END,
     CHAR,
     LYT_START,
     LYT_SEP,
@@ -73,6 +76,7 @@ pub enum SyntaxKind {
     OF_KW,
     LET_KW,
     FOR_KW,
+    WHERE_KW,
 
     // Syntax tree
     MODULE,
@@ -98,6 +102,7 @@ pub enum SyntaxKind {
     ITEM_INSTANCE,
     ITEM_TYPE,
     ITEM_CTOR,
+    CTOR_FIELD,
 
     FUN_DEP,
 
@@ -143,6 +148,8 @@ pub enum SyntaxKind {
     STMT_BIND,
     STMT_EXPR,
 
+    WHERE_CLAUSE,
+
     EXPR_TYPED,
     EXPR_ASSIGN,
     EXPR_INFIX,
@@ -151,7 +158,9 @@ pub enum SyntaxKind {
     EXPR_INDEX,
     EXPR_PATH,
     EXPR_LITERAL,
+    EXPR_INTERP,
     EXPR_PARENS,
+    EXPR_SECTION,
     EXPR_TUPLE,
     EXPR_RECORD,
     EXPR_ARRAY,
@@ -5,6 +5,10 @@ use driver::Driver;
 use markup::{Markup, MarkupRenderer, Styles};
 use repl::{ReadLine, Repl};
 
+const DECL_KEYWORDS: &[&str] = &[
+    "import", "fun", "type", "class", "instance", "static", "const", "foreign", "infix", "infixl", "infixr",
+];
+
 pub fn run() {
     let (driver, lib, main_file, type_file, resolve_file) = Driver::interactive();
 
@@ -91,7 +95,56 @@ impl Interactive {
     fn type_(&mut self, text: &str) {
     }
 
+    /// Declarations are added to the interactive module, expressions are compiled to a function `it`
+    /// and evaluated in this process.
     fn eval(&mut self, text: &str) {
+        let is_decl = text
+            .split(char::is_whitespace)
+            .next()
+            .map_or(false, |word| DECL_KEYWORDS.contains(&word));
+
+        let src = if is_decl {
+            format!("module INTERACTIVE =\n\n{}{}\n", self.main_str, text)
+        } else {
+            format!("module INTERACTIVE =\n\n{}fun it = {}\n", self.main_str, text)
+        };
+
+        self.driver.db.set_file_text(self.main_file, src.into());
+
+        let errors = driver::diagnostics::emit_diagnostics(&self.db, self.lib.into(), &mut std::io::stderr());
+
+        if !matches!(errors, Ok(0)) {
+            return;
+        }
+
+        if is_decl {
+            self.main_str.push_str(text);
+            self.main_str.push('\n');
+            return;
+        }
+
+        let db = &self.db;
+        let lib: hir::Lib = self.lib.into();
+        let it = lib
+            .root_module(db)
+            .declarations(db)
+            .into_iter()
+            .find_map(|def| match def {
+                | hir::ModuleDef::Func(f) if f.name(db).to_string() == "it" => Some(f),
+                | _ => None,
+            });
+
+        if let Some(it) = it {
+            use hir::display::HirDisplay;
+            let value = self.driver.eval(it);
+            let db = &self.db;
+
+            match value {
+                | Ok(value) => println!("{} :: {}", value, it.ty(db).display(db)),
+                | Err(driver::EvalError::NotScalar) => println!("<value> :: {}", it.ty(db).display(db)),
+                | Err(e) => eprintln!("\x1B[1;31merror\x1B[0m: {}", e),
+            }
+        }
     }
 }
 
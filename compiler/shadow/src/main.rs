@@ -4,7 +4,7 @@ mod interactive;
 
 use base_db::libs::LibKind;
 use clap::clap_app;
-use driver::{Driver, Opts};
+use driver::{Driver, Emit, Opts};
 
 fn main() {
     let matches = clap_app!(shadow =>
@@ -12,22 +12,49 @@ fn main() {
         (@arg file: +takes_value)
         (@arg target: --target +takes_value)
         (@arg output: --output +takes_value)
+        (@arg debug: -g)
+        (@arg release: -O --release)
+        (@arg linker: --linker +takes_value)
+        (@arg link_arg: --("link-arg") +takes_value ... number_of_values(1) allow_hyphen_values(true))
+        (@arg static_link: --static)
         (@subcommand check =>
             (@arg input: +takes_value default_value("."))
         )
         (@subcommand build =>
             (@arg target: --target +takes_value)
+            (@arg emit: --emit +takes_value +use_delimiter ... possible_values(&["header", "tokens", "cst", "hir", "types", "mir", "clif", "asm", "obj"]))
+            (@arg debug: -g)
+            (@arg release: -O --release)
+            (@arg linker: --linker +takes_value)
+            (@arg link_arg: --("link-arg") +takes_value ... number_of_values(1) allow_hyphen_values(true))
+            (@arg static_link: --static)
             (@arg input: +takes_value default_value("."))
         )
         (@subcommand run =>
             (@setting TrailingVarArg)
             (@arg target: --target +takes_value)
+            (@arg debug: -g)
+            (@arg release: -O --release)
+            (@arg jit: --jit)
+            (@arg linker: --linker +takes_value)
+            (@arg link_arg: --("link-arg") +takes_value ... number_of_values(1) allow_hyphen_values(true))
+            (@arg static_link: --static)
             (@arg input: +takes_value default_value("."))
             (@arg args: ...)
         )
         (@subcommand docs =>
             (@arg input: +takes_value default_value("."))
         )
+        (@subcommand fuzz =>
+            (@arg core: --core +takes_value default_value("lib/core"))
+            (@arg iterations: -n --iterations +takes_value default_value("1000"))
+            (@arg seed: --seed +takes_value default_value("1"))
+        )
+        (@subcommand bindgen =>
+            (@arg header: +takes_value +required)
+            (@arg module: --module +takes_value)
+            (@arg output: -o --output +takes_value)
+        )
     )
     .get_matches();
 
@@ -56,10 +83,33 @@ fn main() {
     } else if let Some(matches) = matches.subcommand_matches("build") {
         let input = matches.value_of("input").unwrap();
         let target = matches.value_of("target");
+        let emit = matches
+            .values_of("emit")
+            .into_iter()
+            .flatten()
+            .map(|e| match e {
+                | "header" => Emit::Header,
+                | "tokens" => Emit::Tokens,
+                | "cst" => Emit::Cst,
+                | "hir" => Emit::Hir,
+                | "types" => Emit::Types,
+                | "mir" => Emit::Mir,
+                | "clif" => Emit::Clif,
+                | "asm" => Emit::Asm,
+                | "obj" => Emit::Obj,
+                | _ => panic!("invalid emit kind '{}'", e),
+            })
+            .collect();
 
         if let Some((driver, _)) = Driver::init(Opts {
             input,
             target,
+            emit,
+            debug: matches.is_present("debug"),
+            release: matches.is_present("release"),
+            linker: matches.value_of("linker"),
+            link_args: matches.values_of("link_arg").into_iter().flatten().collect(),
+            static_link: matches.is_present("static_link"),
             ..Opts::default()
         }) {
             driver.build();
@@ -71,12 +121,18 @@ fn main() {
         if let Some((driver, lib)) = Driver::init(Opts {
             input,
             target,
+            debug: matches.is_present("debug"),
+            release: matches.is_present("release"),
+            linker: matches.value_of("linker"),
+            link_args: matches.values_of("link_arg").into_iter().flatten().collect(),
+            static_link: matches.is_present("static_link"),
             ..Opts::default()
         }) {
-            let status = if let Some(args) = matches.values_of_os("args") {
-                driver.run(lib, args.into_iter())
+            let args = matches.values_of_os("args").into_iter().flatten();
+            let status = if matches.is_present("jit") {
+                driver.run_jit(lib, args)
             } else {
-                driver.run(lib, std::iter::empty())
+                driver.run(lib, args)
             };
 
             if status {
@@ -94,6 +150,45 @@ fn main() {
         }) {
             driver.docs(lib);
         }
+    } else if let Some(matches) = matches.subcommand_matches("fuzz") {
+        let core = matches.value_of("core").unwrap();
+        let iterations = matches.value_of("iterations").unwrap().parse().unwrap_or(1000);
+        let seed = matches.value_of("seed").unwrap().parse().unwrap_or(1);
+
+        match driver::fuzz::run(core, iterations, seed) {
+            | Some(0) => println!("{} iterations, 0 panics", iterations),
+            | Some(failures) => {
+                println!("{} iterations, {} panics", iterations, failures);
+                std::process::exit(1);
+            },
+            | None => std::process::exit(1),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("bindgen") {
+        let header = std::path::Path::new(matches.value_of("header").unwrap());
+        let module = matches
+            .value_of("module")
+            .map(String::from)
+            .unwrap_or_else(|| header.file_stem().unwrap().to_string_lossy().into_owned());
+
+        let src = match std::fs::read_to_string(header) {
+            | Ok(src) => src,
+            | Err(e) => {
+                eprintln!("\x1B[1;31merror\x1B[0m: cannot read '{}': {}", header.display(), e);
+                std::process::exit(1);
+            },
+        };
+
+        let out = bindgen::generate(&module, &src);
+
+        match matches.value_of("output") {
+            | Some(path) => {
+                if let Err(e) = std::fs::write(path, out) {
+                    eprintln!("\x1B[1;31merror\x1B[0m: cannot write '{}': {}", path, e);
+                    std::process::exit(1);
+                }
+            },
+            | None => print!("{}", out),
+        }
     } else if let Some(input) = matches.value_of("input") {
         let target = matches.value_of("target");
         let output = matches.value_of("output").map(|o| match o {
@@ -103,7 +198,17 @@ fn main() {
             | _ => panic!("invalid output kind '{}'", o),
         });
 
-        if let Some((driver, _)) = Driver::init_no_manifest(Opts { input, target, output }) {
+        if let Some((driver, _)) = Driver::init_no_manifest(Opts {
+            input,
+            target,
+            output,
+            debug: matches.is_present("debug"),
+            release: matches.is_present("release"),
+            linker: matches.value_of("linker"),
+            link_args: matches.values_of("link_arg").into_iter().flatten().collect(),
+            static_link: matches.is_present("static_link"),
+            ..Opts::default()
+        }) {
             driver.build();
         }
     } else {
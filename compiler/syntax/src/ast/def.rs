@@ -32,6 +32,7 @@ ast_node!(Item {
 
 ast_node!(ImportItems, IMPORT_ITEMS);
 ast_node!(Ctor, ITEM_CTOR);
+ast_node!(CtorField, CTOR_FIELD);
 ast_node!(Instance, ITEM_INSTANCE);
 ast_node!(FunDep, FUN_DEP);
 
@@ -85,6 +86,8 @@ ast_node!(Stmt {
     Expr(StmtExpr, STMT_EXPR),
 });
 
+ast_node!(WhereClause, WHERE_CLAUSE);
+
 ast_node!(Expr {
     Typed(ExprTyped, EXPR_TYPED),
     Infix(ExprInfix, EXPR_INFIX),
@@ -93,7 +96,9 @@ ast_node!(Expr {
     Index(ExprIndex, EXPR_INDEX),
     Path(ExprPath, EXPR_PATH),
     Lit(ExprLit, EXPR_LITERAL),
+    Interp(ExprInterp, EXPR_INTERP),
     Parens(ExprParens, EXPR_PARENS),
+    Section(ExprSection, EXPR_SECTION),
     Tuple(ExprTuple, EXPR_TUPLE),
     Record(ExprRecord, EXPR_RECORD),
     Array(ExprArray, EXPR_ARRAY),
@@ -279,6 +279,14 @@ impl ItemFun {
     pub fn generics(&self) -> Option<Generics> {
         support::child(&self.0)
     }
+
+    pub fn guard(&self) -> Option<CaseGuard> {
+        support::child(&self.0)
+    }
+
+    pub fn where_clause(&self) -> Option<WhereClause> {
+        support::child(&self.0)
+    }
 }
 
 impl AttrsOwner for ItemStatic {
@@ -349,6 +357,20 @@ impl Ctor {
     pub fn types(&self) -> AstChildren<Type> {
         support::children(&self.0)
     }
+
+    /// The fields of a constructor declared with record syntax: `Point { x :: Int32, y :: Int32 }`.
+    pub fn fields(&self) -> AstChildren<CtorField> {
+        support::children(&self.0)
+    }
+}
+
+impl NameOwner for CtorField {
+}
+
+impl CtorField {
+    pub fn ty(&self) -> Option<Type> {
+        support::child(&self.0)
+    }
 }
 
 impl AttrsOwner for ItemClass {
@@ -621,6 +643,12 @@ impl PatTyped {
     }
 }
 
+impl PatCtor {
+    pub fn path(&self) -> Option<Path> {
+        support::child(&self.0)
+    }
+}
+
 impl PatBind {
     pub fn name(&self) -> Option<Name> {
         support::child(&self.0)
@@ -631,6 +659,12 @@ impl PatBind {
     }
 }
 
+impl PatLit {
+    pub fn literal(&self) -> Option<Literal> {
+        support::child(&self.0)
+    }
+}
+
 impl PatApp {
     pub fn base(&self) -> Option<Pat> {
         support::child(&self.0)
@@ -754,6 +788,34 @@ impl ExprParens {
     }
 }
 
+impl ExprSection {
+    pub fn op(&self) -> Option<Operator> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|it| it.into_token())
+            .find(|it| it.kind() == SyntaxKind::OPERATOR || it.kind() == SyntaxKind::STAR)
+            .map(Operator)
+    }
+
+    /// The operand before the operator in `(1 +)`.
+    pub fn lhs(&self) -> Option<Expr> {
+        let op = self.op()?;
+
+        self.expr().filter(|e| e.syntax().text_range().end() <= op.0.text_range().start())
+    }
+
+    /// The operand after the operator in `(+ 1)`.
+    pub fn rhs(&self) -> Option<Expr> {
+        let op = self.op()?;
+
+        self.expr().filter(|e| e.syntax().text_range().start() >= op.0.text_range().end())
+    }
+
+    fn expr(&self) -> Option<Expr> {
+        support::child(&self.0)
+    }
+}
+
 impl ExprTuple {
     pub fn exprs(&self) -> AstChildren<Expr> {
         support::children(&self.0)
@@ -761,6 +823,11 @@ impl ExprTuple {
 }
 
 impl ExprRecord {
+    /// The record that is updated in `{ base | field: val }`.
+    pub fn base(&self) -> Option<Expr> {
+        support::child(&self.0)
+    }
+
     pub fn fields(&self) -> AstChildren<Field> {
         support::children(&self.0)
     }
@@ -896,6 +963,12 @@ impl StmtLet {
     }
 }
 
+impl WhereClause {
+    pub fn bindings(&self) -> AstChildren<StmtLet> {
+        support::children(&self.0)
+    }
+}
+
 impl StmtBind {
     pub fn pat(&self) -> Option<Pat> {
         support::child(&self.0)
@@ -970,6 +1043,14 @@ impl LitChar {
                     input.next();
                     Some('"')
                 },
+                | '{' => {
+                    input.next();
+                    Some('{')
+                },
+                | '}' => {
+                    input.next();
+                    Some('}')
+                },
                 | '\\' => {
                     input.next();
                     Some('\\')
@@ -1035,18 +1116,44 @@ impl LitString {
         if text.starts_with('r') {
             Some(text[2..text.len() - 1].into())
         } else {
-            let mut chars = text[1..text.len() - 1].chars();
-            let mut text = String::with_capacity(chars.as_str().len());
-            let mut chars = chars.peekable();
+            Some(unescape(&text[1..text.len() - 1]))
+        }
+    }
+}
 
-            while let Some(ch) = LitChar::escape(&mut chars) {
-                text.push(ch);
-            }
+fn unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
 
-            text.shrink_to_fit();
+    while let Some(ch) = LitChar::escape(&mut chars) {
+        res.push(ch);
+    }
 
-            Some(text)
-        }
+    res.shrink_to_fit();
+    res
+}
+
+/// A part of an interpolated string.
+pub enum InterpPart {
+    Text(String),
+    Expr(Expr),
+}
+
+impl ExprInterp {
+    /// The text and expressions of the string, in order. The text parts include the text before
+    /// the first and after the last expression, which may be empty.
+    pub fn parts(&self) -> impl Iterator<Item = InterpPart> {
+        self.0.children_with_tokens().filter_map(|it| match it {
+            | rowan::NodeOrToken::Node(node) => Expr::cast(node).map(InterpPart::Expr),
+            | rowan::NodeOrToken::Token(token) => match token.kind() {
+                | STRING_START | STRING_MID | STRING_END => {
+                    let text = token.text();
+
+                    Some(InterpPart::Text(unescape(&text[1..text.len() - 1])))
+                },
+                | _ => None,
+            },
+        })
     }
 }
 
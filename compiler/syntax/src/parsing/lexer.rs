@@ -59,6 +59,8 @@ enum LayoutDelim {
     Brace,
     Square,
     Angle,
+    /// An expression inside of an interpolated string.
+    Interp,
     If,
     Then,
     Else,
@@ -148,10 +150,10 @@ impl<'src> Lexer<'src> {
             },
             | '-' if self.peek().is_digit(10) => self.number(ch, start),
             | '0'..='9' => self.number(ch, start),
-            | '"' => self.string(start, false),
+            | '"' => self.string_part(start, STRING_START, STRING),
             | 'r' if self.peek() == '"' => {
                 self.advance();
-                self.string(start, true)
+                self.raw_string(start)
             },
             | '\'' => self.character(start),
             | ch if ch.is_xid_start() => self.name(start),
@@ -199,6 +201,12 @@ impl<'src> Lexer<'src> {
             | '}' => {
                 Collapse::new(self.tokens.len()).collapse(start, indented_p, &mut self.stack, &mut self.tokens);
 
+                if let [.., (_, LayoutDelim::Interp)] = self.stack[..] {
+                    self.stack.pop().unwrap();
+                    self.string_part(start, STRING_MID, STRING_END);
+                    return;
+                }
+
                 if let [.., (_, LayoutDelim::Prop)] = self.stack[..] {
                     self.stack.pop().unwrap();
                 }
@@ -404,21 +412,59 @@ impl<'src> Lexer<'src> {
         }
     }
 
-    fn string(&mut self, start: (usize, usize), raw: bool) {
+    fn raw_string(&mut self, start: (usize, usize)) {
+        while self.pos < TextSize::of(self.source) && self.peek() != '"' {
+            self.advance();
+        }
+
+        if self.peek() == '"' {
+            self.advance();
+            self.insert_default(start, STRING);
+        } else {
+            self.errors
+                .push(SyntaxError::new("unterminated string literal", self.span()));
+            self.emit(ERROR);
+        }
+    }
+
+    /// Lexes the text of a string up to its end or the start of an interpolated expression.
+    /// A string without interpolations is a single `STRING` token, otherwise the text parts
+    /// are `STRING_START`, `STRING_MID` and `STRING_END` tokens around the expressions.
+    ///
+    /// Every unescaped `{` starts an interpolated expression, a literal brace is written as `\{`.
+    /// Raw strings are never interpolated.
+    fn string_part(&mut self, start: (usize, usize), open: SyntaxKind, close: SyntaxKind) {
         while self.pos < TextSize::of(self.source) {
             match self.peek() {
                 | '"' => break,
-                | '\\' if !raw => {
+                | '\\' => {
                     self.advance();
                     self.escape();
                 },
+                | '{' => {
+                    self.advance();
+
+                    if open == STRING_START {
+                        self.insert_default(start, STRING_START);
+                    } else {
+                        self.emit(open);
+                    }
+
+                    self.stack.push((start, LayoutDelim::Interp));
+                    return;
+                },
                 | _ => self.advance(),
             }
         }
 
         if self.peek() == '"' {
             self.advance();
-            self.insert_default(start, STRING);
+
+            if close == STRING {
+                self.insert_default(start, STRING);
+            } else {
+                self.emit(close);
+            }
         } else {
             self.errors
                 .push(SyntaxError::new("unterminated string literal", self.span()));
@@ -452,7 +498,7 @@ impl<'src> Lexer<'src> {
 
     fn escape(&mut self) {
         match self.peek() {
-            | '\'' | '"' | '\\' | 'r' | 'n' | '0' | 't' => self.advance(),
+            | '\'' | '"' | '\\' | 'r' | 'n' | '0' | 't' | '{' | '}' => self.advance(),
             | 'x' => {
                 self.advance();
 
@@ -846,6 +892,26 @@ impl<'src> Lexer<'src> {
                     self.insert_default(start, FOR_KW);
                 }
             },
+            | "where" => {
+                if let [.., (_, LayoutDelim::Prop)] = self.stack[..] {
+                    self.emit(IDENT);
+                    self.stack.pop().unwrap();
+                } else {
+                    Collapse::new(self.tokens.len()).collapse(
+                        start,
+                        |tok, pos, lyt| match lyt {
+                            | LayoutDelim::Do => true,
+                            | _ => offside_end_p(tok, pos, lyt),
+                        },
+                        &mut self.stack,
+                        &mut self.tokens,
+                    );
+
+                    self.insert_sep(start);
+                    self.emit(WHERE_KW);
+                    self.insert_start(LayoutDelim::Where);
+                }
+            },
             | _ => {
                 self.insert_default(start, IDENT);
 